@@ -13,6 +13,7 @@ from pydantic import (
     BaseModel,
     Field,
     PositiveFloat,
+    PositiveInt,
     StringConstraints,
 )
 
@@ -28,6 +29,7 @@ NonEmptyStr = Annotated[str, StringConstraints(min_length=1)]
 PathNoBackslash = Annotated[str, StringConstraints(pattern=r"^[^\\]+$")]
 Glob = NonEmptyStr
 UnsignedInt = Annotated[int, Field(strict=True, ge=0)]
+KeyringEntry = Annotated[str, StringConstraints(pattern=r"^.+:.+$")]
 GitUrl = Annotated[
     str, StringConstraints(pattern=r"((git|ssh|http(s)?)|(git@[\w\.]+))(:(\/\/)?)([\w\.@:\/\\-~]+)")
 ]
@@ -113,6 +115,65 @@ class Project(StrictBaseModel):
     conda_pypi_map: dict[ChannelName, AnyHttpUrl | NonEmptyStr] | None = Field(
         None, alias="conda-pypi-map", description="The `conda` to PyPI mapping configuration"
     )
+    preferred_features: list[NonEmptyStr] | None = Field(
+        None,
+        alias="preferred-features",
+        description="The `conda` features (e.g. `nomkl`) that are preferred by the solver when selecting package variants",
+    )
+    avoided_features: list[NonEmptyStr] | None = Field(
+        None,
+        alias="avoided-features",
+        description="The `conda` features (e.g. `mkl`) that are avoided by the solver when selecting package variants",
+    )
+    disallowed_licenses: list[NonEmptyStr] | None = Field(
+        None,
+        alias="disallowed-licenses",
+        description="The licenses or license families (e.g. `GPL-3.0` or `GPL`) of `conda` packages that must not be selected by the solver",
+    )
+    python_implementation: Optional[Literal["cpython", "pypy"]] = Field(
+        None,
+        alias="python-implementation",
+        description="The implementation of python (`cpython` or `pypy`) that is selected by the solver",
+    )
+    noarch_python_min: NonEmptyStr | None = Field(
+        None,
+        alias="noarch-python-min",
+        description="The minimum python version that the selected `noarch: python` packages have to support, unless an explicit python dependency excludes it",
+        examples=["3.9"],
+    )
+    constraints: list[PathNoBackslash] | None = Field(
+        None,
+        description="Files containing a `conda` matchspec per line that bound the versions of packages without adding them as dependencies",
+    )
+    proxy: AnyHttpUrl | None = Field(
+        None,
+        description="The HTTP(S) proxy to use for all requests, takes precedence over the `HTTPS_PROXY` and `HTTP_PROXY` environment variables",
+    )
+    lockfile_per_environment: bool | None = Field(
+        None,
+        alias="lockfile-per-environment",
+        description="Write a `pixi.<environment>.lock` file per environment instead of a single `pixi.lock`",
+    )
+    pre_run_task: NonEmptyStr | None = Field(
+        None,
+        alias="pre-run-task",
+        description="The name of a task that is run before the tasks of every `pixi run`",
+    )
+    post_run_task: NonEmptyStr | None = Field(
+        None,
+        alias="post-run-task",
+        description="The name of a task that is run after the tasks of every `pixi run`, also when one of them failed",
+    )
+    post_install_check: NonEmptyStr | None = Field(
+        None,
+        alias="post-install-check",
+        description="A command that is run in the environment after `pixi install` to verify the installation",
+    )
+    sort_dependencies: bool | None = Field(
+        None,
+        alias="sort-dependencies",
+        description="Sort the dependency tables alphabetically when adding dependencies with `pixi add`",
+    )
 
 
 ########################
@@ -139,6 +200,13 @@ MatchSpec = NonEmptyStr | MatchspecTable
 CondaPackageName = NonEmptyStr
 
 
+class Pin(StrictBaseModel):
+    """A pinned version of a conda package together with the reason for the pin."""
+
+    version: NonEmptyStr = Field(..., description="The version spec of the pin")
+    reason: NonEmptyStr | None = Field(None, description="Why the package is pinned")
+
+
 # { version = "sdfds" extras = ["sdf"] }
 # { git = "sfds", rev = "fssd" }
 # { path = "asfdsf" }
@@ -193,6 +261,13 @@ class PyPIVersion(_PyPIRequirement):
         None,
         description="The version of the package in [PEP 440](https://www.python.org/dev/peps/pep-0440/) format",
     )
+    prerelease: Optional[bool] = Field(
+        None, description="If `true` pre-release versions of the package may be selected"
+    )
+    format: Optional[Literal["wheel", "sdist"]] = Field(
+        None,
+        description="Only allow wheels or only allow source distributions of the package",
+    )
 
 
 PyPIRequirement = (
@@ -229,6 +304,48 @@ Dependencies = dict[CondaPackageName, MatchSpec] | None
 TaskName = Annotated[str, Field(pattern=r"^[^\s\$]+$", description="A valid task name.")]
 
 
+class TaskDependencyTable(StrictBaseModel):
+    """A dependency on another task, with optional conditions."""
+
+    task: TaskName
+    platform: Platform | None = Field(
+        None, description="Only depend on the task when running on this platform"
+    )
+    allow_failure: bool = Field(
+        False,
+        alias="allow-failure",
+        description="Continue running the dependent tasks when this task fails",
+    )
+
+
+class TaskULimit(StrictBaseModel):
+    """The limits of the resources a task can use, only supported on Unix."""
+
+    memory: PositiveInt | None = Field(
+        None, description="The maximum size of the virtual memory of the process in MiB"
+    )
+    open_files: PositiveInt | None = Field(
+        None,
+        alias="open-files",
+        description="The maximum number of file descriptors the process can open",
+    )
+
+
+class TaskArg(StrictBaseModel):
+    """An argument of a task that is passed on the command line."""
+
+    name: str = Field(
+        pattern=r"^[A-Za-z_][A-Za-z0-9_]*$",
+        description="The name of the argument, available in the command as `$name` and `{{ name }}`",
+    )
+    choices: list[NonEmptyStr] | None = Field(
+        None, description="The values that are allowed for this argument"
+    )
+    default: str | None = Field(
+        None, description="The value used when the argument is not passed on the command line"
+    )
+
+
 class TaskInlineTable(StrictBaseModel):
     """A precise definition of a task."""
 
@@ -238,12 +355,12 @@ class TaskInlineTable(StrictBaseModel):
     )
     cwd: PathNoBackslash | None = Field(None, description="The working directory to run the task")
     # BREAK: `depends_on` is deprecated, use `depends-on`
-    depends_on_deprecated: list[TaskName] | TaskName | None = Field(
+    depends_on_deprecated: list[TaskName | TaskDependencyTable] | TaskName | None = Field(
         None,
         alias = "depends_on",
         description="The tasks that this task depends on. Environment variables will **not** be expanded. Deprecated in favor of `depends-on` from v0.21.0 onward.",
     )
-    depends_on: list[TaskName] | TaskName | None = Field(
+    depends_on: list[TaskName | TaskDependencyTable] | TaskName | None = Field(
         None,
         alias="depends-on",
         description="The tasks that this task depends on. Environment variables will **not** be expanded.",
@@ -261,6 +378,30 @@ class TaskInlineTable(StrictBaseModel):
         description="A map of environment variables to values, used in the task, these will be overwritten by the shell.",
         examples=[{"key": "value"}, {"ARGUMENT": "value"}],
     )
+    env_from_keyring: dict[NonEmptyStr, KeyringEntry] | None = Field(
+        None,
+        alias="env-from-keyring",
+        description="A map of environment variables to keyring entries of the form `service:account`, the secrets are read from the keyring of the operating system when the task runs.",
+        examples=[{"TOKEN": "pypi:__token__"}],
+    )
+    args: list[TaskArg] | None = Field(
+        None,
+        description="The arguments that are passed to the task on the command line, in order",
+    )
+    shell: NonEmptyStr | None = Field(
+        None,
+        description="The shell (e.g. `pwsh` or `bash`) to run the command in instead of `deno_task_shell`, searched for on the `PATH`",
+    )
+    nice: int | None = Field(
+        None,
+        ge=-20,
+        le=19,
+        description="The niceness of the process of the task, from -20 (highest priority) to 19 (lowest priority). On Windows this is mapped to a priority class",
+    )
+    ulimit: TaskULimit | None = Field(
+        None,
+        description="The limits of the resources the process of the task can use, only supported on Unix",
+    )
 
 
 #######################
@@ -316,6 +457,10 @@ class Environment(StrictBaseModel):
         alias="no-default-feature",
         description="Whether to add the default feature to this environment",
     )
+    exclude: list[CondaPackageName] | None = Field(
+        None,
+        description="The `conda` packages that must never be part of this environment, even if a dependency requires them",
+    )
 
 
 ######################
@@ -329,6 +474,11 @@ class Activation(StrictBaseModel):
         description="The scripts to run when the environment is activated",
         examples=["activate.sh", "activate.bat"],
     )
+    env: dict[NonEmptyStr, str] | None = Field(
+        None,
+        description="A map of environment variables to values, set when the environment is activated. `$CONDA_PREFIX` and `$PIXI_PROJECT_ROOT` are expanded",
+        examples=[{"DATA_DIR": "$PIXI_PROJECT_ROOT/data"}],
+    )
 
 
 ##################
@@ -368,6 +518,11 @@ class Feature(StrictBaseModel):
         None,
         description="The platforms that the feature supports: a union of all features combined in one environment is used for the environment.",
     )
+    omit_on_other_platforms: bool = Field(
+        False,
+        alias="omit-on-other-platforms",
+        description="Omit the dependencies of this feature on platforms that are not in its `platforms` instead of restricting the platforms of the environment",
+    )
     dependencies: Dependencies = DependenciesField
     host_dependencies: Dependencies = HostDependenciesField
     build_dependencies: Dependencies = BuildDependenciesField
@@ -439,6 +594,9 @@ class BaseManifest(StrictBaseModel):
         None, alias="pypi-dependencies", description="The PyPI dependencies"
     )
     pypi_options: PyPIOptions | None = Field(None, alias="pypi-options", description="Options related to PyPI indexes")
+    pins: dict[CondaPackageName, NonEmptyStr | Pin] | None = Field(
+        None, description="The versions of conda packages that are pinned for all environments"
+    )
     tasks: dict[TaskName, TaskInlineTable | NonEmptyStr] | None = Field(
         None, description="The tasks of the project"
     )
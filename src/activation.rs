@@ -1,14 +1,19 @@
 use indexmap::IndexMap;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::Path;
 
 use itertools::Itertools;
-use miette::IntoDiagnostic;
+use miette::{IntoDiagnostic, WrapErr};
 use rattler_conda_types::Platform;
 use rattler_shell::activation::ActivationError::FailedToRunActivationScript;
 use rattler_shell::{
     activation::{ActivationError, ActivationVariables, Activator, PathModificationBehavior},
     shell::ShellEnum,
 };
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::Xxh3;
 
 use crate::project::has_features::HasFeatures;
 use crate::{
@@ -121,13 +126,58 @@ pub fn get_activator<'p>(
         .extend(additional_activation_scripts);
 
     // Add the environment variables from the project.
+    let mut variables = get_environment_variables(environment);
+    activator.env_vars.extend(variables.clone());
+
+    // Add the static environment variables of the `[activation.env]` tables.
+    variables.insert(
+        "CONDA_PREFIX".to_string(),
+        environment.dir().to_string_lossy().to_string(),
+    );
     activator
         .env_vars
-        .extend(get_environment_variables(environment));
+        .extend(
+            environment
+                .activation_env(Some(platform))
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = expand_variables(&value, &variables);
+                    (key, value)
+                }),
+        );
 
     Ok(activator)
 }
 
+/// Expands the references (`$NAME` or `${NAME}`) to the given variables in a value. References to
+/// other variables are kept as is.
+fn expand_variables(value: &str, variables: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let reference = &rest[start + 1..];
+        let (name, len) = if let Some(braced) = reference.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = reference
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(reference.len());
+            (&reference[..end], end)
+        };
+        match variables.get(name) {
+            Some(expanded) if !name.is_empty() => result.push_str(expanded),
+            _ => result.push_str(&rest[start..start + 1 + len]),
+        }
+        rest = &reference[len..];
+    }
+    result.push_str(rest);
+    result
+}
+
 /// Runs and caches the activation script.
 pub async fn run_activation(
     environment: &Environment<'_>,
@@ -188,6 +238,135 @@ pub async fn run_activation(
     Ok(activator_result)
 }
 
+/// Runs the activation of a prefix that is not part of a project, like the environment of a
+/// global tool, and returns the resulting environment variables.
+pub async fn run_prefix_activation(prefix: &Path) -> miette::Result<HashMap<String, String>> {
+    let activator = Activator::from_path(prefix, ShellEnum::default(), Platform::current())
+        .into_diagnostic()?;
+    tokio::task::spawn_blocking(move || {
+        activator.run_activation(ActivationVariables {
+            path: Default::default(),
+            conda_prefix: None,
+            path_modification_behavior: PathModificationBehavior::Prepend,
+        })
+    })
+    .await
+    .into_diagnostic()?
+    .into_diagnostic()
+    .wrap_err_with(|| format!("failed to activate '{}'", prefix.display()))
+}
+
+/// An activation environment that is cached in the `.pixi` folder, see [`run_activation_cached`].
+#[derive(Debug, Serialize, Deserialize)]
+struct ActivationCache {
+    /// The hash of the inputs of the activation, see [`activation_hash`].
+    hash: String,
+    environment: HashMap<String, String>,
+}
+
+/// Runs the activation like [`run_activation`], but reuses the environment of a previous run as
+/// long as the prefix and the other inputs of the activation did not change. This avoids running
+/// the activation scripts for every `pixi run`.
+pub async fn run_activation_cached(
+    environment: &Environment<'_>,
+) -> miette::Result<HashMap<String, String>> {
+    let cache_file = environment
+        .project()
+        .activation_cache_folder()
+        .join(format!("{}.json", environment.name()));
+    let hash = activation_hash(environment);
+
+    let cached = std::fs::read_to_string(&cache_file)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<ActivationCache>(&contents).ok());
+    if let Some(cached) = cached.filter(|cached| cached.hash == hash) {
+        tracing::debug!(
+            "reusing the cached activation of environment '{}'",
+            environment.name()
+        );
+        return Ok(cached.environment);
+    }
+
+    let cache = ActivationCache {
+        hash,
+        environment: run_activation(environment).await?,
+    };
+    if let Err(err) = write_activation_cache(&cache_file, &cache) {
+        tracing::debug!(
+            "failed to write the activation cache '{}': {err}",
+            cache_file.display()
+        );
+    }
+    Ok(cache.environment)
+}
+
+/// Writes the activation cache. The activated environment may contain secrets, so on unix the
+/// file is only readable by the current user.
+fn write_activation_cache(path: &Path, cache: &ActivationCache) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files, restrict a cache file of an older version as well.
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path)?;
+    file.write_all(serde_json::to_string(cache)?.as_bytes())
+}
+
+/// Computes a hash of everything the activation of the environment depends on: the state of the
+/// prefix, the activation scripts and variables of the manifest and the environment variables of
+/// the current process, which the activation scripts may read. The `conda-meta` folder changes
+/// whenever a package is installed or removed.
+fn activation_hash(environment: &Environment<'_>) -> String {
+    let platform = Platform::current();
+    let mut hasher = Xxh3::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    std::env::vars_os()
+        .collect::<BTreeMap<_, _>>()
+        .hash(&mut hasher);
+    environment.dir().hash(&mut hasher);
+
+    let conda_meta = environment.dir().join("conda-meta");
+    let scripts = environment
+        .activation_scripts(Some(platform))
+        .into_iter()
+        .map(|script| environment.project().root().join(script));
+    let files = std::fs::read_dir(conda_meta)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .sorted()
+        .chain(scripts);
+    for file in files {
+        file.hash(&mut hasher);
+        if let Ok(metadata) = file.metadata() {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+
+    get_environment_variables(environment)
+        .into_iter()
+        .collect::<BTreeMap<_, _>>()
+        .hash(&mut hasher);
+    environment
+        .activation_env(Some(platform))
+        .into_iter()
+        .collect_vec()
+        .hash(&mut hasher);
+
+    format!("{:x}", hasher.finish())
+}
+
 /// Get the environment variables that are statically generated from the project and the environment.
 pub fn get_environment_variables<'p>(environment: &'p Environment<'p>) -> HashMap<String, String> {
     // Get environment variables from the project
@@ -263,6 +442,50 @@ mod tests {
         assert!(env.get("PIXI_PROMPT").unwrap().contains("test"));
     }
 
+    #[test]
+    fn test_expand_variables() {
+        let variables = HashMap::from([
+            ("CONDA_PREFIX".to_string(), "/prefix".to_string()),
+            ("PIXI_PROJECT_ROOT".to_string(), "/project".to_string()),
+        ]);
+        assert_eq!(
+            expand_variables("$CONDA_PREFIX/lib:${PIXI_PROJECT_ROOT}/lib", &variables),
+            "/prefix/lib:/project/lib"
+        );
+        assert_eq!(
+            expand_variables("$HOME/bin:${CONDA_PREFIX}", &variables),
+            "$HOME/bin:/prefix"
+        );
+        assert_eq!(expand_variables("costs $5 ${", &variables), "costs $5 ${");
+    }
+
+    #[test]
+    fn test_activation_env_overrides() {
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64"]
+
+        [activation]
+        env = { LEVEL = "info", DATA = "data" }
+
+        [feature.debug.activation]
+        env = { LEVEL = "debug" }
+
+        [environments]
+        debug = ["debug"]
+        "#;
+        let project = Project::from_str(Path::new("pixi.toml"), project).unwrap();
+
+        let env = project.default_environment().activation_env(None);
+        assert_eq!(env.get("LEVEL").unwrap(), "info");
+
+        let env = project.environment("debug").unwrap().activation_env(None);
+        assert_eq!(env.get("LEVEL").unwrap(), "debug");
+        assert_eq!(env.get("DATA").unwrap(), "data");
+    }
+
     #[test]
     fn test_metadata_project_env() {
         let project = r#"
@@ -289,4 +512,44 @@ mod tests {
             &project.version().as_ref().unwrap().to_string()
         );
     }
+
+    #[test]
+    fn test_activation_hash_changes_with_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64"]
+        "#;
+        let project = Project::from_str(&temp_dir.path().join("pixi.toml"), project).unwrap();
+        let environment = project.default_environment();
+        let conda_meta = environment.dir().join("conda-meta");
+        std::fs::create_dir_all(&conda_meta).unwrap();
+
+        let empty = activation_hash(&environment);
+        assert_eq!(empty, activation_hash(&environment));
+
+        std::fs::write(conda_meta.join("python-3.12.0-0.json"), "{}").unwrap();
+        assert_ne!(empty, activation_hash(&environment));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_activation_cache_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_file = temp_dir
+            .path()
+            .join("activation-cache")
+            .join("default.json");
+        let cache = ActivationCache {
+            hash: String::from("hash"),
+            environment: HashMap::from([(String::from("TOKEN"), String::from("secret"))]),
+        };
+        write_activation_cache(&cache_file, &cache).unwrap();
+        let mode = std::fs::metadata(&cache_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
 }
@@ -1,26 +1,41 @@
 use crate::{
     config::ConfigCli,
     environment::{get_up_to_date_prefix, verify_prefix_location_unchanged, LockFileUsage},
-    project::{has_features::HasFeatures, DependencyType, Project, SpecType},
-    FeatureName,
+    project::{
+        has_features::HasFeatures,
+        manifest::{python::parse_requirements_txt, DistributionFormat, TargetSelector},
+        DependencyType, Project, SpecType,
+    },
+    EnvironmentName, FeatureName,
 };
 use clap::Parser;
+use dialoguer::theme::ColorfulTheme;
+use human_bytes::human_bytes;
 use itertools::{Either, Itertools};
 
+use crate::consts;
+use crate::lock_file::{
+    conda_solve_options, load_lock_file, resolve_conda, LockFileDiff, SolveStrategy,
+    UpdateLockFileOptions,
+};
 use crate::project::grouped_environment::GroupedEnvironment;
+use crate::repodata::friendly_channel_name;
 use indexmap::IndexMap;
 use miette::{IntoDiagnostic, WrapErr};
 use rattler_conda_types::{
-    version_spec::{LogicalOperator, RangeOperator},
+    version_spec::{EqualityOperator, LogicalOperator, RangeOperator},
     Channel, MatchSpec, NamelessMatchSpec, PackageName, ParseStrictness, Platform, Version,
     VersionBumpType, VersionSpec,
 };
+use rattler_lock::{LockFile, Package};
 use rattler_repodata_gateway::sparse::SparseRepoData;
-use rattler_solve::{resolvo, ChannelPriority, SolverImpl};
 use std::{
     collections::{HashMap, HashSet},
     path::PathBuf,
+    str::FromStr,
 };
+use strsim::jaro;
+use url::Url;
 
 /// Adds a dependency to the project
 #[derive(Parser, Debug, Default)]
@@ -40,6 +55,9 @@ pub struct Args {
     /// - `pixi add python`: In absence of a specified version, the latest version will be chosen.
     ///   For instance, this could resolve to python version 3.11.3.* at the time of writing.
     ///
+    /// - `pixi add python@latest`: Pins the highest version of python that is available in the
+    ///   channels exactly, e.g. `==3.12.3`.
+    ///
     /// Adding multiple dependencies at once is also supported:
     ///
     /// - `pixi add python pytest`: This will add both `python` and `pytest` to the project's dependencies.
@@ -56,9 +74,14 @@ pub struct Args {
     /// - `pixi add --pypi boto3`
     /// - `pixi add --pypi "boto3==version"
     ///
-    #[arg(required = true)]
+    #[arg(required_unless_present = "requirement")]
     pub specs: Vec<String>,
 
+    /// Add the PEP 508 requirements of a `requirements.txt` file as pypi dependencies, together
+    /// with the given specs in a single solve. Unsupported lines like `-e` and `-r` are skipped.
+    #[arg(long, short = 'r', value_name = "FILE", conflicts_with_all = ["host", "build"])]
+    pub requirement: Option<PathBuf>,
+
     /// The path to 'pixi.toml' or 'pyproject.toml'
     #[arg(long)]
     pub manifest_path: Option<PathBuf>,
@@ -83,21 +106,105 @@ pub struct Args {
     #[arg(long)]
     pub no_install: bool,
 
+    /// Solve with the new dependencies and show the version that would be added and the packages
+    /// that would be added to the lock-file, without modifying the manifest or the lock-file.
+    #[arg(long, conflicts_with = "no_lockfile_update")]
+    pub dry_run: bool,
+
     /// The platform(s) for which the dependency should be added
     #[arg(long, short)]
     pub platform: Vec<Platform>,
 
+    /// The target(s) for which the dependency should be added. Besides platforms this accepts
+    /// the groups `unix`, `linux`, `osx` and `win`, which expand to the matching platforms of the
+    /// project.
+    #[arg(long, conflicts_with = "platform")]
+    pub target: Vec<TargetSelector>,
+
+    /// Detect the platforms of the feature for which each conda dependency has builds in the
+    /// channels and add it only for those platforms. You are asked for confirmation before a
+    /// dependency is scoped to target tables.
+    #[arg(long, conflicts_with_all = ["platform", "target", "pypi"])]
+    pub auto_platform: bool,
+
+    /// Don't ask for confirmation before scoping the dependencies with `--auto-platform`.
+    #[arg(long, short, requires = "auto_platform")]
+    pub yes: bool,
+
     /// The feature for which the dependency should be added
     #[arg(long, short)]
     pub feature: Option<String>,
 
+    /// Add the pypi dependencies to this optional dependency group, which is written to
+    /// `[project.optional-dependencies]` of a `pyproject.toml`. An environment with the same name
+    /// is added for the group if there is none yet, install it with `pixi install --with <GROUP>`.
+    #[arg(long, value_name = "GROUP", conflicts_with = "feature")]
+    pub optional: Option<String>,
+
+    /// Add the dependencies to all environments of this solve group. They are added to the
+    /// feature that is used by exactly the environments of the solve group, or otherwise to a
+    /// feature with the name of the solve group that is added to all of its environments.
+    #[arg(long, value_name = "GROUP", conflicts_with_all = ["feature", "optional"])]
+    pub solve_group: Option<String>,
+
     #[clap(flatten)]
     pub config: ConfigCli,
+
+    /// The strategy used to determine the version constraint of the added dependencies. Only
+    /// applies to dependencies for which no version was specified.
+    #[arg(long, value_enum)]
+    pub pin: Option<PinningStrategy>,
+
+    /// Allow the pypi resolver to select pre-release versions of the added pypi dependencies.
+    /// This is stored in the manifest as `prerelease = true`.
+    #[arg(long, requires = "pypi")]
+    pub allow_prerelease: bool,
+
+    /// Only allow wheels for the added pypi dependencies, resolving or installing fails if only a
+    /// source distribution is available. This is stored in the manifest as `format = "wheel"`.
+    #[arg(long, requires = "pypi", conflicts_with = "sdist_only")]
+    pub wheel_only: bool,
+
+    /// Only allow source distributions for the added pypi dependencies, they are always built
+    /// from source. This is stored in the manifest as `format = "sdist"`.
+    #[arg(long, requires = "pypi")]
+    pub sdist_only: bool,
+
+    /// Sort the dependency table alphabetically after adding the dependencies, comments stay
+    /// attached to their dependencies. Enabled by default with `sort-dependencies = true` in the
+    /// `[project]` table.
+    #[arg(long)]
+    pub sort: bool,
+
+    /// Pin the added conda package to the build with this sha256 hash, only that build is
+    /// selected by the solver. This is stored in the manifest as `sha256 = "<HASH>"` and requires
+    /// a single package.
+    #[arg(long, value_name = "HASH", conflicts_with = "pypi")]
+    pub sha256: Option<String>,
+}
+
+/// The suffix of a conda spec that pins the highest version available in the channels, e.g.
+/// `foo@latest`.
+const LATEST_SUFFIX: &str = "@latest";
+
+/// Determines which version constraint is written to the manifest for a newly added dependency,
+/// based on the version that was selected by the solver.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinningStrategy {
+    /// Pin to the exact version, e.g. `==1.2.3`
+    Exact,
+    /// Allow newer patch versions, e.g. `>=1.2.3,<1.3`
+    Minor,
+    /// Allow newer minor versions, e.g. `>=1.2,<2`
+    Major,
+    /// Don't constrain the version, e.g. `*`
+    #[value(name = "none")]
+    NoPin,
 }
 
 impl DependencyType {
     pub fn from_args(args: &Args) -> Self {
-        if args.pypi {
+        if args.pypi || args.requirement.is_some() {
             Self::PypiDependency
         } else if args.host {
             DependencyType::CondaDependency(SpecType::Host)
@@ -113,7 +220,13 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     let mut project = Project::load_or_else_discover(args.manifest_path.as_deref())?
         .with_cli_config(args.config.clone());
     let dependency_type = DependencyType::from_args(&args);
-    let spec_platforms = &args.platform;
+    let spec_platforms = expand_targets(
+        &args,
+        project
+            .platforms()
+            .into_iter()
+            .sorted_by_key(|p| p.as_str()),
+    )?;
 
     // Sanity check of prefix location
     verify_prefix_location_unchanged(project.default_environment().dir().as_path()).await?;
@@ -128,19 +241,116 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         .manifest
         .add_platforms(platforms_to_add.iter(), &FeatureName::Default)?;
 
-    let feature_name = args
-        .feature
-        .map_or(FeatureName::Default, FeatureName::Named);
+    if args.optional.is_some() && !matches!(dependency_type, DependencyType::PypiDependency) {
+        miette::bail!(
+            help = "add the dependencies with `--pypi` or from a requirements file with `--requirement`",
+            "optional dependency groups can only contain pypi dependencies"
+        );
+    }
+    let feature_name = match &args.solve_group {
+        Some(group) => project.manifest.solve_group_feature(group)?,
+        None => args
+            .feature
+            .or(args.optional.clone())
+            .map_or(FeatureName::Default, FeatureName::Named),
+    };
+
+    // An optional dependency group is installable as the environment of the same name.
+    if let Some(group) = &args.optional {
+        let has_environment = project
+            .manifest
+            .parsed
+            .environments
+            .iter()
+            .any(|env| env.features.contains(group));
+        if !has_environment {
+            let name = EnvironmentName::from_str(group).into_diagnostic()?;
+            project
+                .manifest
+                .add_environment(name, vec![group.clone()])?;
+            eprintln!(
+                "{}Added environment {} for the optional dependency group",
+                console::style(console::Emoji("✔ ", "")).green(),
+                console::style(group).bold(),
+            );
+        }
+    }
+
+    // The requirements of the requirements file are added like the specs.
+    let mut specs = args.specs.clone();
+    let mut spec_lines = HashMap::new();
+    if let Some(path) = &args.requirement {
+        let contents = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read '{}'", path.display()))?;
+        let lines = parse_requirements_txt(&contents);
+        for skipped in &lines.skipped {
+            eprintln!(
+                "{}Skipped line {} of '{}': {}, {}",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                skipped.line,
+                path.display(),
+                console::style(&skipped.content).bold(),
+                skipped.reason
+            );
+        }
+        for (line, requirement) in lines.requirements {
+            spec_lines.insert(specs.len(), line);
+            specs.push(requirement);
+        }
+    }
+
+    // The specs that were written to the manifest for `@latest` specs.
+    let mut resolved_specs = HashMap::new();
 
-    match dependency_type {
+    // The platforms of the conda specs that are scoped with `--auto-platform`.
+    let mut scoped_platforms = HashMap::new();
+
+    // The conda packages that were already present in the manifest and did not change.
+    let unchanged = match dependency_type {
         DependencyType::CondaDependency(spec_type) => {
-            let specs = args
-                .specs
-                .clone()
+            let (mut specs, latest): (Vec<_>, Vec<_>) = specs
+                .iter()
+                .map(|s| parse_conda_spec(s))
+                .collect::<miette::Result<Vec<_>>>()?
                 .into_iter()
-                .map(|s| MatchSpec::from_str(&s, ParseStrictness::Strict))
-                .collect::<Result<Vec<_>, _>>()
-                .into_diagnostic()?;
+                .unzip();
+
+            if let Some(sha256) = &args.sha256 {
+                let [spec] = specs.as_mut_slice() else {
+                    miette::bail!("`--sha256` can only be used when adding a single package");
+                };
+                spec.sha256 = Some(
+                    rattler_digest::parse_digest_from_hex::<rattler_digest::Sha256>(sha256)
+                        .ok_or_else(|| miette::miette!("'{sha256}' is not a valid sha256 hash"))?,
+                );
+            }
+
+            // Fetch the repodata for the project
+            let sparse_repo_data = project.fetch_sparse_repodata().await?;
+
+            if args.auto_platform {
+                scoped_platforms = detect_platforms_with_builds(
+                    &feature_platforms(&project, &feature_name),
+                    &specs,
+                    &sparse_repo_data,
+                    args.yes,
+                )?;
+            }
+
+            // Pin the `@latest` specs before anything is written to the manifest.
+            for ((spec, original), latest) in specs.iter_mut().zip(&args.specs).zip(latest) {
+                if latest {
+                    let platforms = spec
+                        .name
+                        .as_ref()
+                        .and_then(|name| scoped_platforms.get(name))
+                        .map_or(spec_platforms.as_slice(), Vec::as_slice);
+                    pin_latest_version(&project, spec, platforms, &sparse_repo_data)?;
+                    resolved_specs.insert(original.clone(), spec.to_string());
+                }
+            }
+
             add_conda_specs_to_project(
                 &mut project,
                 &feature_name,
@@ -148,39 +358,127 @@ pub async fn execute(args: Args) -> miette::Result<()> {
                 spec_type,
                 args.no_install,
                 args.no_lockfile_update,
-                spec_platforms,
+                args.dry_run,
+                &spec_platforms,
+                &scoped_platforms,
+                args.pin,
+                sparse_repo_data,
             )
-            .await
+            .await?
         }
         DependencyType::PypiDependency => {
             // Parse specs as pep508_rs requirements
-            let pep508_requirements = args
-                .specs
-                .clone()
-                .into_iter()
-                .map(|input| {
-                    pep508_rs::Requirement::parse(input.as_ref(), project.root()).into_diagnostic()
+            let pep508_requirements = specs
+                .iter()
+                .enumerate()
+                .map(|(idx, input)| {
+                    let requirement = pep508_rs::Requirement::parse(input.as_ref(), project.root())
+                        .into_diagnostic();
+                    match (spec_lines.get(&idx), &args.requirement) {
+                        (Some(line), Some(path)) => requirement.wrap_err_with(|| {
+                            format!("failed to parse line {line} of '{}'", path.display())
+                        }),
+                        _ => requirement,
+                    }
                 })
                 .collect::<miette::Result<Vec<_>>>()?;
 
+            // A requirement with environment markers would be added unconditionally, so those
+            // lines of the requirements file are skipped.
+            let pep508_requirements = pep508_requirements
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, requirement)| {
+                    match (spec_lines.get(&idx), &args.requirement, &requirement.marker) {
+                        (Some(line), Some(path), Some(_)) => {
+                            eprintln!(
+                                "{}Skipped line {line} of '{}': {}, environment markers are not supported",
+                                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                                path.display(),
+                                console::style(&specs[idx]).bold(),
+                            );
+                            None
+                        }
+                        _ => Some(requirement),
+                    }
+                })
+                .collect_vec();
+
+            let format = if args.wheel_only {
+                Some(DistributionFormat::Wheel)
+            } else if args.sdist_only {
+                Some(DistributionFormat::Sdist)
+            } else {
+                None
+            };
+
             add_pypi_requirements_to_project(
                 &mut project,
                 &feature_name,
                 pep508_requirements,
-                spec_platforms,
+                &spec_platforms,
                 args.no_lockfile_update,
                 args.no_install,
+                args.dry_run,
+                args.allow_prerelease,
+                format,
             )
-            .await
+            .await?;
+            HashSet::new()
         }
-    }?;
+    };
 
-    for package in args.specs {
-        eprintln!(
-            "{}Added {}",
-            console::style(console::Emoji("✔ ", "")).green(),
-            console::style(package).bold(),
-        );
+    if args.dry_run {
+        eprintln!("The manifest and the lock-file were not updated because of `--dry-run`");
+        Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+        return Ok(());
+    }
+
+    if args.sort || project.sort_dependencies() {
+        if spec_platforms.is_empty() {
+            project
+                .manifest
+                .sort_dependencies(dependency_type, None, &feature_name)?;
+        }
+        for platform in spec_platforms
+            .iter()
+            .chain(scoped_platforms.values().flatten())
+            .unique()
+        {
+            project
+                .manifest
+                .sort_dependencies(dependency_type, Some(*platform), &feature_name)?;
+        }
+        project.save()?;
+    }
+
+    for package in specs {
+        let package = resolved_specs.remove(&package).unwrap_or(package);
+        let name = MatchSpec::from_str(&package, ParseStrictness::Strict)
+            .ok()
+            .and_then(|spec| spec.name);
+        let is_unchanged = name.as_ref().is_some_and(|name| unchanged.contains(name));
+        let scoped = name.as_ref().and_then(|name| scoped_platforms.get(name));
+        if is_unchanged {
+            eprintln!(
+                "{}{} is already present in the manifest, nothing changed",
+                console::style(console::Emoji("✔ ", "")).green(),
+                console::style(package).bold(),
+            );
+        } else if let Some(platforms) = scoped {
+            eprintln!(
+                "{}Added {} only for platform(s): {}",
+                console::style(console::Emoji("✔ ", "")).green(),
+                console::style(package).bold(),
+                console::style(platforms.iter().join(", ")).bold()
+            );
+        } else {
+            eprintln!(
+                "{}Added {}",
+                console::style(console::Emoji("✔ ", "")).green(),
+                console::style(package).bold(),
+            );
+        }
     }
 
     // Print if it is something different from host and dep
@@ -195,10 +493,10 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     }
 
     // Print something if we've added for platforms
-    if !args.platform.is_empty() {
+    if !spec_platforms.is_empty() {
         eprintln!(
             "Added these only for platform(s): {}",
-            console::style(args.platform.iter().join(", ")).bold()
+            console::style(spec_platforms.iter().join(", ")).bold()
         )
     }
 
@@ -206,6 +504,153 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     Ok(())
 }
 
+/// Returns the platforms of the feature on which every spec has at least one matching build, specs
+/// that are available on all of them are not part of the result and don't need to be scoped.
+/// Scoping a spec to a subset of the platforms requires confirmation, without it an error is
+/// returned that lists the platforms lacking builds.
+fn detect_platforms_with_builds(
+    feature_platforms: &[Platform],
+    specs: &[MatchSpec],
+    sparse_repo_data: &IndexMap<(Channel, Platform), SparseRepoData>,
+    yes: bool,
+) -> miette::Result<HashMap<PackageName, Vec<Platform>>> {
+    let mut scoped = Vec::new();
+    for spec in specs {
+        let Some(name) = &spec.name else {
+            continue;
+        };
+        let mut available = Vec::new();
+        let mut lacking = Vec::new();
+        for &platform in feature_platforms {
+            if has_builds_for_platform(spec, sparse_repo_data, platform)? {
+                available.push(platform);
+            } else {
+                lacking.push(platform);
+            }
+        }
+        if lacking.is_empty() {
+            continue;
+        }
+        if available.is_empty() {
+            miette::bail!(
+                "no builds of {spec} are available for any of the platforms of the project"
+            );
+        }
+        scoped.push((spec, name, available, lacking));
+    }
+
+    if scoped.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let confirmed = yes || {
+        let theme = ColorfulTheme {
+            active_item_style: console::Style::new().for_stderr().magenta(),
+            ..ColorfulTheme::default()
+        };
+        let lacking_builds = scoped
+            .iter()
+            .map(|(spec, _, available, lacking)| {
+                format!(
+                    "{spec} has no builds for {}, it is added only for {}.",
+                    lacking.iter().join(", "),
+                    available.iter().join(", ")
+                )
+            })
+            .join("\n");
+        dialoguer::Confirm::with_theme(&theme)
+            .with_prompt(format!("{lacking_builds}\nDo you want to continue?"))
+            .default(true)
+            .interact_opt()
+            .ok()
+            .flatten()
+            .unwrap_or(false)
+    };
+    if !confirmed {
+        miette::bail!(
+            help = "pass `--yes` to add the dependencies only for the platforms with builds",
+            "no builds are available for {}",
+            scoped
+                .iter()
+                .map(|(spec, _, _, lacking)| format!("{spec} on {}", lacking.iter().join(", ")))
+                .join(", ")
+        );
+    }
+    Ok(scoped
+        .into_iter()
+        .map(|(_, name, available, _)| (name.clone(), available))
+        .collect())
+}
+
+/// Returns true if the spec matches a record of the platform itself or of `noarch`.
+fn has_builds_for_platform(
+    spec: &MatchSpec,
+    sparse_repo_data: &IndexMap<(Channel, Platform), SparseRepoData>,
+    platform: Platform,
+) -> miette::Result<bool> {
+    let Some(name) = &spec.name else {
+        return Ok(true);
+    };
+    for ((_, subdir), repo_data) in sparse_repo_data {
+        if *subdir != platform && *subdir != Platform::NoArch {
+            continue;
+        }
+        if repo_data
+            .load_records(name)
+            .into_diagnostic()?
+            .iter()
+            .any(|record| spec.matches(&record.package_record))
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the platforms of the feature the dependencies are added to, sorted by name. A feature
+/// without platforms of its own uses the platforms of the project.
+fn feature_platforms(project: &Project, feature_name: &FeatureName) -> Vec<Platform> {
+    project
+        .manifest
+        .feature(feature_name)
+        .and_then(|feature| feature.platforms.as_ref())
+        .unwrap_or(&project.manifest.parsed.project.platforms)
+        .value
+        .iter()
+        .copied()
+        .sorted_by_key(|platform| platform.as_str())
+        .collect()
+}
+
+/// Returns the platforms for which the dependencies should be added. Target groups like `unix`
+/// are expanded to the matching platforms of the project.
+fn expand_targets(
+    args: &Args,
+    project_platforms: impl IntoIterator<Item = Platform> + Clone,
+) -> miette::Result<Vec<Platform>> {
+    if args.target.is_empty() {
+        return Ok(args.platform.clone());
+    }
+
+    let mut platforms = Vec::new();
+    for target in &args.target {
+        let expanded = target.expand(project_platforms.clone());
+        if expanded.is_empty() {
+            miette::bail!(
+                "the target '{}' does not match any of the platforms of the project",
+                target.to_string()
+            );
+        }
+        for platform in expanded {
+            if !platforms.contains(&platform) {
+                platforms.push(platform);
+            }
+        }
+    }
+    Ok(platforms)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn add_pypi_requirements_to_project(
     project: &mut Project,
     feature_name: &FeatureName,
@@ -213,21 +658,38 @@ pub async fn add_pypi_requirements_to_project(
     platforms: &[Platform],
     no_update_lockfile: bool,
     no_install: bool,
+    dry_run: bool,
+    allow_prerelease: bool,
+    format: Option<DistributionFormat>,
 ) -> miette::Result<()> {
+    // Make sure the packages exist before trying to resolve with them.
+    verify_pypi_packages_exist(project, feature_name, &requirements).await?;
+
     for requirement in &requirements {
         // TODO: Get best version
         // Add the dependency to the project
         if platforms.is_empty() {
-            project
-                .manifest
-                .add_pypi_dependency(requirement, None, feature_name)?;
+            project.manifest.add_pypi_dependency(
+                requirement,
+                None,
+                feature_name,
+                allow_prerelease,
+                format,
+            )?;
         } else {
             for platform in platforms.iter() {
-                project
-                    .manifest
-                    .add_pypi_dependency(requirement, Some(*platform), feature_name)?;
+                project.manifest.add_pypi_dependency(
+                    requirement,
+                    Some(*platform),
+                    feature_name,
+                    allow_prerelease,
+                    format,
+                )?;
             }
         }
+        if dry_run {
+            eprintln!("Would add {}", console::style(requirement).bold());
+        }
     }
     let lock_file_usage = if no_update_lockfile {
         LockFileUsage::Frozen
@@ -235,19 +697,20 @@ pub async fn add_pypi_requirements_to_project(
         LockFileUsage::Update
     };
 
-    get_up_to_date_prefix(
-        &project.default_environment(),
+    update_lock_file_and_save(
+        project,
         lock_file_usage,
         no_install,
+        dry_run,
         IndexMap::default(),
     )
-    .await?;
-
-    project.save()?;
-
-    Ok(())
+    .await
 }
 
+/// Adds the conda specs to the manifest and updates the lock-file. The specs are added for the
+/// `specs_platforms`, or without a target if there are none. A spec in `scoped_platforms` is only
+/// added for the platforms listed there instead.
+#[allow(clippy::too_many_arguments)]
 pub async fn add_conda_specs_to_project(
     project: &mut Project,
     feature_name: &FeatureName,
@@ -255,8 +718,12 @@ pub async fn add_conda_specs_to_project(
     spec_type: SpecType,
     no_install: bool,
     no_update_lockfile: bool,
+    dry_run: bool,
     specs_platforms: &[Platform],
-) -> miette::Result<()> {
+    scoped_platforms: &HashMap<PackageName, Vec<Platform>>,
+    pin: Option<PinningStrategy>,
+    sparse_repo_data: IndexMap<(Channel, Platform), SparseRepoData>,
+) -> miette::Result<HashSet<PackageName>> {
     // Split the specs into package name and version specifier
     let new_specs = specs
         .into_iter()
@@ -266,8 +733,8 @@ pub async fn add_conda_specs_to_project(
         })
         .collect::<miette::Result<HashMap<PackageName, NamelessMatchSpec>>>()?;
 
-    // Fetch the repodata for the project
-    let sparse_repo_data = project.fetch_sparse_repodata().await?;
+    // Make sure the packages exist before trying to solve with them.
+    verify_conda_packages_exist(project, &new_specs, &sparse_repo_data)?;
 
     // Determine the best version per platform
     let mut package_versions = HashMap::<PackageName, HashSet<Version>>::new();
@@ -295,19 +762,61 @@ pub async fn add_conda_specs_to_project(
         };
 
         for platform in platforms {
+            // Only the specs that are added for this platform are solved.
+            let platform_specs: HashMap<PackageName, NamelessMatchSpec> = new_specs
+                .iter()
+                .filter(|(name, _)| {
+                    scoped_platforms
+                        .get(*name)
+                        .map_or(true, |platforms| platforms.contains(&platform))
+                })
+                .map(|(name, spec)| (name.clone(), spec.clone()))
+                .collect();
+            if platform_specs.is_empty() {
+                continue;
+            }
+
             // Solve the environment with the new specs added
             let solved_versions = match determine_best_version(
                 &grouped_environment,
-                &new_specs,
+                &platform_specs,
                 spec_type,
                 &sparse_repo_data,
                 platform,
-            ) {
+            )
+            .await
+            {
                 Ok(versions) => versions,
+                Err(err) if platform_specs.len() > 1 => {
+                    // Point out the specs that likely cause the conflict, none of the specs are
+                    // added to the manifest.
+                    let conflicting = find_conflicting_specs(
+                        &grouped_environment,
+                        &platform_specs,
+                        spec_type,
+                        &sparse_repo_data,
+                        platform,
+                    )
+                    .await;
+                    let all_specs = platform_specs
+                        .iter()
+                        .sorted_by(|(a, _), (b, _)| a.as_normalized().cmp(b.as_normalized()))
+                        .map(|(name, spec)| format_spec(name, spec))
+                        .join(", ");
+                    if conflicting.is_empty() {
+                        return Err(err).wrap_err_with(|| miette::miette!(
+                            "could not solve {all_specs} together on {platform}, none of the packages were added"
+                        ));
+                    }
+                    return Err(err).wrap_err_with(|| miette::miette!(
+                        "could not solve {all_specs} together on {platform}, the conflict is likely caused by {}. None of the packages were added.",
+                        conflicting.iter().map(|spec| format!("'{spec}'")).join(", ")
+                    ));
+                }
                 Err(err) => {
                     return Err(err).wrap_err_with(|| miette::miette!(
                         "could not determine any available versions for {} on {platform}. Either the package could not be found or version constraints on other dependencies result in a conflict.",
-                        new_specs.keys().map(|s| s.as_source()).join(", ")
+                        platform_specs.keys().map(|s| s.as_source()).join(", ")
                     ));
                 }
             };
@@ -320,38 +829,69 @@ pub async fn add_conda_specs_to_project(
     }
 
     // Update the specs passed on the command line with the best available versions.
+    let targets = if specs_platforms.is_empty() {
+        vec![None]
+    } else {
+        specs_platforms.iter().copied().map(Some).collect_vec()
+    };
+    let mut unchanged = HashSet::new();
     for (name, spec) in new_specs {
-        let updated_spec = if spec.version.is_none() {
-            let mut updated_spec = spec.clone();
-            if let Some(versions_seen) = package_versions.get(&name).cloned() {
-                updated_spec.version = determine_version_constraint(&versions_seen);
-            } else {
-                updated_spec.version = determine_version_constraint(&determine_latest_versions(
-                    project,
-                    specs_platforms,
-                    &sparse_repo_data,
-                    &name,
-                )?);
-            }
-            updated_spec
-        } else {
-            spec
+        let spec_platforms = scoped_platforms
+            .get(&name)
+            .map_or(specs_platforms, Vec::as_slice);
+        let spec_targets = match scoped_platforms.get(&name) {
+            Some(platforms) => platforms.iter().copied().map(Some).collect_vec(),
+            None => targets.clone(),
         };
-        let spec = MatchSpec::from_nameless(updated_spec, Some(name));
+        let mut changed = false;
+        for platform in spec_targets {
+            // A dependency that is already present keeps its version unless a new version is
+            // specified.
+            let updated_spec = if spec.version.is_none()
+                && !project
+                    .manifest
+                    .has_dependency(&name, spec_type, platform, feature_name)
+            {
+                let mut updated_spec = spec.clone();
+                if let Some(versions_seen) = package_versions.get(&name).cloned() {
+                    updated_spec.version = determine_version_constraint(&versions_seen, pin);
+                } else {
+                    updated_spec.version = determine_version_constraint(
+                        &determine_latest_versions(
+                            project,
+                            spec_platforms,
+                            &sparse_repo_data,
+                            &name,
+                        )?,
+                        pin,
+                    );
+                }
+                updated_spec
+            } else {
+                spec.clone()
+            };
+            let spec = MatchSpec::from_nameless(updated_spec, Some(name.clone()));
 
-        // Add the dependency to the project
-        if specs_platforms.is_empty() {
-            project
-                .manifest
-                .add_dependency(&spec, spec_type, None, feature_name)?;
-        } else {
-            for platform in specs_platforms.iter() {
+            // Add the dependency to the project, an existing dependency is updated in place.
+            let added =
                 project
                     .manifest
-                    .add_dependency(&spec, spec_type, Some(*platform), feature_name)?;
+                    .add_dependency(&spec, spec_type, platform, feature_name)?;
+            if dry_run && added {
+                match platform {
+                    Some(platform) => {
+                        eprintln!("Would add {} for {platform}", console::style(&spec).bold())
+                    }
+                    None => eprintln!("Would add {}", console::style(&spec).bold()),
+                }
             }
+            changed |= added;
+        }
+        if !changed {
+            unchanged.insert(name);
         }
     }
+
     let lock_file_usage = if no_update_lockfile {
         LockFileUsage::Frozen
     } else {
@@ -359,16 +899,293 @@ pub async fn add_conda_specs_to_project(
     };
 
     // Update the prefix
-    get_up_to_date_prefix(
-        &project.default_environment(),
+    update_lock_file_and_save(
+        project,
         lock_file_usage,
         no_install,
+        dry_run,
         sparse_repo_data,
     )
     .await?;
 
-    project.save()?;
+    Ok(unchanged)
+}
 
+/// Updates the lock-file and the prefix of the default environment for the modified manifest and
+/// saves the manifest. With `dry_run` the environments are solved without writing the manifest or
+/// the lock-file, and the packages that would change in the lock-file are printed instead.
+async fn update_lock_file_and_save(
+    project: &Project,
+    lock_file_usage: LockFileUsage,
+    no_install: bool,
+    dry_run: bool,
+    sparse_repo_data: IndexMap<(Channel, Platform), SparseRepoData>,
+) -> miette::Result<()> {
+    if !dry_run {
+        get_up_to_date_prefix(
+            &project.default_environment(),
+            lock_file_usage,
+            no_install,
+            sparse_repo_data,
+        )
+        .await?;
+        return project.save();
+    }
+
+    let previous_lock_file = load_lock_file(project).await?;
+    let lock_file = project
+        .up_to_date_lock_file(UpdateLockFileOptions {
+            lock_file_usage: LockFileUsage::Update,
+            no_install: true,
+            existing_repo_data: sparse_repo_data,
+            dry_run: true,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+
+    let diff = LockFileDiff::from_lock_files(&previous_lock_file, &lock_file);
+    diff.print();
+    let (count, size) = added_conda_packages_size(&diff, &lock_file);
+    if count > 0 {
+        eprintln!(
+            "{} conda packages would be added with a total download size of {}",
+            count,
+            human_bytes(size as f64)
+        );
+    }
+    Ok(())
+}
+
+/// Returns the number of distinct conda packages that were added according to the diff and their
+/// total download size in `lock_file`. A package that is added to several environments is counted
+/// once.
+fn added_conda_packages_size(diff: &LockFileDiff, lock_file: &LockFile) -> (usize, u64) {
+    let mut sizes = HashMap::new();
+    for (name, platforms) in &diff.environments {
+        let Some(environment) = lock_file.environment(name) else {
+            continue;
+        };
+        for (platform, platform_diff) in platforms {
+            let Ok(platform) = Platform::from_str(platform) else {
+                continue;
+            };
+            let added: HashSet<&str> = platform_diff
+                .added
+                .iter()
+                .filter(|package| package.kind == "conda")
+                .map(|package| package.name.as_str())
+                .collect();
+            for package in environment.packages(platform).into_iter().flatten() {
+                if let Package::Conda(conda) = package {
+                    let record = conda.package_record();
+                    if added.contains(record.name.as_normalized()) {
+                        sizes.insert(conda.url().clone(), record.size.unwrap_or_default());
+                    }
+                }
+            }
+        }
+    }
+    (sizes.len(), sizes.values().sum())
+}
+
+/// Verifies that every spec matches at least one package in the channels of the project, to
+/// give a clear error for a misspelled package name instead of a solver error.
+fn verify_conda_packages_exist(
+    project: &Project,
+    specs: &HashMap<PackageName, NamelessMatchSpec>,
+    sparse_repo_data: &IndexMap<(Channel, Platform), SparseRepoData>,
+) -> miette::Result<()> {
+    let channels = || {
+        project
+            .channels()
+            .into_iter()
+            .map(friendly_channel_name)
+            .join(", ")
+    };
+
+    for (name, spec) in specs.iter().sorted_by_key(|(name, _)| name.as_normalized()) {
+        let mut records = Vec::new();
+        for repo_data in sparse_repo_data.values() {
+            records.extend(repo_data.load_records(name).into_diagnostic()?);
+        }
+
+        if records.is_empty() {
+            let similar = sparse_repo_data
+                .values()
+                .flat_map(|repo_data| repo_data.package_names())
+                .unique()
+                .map(|candidate| (jaro(candidate, name.as_normalized()), candidate))
+                .filter(|(similarity, _)| *similarity > 0.8)
+                .max_by(|(a, _), (b, _)| a.total_cmp(b));
+            match similar {
+                Some((_, similar)) => miette::bail!(
+                    help = format!("did you mean '{similar}'?"),
+                    "no package named '{}' found in channels [{}]",
+                    name.as_source(),
+                    channels()
+                ),
+                None => miette::bail!(
+                    "no package named '{}' found in channels [{}]",
+                    name.as_source(),
+                    channels()
+                ),
+            }
+        }
+
+        let match_spec = MatchSpec::from_nameless(spec.clone(), Some(name.clone()));
+        if !records
+            .iter()
+            .any(|record| match_spec.matches(&record.package_record))
+        {
+            miette::bail!(
+                help = format!(
+                    "available versions are {}",
+                    records
+                        .iter()
+                        .map(|record| record.package_record.version.version().clone())
+                        .unique()
+                        .sorted()
+                        .rev()
+                        .take(5)
+                        .join(", ")
+                ),
+                "no version of '{}' matching '{spec}' found in channels [{}]",
+                name.as_source(),
+                channels()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Verifies that the PyPI packages exist on one of the configured indexes, to give a clear error
+/// for a misspelled package name instead of a resolver error. Requirements on direct urls are not
+/// checked, and neither are any requirements if flat indexes (`find-links`) are configured because
+/// those cannot be queried by name.
+async fn verify_pypi_packages_exist(
+    project: &Project,
+    feature_name: &FeatureName,
+    requirements: &[pep508_rs::Requirement],
+) -> miette::Result<()> {
+    let pypi_options = project
+        .manifest
+        .feature(feature_name)
+        .and_then(|feature| feature.pypi_options())
+        .cloned()
+        .unwrap_or_default()
+        .union(&project.default_environment().pypi_options())
+        .into_diagnostic()?;
+    if pypi_options.find_links.is_some() {
+        return Ok(());
+    }
+    let indexes = pypi_options
+        .index_url
+        .iter()
+        .chain(pypi_options.extra_index_urls.iter().flatten())
+        .cloned()
+        .collect_vec();
+    let indexes = if indexes.is_empty() {
+        vec![consts::DEFAULT_PYPI_INDEX_URL.clone()]
+    } else {
+        indexes
+    };
+
+    for requirement in requirements {
+        if matches!(
+            requirement.version_or_url,
+            Some(pep508_rs::VersionOrUrl::Url(_))
+        ) {
+            continue;
+        }
+
+        let name = requirement.name.to_string();
+        let mut found = false;
+        for index in &indexes {
+            let Ok(url) = pypi_project_url(index, &name) else {
+                continue;
+            };
+            match project.authenticated_client().get(url.clone()).send().await {
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {}
+                Ok(_) => {
+                    found = true;
+                    break;
+                }
+                // Don't block adding the package when the index cannot be reached, the resolver
+                // reports the actual problem.
+                Err(err) => {
+                    tracing::warn!("failed to check whether '{name}' exists on '{url}': {err}");
+                    found = true;
+                    break;
+                }
+            }
+        }
+
+        if !found {
+            miette::bail!(
+                "no package named '{name}' found on {}",
+                indexes.iter().map(|index| format!("'{index}'")).join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns the url of the page of a project on a simple index. The index url is treated as a
+/// directory, also when it is configured without a trailing slash.
+fn pypi_project_url(index: &Url, name: &str) -> Result<Url, url::ParseError> {
+    let mut index = index.clone();
+    if !index.path().ends_with('/') {
+        index.set_path(&format!("{}/", index.path()));
+    }
+    index.join(&format!("{name}/"))
+}
+
+/// Parses a conda spec from the command line, returns whether it ends with `@latest`. A `@latest`
+/// spec can't contain a version.
+fn parse_conda_spec(spec: &str) -> miette::Result<(MatchSpec, bool)> {
+    let Some(name) = spec.strip_suffix(LATEST_SUFFIX) else {
+        return Ok((
+            MatchSpec::from_str(spec, ParseStrictness::Strict).into_diagnostic()?,
+            false,
+        ));
+    };
+    let match_spec = MatchSpec::from_str(name, ParseStrictness::Strict).into_diagnostic()?;
+    if match_spec.version.is_some() || match_spec.build.is_some() {
+        miette::bail!(
+            help = format!("use either `{name}` with a version or `@latest`"),
+            "'{spec}' pins the latest version and can't contain a version or build"
+        );
+    }
+    Ok((match_spec, true))
+}
+
+/// Pins the spec exactly to the highest version of the package that is available in the channels
+/// for the platforms, returns an error if there is none.
+fn pin_latest_version(
+    project: &Project,
+    spec: &mut MatchSpec,
+    platforms: &[Platform],
+    sparse_repo_data: &IndexMap<(Channel, Platform), SparseRepoData>,
+) -> miette::Result<()> {
+    let Some(name) = &spec.name else {
+        miette::bail!("missing package name for spec '{spec}'");
+    };
+    let Some(latest) = determine_latest_versions(project, platforms, sparse_repo_data, name)?
+        .into_iter()
+        .max()
+    else {
+        miette::bail!(
+            "no version of '{}' is available in channels [{}], nothing was added",
+            name.as_source(),
+            project
+                .channels()
+                .into_iter()
+                .map(friendly_channel_name)
+                .join(", ")
+        );
+    };
+    spec.version = Some(VersionSpec::Exact(EqualityOperator::Equals, latest));
     Ok(())
 }
 
@@ -416,9 +1233,66 @@ fn determine_latest_versions(
         .map(|record| record.package_record.version.version().clone())
         .collect_vec())
 }
+
+/// Determines which of the specs likely cause the combined solve to fail. These are the specs that
+/// cannot be solved on their own or, if each spec can be solved on its own, the specs without which
+/// the remaining specs can be solved together.
+async fn find_conflicting_specs(
+    environment: &GroupedEnvironment<'_>,
+    new_specs: &HashMap<PackageName, NamelessMatchSpec>,
+    new_specs_type: SpecType,
+    sparse_repo_data: &IndexMap<(Channel, Platform), SparseRepoData>,
+    platform: Platform,
+) -> Vec<String> {
+    let solves = |specs: HashMap<PackageName, NamelessMatchSpec>| async move {
+        determine_best_version(
+            environment,
+            &specs,
+            new_specs_type,
+            sparse_repo_data,
+            platform,
+        )
+        .await
+        .is_ok()
+    };
+
+    let sorted_specs = new_specs
+        .iter()
+        .sorted_by(|(a, _), (b, _)| a.as_normalized().cmp(b.as_normalized()))
+        .collect_vec();
+
+    let mut failing_alone = Vec::new();
+    for (name, spec) in &sorted_specs {
+        if !solves(HashMap::from([((*name).clone(), (*spec).clone())])).await {
+            failing_alone.push(format_spec(name, spec));
+        }
+    }
+    if !failing_alone.is_empty() {
+        return failing_alone;
+    }
+
+    let mut conflicting = Vec::new();
+    for (name, spec) in &sorted_specs {
+        let others = new_specs
+            .iter()
+            .filter(|(other, _)| other != name)
+            .map(|(name, spec)| (name.clone(), spec.clone()))
+            .collect();
+        if solves(others).await {
+            conflicting.push(format_spec(name, spec));
+        }
+    }
+    conflicting
+}
+
+/// Formats a spec as it was passed on the command line.
+fn format_spec(name: &PackageName, spec: &NamelessMatchSpec) -> String {
+    MatchSpec::from_nameless(spec.clone(), Some(name.clone())).to_string()
+}
+
 /// Given several specs determines the highest installable version for them.
-pub fn determine_best_version(
-    environment: &GroupedEnvironment,
+pub async fn determine_best_version(
+    environment: &GroupedEnvironment<'_>,
     new_specs: &HashMap<PackageName, NamelessMatchSpec>,
     new_specs_type: SpecType,
     sparse_repo_data: &IndexMap<(Channel, Platform), SparseRepoData>,
@@ -458,26 +1332,19 @@ pub fn determine_best_version(
     )
     .into_diagnostic()?;
 
-    // Construct a solver task to start solving.
-    let task = rattler_solve::SolverTask {
-        specs: dependencies
+    // Solve with the same restrictions as the solve that updates the lock-file, so the versions
+    // are only taken from records that can end up in the lock-file.
+    let records = resolve_conda(
+        dependencies
             .iter_specs()
             .map(|(name, spec)| MatchSpec::from_nameless(spec.clone(), Some(name.clone())))
             .collect(),
-
-        available_packages: &available_packages,
-
-        virtual_packages: environment.virtual_packages(platform),
-
-        locked_packages: vec![],
-
-        pinned_packages: vec![],
-
-        timeout: None,
-        channel_priority: ChannelPriority::Strict,
-    };
-
-    let records = resolvo::Solver.solve(task).into_diagnostic()?;
+        environment.virtual_packages(platform),
+        vec![],
+        available_packages,
+        conda_solve_options(environment, SolveStrategy::default())?,
+    )
+    .await?;
 
     // Determine the versions of the new packages
     Ok(records
@@ -493,16 +1360,56 @@ pub fn determine_best_version(
 }
 
 /// Given a set of versions, determines the best version constraint to use that captures all of them.
+///
+/// If no pinning strategy is specified the upper bound is determined by bumping the second to
+/// last segment of the highest version.
 fn determine_version_constraint<'a>(
     versions: impl IntoIterator<Item = &'a Version>,
+    pin: Option<PinningStrategy>,
 ) -> Option<VersionSpec> {
-    let (min_version, max_version) = versions.into_iter().minmax().into_option()?;
-    let lower_bound = min_version.clone();
-    let upper_bound = max_version
-        .pop_segments(1)
-        .unwrap_or_else(|| max_version.clone())
-        .bump(VersionBumpType::Last)
-        .ok()?;
+    let versions = versions.into_iter().collect_vec();
+    let (min_version, max_version) = versions.iter().copied().minmax().into_option()?;
+
+    // Truncates a version to the given number of segments.
+    let truncate = |version: &Version, segments: usize| {
+        version
+            .pop_segments(version.segment_count().saturating_sub(segments))
+            .unwrap_or_else(|| version.clone())
+    };
+
+    let (lower_bound, upper_bound) = match pin {
+        Some(PinningStrategy::NoPin) => return Some(VersionSpec::Any),
+        Some(PinningStrategy::Exact) => {
+            let exact_specs = versions
+                .into_iter()
+                .unique()
+                .sorted()
+                .map(|v| VersionSpec::Exact(EqualityOperator::Equals, v.clone()))
+                .collect_vec();
+            return if exact_specs.len() == 1 {
+                exact_specs.into_iter().next()
+            } else {
+                Some(VersionSpec::Group(LogicalOperator::Or, exact_specs))
+            };
+        }
+        Some(PinningStrategy::Minor) => (
+            min_version.clone(),
+            truncate(max_version, 2).bump(VersionBumpType::Last).ok()?,
+        ),
+        Some(PinningStrategy::Major) => (
+            truncate(min_version, 2),
+            truncate(max_version, 1).bump(VersionBumpType::Last).ok()?,
+        ),
+        None => (
+            min_version.clone(),
+            max_version
+                .pop_segments(1)
+                .unwrap_or_else(|| max_version.clone())
+                .bump(VersionBumpType::Last)
+                .ok()?,
+        ),
+    };
+
     Some(VersionSpec::Group(
         LogicalOperator::And,
         vec![
@@ -516,14 +1423,133 @@ fn determine_version_constraint<'a>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_pypi_project_url() {
+        let project_url = |index: &str| {
+            pypi_project_url(&Url::parse(index).unwrap(), "flask")
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            project_url("https://pypi.org/simple/"),
+            "https://pypi.org/simple/flask/"
+        );
+        assert_eq!(
+            project_url("https://pypi.org/simple"),
+            "https://pypi.org/simple/flask/"
+        );
+        assert_eq!(
+            project_url("https://example.com"),
+            "https://example.com/flask/"
+        );
+    }
+
     #[test]
     fn test_determine_version_constraint() {
-        insta::assert_snapshot!(determine_version_constraint(&["1.2.0".parse().unwrap()])
+        insta::assert_snapshot!(determine_version_constraint(&["1.2.0".parse().unwrap()], None)
             .unwrap()
             .to_string(), @">=1.2.0,<1.3");
 
-        insta::assert_snapshot!(determine_version_constraint(&["1.2.0".parse().unwrap(), "1.3.0".parse().unwrap()])
+        insta::assert_snapshot!(determine_version_constraint(&["1.2.0".parse().unwrap(), "1.3.0".parse().unwrap()], None)
             .unwrap()
             .to_string(), @">=1.2.0,<1.4");
     }
+
+    #[test]
+    fn test_determine_version_constraint_with_pin() {
+        let versions: Vec<Version> = vec!["1.2.3".parse().unwrap()];
+        let constraint = |pin| {
+            determine_version_constraint(&versions, Some(pin))
+                .unwrap()
+                .to_string()
+        };
+
+        insta::assert_snapshot!(constraint(PinningStrategy::Exact), @"==1.2.3");
+        insta::assert_snapshot!(constraint(PinningStrategy::Minor), @">=1.2.3,<1.3");
+        insta::assert_snapshot!(constraint(PinningStrategy::Major), @">=1.2,<2");
+        insta::assert_snapshot!(constraint(PinningStrategy::NoPin), @"*");
+
+        // The solved version must satisfy the written constraint.
+        for pin in [
+            PinningStrategy::Exact,
+            PinningStrategy::Minor,
+            PinningStrategy::Major,
+            PinningStrategy::NoPin,
+        ] {
+            let spec = determine_version_constraint(&versions, Some(pin)).unwrap();
+            assert!(spec.matches(&versions[0]), "{pin:?} does not match");
+        }
+    }
+
+    #[test]
+    fn test_parse_conda_spec() {
+        let (spec, latest) = parse_conda_spec("numpy@latest").unwrap();
+        assert!(latest);
+        assert_eq!(spec.name.unwrap().as_normalized(), "numpy");
+        assert!(spec.version.is_none());
+
+        let (spec, latest) = parse_conda_spec("numpy >=1.26").unwrap();
+        assert!(!latest);
+        assert!(spec.version.is_some());
+
+        assert!(parse_conda_spec("numpy>=1.26@latest").is_err());
+    }
+
+    #[test]
+    fn test_added_conda_packages_size() {
+        let previous = LockFile::from_str(
+            r#"
+version: 4
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64: []
+  test:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64: []
+packages: []
+"#,
+        )
+        .unwrap();
+        let current = LockFile::from_str(
+            r#"
+version: 4
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  test:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+packages:
+- kind: conda
+  name: libzlib
+  version: 1.2.13
+  build: hd590300_5
+  build_number: 5
+  subdir: linux-64
+  url: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  sha256: 370c7c5893b737596fd6ca0d9190c9715d89d888b8c88537ae1ef168c25e82e4
+  md5: f36c115f1ee199da648e0597ec2047ad
+  size: 61588
+"#,
+        )
+        .unwrap();
+
+        // The package that is added to both environments is counted once.
+        let diff = LockFileDiff::from_lock_files(&previous, &current);
+        assert_eq!(added_conda_packages_size(&diff, &current), (1, 61588));
+        let diff = LockFileDiff::from_lock_files(&current, &current);
+        assert_eq!(added_conda_packages_size(&diff, &current), (0, 0));
+    }
 }
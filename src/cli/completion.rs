@@ -19,6 +19,7 @@ pub(crate) fn execute(args: CompletionCommand) -> miette::Result<()> {
     let script = match clap_shell {
         clap_complete::Shell::Bash => replace_bash_completion(&script),
         clap_complete::Shell::Zsh => replace_zsh_completion(&script),
+        clap_complete::Shell::Fish => append_fish_completion(&script),
         _ => Cow::Owned(script),
     };
 
@@ -48,11 +49,17 @@ fn replace_bash_completion(script: &str) -> Cow<str> {
                COMPREPLY=( $$(compgen -W "$${opts}" -- "$${cur}") )
                return 0
             elif [[ $${COMP_CWORD} -eq 2 ]]; then
-               local tasks=$$(pixi task list --summary 2> /dev/null)
+               local tasks=$$(pixi run --print-completions 2> /dev/null)
                if [[ $$? -eq 0 ]]; then
                    COMPREPLY=( $$(compgen -W "$${tasks}" -- "$${cur}") )
                    return 0
                fi
+            elif [[ $${COMP_CWORD} -gt 2 ]]; then
+               local choices=$$(pixi run --print-completions "$${COMP_WORDS[@]:2:$$((COMP_CWORD - 2))}" 2> /dev/null)
+               if [[ -n "$${choices}" ]]; then
+                   COMPREPLY=( $$(compgen -W "$${choices}" -- "$${cur}") )
+                   return 0
+               fi
             fi"#;
     let re = Regex::new(pattern).unwrap();
     re.replace(script, replacement)
@@ -64,8 +71,16 @@ fn replace_zsh_completion(script: &str) -> Cow<str> {
     // Adds tab completion to the pixi run command.
     // NOTE THIS IS FORMATTED BY HAND
     let zsh_replacement = r#"$1
+if (( CURRENT > 2 )); then
+    local choices
+    choices=("$${(@f)$$(pixi run --print-completions $${words[2,CURRENT-1]} 2> /dev/null)}")
+    if [[ -n "$$choices" ]]; then
+        _values 'argument' "$${choices[@]}"
+        return
+    fi
+fi
 local tasks
-tasks=("$${(@s/ /)$$(pixi task list --summary 2> /dev/null)}")
+tasks=("$${(@f)$$(pixi run --print-completions 2> /dev/null)}")
 
 if [[ -n "$$tasks" ]]; then
     _values 'task' "$${tasks[@]}"
@@ -78,6 +93,15 @@ $2::task"#;
     re.replace(script, zsh_replacement)
 }
 
+/// Add the completion of the tasks and of the choices of their arguments to the fish completion
+/// script, `pixi run --print-completions` receives the words that were typed after `pixi run`.
+fn append_fish_completion(script: &str) -> Cow<str> {
+    // NOTE THIS IS FORMATTED BY HAND
+    let addition = r#"complete -c pixi -n "__fish_seen_subcommand_from run" -f -a "(pixi run --print-completions (commandline -opc)[3..] 2> /dev/null)"
+"#;
+    Cow::Owned(format!("{script}{addition}"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +194,16 @@ _arguments "${_arguments_options[@]}" \
         insta::assert_snapshot!(result);
     }
 
+    #[test]
+    pub fn test_fish_completion() {
+        let script = get_completion_script(clap_complete::Shell::Fish);
+        let result = append_fish_completion(&script);
+        assert!(result.starts_with(&script));
+        assert!(result.ends_with(
+            "-a \"(pixi run --print-completions (commandline -opc)[3..] 2> /dev/null)\"\n"
+        ));
+    }
+
     #[test]
     pub fn test_bash_completion_working_regex() {
         // Generate the original completion script.
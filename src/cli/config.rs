@@ -0,0 +1,547 @@
+use crate::config::{get_cache_dir, home_path, Config};
+use crate::{consts, Project};
+use clap::Parser;
+use miette::{IntoDiagnostic, WrapErr};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, Item, Table, TableLike, Value};
+use url::Url;
+
+/// Read and write the global and project configuration.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The subcommand to execute
+    #[clap(subcommand)]
+    pub command: Command,
+
+    /// The path to 'pixi.toml' or 'pyproject.toml'
+    #[arg(long, global = true)]
+    pub manifest_path: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub enum Command {
+    /// Print the effective value of a configuration key
+    Get(GetArgs),
+
+    /// Set a configuration key, or unset it when no value is given
+    Set(SetArgs),
+
+    /// List the effective value of every configuration key and where it is set
+    #[clap(visible_alias = "ls")]
+    List,
+}
+
+#[derive(Parser, Debug)]
+pub struct GetArgs {
+    /// The configuration key, e.g. `default-channels` or `concurrency.solves`
+    pub key: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetArgs {
+    /// The configuration key, e.g. `default-channels` or `concurrency.solves`
+    pub key: String,
+
+    /// The value of the key, lists are comma separated. The key is removed when no value is given.
+    pub value: Option<String>,
+
+    /// Write to the global configuration file instead of the one of the project
+    #[arg(long, short)]
+    pub global: bool,
+}
+
+/// The kind of value a configuration key holds, used to parse the value given on the command
+/// line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Bool,
+    Integer,
+    String,
+    Url,
+    List,
+}
+
+/// A configuration key that can be read and written with `pixi config`.
+#[derive(Debug)]
+struct ConfigKey {
+    name: &'static str,
+    kind: ValueKind,
+    /// Whether the key is only read from the global configuration files.
+    global_only: bool,
+}
+
+const fn key(name: &'static str, kind: ValueKind) -> ConfigKey {
+    ConfigKey {
+        name,
+        kind,
+        global_only: false,
+    }
+}
+
+const KEYS: &[ConfigKey] = &[
+    key("default-channels", ValueKind::List),
+    key("change-ps1", ValueKind::Bool),
+    key("tls-no-verify", ValueKind::Bool),
+    key("authentication-override-file", ValueKind::String),
+    ConfigKey {
+        name: "cache-dir",
+        kind: ValueKind::String,
+        global_only: true,
+    },
+    key("proxy", ValueKind::Url),
+    key("concurrency.solves", ValueKind::Integer),
+    key("repodata-config.disable-jlap", ValueKind::Bool),
+    key("repodata-config.disable-bzip2", ValueKind::Bool),
+    key("repodata-config.disable-zstd", ValueKind::Bool),
+    key("pypi-config.index-url", ValueKind::Url),
+    key("pypi-config.extra-index-urls", ValueKind::List),
+    key("pypi-config.keyring-provider", ValueKind::String),
+];
+
+/// Where the effective value of a configuration key comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    Default,
+    File(PathBuf),
+    Manifest(PathBuf),
+    Env(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Manifest(path) => write!(f, "manifest {}", path.display()),
+            Source::Env(var) => write!(f, "environment variable {}", var),
+        }
+    }
+}
+
+/// A configuration file that was read, in the order of precedence.
+struct Layer {
+    path: PathBuf,
+    document: DocumentMut,
+    global: bool,
+}
+
+pub fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::Get(get_args) => {
+            let key = find_key(&get_args.key)?;
+            let project = load_project(args.manifest_path.as_deref())?;
+            let layers = load_layers(project.as_ref())?;
+            match effective_value(key, &layers, project.as_ref()) {
+                Some((value, _)) => println!("{}", display_value(&value)),
+                None => eprintln!("`{}` is not set", key.name),
+            }
+        }
+        Command::Set(set_args) => {
+            let key = find_key(&set_args.key)?;
+            let path = if set_args.global {
+                home_path()
+                    .ok_or_else(|| miette::miette!("could not determine the pixi home directory"))?
+                    .join(consts::CONFIG_FILE)
+            } else {
+                if key.global_only {
+                    miette::bail!(
+                        help = "use `--global` to set it in the global configuration",
+                        "`{}` can only be set in the global configuration",
+                        key.name
+                    );
+                }
+                Project::load_or_else_discover(args.manifest_path.as_deref())
+                    .wrap_err(
+                        "use `--global` to change the global configuration outside of a project",
+                    )?
+                    .pixi_dir()
+                    .join(consts::CONFIG_FILE)
+            };
+
+            let mut document = if path.exists() {
+                read_document(&path)?
+            } else {
+                DocumentMut::new()
+            };
+            let value = set_args
+                .value
+                .as_deref()
+                .map(|value| parse_value(key, value))
+                .transpose()?;
+            let message = match &value {
+                Some(value) => format!("Set `{}` to {}", key.name, display_value(value)),
+                None => format!("Unset `{}`", key.name),
+            };
+            set_value(&mut document, key.name, value);
+
+            // Make sure the configuration can still be read before writing it.
+            let content = document.to_string();
+            Config::from_toml(&content, &path)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+            }
+            std::fs::write(&path, content)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+
+            eprintln!(
+                "{}{} in {}",
+                console::style(console::Emoji("✔ ", "")).green(),
+                message,
+                path.display()
+            );
+        }
+        Command::List => {
+            let project = load_project(args.manifest_path.as_deref())?;
+            let layers = load_layers(project.as_ref())?;
+            for key in KEYS {
+                match effective_value(key, &layers, project.as_ref()) {
+                    Some((value, source)) => println!(
+                        "{} = {} {}",
+                        console::style(key.name).bold(),
+                        display_value(&value),
+                        console::style(format!("({source})")).dim()
+                    ),
+                    None => println!(
+                        "{} {}",
+                        console::style(key.name).bold(),
+                        console::style("(not set)").dim()
+                    ),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the configuration key, the snake_case spelling of older versions of pixi is accepted as
+/// well.
+fn find_key(name: &str) -> miette::Result<&'static ConfigKey> {
+    let name = name.replace('_', "-");
+    KEYS.iter().find(|key| key.name == name).ok_or_else(|| {
+        miette::miette!(
+            help = format!(
+                "the supported keys are: {}",
+                KEYS.iter()
+                    .map(|key| key.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            "unknown configuration key `{}`",
+            name
+        )
+    })
+}
+
+/// Loads the project when a manifest path is given or when the current directory is part of a
+/// project.
+fn load_project(manifest_path: Option<&Path>) -> miette::Result<Option<Project>> {
+    match manifest_path {
+        Some(path) => Project::load(path).map(Some),
+        None => Ok(Project::discover().ok()),
+    }
+}
+
+fn read_document(path: &Path) -> miette::Result<DocumentMut> {
+    std::fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read {}", path.display()))?
+        .parse::<DocumentMut>()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse {}", path.display()))
+}
+
+/// Reads the global configuration files and the configuration file of the project, in the order
+/// in which they are merged.
+fn load_layers(project: Option<&Project>) -> miette::Result<Vec<Layer>> {
+    let global = Config::global_config_locations()
+        .into_iter()
+        .map(|path| (path, true));
+    let local = project.map(|project| (project.pixi_dir().join(consts::CONFIG_FILE), false));
+
+    global
+        .chain(local)
+        .filter(|(path, _)| path.is_file())
+        .map(|(path, global)| {
+            Ok(Layer {
+                document: read_document(&path)?,
+                path,
+                global,
+            })
+        })
+        .collect()
+}
+
+/// Returns the effective value of the key together with where it comes from. Environment
+/// variables and the manifest take precedence over the configuration files.
+fn effective_value(
+    key: &ConfigKey,
+    layers: &[Layer],
+    project: Option<&Project>,
+) -> Option<(Value, Source)> {
+    let env_vars: &[&'static str] = match key.name {
+        "cache-dir" => &["PIXI_CACHE_DIR", "RATTLER_CACHE_DIR"],
+        "authentication-override-file" => &["RATTLER_AUTH_FILE"],
+        _ => &[],
+    };
+    if let Some((var, value)) = env_vars
+        .iter()
+        .find_map(|var| std::env::var(var).ok().map(|value| (*var, value)))
+    {
+        return Some((Value::from(value), Source::Env(var)));
+    }
+
+    if key.name == "proxy" {
+        if let Some((proxy, project)) = project.and_then(|p| {
+            p.manifest
+                .parsed
+                .project
+                .proxy
+                .as_ref()
+                .map(|proxy| (proxy, p))
+        }) {
+            return Some((
+                Value::from(proxy.as_str()),
+                Source::Manifest(project.manifest_path()),
+            ));
+        }
+    }
+
+    if let Some((value, path)) = layers
+        .iter()
+        .rev()
+        .filter(|layer| layer.global || !key.global_only)
+        .find_map(|layer| {
+            lookup(layer.document.as_table(), key.name)
+                .and_then(Item::as_value)
+                .map(|value| (value.clone(), layer.path.clone()))
+        })
+    {
+        return Some((value, Source::File(path)));
+    }
+
+    default_value(key).map(|value| (value, Source::Default))
+}
+
+/// The value that is used when the key is not set.
+fn default_value(key: &ConfigKey) -> Option<Value> {
+    match key.name {
+        "default-channels" => Some(Value::Array(
+            consts::DEFAULT_CHANNELS.iter().copied().collect(),
+        )),
+        "change-ps1" => Some(Value::from(true)),
+        "tls-no-verify"
+        | "repodata-config.disable-jlap"
+        | "repodata-config.disable-bzip2"
+        | "repodata-config.disable-zstd" => Some(Value::from(false)),
+        "cache-dir" => get_cache_dir()
+            .ok()
+            .map(|dir| Value::from(dir.display().to_string())),
+        "concurrency.solves" => Some(Value::from(Config::default().max_concurrent_solves() as i64)),
+        "pypi-config.keyring-provider" => Some(Value::from("disabled")),
+        _ => None,
+    }
+}
+
+/// Parses a value given on the command line for the key.
+fn parse_value(key: &ConfigKey, value: &str) -> miette::Result<Value> {
+    let invalid = || miette::miette!("invalid value `{}` for `{}`", value, key.name);
+    let value = match key.kind {
+        ValueKind::Bool => Value::from(value.parse::<bool>().map_err(|_| invalid())?),
+        ValueKind::Integer => Value::from(
+            value
+                .parse::<i64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(invalid)?,
+        ),
+        ValueKind::String => Value::from(value),
+        ValueKind::Url => {
+            Url::parse(value).map_err(|_| invalid())?;
+            Value::from(value)
+        }
+        ValueKind::List if value.trim_start().starts_with('[') => value
+            .parse::<Value>()
+            .ok()
+            .filter(Value::is_array)
+            .ok_or_else(invalid)?,
+        ValueKind::List => Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect::<Array>(),
+        ),
+    };
+    Ok(value)
+}
+
+fn display_value(value: &Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string()
+}
+
+/// The kebab-case spelling of a key and the snake_case spelling of older versions of pixi.
+fn spellings(segment: &str) -> [String; 2] {
+    [segment.to_string(), segment.replace('-', "_")]
+}
+
+/// Returns the item of a dotted key.
+fn lookup<'a>(table: &'a dyn TableLike, key: &str) -> Option<&'a Item> {
+    let (parents, leaf) = match key.rsplit_once('.') {
+        Some((parents, leaf)) => (parents.split('.').collect(), leaf),
+        None => (Vec::new(), key),
+    };
+    let mut table = table;
+    for segment in parents {
+        table = spellings(segment)
+            .iter()
+            .find_map(|name| table.get(name).and_then(Item::as_table_like))?;
+    }
+    spellings(leaf).iter().find_map(|name| table.get(name))
+}
+
+/// Sets the dotted key to the value, or removes it when the value is `None`. A snake_case
+/// spelling of the key is replaced.
+fn set_value(document: &mut DocumentMut, key: &str, value: Option<Value>) {
+    let (parents, leaf) = match key.rsplit_once('.') {
+        Some((parents, leaf)) => (parents.split('.').collect(), leaf),
+        None => (Vec::new(), key),
+    };
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    for segment in parents {
+        let name = spellings(segment)
+            .into_iter()
+            .find(|name| table.get(name).is_some_and(Item::is_table_like))
+            .unwrap_or_else(|| segment.to_string());
+        if !table.get(&name).is_some_and(Item::is_table_like) {
+            if value.is_none() {
+                return;
+            }
+            table.insert(&name, Item::Table(Table::new()));
+        }
+        table = table
+            .get_mut(&name)
+            .and_then(Item::as_table_like_mut)
+            .expect("the table was inserted");
+    }
+
+    for name in spellings(leaf) {
+        table.remove(&name);
+    }
+    if let Some(value) = value {
+        table.insert(leaf, Item::Value(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_value() {
+        let mut document = r#"default_channels = ["conda-forge"]
+
+[repodata_config]
+disable_jlap = true
+"#
+        .parse::<DocumentMut>()
+        .unwrap();
+
+        let key = find_key("default-channels").unwrap();
+        set_value(
+            &mut document,
+            key.name,
+            Some(parse_value(key, "conda-forge, bioconda").unwrap()),
+        );
+        let key = find_key("repodata_config.disable_jlap").unwrap();
+        set_value(
+            &mut document,
+            key.name,
+            Some(parse_value(key, "false").unwrap()),
+        );
+        let key = find_key("concurrency.solves").unwrap();
+        set_value(
+            &mut document,
+            key.name,
+            Some(parse_value(key, "2").unwrap()),
+        );
+        set_value(&mut document, "pypi-config.index-url", None);
+
+        let content = document.to_string();
+        let config = Config::from_toml(&content, Path::new("config.toml")).unwrap();
+        assert_eq!(config.default_channels, vec!["conda-forge", "bioconda"]);
+        assert_eq!(
+            config.repodata_config.and_then(|c| c.disable_jlap),
+            Some(false)
+        );
+        assert_eq!(config.max_concurrent_solves(), 2);
+        assert!(!content.contains("default_channels"));
+        assert!(!content.contains("pypi-config"));
+
+        assert_eq!(
+            lookup(document.as_table(), "concurrency.solves").and_then(Item::as_integer),
+            Some(2)
+        );
+        set_value(&mut document, "concurrency.solves", None);
+        assert!(lookup(document.as_table(), "concurrency.solves").is_none());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let key = find_key("change-ps1").unwrap();
+        assert_eq!(parse_value(key, "false").unwrap().as_bool(), Some(false));
+        assert!(parse_value(key, "no").is_err());
+
+        let key = find_key("concurrency.solves").unwrap();
+        assert!(parse_value(key, "0").is_err());
+
+        let key = find_key("proxy").unwrap();
+        assert!(parse_value(key, "not a url").is_err());
+
+        let key = find_key("default-channels").unwrap();
+        let value = parse_value(key, r#"["conda-forge", "bioconda"]"#).unwrap();
+        assert_eq!(value.as_array().map(Array::len), Some(2));
+
+        assert!(find_key("unknown").is_err());
+    }
+
+    #[test]
+    fn test_effective_value() {
+        let layer = |path: &str, content: &str, global: bool| Layer {
+            path: PathBuf::from(path),
+            document: content.parse().unwrap(),
+            global,
+        };
+        let layers = vec![
+            layer(
+                "global.toml",
+                "change-ps1 = false\ncache-dir = \"/global\"",
+                true,
+            ),
+            layer(
+                "local.toml",
+                "change-ps1 = true\ncache-dir = \"/local\"",
+                false,
+            ),
+        ];
+
+        let (value, source) =
+            effective_value(find_key("change-ps1").unwrap(), &layers, None).unwrap();
+        assert_eq!(value.as_bool(), Some(true));
+        assert_eq!(source, Source::File(PathBuf::from("local.toml")));
+
+        let (_, source) =
+            effective_value(find_key("tls-no-verify").unwrap(), &layers, None).unwrap();
+        assert_eq!(source, Source::Default);
+
+        assert!(
+            effective_value(find_key("pypi-config.index-url").unwrap(), &layers, None).is_none()
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+
+use crate::activation::run_activation;
+use crate::cli::global::common::bin_dir;
+use crate::config;
+use crate::lock_file::{load_lock_file, OutdatedEnvironments};
+use crate::prefix::Prefix;
+use crate::project::has_features::HasFeatures;
+use crate::Project;
+
+/// Check the health of the project and the pixi installation.
+///
+/// Runs a number of checks and reports whether each of them passed, needs attention or failed,
+/// together with a hint on how to fix it.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The path to 'pixi.toml' or 'pyproject.toml'
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+}
+
+/// The outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// The result of a single check that is reported to the user.
+#[derive(Debug)]
+struct CheckResult {
+    name: &'static str,
+    status: Status,
+    message: String,
+    hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+impl Display for CheckResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let icon = match self.status {
+            Status::Pass => console::style(console::Emoji("✔ ", "")).green(),
+            Status::Warn => console::style(console::Emoji("⚠️ ", "")).yellow(),
+            Status::Fail => console::style(console::Emoji("✘ ", "")).red(),
+        };
+        write!(
+            f,
+            "{icon}{}: {}",
+            console::style(self.name).bold(),
+            self.message
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n    {} {hint}", console::style("hint:").dim())?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let mut results = Vec::new();
+
+    match Project::load_or_else_discover(args.manifest_path.as_deref()) {
+        Ok(project) => {
+            results.extend(check_project(&project).await?);
+        }
+        Err(err) => results.push(CheckResult::warn(
+            "project",
+            format!("no project found: {err}"),
+            "run `pixi doctor` from within a project, or pass `--manifest-path`",
+        )),
+    }
+    results.push(check_cache_dir());
+    results.extend(check_path_shadowing());
+
+    for result in &results {
+        eprintln!("{result}");
+    }
+
+    let failed = results.iter().filter(|r| r.status == Status::Fail).count();
+    let warned = results.iter().filter(|r| r.status == Status::Warn).count();
+    if failed > 0 {
+        miette::bail!("{failed} checks failed and {warned} checks need attention");
+    }
+
+    eprintln!(
+        "\n{}{} checks passed, {} need attention",
+        console::style(console::Emoji("✔ ", "")).green(),
+        results.len() - warned,
+        warned
+    );
+    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Checks the lock-file and the environments of the project.
+async fn check_project(project: &Project) -> miette::Result<Vec<CheckResult>> {
+    let mut results = Vec::new();
+
+    // Check that the lock-file is up-to-date with the manifest.
+    let lock_file = load_lock_file(project).await?;
+    if !project.has_lock_file() {
+        results.push(CheckResult::fail(
+            "lock-file",
+            format!(
+                "the lock-file '{}' does not exist",
+                project.lock_file_path().display()
+            ),
+            "run `pixi install` to create it",
+        ));
+    } else {
+        let outdated = OutdatedEnvironments::from_project_and_lock_file(project, &lock_file);
+        let outdated_environments = outdated
+            .conda
+            .keys()
+            .chain(outdated.pypi.keys())
+            .map(|env| env.name().as_str().to_string())
+            .unique()
+            .sorted()
+            .collect_vec();
+        if outdated_environments.is_empty() {
+            results.push(CheckResult::pass(
+                "lock-file",
+                "the lock-file is up-to-date with the manifest",
+            ));
+        } else {
+            results.push(CheckResult::fail(
+                "lock-file",
+                format!(
+                    "the lock-file is out of date for: {}",
+                    outdated_environments.join(", ")
+                ),
+                "run `pixi install` or `pixi update` to update it",
+            ));
+        }
+    }
+
+    for environment in project.environments() {
+        let name = environment.name().as_str().to_string();
+        let prefix = Prefix::new(environment.dir());
+
+        // Check that the prefix of the environment exists.
+        if !prefix.root().is_dir() {
+            results.push(CheckResult::warn(
+                "environment",
+                format!("'{name}' is not installed at '{}'", prefix.root().display()),
+                format!("run `pixi install -e {name}` to install it"),
+            ));
+            continue;
+        }
+        results.push(CheckResult::pass(
+            "environment",
+            format!("'{name}' is installed at '{}'", prefix.root().display()),
+        ));
+
+        // Check for packages in the prefix that are not part of the lock-file.
+        let platform = environment.best_platform();
+        if !environment.platforms().contains(&platform) {
+            continue;
+        }
+        let locked_names: HashSet<String> = lock_file
+            .environment(&name)
+            .map(|env| env.conda_repodata_records_for_platform(platform))
+            .transpose()
+            .into_diagnostic()?
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| r.package_record.name.as_normalized().to_string())
+            .collect();
+        let orphaned = prefix
+            .find_installed_packages(None)
+            .await?
+            .into_iter()
+            .map(|r| {
+                r.repodata_record
+                    .package_record
+                    .name
+                    .as_normalized()
+                    .to_string()
+            })
+            .filter(|name| !locked_names.contains(name))
+            .sorted()
+            .collect_vec();
+        if orphaned.is_empty() {
+            results.push(CheckResult::pass(
+                "packages",
+                format!("'{name}' only contains locked packages"),
+            ));
+        } else {
+            results.push(CheckResult::warn(
+                "packages",
+                format!(
+                    "'{name}' contains packages that are not locked: {}",
+                    orphaned.join(", ")
+                ),
+                format!("run `pixi install -e {name}` to remove them"),
+            ));
+        }
+
+        // Check that the activation scripts of the environment run.
+        match run_activation(&environment).await {
+            Ok(_) => results.push(CheckResult::pass(
+                "activation",
+                format!("the activation of '{name}' succeeds"),
+            )),
+            Err(err) => results.push(CheckResult::fail(
+                "activation",
+                format!("the activation of '{name}' fails: {err}"),
+                format!(
+                    "fix the activation scripts in the `activation` table of the manifest or in '{}'",
+                    prefix.root().join("etc/conda/activate.d").display()
+                ),
+            )),
+        }
+    }
+
+    Ok(results)
+}
+
+/// Checks that the package cache directory can be written to.
+fn check_cache_dir() -> CheckResult {
+    let cache_dir = match config::get_cache_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            return CheckResult::fail(
+                "cache",
+                format!("could not determine the cache directory: {err}"),
+                "set `PIXI_CACHE_DIR` to a writable directory",
+            )
+        }
+    };
+
+    match probe_writable(&cache_dir) {
+        Ok(()) => CheckResult::pass(
+            "cache",
+            format!("the cache at '{}' is accessible", cache_dir.display()),
+        ),
+        Err(err) => CheckResult::fail(
+            "cache",
+            format!(
+                "the cache at '{}' is not writable: {err}",
+                cache_dir.display()
+            ),
+            "fix the permissions of the directory or set `PIXI_CACHE_DIR` to a writable directory",
+        ),
+    }
+}
+
+/// Creates the directory if needed and writes and removes a file in it.
+fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".pixi-doctor");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
+
+/// Checks that the binaries installed with `pixi global install` are not shadowed by other
+/// binaries with the same name earlier in the `PATH`.
+fn check_path_shadowing() -> Option<CheckResult> {
+    let bin_dir = bin_dir()?;
+    let binaries = std::fs::read_dir(&bin_dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name())
+        .sorted()
+        .collect_vec();
+    if binaries.is_empty() {
+        return None;
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let path_entries = std::env::split_paths(&path).collect_vec();
+    if !path_entries.iter().any(|entry| entry == &bin_dir) {
+        return Some(CheckResult::warn(
+            "path",
+            format!("'{}' is not on the PATH", bin_dir.display()),
+            format!(
+                "add '{}' to the PATH to use globally installed binaries",
+                bin_dir.display()
+            ),
+        ));
+    }
+
+    let shadowed = binaries
+        .iter()
+        .filter_map(|binary| {
+            let found = path_entries
+                .iter()
+                .map(|entry| entry.join(binary))
+                .find(|candidate| candidate.is_file())?;
+            (found.parent() != Some(bin_dir.as_path())).then(|| {
+                format!(
+                    "'{}' resolves to '{}'",
+                    binary.to_string_lossy(),
+                    found.display()
+                )
+            })
+        })
+        .collect_vec();
+    if shadowed.is_empty() {
+        Some(CheckResult::pass(
+            "path",
+            "globally installed binaries are not shadowed",
+        ))
+    } else {
+        Some(CheckResult::warn(
+            "path",
+            format!(
+                "globally installed binaries are shadowed: {}",
+                shadowed.join(", ")
+            ),
+            format!(
+                "move '{}' before the other directories in the PATH",
+                bin_dir.display()
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_conda_types::Platform;
+
+    /// Returns the status of every check with the given name.
+    fn statuses(results: &[CheckResult], name: &str) -> Vec<Status> {
+        results
+            .iter()
+            .filter(|result| result.name == name)
+            .map(|result| result.status)
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join(crate::consts::PROJECT_MANIFEST);
+        std::fs::write(
+            &manifest_path,
+            format!(
+                r#"
+                [project]
+                name = "foo"
+                channels = []
+                platforms = ["{}"]
+
+                [activation]
+                scripts = ["activate.sh"]
+                "#,
+                Platform::current()
+            ),
+        )
+        .unwrap();
+        let project = Project::load(&manifest_path).unwrap();
+
+        // Without a lock-file and a prefix.
+        let results = check_project(&project).await.unwrap();
+        assert_eq!(statuses(&results, "lock-file"), vec![Status::Fail]);
+        assert_eq!(statuses(&results, "environment"), vec![Status::Warn]);
+        assert!(statuses(&results, "activation").is_empty());
+
+        // An installed environment whose activation script fails.
+        let prefix = project.default_environment().dir();
+        std::fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+        std::fs::write(dir.path().join("activate.sh"), "exit 1\n").unwrap();
+        let results = check_project(&project).await.unwrap();
+        assert_eq!(statuses(&results, "environment"), vec![Status::Pass]);
+        assert_eq!(statuses(&results, "packages"), vec![Status::Pass]);
+        assert_eq!(statuses(&results, "activation"), vec![Status::Fail]);
+
+        std::fs::write(dir.path().join("activate.sh"), "export FOO=bar\n").unwrap();
+        let results = check_project(&project).await.unwrap();
+        assert_eq!(statuses(&results, "activation"), vec![Status::Pass]);
+    }
+
+    #[test]
+    fn test_probe_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        probe_writable(&cache_dir).unwrap();
+        assert!(cache_dir.is_dir());
+        assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 0);
+    }
+}
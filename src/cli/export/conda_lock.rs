@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::Parser;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use pep508_rs::VersionOrUrl;
+use rattler_conda_types::Platform;
+use rattler_digest::{compute_bytes_digest, Sha256};
+use rattler_lock::{LockFile, Package, PackageHashes, UrlOrPath};
+use serde::Serialize;
+
+use crate::lock_file::load_lock_file;
+use crate::Project;
+
+/// Export the lock-file of an environment in the conda-lock v1 format (`conda-lock.yml`).
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The path to 'pixi.toml' or 'pyproject.toml'
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// The environment to export, defaults to the default environment.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+
+    /// The platform(s) to export, defaults to all platforms of the environment.
+    #[arg(long, short)]
+    pub platform: Vec<Platform>,
+
+    /// The path of the file to write.
+    #[arg(long, short, default_value = "conda-lock.yml")]
+    pub output: PathBuf,
+}
+
+/// The root of a conda-lock v1 lock-file.
+#[derive(Debug, Serialize)]
+struct CondaLock {
+    version: u32,
+    metadata: CondaLockMetadata,
+    package: Vec<CondaLockPackage>,
+}
+
+#[derive(Debug, Serialize)]
+struct CondaLockMetadata {
+    content_hash: BTreeMap<String, String>,
+    channels: Vec<CondaLockChannel>,
+    platforms: Vec<String>,
+    sources: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CondaLockChannel {
+    url: String,
+    used_env_vars: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CondaLockPackage {
+    name: String,
+    version: String,
+    manager: &'static str,
+    platform: String,
+    dependencies: IndexMap<String, String>,
+    url: String,
+    hash: BTreeMap<&'static str, String>,
+    category: &'static str,
+    optional: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+
+    if !project.has_lock_file() {
+        miette::bail!(
+            "the lock-file '{}' does not exist, run `pixi install` to create it",
+            project.lock_file_path().display()
+        );
+    }
+    let lock_file = load_lock_file(&project).await?;
+
+    let conda_lock = to_conda_lock(
+        &lock_file,
+        environment.name().as_str(),
+        &args.platform,
+        project.manifest_path().file_name().map_or_else(
+            || String::from(crate::consts::PROJECT_MANIFEST),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+    )?;
+
+    let contents = serde_yaml::to_string(&conda_lock).into_diagnostic()?;
+    std::fs::write(&args.output, contents).into_diagnostic()?;
+
+    eprintln!(
+        "{}Exported the lock-file of {} to {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        environment.name().fancy_display(),
+        args.output.display()
+    );
+
+    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Converts the packages of an environment in the lock-file to the conda-lock v1 format. If no
+/// platforms are specified all platforms of the environment are exported.
+fn to_conda_lock(
+    lock_file: &LockFile,
+    environment_name: &str,
+    platforms: &[Platform],
+    source: String,
+) -> miette::Result<CondaLock> {
+    let environment = lock_file.environment(environment_name).ok_or_else(|| {
+        miette::miette!("the lock-file does not contain the environment '{environment_name}'")
+    })?;
+
+    let platforms = if platforms.is_empty() {
+        environment.platforms().sorted().collect_vec()
+    } else {
+        platforms.to_vec()
+    };
+
+    let mut packages = Vec::new();
+    let mut content_hash = BTreeMap::new();
+    for platform in &platforms {
+        let mut urls = Vec::new();
+        for package in environment.packages(*platform).into_iter().flatten() {
+            let package = match package {
+                Package::Conda(conda) => {
+                    let record = conda.package_record();
+                    let mut hash = BTreeMap::new();
+                    if let Some(md5) = record.md5 {
+                        hash.insert("md5", format!("{md5:x}"));
+                    }
+                    if let Some(sha256) = record.sha256 {
+                        hash.insert("sha256", format!("{sha256:x}"));
+                    }
+                    CondaLockPackage {
+                        name: record.name.as_normalized().to_string(),
+                        version: record.version.to_string(),
+                        manager: "conda",
+                        platform: platform.to_string(),
+                        dependencies: record
+                            .depends
+                            .iter()
+                            .map(|depend| match depend.split_once(' ') {
+                                Some((name, spec)) => (name.to_string(), spec.trim().to_string()),
+                                None => (depend.clone(), String::from("*")),
+                            })
+                            .collect(),
+                        url: conda.url().to_string(),
+                        hash,
+                        category: "main",
+                        optional: false,
+                    }
+                }
+                Package::Pypi(pypi) => {
+                    let data = &pypi.data().package;
+                    let UrlOrPath::Url(url) = &data.url_or_path else {
+                        tracing::warn!(
+                            "skipping pypi package '{}' because it is not installed from a url",
+                            data.name
+                        );
+                        continue;
+                    };
+                    let mut hash = BTreeMap::new();
+                    match &data.hash {
+                        Some(PackageHashes::Md5(md5)) => {
+                            hash.insert("md5", format!("{md5:x}"));
+                        }
+                        Some(PackageHashes::Sha256(sha256)) => {
+                            hash.insert("sha256", format!("{sha256:x}"));
+                        }
+                        Some(PackageHashes::Md5Sha256(md5, sha256)) => {
+                            hash.insert("md5", format!("{md5:x}"));
+                            hash.insert("sha256", format!("{sha256:x}"));
+                        }
+                        None => {}
+                    }
+                    CondaLockPackage {
+                        name: data.name.to_string(),
+                        version: data.version.to_string(),
+                        manager: "pip",
+                        platform: platform.to_string(),
+                        dependencies: data
+                            .requires_dist
+                            .iter()
+                            .map(|requirement| {
+                                let spec = match &requirement.version_or_url {
+                                    Some(VersionOrUrl::VersionSpecifier(spec)) => spec.to_string(),
+                                    Some(VersionOrUrl::Url(url)) => url.to_string(),
+                                    None => String::from("*"),
+                                };
+                                (requirement.name.to_string(), spec)
+                            })
+                            .collect(),
+                        url: url.to_string(),
+                        hash,
+                        category: "main",
+                        optional: false,
+                    }
+                }
+            };
+            urls.push(package.url.clone());
+            packages.push(package);
+        }
+
+        // conda-lock uses the hash to detect changes in the inputs, we use the locked urls.
+        urls.sort();
+        let digest = compute_bytes_digest::<Sha256>(urls.join("\n").as_bytes());
+        content_hash.insert(platform.to_string(), format!("{digest:x}"));
+    }
+
+    Ok(CondaLock {
+        version: 1,
+        metadata: CondaLockMetadata {
+            content_hash,
+            channels: environment
+                .channels()
+                .iter()
+                .map(|channel| CondaLockChannel {
+                    url: channel.url.clone(),
+                    used_env_vars: channel.used_env_vars.clone(),
+                })
+                .collect(),
+            platforms: platforms.iter().map(Platform::to_string).collect(),
+            sources: vec![source],
+        },
+        package: packages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_conda_lock() {
+        let lock_file = LockFile::from_str(
+            r#"
+version: 4
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+      - pypi: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+packages:
+- kind: conda
+  name: libzlib
+  version: 1.2.13
+  build: hd590300_5
+  build_number: 5
+  subdir: linux-64
+  url: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  sha256: 370c7c5893b737596fd6ca0d9190c9715d89d888b8c88537ae1ef168c25e82e4
+  md5: f36c115f1ee199da648e0597ec2047ad
+  depends:
+  - libgcc-ng >=12
+  size: 61588
+  timestamp: 1686575217516
+- kind: pypi
+  name: idna
+  version: '3.7'
+  url: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+  sha256: 82fee1fc78add43492d3a1898bfa6d8a904cc97d8427f683ed8e798d07761aa0
+  requires_python: '>=3.5'
+"#,
+        )
+        .unwrap();
+
+        let conda_lock =
+            to_conda_lock(&lock_file, "default", &[], String::from("pixi.toml")).unwrap();
+        assert_eq!(conda_lock.metadata.platforms, vec!["linux-64"]);
+        assert_eq!(conda_lock.package.len(), 2);
+
+        let libzlib = &conda_lock.package[0];
+        assert_eq!(libzlib.manager, "conda");
+        assert_eq!(libzlib.dependencies.get("libgcc-ng").unwrap(), ">=12");
+        assert_eq!(
+            libzlib.hash.get("md5").unwrap(),
+            "f36c115f1ee199da648e0597ec2047ad"
+        );
+
+        let idna = &conda_lock.package[1];
+        assert_eq!(idna.manager, "pip");
+        assert_eq!(idna.version, "3.7");
+        assert!(idna.hash.contains_key("sha256"));
+    }
+}
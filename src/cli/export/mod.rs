@@ -0,0 +1,22 @@
+use clap::Parser;
+
+pub mod conda_lock;
+
+#[derive(Debug, Parser)]
+pub enum Command {
+    CondaLock(conda_lock::Args),
+}
+
+/// Export the lock-file of the project to other formats.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+pub async fn execute(cmd: Args) -> miette::Result<()> {
+    match cmd.command {
+        Command::CondaLock(args) => conda_lock::execute(args).await?,
+    };
+    Ok(())
+}
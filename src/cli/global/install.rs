@@ -110,7 +110,10 @@ fn is_executable(prefix: &Prefix, relative_path: &Path) -> bool {
 }
 
 /// Find the executable scripts within the specified package installed in this conda prefix.
-fn find_executables<'a>(prefix: &Prefix, prefix_package: &'a PrefixRecord) -> Vec<&'a Path> {
+pub(super) fn find_executables<'a>(
+    prefix: &Prefix,
+    prefix_package: &'a PrefixRecord,
+) -> Vec<&'a Path> {
     prefix_package
         .files
         .iter()
@@ -358,6 +361,7 @@ pub(super) async fn globally_install_package(
                 prefix.root().to_path_buf(),
                 authenticated_client,
                 pb,
+                None,
             )
         })
         .await?;
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_conda_types::{MatchSpec, NamelessMatchSpec, PackageName, ParseStrictness};
+use serde::Deserialize;
+
+use crate::{config::home_path, consts};
+
+/// The declarative description of the globally installed tools, read from a `pixi-global.toml`.
+///
+/// ```toml
+/// channels = ["conda-forge"]
+///
+/// [envs]
+/// ripgrep = "*"
+/// python = { version = "3.12.*", channels = ["conda-forge", "bioconda"] }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GlobalManifest {
+    /// The channels used for all environments that do not specify their own channels.
+    #[serde(default)]
+    pub channels: Vec<String>,
+
+    /// The global environments, keyed by the name of the package that is installed in them.
+    #[serde(default)]
+    pub envs: IndexMap<PackageName, GlobalEnvironment>,
+}
+
+/// A single global environment as described in the [`GlobalManifest`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(from = "TomlGlobalEnvironment")]
+pub struct GlobalEnvironment {
+    /// The version constraint of the package, `None` means any version.
+    pub version: Option<String>,
+
+    /// The channels to install the package from. If empty the channels of the manifest are used.
+    pub channels: Vec<String>,
+}
+
+/// The toml representation of a [`GlobalEnvironment`], either just a version or a table.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TomlGlobalEnvironment {
+    Version(String),
+    #[serde(rename_all = "kebab-case")]
+    Table {
+        version: Option<String>,
+        #[serde(default)]
+        channels: Vec<String>,
+    },
+}
+
+impl From<TomlGlobalEnvironment> for GlobalEnvironment {
+    fn from(value: TomlGlobalEnvironment) -> Self {
+        match value {
+            TomlGlobalEnvironment::Version(version) => Self {
+                version: Some(version),
+                channels: vec![],
+            },
+            TomlGlobalEnvironment::Table { version, channels } => Self { version, channels },
+        }
+    }
+}
+
+impl GlobalEnvironment {
+    /// Returns the [`MatchSpec`] of the package installed in this environment.
+    pub fn spec(&self, name: &PackageName) -> miette::Result<MatchSpec> {
+        let spec = match self.version.as_deref() {
+            None | Some("*") => NamelessMatchSpec::default(),
+            Some(version) => NamelessMatchSpec::from_str(version, ParseStrictness::Strict)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    format!(
+                        "invalid version '{version}' for global environment '{}'",
+                        name.as_source()
+                    )
+                })?,
+        };
+        Ok(MatchSpec::from_nameless(spec, Some(name.clone())))
+    }
+}
+
+impl GlobalManifest {
+    /// Returns the default location of the global manifest: `$PIXI_HOME/pixi-global.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        home_path().map(|path| path.join(consts::GLOBAL_MANIFEST))
+    }
+
+    /// Parses the global manifest from a string.
+    pub fn from_toml_str(contents: &str) -> miette::Result<Self> {
+        toml_edit::de::from_str(contents).into_diagnostic()
+    }
+
+    /// Reads the global manifest from the given path.
+    pub fn from_path(path: &Path) -> miette::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read global manifest '{}'", path.display()))?;
+        Self::from_toml_str(&contents)
+            .wrap_err_with(|| format!("failed to parse global manifest '{}'", path.display()))
+    }
+
+    /// Returns the channels to use for the given environment.
+    pub fn channels<'a>(&'a self, env: &'a GlobalEnvironment) -> &'a [String] {
+        if env.channels.is_empty() {
+            &self.channels
+        } else {
+            &env.channels
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_global_manifest() {
+        let manifest = GlobalManifest::from_toml_str(
+            r#"
+            channels = ["conda-forge"]
+
+            [envs]
+            ripgrep = "*"
+            python = { version = "3.12.*", channels = ["bioconda"] }
+            bat = {}
+            "#,
+        )
+        .unwrap();
+
+        let specs = manifest
+            .envs
+            .iter()
+            .map(|(name, env)| {
+                format!(
+                    "{} [{}]",
+                    env.spec(name).unwrap(),
+                    manifest.channels(env).join(", ")
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            specs,
+            vec![
+                "ripgrep [conda-forge]",
+                "python 3.12.* [bioconda]",
+                "bat [conda-forge]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_field() {
+        assert!(GlobalManifest::from_toml_str("foo = 1").is_err());
+    }
+}
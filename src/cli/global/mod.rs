@@ -1,9 +1,12 @@
 use clap::Parser;
 
-mod common;
+pub(crate) mod common;
 mod install;
 mod list;
+mod manifest;
 mod remove;
+mod run;
+mod sync;
 mod upgrade;
 mod upgrade_all;
 
@@ -21,6 +24,8 @@ pub enum Command {
     Upgrade(upgrade::Args),
     #[clap(visible_alias = "ua")]
     UpgradeAll(upgrade_all::Args),
+    Sync(sync::Args),
+    Run(run::Args),
 }
 
 /// Global is the main entry point for the part of pixi that executes on the global(system) level.
@@ -39,6 +44,8 @@ pub async fn execute(cmd: Args) -> miette::Result<()> {
         Command::List(args) => list::execute(args).await?,
         Command::Upgrade(args) => upgrade::execute(args).await?,
         Command::UpgradeAll(args) => upgrade_all::execute(args).await?,
+        Command::Sync(args) => sync::execute(args).await?,
+        Command::Run(args) => run::execute(args).await?,
     };
     Ok(())
 }
@@ -37,7 +37,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     Ok(())
 }
 
-async fn remove_global_package(
+pub(super) async fn remove_global_package(
     package_name: PackageName,
     verbose: &Verbosity,
 ) -> miette::Result<()> {
@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_conda_types::PackageName;
+
+use crate::activation::run_prefix_activation;
+use crate::prefix::Prefix;
+
+use super::common::{find_installed_package, BinEnvDir};
+use super::install::find_executables;
+
+/// Runs a binary of a globally installed tool in its activated environment, without going through
+/// the scripts in the global binary directory.
+#[derive(Parser, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct Args {
+    /// The globally installed package that provides the binary.
+    tool: String,
+
+    /// The name of the binary to run, e.g. `ruff`.
+    binary: String,
+
+    /// The arguments to pass to the binary, after `--`.
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let package_name = PackageName::from_str(&args.tool).into_diagnostic()?;
+    let prefix_package = find_installed_package(&package_name).await?;
+    let BinEnvDir(bin_env_prefix) = BinEnvDir::from_existing(&package_name).await?;
+    let prefix = Prefix::new(bin_env_prefix);
+
+    let executables = find_executables(&prefix, &prefix_package);
+    let Some(executable) = executables
+        .iter()
+        .find(|executable| binary_name_matches(executable, &args.binary))
+    else {
+        let available = executables
+            .iter()
+            .filter_map(|executable| executable.file_name())
+            .map(|name| name.to_string_lossy())
+            .sorted()
+            .dedup()
+            .join(", ");
+        miette::bail!(
+            help = if available.is_empty() {
+                String::from("the package does not expose any binaries")
+            } else {
+                format!("available binaries: {available}")
+            },
+            "'{}' is not a binary of the globally installed package '{}'",
+            args.binary,
+            package_name.as_source()
+        );
+    };
+
+    let env = run_prefix_activation(prefix.root()).await?;
+    let status = std::process::Command::new(prefix.root().join(executable))
+        .args(&args.args)
+        .envs(env)
+        .status()
+        .into_diagnostic()?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Returns true if the executable is the binary with the given name, the extension is optional so
+/// `ruff` matches `ruff.exe` on Windows.
+fn binary_name_matches(executable: &Path, name: &str) -> bool {
+    executable
+        .file_name()
+        .is_some_and(|file_name| file_name == name)
+        || executable
+            .file_stem()
+            .is_some_and(|file_stem| file_stem == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::binary_name_matches;
+    use std::path::Path;
+
+    #[test]
+    fn test_binary_name_matches() {
+        assert!(binary_name_matches(Path::new("bin/ruff"), "ruff"));
+        assert!(binary_name_matches(
+            Path::new("Library/bin/ruff.exe"),
+            "ruff"
+        ));
+        assert!(binary_name_matches(
+            Path::new("Scripts/black.exe"),
+            "black.exe"
+        ));
+        assert!(!binary_name_matches(Path::new("bin/ruff-lsp"), "ruff"));
+    }
+}
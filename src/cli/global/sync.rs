@@ -0,0 +1,244 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use clap::Parser;
+use clap_verbosity_flag::Verbosity;
+use miette::IntoDiagnostic;
+use rattler_conda_types::{PackageName, PackageRecord, Platform};
+
+use crate::config::{Config, ConfigCli};
+
+use super::common::{find_installed_package, get_client_and_sparse_repodata, load_package_records};
+use super::install::globally_install_package;
+use super::list::list_global_packages;
+use super::manifest::{GlobalEnvironment, GlobalManifest};
+use super::remove::remove_global_package;
+
+/// Make the globally installed packages match the environments declared in a `pixi-global.toml`.
+///
+/// Environments that are missing or whose installed version does not match the manifest are
+/// (re-)installed, globally installed packages that are not part of the manifest are removed.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The path to the global manifest, defaults to `$PIXI_HOME/pixi-global.toml`
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+
+    /// Only print the changes that would be made, without modifying the global installation
+    #[arg(long)]
+    dry_run: bool,
+
+    /// The platform to install the packages for.
+    #[clap(short, long, default_value_t = Platform::current())]
+    platform: Platform,
+
+    #[clap(flatten)]
+    config: ConfigCli,
+
+    #[command(flatten)]
+    verbose: Verbosity,
+}
+
+/// A single change required to bring the global installation in sync with the manifest.
+#[derive(Debug, PartialEq)]
+enum SyncAction<'a> {
+    Install(&'a PackageName, &'a GlobalEnvironment),
+    Update(&'a PackageName, &'a GlobalEnvironment),
+    Remove(PackageName),
+}
+
+impl Display for SyncAction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncAction::Install(name, _) => write!(
+                f,
+                "{} install {}",
+                console::style("+").green(),
+                console::style(name.as_source()).bold()
+            ),
+            SyncAction::Update(name, _) => write!(
+                f,
+                "{} update  {}",
+                console::style("~").yellow(),
+                console::style(name.as_source()).bold()
+            ),
+            SyncAction::Remove(name) => write!(
+                f,
+                "{} remove  {}",
+                console::style("-").red(),
+                console::style(name.as_source()).bold()
+            ),
+        }
+    }
+}
+
+/// Returns the changes that make the globally installed packages match the manifest. The record
+/// of an installed package is only known if it is part of the manifest.
+fn sync_actions<'a>(
+    manifest: &'a GlobalManifest,
+    installed: &[(PackageName, Option<PackageRecord>)],
+) -> miette::Result<Vec<SyncAction<'a>>> {
+    let mut actions = Vec::new();
+    for (name, env) in &manifest.envs {
+        match installed.iter().find(|(installed, _)| installed == name) {
+            None => actions.push(SyncAction::Install(name, env)),
+            Some((_, record)) => {
+                let spec = env.spec(name)?;
+                if !record.as_ref().is_some_and(|record| spec.matches(record)) {
+                    actions.push(SyncAction::Update(name, env));
+                }
+            }
+        }
+    }
+    actions.extend(
+        installed
+            .iter()
+            .filter(|(name, _)| !manifest.envs.contains_key(name))
+            .map(|(name, _)| SyncAction::Remove(name.clone())),
+    );
+    Ok(actions)
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let config = Config::with_cli_config(&args.config);
+    let manifest_path = match args.manifest_path {
+        Some(path) => path,
+        None => GlobalManifest::default_path().ok_or_else(|| {
+            miette::miette!("could not determine the location of the global manifest")
+        })?,
+    };
+    let manifest = GlobalManifest::from_path(&manifest_path)?;
+
+    // Determine what needs to change to match the manifest.
+    let mut installed = Vec::new();
+    for name in list_global_packages().await? {
+        let record = if manifest.envs.contains_key(&name) {
+            Some(
+                find_installed_package(&name)
+                    .await?
+                    .repodata_record
+                    .package_record,
+            )
+        } else {
+            None
+        };
+        installed.push((name, record));
+    }
+    let actions = sync_actions(&manifest, &installed)?;
+
+    if actions.is_empty() {
+        eprintln!(
+            "{}Global environments are already in sync with {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            manifest_path.display()
+        );
+        return Ok(());
+    }
+
+    if args.dry_run {
+        eprintln!("The following changes would be made:");
+        for action in &actions {
+            eprintln!("  {action}");
+        }
+        return Ok(());
+    }
+
+    for action in actions {
+        match action {
+            SyncAction::Install(name, env) | SyncAction::Update(name, env) => {
+                let channels = config
+                    .compute_channels(manifest.channels(env))
+                    .into_diagnostic()?;
+                let (authenticated_client, sparse_repodata) =
+                    get_client_and_sparse_repodata(&channels, args.platform, &config).await?;
+                let records = load_package_records(env.spec(name)?, &sparse_repodata)?;
+                let (prefix_package, _, _) =
+                    globally_install_package(name, records, authenticated_client, &args.platform)
+                        .await?;
+                let record = &prefix_package.repodata_record.package_record;
+                eprintln!(
+                    "{}Installed package {} {} {}",
+                    console::style(console::Emoji("✔ ", "")).green(),
+                    console::style(record.name.as_source()).bold(),
+                    console::style(record.version.version()).bold(),
+                    console::style(record.build.as_str()).bold(),
+                );
+            }
+            SyncAction::Remove(name) => remove_global_package(name, &args.verbose).await?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_conda_types::Version;
+    use std::str::FromStr;
+
+    fn record(name: &str, version: &str) -> (PackageName, Option<PackageRecord>) {
+        let name = PackageName::from_str(name).unwrap();
+        let record = PackageRecord::new(
+            name.clone(),
+            Version::from_str(version).unwrap(),
+            String::from("h_0"),
+        );
+        (name, Some(record))
+    }
+
+    #[test]
+    fn test_sync_actions() {
+        let manifest = GlobalManifest::from_toml_str(
+            r#"
+            channels = ["conda-forge"]
+
+            [envs]
+            ripgrep = "*"
+            python = "3.12.*"
+            bat = ">=0.24"
+            "#,
+        )
+        .unwrap();
+        let installed = vec![
+            record("python", "3.11.9"),
+            record("bat", "0.24.0"),
+            (PackageName::from_str("black").unwrap(), None),
+        ];
+
+        let actions = sync_actions(&manifest, &installed).unwrap();
+        let name = |name: &str| {
+            manifest
+                .envs
+                .get_key_value(&PackageName::from_str(name).unwrap())
+                .unwrap()
+        };
+        let (ripgrep, ripgrep_env) = name("ripgrep");
+        let (python, python_env) = name("python");
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Install(ripgrep, ripgrep_env),
+                SyncAction::Update(python, python_env),
+                SyncAction::Remove(PackageName::from_str("black").unwrap()),
+            ]
+        );
+
+        // The dry run prints the changes in this form.
+        assert_eq!(
+            actions
+                .iter()
+                .map(|action| console::strip_ansi_codes(&action.to_string()).into_owned())
+                .collect::<Vec<_>>(),
+            vec!["+ install ripgrep", "~ update  python", "- remove  black"]
+        );
+
+        // Nothing changes once the installation matches the manifest.
+        let installed = vec![
+            record("ripgrep", "14.1.0"),
+            record("python", "3.12.3"),
+            record("bat", "0.24.0"),
+        ];
+        assert!(sync_actions(&manifest, &installed).unwrap().is_empty());
+    }
+}
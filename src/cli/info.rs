@@ -271,12 +271,20 @@ fn dir_size(path: impl Into<PathBuf>) -> miette::Result<String> {
     Ok(format!("{} MiB", size / 1024 / 1024))
 }
 
-/// Returns last update time of file, formatted: DD-MM-YYYY H:M:S
-fn last_updated(path: impl Into<PathBuf>) -> miette::Result<String> {
-    let time = fs::metadata(path.into())
-        .into_diagnostic()?
-        .modified()
-        .into_diagnostic()?;
+/// Returns the last update time of the most recently modified file, formatted: DD-MM-YYYY H:M:S
+fn last_updated(paths: impl IntoIterator<Item = PathBuf>) -> miette::Result<String> {
+    let mut times = Vec::new();
+    for path in paths.into_iter().filter(|path| path.is_file()) {
+        times.push(
+            fs::metadata(path)
+                .into_diagnostic()?
+                .modified()
+                .into_diagnostic()?,
+        );
+    }
+    let Some(time) = times.into_iter().max() else {
+        miette::bail!("no lock file found");
+    };
     let formated_time = DateTime::<Local>::from(time)
         .format("%d-%m-%Y %H:%M:%S")
         .to_string();
@@ -305,7 +313,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
     let project_info = project.clone().map(|p| ProjectInfo {
         manifest_path: p.manifest_path(),
-        last_updated: last_updated(p.lock_file_path()).ok(),
+        last_updated: last_updated(p.lock_file_paths()).ok(),
         pixi_folder_size,
         configuration: p.config().loaded_from.clone(),
         version: p.version().clone().map(|v| v.to_string()),
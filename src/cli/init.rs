@@ -6,11 +6,13 @@ use crate::{config::get_default_author, consts};
 use crate::{FeatureName, Project};
 use clap::Parser;
 use indexmap::IndexMap;
+use itertools::Itertools;
 use miette::IntoDiagnostic;
 use minijinja::{context, Environment};
 use rattler_conda_types::Platform;
 use std::io::{Error, ErrorKind, Write};
 use std::path::Path;
+use std::str::FromStr;
 use std::{fs, path::PathBuf};
 use url::Url;
 
@@ -25,7 +27,7 @@ pub struct Args {
     #[arg(short, long = "channel", id = "channel", conflicts_with = "env_file")]
     pub channels: Option<Vec<String>>,
 
-    /// Platforms that the project supports.
+    /// Platforms that the project supports, defaults to the current platform.
     #[arg(short, long = "platform", id = "platform")]
     pub platforms: Vec<String>,
 
@@ -152,7 +154,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     let platforms = if args.platforms.is_empty() {
         vec![Platform::current().to_string()]
     } else {
-        args.platforms.clone()
+        parse_platforms(&args.platforms)?
     };
 
     // Create a 'pixi.toml' manifest and populate it by importing a conda environment file
@@ -197,6 +199,8 @@ pub async fn execute(args: Args) -> miette::Result<()> {
                     &requirement,
                     Some(platform.parse().into_diagnostic()?),
                     &FeatureName::default(),
+                    false,
+                    None,
                 )?;
             }
         }
@@ -211,7 +215,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         .await?;
     } else {
         let channels = if let Some(channels) = args.channels {
-            channels
+            channels.into_iter().unique().collect()
         } else {
             config.default_channels().to_vec()
         };
@@ -370,6 +374,28 @@ fn render_project(
     .unwrap()
 }
 
+/// Validates the platforms given on the command line against the known platforms, duplicates are
+/// removed while keeping the order in which they were given.
+fn parse_platforms(platforms: &[String]) -> miette::Result<Vec<String>> {
+    let mut parsed = Vec::new();
+    for platform in platforms {
+        let platform = Platform::from_str(platform).map_err(|_| {
+            miette::miette!(
+                help = format!(
+                    "the known platforms are: {}",
+                    Platform::all().map(|p| p.to_string()).join(", ")
+                ),
+                "'{platform}' is not a known platform"
+            )
+        })?;
+        let platform = platform.to_string();
+        if !parsed.contains(&platform) {
+            parsed.push(platform);
+        }
+    }
+    Ok(parsed)
+}
+
 fn get_name_from_dir(path: &Path) -> miette::Result<String> {
     Ok(path
         .file_name()
@@ -440,6 +466,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_platforms() {
+        assert_eq!(
+            parse_platforms(&[
+                "linux-64".to_string(),
+                "osx-arm64".to_string(),
+                "linux-64".to_string()
+            ])
+            .unwrap(),
+            vec!["linux-64".to_string(), "osx-arm64".to_string()]
+        );
+        assert!(parse_platforms(&["linux-65".to_string()]).is_err());
+    }
+
     #[test]
     fn test_get_name_panic() {
         match get_dir(PathBuf::from("invalid/path")) {
@@ -1,9 +1,22 @@
-use crate::config::ConfigCli;
-use crate::environment::get_up_to_date_prefix;
+use crate::cli::run::get_installed_task_env;
+use crate::config::{Config, ConfigCli};
+use crate::environment::get_up_to_date_prefix_excluding;
+use crate::install::LinkMethod;
+use crate::lock_file::{
+    load_lock_file, LockFileDiff, OutdatedEnvironments, SolveStrategy, UpdateLockFileOptions,
+};
+use crate::prefix::Prefix;
+use crate::progress::{set_progress_format, ProgressFormat};
+use crate::project::has_features::HasFeatures;
+use crate::project::Environment;
+use crate::task::ExecutableTask;
 use crate::Project;
 use clap::Parser;
-use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// Install all dependencies
 #[derive(Parser, Debug)]
@@ -18,29 +31,381 @@ pub struct Args {
     #[arg(long, short)]
     pub environment: Option<String>,
 
+    /// Install the environment that is made up of these optional dependency groups, e.g. the
+    /// group added with `pixi add --optional docs` is installed with `--with docs`.
+    #[arg(long, value_name = "GROUP", conflicts_with = "environment")]
+    pub with: Vec<String>,
+
     #[clap(flatten)]
     pub config: ConfigCli,
+
+    /// Only check that the lock-file is up-to-date with the manifest and that the environment
+    /// matches the lock-file. Nothing is installed or modified.
+    #[arg(long)]
+    pub check: bool,
+
+    /// Install the environment into this directory instead of the default location managed by
+    /// pixi. The directory can be activated with `pixi shell --prefix`.
+    #[arg(long)]
+    pub prefix: Option<PathBuf>,
+
+    /// The format in which progress is reported. `json` writes newline-delimited JSON events to
+    /// stderr instead of showing progress bars.
+    #[arg(long, value_enum, default_value_t)]
+    pub progress_format: ProgressFormat,
+
+    /// Don't read from the repodata and package caches, everything is fetched again. The fetched
+    /// data is still written to the caches.
+    #[arg(long, conflicts_with = "check")]
+    pub no_cache: bool,
+
+    /// Don't access the network, the repodata and packages are only taken from the caches. Fails
+    /// if something that is required is not in the caches.
+    #[arg(long, conflicts_with = "no_cache")]
+    pub offline: bool,
+
+    /// After installing, print the packages that were added, removed or changed in the
+    /// lock-file.
+    #[arg(long, conflicts_with = "check")]
+    pub summary: bool,
+
+    /// Print the summary as JSON.
+    #[arg(long, requires = "summary")]
+    pub json: bool,
+
+    /// Solve every platform independently and update the lock-file for the platforms that can be
+    /// solved. The platforms that fail keep their previously locked packages and are reported. The
+    /// current platform always has to be solvable.
+    #[arg(long, conflicts_with = "check")]
+    pub allow_partial_platforms: bool,
+
+    /// Install the dependencies of this package but not the package itself, e.g. to install it in
+    /// editable mode separately. The package has to be a dependency in the manifest.
+    #[arg(long, value_name = "PACKAGE", conflicts_with = "check")]
+    pub only_deps: Vec<String>,
+
+    /// Don't run the `post-install-check` of the project after installing.
+    #[arg(long, conflicts_with = "check")]
+    pub skip_checks: bool,
+
+    /// The versions the conda solver prefers. `lowest` and `lowest-direct` solve all the
+    /// environments again, e.g. to test the lower bounds of the dependencies. This is
+    /// best-effort, a lowest version that conflicts falls back to the highest version that fits.
+    #[arg(long, value_enum, default_value_t, conflicts_with = "check")]
+    pub solve_strategy: SolveStrategy,
+
+    /// How the files of the packages are linked into the environment. pixi falls back to copying
+    /// the files, with a warning, when the method isn't supported between the package cache and
+    /// the environment. By default the best supported method is picked per file.
+    #[arg(long, value_enum, conflicts_with = "check")]
+    pub link_method: Option<LinkMethod>,
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
-    let project =
+    set_progress_format(args.progress_format);
+
+    let mut project =
         Project::load_or_else_discover(args.manifest_path.as_deref())?.with_cli_config(args.config);
-    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let environment_name = if args.with.is_empty() {
+        args.environment
+    } else {
+        Some(environment_with_groups(&project, &args.with)?)
+    };
+    if let Some(prefix) = &args.prefix {
+        let name = project
+            .environment_from_name_or_env_var(environment_name.clone())?
+            .name()
+            .clone();
+        project = project.with_prefix_override(name, prefix)?;
+    }
+    if args.no_cache {
+        eprintln!(
+            "{}caching is disabled, all package metadata and packages are fetched again which is slower",
+            console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+        );
+        project = project.with_cli_config(Config::default().with_no_cache(true));
+    }
+    if args.offline {
+        project = project.with_cli_config(Config::default().with_offline(true));
+    }
+    if args.link_method.is_some() {
+        project = project.with_cli_config(Config::default().with_link_method(args.link_method));
+    }
+    let environment = project.environment_from_name_or_env_var(environment_name)?;
+
+    if args.check {
+        check(&environment).await?;
+        Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+        return Ok(());
+    }
 
-    get_up_to_date_prefix(
+    // Remember the current lock-file to be able to summarize the changes.
+    let previous_lock_file = if args.summary {
+        Some(load_lock_file(&project).await?)
+    } else {
+        None
+    };
+
+    let excluded = only_deps_exclusions(&environment, &args.only_deps)?;
+    get_up_to_date_prefix_excluding(
         &environment,
-        args.lock_file_usage.into(),
-        false,
-        IndexMap::default(),
+        UpdateLockFileOptions {
+            lock_file_usage: args.lock_file_usage.into(),
+            allow_partial_platforms: args.allow_partial_platforms,
+            solve_strategy: args.solve_strategy,
+            ..UpdateLockFileOptions::default()
+        },
+        &excluded,
     )
     .await?;
 
+    if let Some(post_install_check) = project.post_install_check() {
+        if args.skip_checks {
+            tracing::info!("skipping the post-install-check because of `--skip-checks`");
+        } else {
+            run_post_install_check(&environment, post_install_check).await?;
+        }
+    }
+
+    if let Some(previous_lock_file) = previous_lock_file {
+        let diff =
+            LockFileDiff::from_lock_files(&previous_lock_file, &load_lock_file(&project).await?);
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&diff).into_diagnostic()?);
+        } else {
+            diff.print();
+        }
+    }
+
     // Emit success
+    for name in &args.only_deps {
+        eprintln!(
+            "{}Installed the dependencies of {} without the package itself",
+            console::style(console::Emoji("✔ ", "")).green(),
+            console::style(name).bold()
+        );
+    }
+    if args.prefix.is_some() {
+        eprintln!(
+            "{}Environment {} is installed in {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            environment.name().fancy_display(),
+            environment.dir().display()
+        );
+    } else {
+        eprintln!(
+            "{}Project in {} is ready to use!",
+            console::style(console::Emoji("✔ ", "")).green(),
+            project.root().display()
+        );
+    }
+    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Returns the name of the environment that is made up of exactly the given optional dependency
+/// groups, next to the default feature.
+fn environment_with_groups(project: &Project, groups: &[String]) -> miette::Result<String> {
+    let groups: BTreeSet<&str> = groups.iter().map(String::as_str).collect();
+    project
+        .manifest
+        .parsed
+        .environments
+        .iter()
+        .find(|env| {
+            !env.no_default_feature
+                && env.features.iter().map(String::as_str).collect::<BTreeSet<_>>() == groups
+        })
+        .map(|env| env.name.as_str().to_string())
+        .ok_or_else(|| {
+            miette::miette!(
+                help = "add an environment with these features to the manifest, `pixi add --optional <GROUP>` adds one for a single group",
+                "there is no environment with the optional dependency groups {}",
+                groups.iter().map(|group| format!("'{group}'")).join(", ")
+            )
+        })
+}
+
+/// Runs the `post-install-check` of the project in the installed environment, like a custom
+/// command of `pixi run`. Returns an error if the check exits with a non-zero exit code.
+async fn run_post_install_check(environment: &Environment<'_>, check: &str) -> miette::Result<()> {
+    let reproducible = environment.project().reproducible_env();
+    let task =
+        ExecutableTask::from_cmd(environment.clone(), check).with_reproducible_env(reproducible);
+    let Some(script) = task.as_deno_script()? else {
+        return Ok(());
+    };
+    let cwd = task.working_directory()?;
+
+    // The environment of the check is the same as the one of `pixi run`.
+    let command_env = get_installed_task_env(environment, true, reproducible).await?;
+
     eprintln!(
-        "{}Project in {} is ready to use!",
+        "{}{}{}",
+        console::Emoji("✨ ", ""),
+        console::style("Pixi post-install-check: ").bold(),
+        task.display_command(),
+    );
+    let code = deno_task_shell::execute(script, command_env, &cwd, Default::default()).await;
+    if code != 0 {
+        miette::bail!(
+            help = "fix the environment or skip the check with `--skip-checks`",
+            "the post-install-check of environment '{}' failed with exit code {code}",
+            environment.name().fancy_display()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the normalized names of the packages passed to `--only-deps`. Every package has to be a
+/// conda or pypi dependency of the environment in the manifest.
+fn only_deps_exclusions(
+    environment: &Environment<'_>,
+    packages: &[String],
+) -> miette::Result<HashSet<String>> {
+    let platform = environment.best_platform();
+    let conda_dependencies = environment.dependencies(None, Some(platform));
+    let pypi_dependencies = environment.pypi_dependencies(Some(platform));
+
+    let mut excluded = HashSet::new();
+    for package in packages {
+        let conda_name = rattler_conda_types::PackageName::from_str(package).ok();
+        let pypi_name = uv_normalize::PackageName::from_str(package).ok();
+        let is_conda = conda_name
+            .as_ref()
+            .is_some_and(|name| conda_dependencies.names().any(|dep| dep == name));
+        let is_pypi = pypi_name.as_ref().is_some_and(|name| {
+            pypi_dependencies
+                .names()
+                .any(|dep| dep.as_normalized() == name)
+        });
+        if is_conda {
+            excluded.extend(conda_name.map(|name| name.as_normalized().to_string()));
+        }
+        if is_pypi {
+            excluded.extend(pypi_name.map(|name| name.to_string()));
+        }
+        if !is_conda && !is_pypi {
+            miette::bail!(
+                help =
+                    "only the dependencies of packages that are in the manifest can be installed",
+                "'{package}' is not a dependency of the environment '{}'",
+                environment.name().fancy_display()
+            );
+        }
+    }
+    Ok(excluded)
+}
+
+/// Verifies that the lock-file is up-to-date with the manifest and that the conda packages
+/// installed in the prefix of the environment match the lock-file. Neither the lock-file nor the
+/// prefix are created or modified. Returns an error if any discrepancy was found.
+async fn check(environment: &Environment<'_>) -> miette::Result<()> {
+    let project = environment.project();
+    let mut discrepancies = Vec::new();
+
+    // Check the lock-file against the manifest.
+    let lock_file_path = project.lock_file_path();
+    let lock_file = load_lock_file(project).await?;
+    if !project.has_lock_file() {
+        discrepancies.push(format!(
+            "the lock-file '{}' does not exist",
+            lock_file_path.display()
+        ));
+    } else {
+        let outdated = OutdatedEnvironments::from_project_and_lock_file(project, &lock_file);
+        let mut outdated_platforms = BTreeMap::<String, BTreeSet<String>>::new();
+        for (env, platforms) in outdated.conda.iter().chain(outdated.pypi.iter()) {
+            outdated_platforms
+                .entry(env.name().to_string())
+                .or_default()
+                .extend(platforms.iter().map(|p| p.to_string()));
+        }
+        for (env_name, platforms) in outdated_platforms {
+            discrepancies.push(format!(
+                "the lock-file is out of date for environment '{}' on {}",
+                env_name,
+                platforms.iter().join(", ")
+            ));
+        }
+    }
+
+    // Check the installed packages against the lock-file.
+    let platform = environment.best_platform();
+    let prefix = Prefix::new(environment.dir());
+    if !prefix.root().is_dir() {
+        discrepancies.push(format!(
+            "the environment '{}' is not installed at '{}'",
+            environment.name().fancy_display(),
+            prefix.root().display()
+        ));
+    } else if environment.platforms().contains(&platform) {
+        let locked_records = lock_file
+            .environment(environment.name().as_str())
+            .map(|env| env.conda_repodata_records_for_platform(platform))
+            .transpose()
+            .into_diagnostic()?
+            .flatten()
+            .unwrap_or_default();
+        let locked_records: HashMap<_, _> = locked_records
+            .iter()
+            .map(|r| (r.package_record.name.as_normalized(), &r.package_record))
+            .collect();
+
+        let installed_records = prefix.find_installed_packages(None).await?;
+        let installed_records: HashMap<_, _> = installed_records
+            .iter()
+            .map(|r| {
+                (
+                    r.repodata_record.package_record.name.as_normalized(),
+                    &r.repodata_record.package_record,
+                )
+            })
+            .collect();
+
+        for (name, locked) in locked_records.iter().sorted_by_key(|(name, _)| *name) {
+            match installed_records.get(name) {
+                None => discrepancies.push(format!("'{name}' is locked but not installed")),
+                Some(installed)
+                    if installed.version != locked.version || installed.build != locked.build =>
+                {
+                    discrepancies.push(format!(
+                        "'{name}' is installed as {} {} but locked as {} {}",
+                        installed.version, installed.build, locked.version, locked.build
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in installed_records
+            .keys()
+            .filter(|name| !locked_records.contains_key(*name))
+            .sorted()
+        {
+            discrepancies.push(format!(
+                "'{name}' is installed but not part of the lock-file"
+            ));
+        }
+    }
+
+    if !discrepancies.is_empty() {
+        for discrepancy in &discrepancies {
+            eprintln!(
+                "{}{}",
+                console::style(console::Emoji("✘ ", "")).red(),
+                discrepancy
+            );
+        }
+        miette::bail!(
+            "found {} discrepancies between the manifest, the lock-file and the installed environment",
+            discrepancies.len()
+        );
+    }
+
+    eprintln!(
+        "{}Project in {} is up-to-date!",
         console::style(console::Emoji("✔ ", "")).green(),
         project.root().display()
     );
-    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
     Ok(())
 }
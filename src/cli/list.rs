@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io;
 use std::io::{stdout, Write};
 use std::path::PathBuf;
@@ -5,15 +6,20 @@ use std::path::PathBuf;
 use clap::Parser;
 use console::Color;
 use human_bytes::human_bytes;
+use indexmap::IndexMap;
 use itertools::Itertools;
+use miette::IntoDiagnostic;
 
-use rattler_conda_types::Platform;
+use rattler_conda_types::{PackageRecord, Platform, PrefixRecord};
 use rattler_lock::{Package, UrlOrPath};
 use serde::Serialize;
 use uv_distribution::RegistryWheelIndex;
 
-use crate::lock_file::{UpdateLockFileOptions, UvResolutionContext};
+use crate::lock_file::{load_lock_file, UpdateLockFileOptions, UvResolutionContext};
+use crate::prefix::Prefix;
 use crate::project::has_features::HasFeatures;
+use crate::project::Environment;
+use crate::pypi_marker_env::determine_marker_environment;
 use crate::pypi_tags::{get_pypi_tags, is_python_record};
 use crate::Project;
 
@@ -27,6 +33,85 @@ pub enum SortBy {
     Kind,
 }
 
+/// A column of the output that can be selected with `--field`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListField {
+    Name,
+    Version,
+    Build,
+    Size,
+    Kind,
+    Dependents,
+    Source,
+}
+
+impl ListField {
+    /// The header of the column in the table.
+    fn header(self) -> &'static str {
+        match self {
+            ListField::Name => "Package",
+            ListField::Version => "Version",
+            ListField::Build => "Build",
+            ListField::Size => "Size",
+            ListField::Kind => "Kind",
+            ListField::Dependents => "Dependents",
+            ListField::Source => "Source",
+        }
+    }
+
+    /// The key of the field in the json output, the same as without `--field`.
+    fn json_key(self) -> &'static str {
+        match self {
+            ListField::Name => "name",
+            ListField::Version => "version",
+            ListField::Build => "build",
+            ListField::Size => "size_bytes",
+            ListField::Kind => "kind",
+            ListField::Dependents => "dependents",
+            ListField::Source => "source",
+        }
+    }
+
+    fn json_value(self, package: &PackageToOutput) -> serde_json::Value {
+        match self {
+            ListField::Name => package.name.clone().into(),
+            ListField::Version => package.version.clone().into(),
+            ListField::Build => package.build.clone().into(),
+            ListField::Size => package.size_bytes.into(),
+            ListField::Kind => package.kind.clone().into(),
+            ListField::Dependents => package.dependents.into(),
+            ListField::Source => package.source.clone().into(),
+        }
+    }
+
+    fn table_value(self, package: &PackageToOutput) -> String {
+        match self {
+            ListField::Name if package.is_explicit => console::style(&package.name)
+                .fg(Color::Green)
+                .bold()
+                .to_string(),
+            ListField::Name => package.name.clone(),
+            ListField::Version => package.version.clone(),
+            ListField::Build => package.build.clone().unwrap_or_default(),
+            ListField::Size => package
+                .size_bytes
+                .map(|size| human_bytes(size as f64))
+                .unwrap_or_default(),
+            ListField::Kind => package.kind.clone(),
+            ListField::Dependents => package
+                .dependents
+                .map(|count| count.to_string())
+                .unwrap_or_default(),
+            ListField::Source if package.is_editable => format!(
+                "{} {}",
+                package.source.as_deref().unwrap_or(""),
+                console::style("(editable)").fg(Color::Yellow)
+            ),
+            ListField::Source => package.source.clone().unwrap_or_default(),
+        }
+    }
+}
+
 /// List project's packages. Highlighted packages are explicit dependencies.
 #[derive(Debug, Parser)]
 #[clap(arg_required_else_help = false)]
@@ -35,6 +120,14 @@ pub struct Args {
     #[arg()]
     pub regex: Option<String>,
 
+    /// List only packages whose name matches a glob pattern, e.g. `py*` or `lib?`
+    #[arg(long)]
+    pub filter: Option<String>,
+
+    /// Only list packages that are explicitly defined in the project
+    #[arg(short = 'x', long)]
+    pub explicit: bool,
+
     /// The platform to list packages for. Defaults to the current platform.
     #[arg(long)]
     pub platform: Option<Platform>,
@@ -65,6 +158,40 @@ pub struct Args {
     /// Don't install the environment for pypi solving, only update the lock-file if it can solve without installing.
     #[arg(long)]
     pub no_install: bool,
+
+    /// Show the tree of dependencies of a single package, including their versions.
+    #[arg(long, conflicts_with_all = ["regex", "filter", "explicit", "json", "json_pretty"])]
+    pub tree_of: Option<String>,
+
+    /// Add a column with the number of packages in the environment that directly depend on each
+    /// package. Packages without dependents are highlighted.
+    #[arg(long)]
+    pub with_dependents: bool,
+
+    /// List the conda packages that are installed in the environment, read from its `conda-meta`
+    /// directory, instead of the packages in the lock-file.
+    #[arg(long, conflicts_with_all = ["tree_of", "with_dependents", "platform", "compare"])]
+    pub installed_only: bool,
+
+    /// Compare the conda packages that are installed in the environment with the lock-file and
+    /// only list the packages that differ.
+    #[arg(long, conflicts_with_all = ["tree_of", "with_dependents", "platform"])]
+    pub compare: bool,
+
+    /// List the packages that are locked at more than one version across all the environments
+    /// of the project, together with the environments that use each version.
+    #[arg(long, conflicts_with_all = ["tree_of", "with_dependents", "installed_only", "compare", "environment", "explicit"])]
+    pub duplicates: bool,
+
+    /// Only report packages that differ between environments of the same solve group, packages
+    /// in distinct solve groups are expected to differ.
+    #[arg(long, requires = "duplicates")]
+    pub within_solve_group: bool,
+
+    /// Only output these fields of the packages, in the given order. Also applies to the json
+    /// output. (Allowed to be used more than once)
+    #[arg(long = "field", value_enum, value_name = "FIELD", conflicts_with_all = ["tree_of", "compare", "duplicates"])]
+    pub fields: Vec<ListField>,
 }
 
 fn serde_skip_is_editable(editable: &bool) -> bool {
@@ -82,6 +209,8 @@ struct PackageToOutput {
     is_explicit: bool,
     #[serde(skip_serializing_if = "serde_skip_is_editable")]
     is_editable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependents: Option<usize>,
 }
 
 /// Get directory size
@@ -108,7 +237,83 @@ where
 
 pub async fn execute(args: Args) -> miette::Result<()> {
     let project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
-    let environment = project.environment_from_name_or_env_var(args.environment)?;
+
+    // Compare the versions of all the environments in the lock-file as it is on disk.
+    if args.duplicates {
+        let lock_file = load_lock_file(&project).await?;
+        let mut locked = Vec::new();
+        for environment in project.environments() {
+            let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+            let packages = lock_file
+                .environment(environment.name().as_str())
+                .and_then(|env| env.packages(platform).map(Vec::from_iter))
+                .unwrap_or_default();
+            locked.push(LockedEnvironment {
+                name: environment.name().to_string(),
+                solve_group: environment
+                    .solve_group()
+                    .map(|group| group.name().to_string()),
+                packages: packages
+                    .iter()
+                    .map(|p| {
+                        let kind = match p {
+                            Package::Conda(_) => "conda",
+                            Package::Pypi(_) => "pypi",
+                        };
+                        (kind, p.name().to_string(), p.version().into_owned())
+                    })
+                    .collect(),
+            });
+        }
+        let mut duplicates = duplicate_packages(&locked, args.within_solve_group);
+        if let Some(regex) = &args.regex {
+            let regex = regex::Regex::new(regex).map_err(|_| miette::miette!("Invalid regex"))?;
+            duplicates.retain(|p| regex.is_match(&p.name));
+        }
+        if let Some(filter) = &args.filter {
+            let pattern = glob_to_regex(filter)?;
+            duplicates.retain(|p| pattern.is_match(&p.name));
+        }
+        print_duplicate_packages(&duplicates, &args)?;
+        Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+        return Ok(());
+    }
+
+    let environment = project.environment_from_name_or_env_var(args.environment.clone())?;
+
+    // Read the packages from the prefix instead of the lock-file, this never solves or installs.
+    if args.installed_only || args.compare {
+        let installed = Prefix::new(environment.dir())
+            .find_installed_packages(None)
+            .await?;
+        if args.compare {
+            let lock_file = load_lock_file(&project).await?;
+            let locked = lock_file
+                .environment(environment.name().as_str())
+                .and_then(|env| {
+                    env.packages(environment.best_platform())
+                        .map(Vec::from_iter)
+                })
+                .unwrap_or_default();
+            let drift = package_drift(
+                locked
+                    .iter()
+                    .filter_map(|p| p.as_conda())
+                    .map(|p| p.package_record()),
+                installed.iter().map(|r| &r.repodata_record.package_record),
+            );
+            print_package_drift(&drift, &args, &environment)?;
+        } else {
+            let dependency_names = explicit_dependency_names(&environment, None);
+            let packages = installed
+                .iter()
+                .map(|record| installed_package_to_output(record, &dependency_names))
+                .collect();
+            output_packages(packages, &args, &environment)?;
+        }
+        Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+        return Ok(());
+    }
 
     let lock_file = project
         .up_to_date_lock_file(UpdateLockFileOptions {
@@ -128,6 +333,25 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         .and_then(|env| env.packages(platform).map(Vec::from_iter))
         .unwrap_or_default();
 
+    // Print the dependency tree of a single package if requested
+    if let Some(package_name) = &args.tree_of {
+        let dep_map = super::tree::generate_dependency_map(&locked_deps);
+        let direct_deps = super::tree::direct_dependencies(&environment, &platform, &dep_map);
+        if !environment.is_default() {
+            eprintln!("Environment: {}", environment.name().fancy_display());
+        }
+        if !super::tree::print_package_subtree(&dep_map, &direct_deps, package_name) {
+            miette::bail!(
+                "package '{}' is not part of the '{}' environment for platform '{}'",
+                package_name,
+                environment.name().fancy_display(),
+                platform
+            );
+        }
+        Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+        return Ok(());
+    }
+
     // Get the python record from the lock file
     let mut conda_records = locked_deps.iter().filter_map(|d| d.as_conda());
 
@@ -155,25 +379,69 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     };
 
     // Get the explicit project dependencies
-    let mut project_dependency_names = environment
+    let project_dependency_names = explicit_dependency_names(&environment, Some(platform));
+    // Convert the list of package record to specific output format
+    let mut packages_to_output = locked_deps
+        .iter()
+        .map(|p| create_package_to_output(p, &project_dependency_names, &mut registry_index))
+        .collect::<Vec<PackageToOutput>>();
+
+    // Count the direct dependents of every package in the resolved graph if requested
+    if args.with_dependents || args.fields.contains(&ListField::Dependents) {
+        // The dependencies of pypi packages that only apply to some environments are included
+        // if they apply to the python interpreter of the environment.
+        let marker_environment = python_record.and_then(|python_record| {
+            determine_marker_environment(platform, python_record.package_record()).ok()
+        });
+        let dep_map = match &marker_environment {
+            Some(marker_environment) => {
+                super::tree::generate_dependency_map_with_markers(&locked_deps, marker_environment)
+            }
+            None => super::tree::generate_dependency_map(&locked_deps),
+        };
+        let dependents = super::tree::dependents_count(&dep_map);
+        for (package, locked) in packages_to_output.iter_mut().zip(locked_deps.iter()) {
+            let key = match locked {
+                Package::Conda(pkg) => pkg.package_record().name.as_normalized().to_string(),
+                Package::Pypi(pkg) => pkg.data().package.name.as_dist_info_name().into_owned(),
+            };
+            package.dependents = Some(dependents.get(&key).copied().unwrap_or_default());
+        }
+    }
+
+    output_packages(packages_to_output, &args, &environment)?;
+
+    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Returns the names of the conda and pypi dependencies that are explicitly defined in the
+/// project for the platform, the current platform is used when none is given.
+fn explicit_dependency_names(environment: &Environment, platform: Option<Platform>) -> Vec<String> {
+    let platform = platform.unwrap_or_else(|| environment.best_platform());
+    let mut names = environment
         .dependencies(None, Some(platform))
         .names()
         .map(|p| p.as_source().to_string())
         .collect_vec();
-    project_dependency_names.extend(
+    names.extend(
         environment
             .pypi_dependencies(Some(platform))
             .into_iter()
             .map(|(name, _)| name.as_normalized().as_dist_info_name().into_owned()),
     );
-    // Convert the list of package record to specific output format
-    let mut packages_to_output = locked_deps
-        .iter()
-        .map(|p| create_package_to_output(p, &project_dependency_names, &mut registry_index))
-        .collect::<Vec<PackageToOutput>>();
+    names
+}
 
+/// Filters and sorts the packages according to the arguments and prints them as a table or as
+/// json.
+fn output_packages(
+    mut packages_to_output: Vec<PackageToOutput>,
+    args: &Args,
+    environment: &Environment,
+) -> miette::Result<()> {
     // Filter packages by regex if needed
-    if let Some(regex) = args.regex {
+    if let Some(regex) = &args.regex {
         let regex = regex::Regex::new(&regex).map_err(|_| miette::miette!("Invalid regex"))?;
         packages_to_output = packages_to_output
             .into_iter()
@@ -181,6 +449,17 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             .collect::<Vec<_>>();
     }
 
+    // Filter packages by glob pattern if needed
+    if let Some(filter) = &args.filter {
+        let pattern = glob_to_regex(filter)?;
+        packages_to_output.retain(|p| pattern.is_match(&p.name));
+    }
+
+    // Only keep the explicit dependencies if requested
+    if args.explicit {
+        packages_to_output.retain(|p| p.is_explicit);
+    }
+
     // Sort according to the sorting strategy
     match args.sort_by {
         SortBy::Size => {
@@ -196,16 +475,32 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     }
 
     if packages_to_output.is_empty() {
+        // An empty match of a filter is not an error, print nothing.
+        if args.filter.is_some() {
+            return Ok(());
+        }
         eprintln!(
             "{}No packages found.",
             console::style(console::Emoji("✘ ", "")).red(),
         );
-        Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
         return Ok(());
     }
 
     // Print as table string or JSON
-    if args.json || args.json_pretty {
+    if !args.fields.is_empty() {
+        if args.json || args.json_pretty {
+            let packages = select_fields(&packages_to_output, &args.fields);
+            let json_string = if args.json_pretty {
+                serde_json::to_string_pretty(&packages)
+            } else {
+                serde_json::to_string(&packages)
+            }
+            .into_diagnostic()?;
+            println!("{}", json_string);
+        } else {
+            print_fields_as_table(&packages_to_output, &args.fields).into_diagnostic()?;
+        }
+    } else if args.json || args.json_pretty {
         // print packages as json
         json_packages(&packages_to_output, args.json_pretty);
     } else {
@@ -214,27 +509,281 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         }
 
         // print packages as table
-        print_packages_as_table(&packages_to_output).expect("an io error occurred");
+        print_packages_as_table(&packages_to_output, args.with_dependents)
+            .expect("an io error occurred");
     }
-
-    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
     Ok(())
 }
 
-fn print_packages_as_table(packages: &Vec<PackageToOutput>) -> io::Result<()> {
+/// A conda package of which the installed version differs from the locked version, or that is
+/// only installed or only locked.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct PackageDrift {
+    name: String,
+    locked: Option<String>,
+    installed: Option<String>,
+}
+
+/// Compares the locked and the installed conda packages and returns the packages that differ,
+/// sorted by name.
+fn package_drift<'a>(
+    locked: impl IntoIterator<Item = &'a PackageRecord>,
+    installed: impl IntoIterator<Item = &'a PackageRecord>,
+) -> Vec<PackageDrift> {
+    let describe = |record: &PackageRecord| format!("{} {}", record.version, record.build);
+    let locked: HashMap<&str, String> = locked
+        .into_iter()
+        .map(|record| (record.name.as_normalized(), describe(record)))
+        .collect();
+    let installed: HashMap<&str, String> = installed
+        .into_iter()
+        .map(|record| (record.name.as_normalized(), describe(record)))
+        .collect();
+
+    locked
+        .keys()
+        .chain(installed.keys())
+        .unique()
+        .sorted()
+        .filter(|name| locked.get(*name) != installed.get(*name))
+        .map(|name| PackageDrift {
+            name: name.to_string(),
+            locked: locked.get(name).cloned(),
+            installed: installed.get(name).cloned(),
+        })
+        .collect()
+}
+
+/// Prints the differences between the installed and the locked packages.
+fn print_package_drift(
+    drift: &[PackageDrift],
+    args: &Args,
+    environment: &Environment,
+) -> miette::Result<()> {
+    if args.json || args.json_pretty {
+        let json_string = if args.json_pretty {
+            serde_json::to_string_pretty(drift)
+        } else {
+            serde_json::to_string(drift)
+        }
+        .expect("Cannot serialize packages to JSON");
+        println!("{}", json_string);
+        return Ok(());
+    }
+
+    if drift.is_empty() {
+        eprintln!(
+            "{}The installed packages of '{}' match the lock-file",
+            console::style(console::Emoji("✔ ", "")).green(),
+            environment.name().fancy_display(),
+        );
+        return Ok(());
+    }
+
     let mut writer = tabwriter::TabWriter::new(stdout());
+    let header_style = console::Style::new().bold();
+    writeln!(
+        writer,
+        "{}\t{}\t{}",
+        header_style.apply_to("Package"),
+        header_style.apply_to("Locked"),
+        header_style.apply_to("Installed"),
+    )
+    .into_diagnostic()?;
+    for package in drift {
+        let describe = |version: &Option<String>| match version {
+            Some(version) => console::style(version.clone()).fg(Color::Yellow),
+            None => console::style(String::from("missing")).fg(Color::Red),
+        };
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            package.name,
+            describe(&package.locked),
+            describe(&package.installed),
+        )
+        .into_diagnostic()?;
+    }
+    writer.flush().into_diagnostic()
+}
+
+/// The packages that are locked for an environment, as `(kind, name, version)`.
+struct LockedEnvironment {
+    name: String,
+    solve_group: Option<String>,
+    packages: Vec<(&'static str, String, String)>,
+}
+
+/// A package that is locked at more than one version across environments.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct DuplicatePackage {
+    name: String,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solve_group: Option<String>,
+    /// The environments that use each version.
+    versions: BTreeMap<String, Vec<String>>,
+}
+
+/// Returns the packages that are locked at more than one version across the environments, sorted
+/// by name. With `within_solve_group` only environments of the same solve group are compared,
+/// environments without a solve group are then never compared.
+fn duplicate_packages(
+    environments: &[LockedEnvironment],
+    within_solve_group: bool,
+) -> Vec<DuplicatePackage> {
+    type Key<'a> = (Option<&'a str>, &'a str, &'static str);
+    let mut versions: BTreeMap<Key, BTreeMap<String, BTreeSet<&str>>> = BTreeMap::new();
+    for environment in environments {
+        let solve_group = if within_solve_group {
+            match &environment.solve_group {
+                Some(solve_group) => Some(solve_group.as_str()),
+                None => continue,
+            }
+        } else {
+            None
+        };
+        for (kind, name, version) in &environment.packages {
+            versions
+                .entry((solve_group, name.as_str(), *kind))
+                .or_default()
+                .entry(version.clone())
+                .or_default()
+                .insert(environment.name.as_str());
+        }
+    }
+
+    versions
+        .into_iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .sorted_by(|((a_group, a_name, _), _), ((b_group, b_name, _), _)| {
+            a_name.cmp(b_name).then_with(|| a_group.cmp(b_group))
+        })
+        .map(|((solve_group, name, kind), versions)| DuplicatePackage {
+            name: name.to_string(),
+            kind,
+            solve_group: solve_group.map(ToString::to_string),
+            versions: versions
+                .into_iter()
+                .map(|(version, envs)| (version, envs.into_iter().map(String::from).collect()))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Prints the packages that are locked at more than one version.
+fn print_duplicate_packages(duplicates: &[DuplicatePackage], args: &Args) -> miette::Result<()> {
+    if args.json || args.json_pretty {
+        let json_string = if args.json_pretty {
+            serde_json::to_string_pretty(duplicates)
+        } else {
+            serde_json::to_string(duplicates)
+        }
+        .expect("Cannot serialize packages to JSON");
+        println!("{}", json_string);
+        return Ok(());
+    }
 
+    if duplicates.is_empty() {
+        eprintln!(
+            "{}Every package is locked at a single version across the environments",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+        return Ok(());
+    }
+
+    let mut writer = tabwriter::TabWriter::new(stdout());
     let header_style = console::Style::new().bold();
+    write!(
+        writer,
+        "{}	{}	",
+        header_style.apply_to("Package"),
+        header_style.apply_to("Kind"),
+    )
+    .into_diagnostic()?;
+    if args.within_solve_group {
+        write!(writer, "{}	", header_style.apply_to("Solve group")).into_diagnostic()?;
+    }
     writeln!(
         writer,
-        "{}\t{}\t{}\t{}\t{}\t{}",
+        "{}	{}",
+        header_style.apply_to("Version"),
+        header_style.apply_to("Environments"),
+    )
+    .into_diagnostic()?;
+    for package in duplicates {
+        for (idx, (version, environments)) in package.versions.iter().enumerate() {
+            // Only the first row of a package names it.
+            let (name, kind) = if idx == 0 {
+                (package.name.as_str(), package.kind)
+            } else {
+                ("", "")
+            };
+            write!(writer, "{}	{}	", console::style(name).bold(), kind).into_diagnostic()?;
+            if args.within_solve_group {
+                let solve_group = if idx == 0 {
+                    package.solve_group.as_deref().unwrap_or_default()
+                } else {
+                    ""
+                };
+                write!(writer, "{solve_group}	").into_diagnostic()?;
+            }
+            writeln!(
+                writer,
+                "{}	{}",
+                console::style(version).fg(Color::Yellow),
+                environments.join(", ")
+            )
+            .into_diagnostic()?;
+        }
+    }
+    writer.flush().into_diagnostic()
+}
+
+/// Converts a glob pattern into a regex that matches the whole package name. `*` matches any
+/// sequence of characters, `?` matches a single character and `[...]` matches a character class.
+fn glob_to_regex(glob: &str) -> miette::Result<regex::Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' => {
+                let class = chars.by_ref().take_while(|c| *c != ']').collect::<String>();
+                let class = match class.strip_prefix('!') {
+                    Some(negated) => format!("^{negated}"),
+                    None => class,
+                };
+                pattern.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    regex::Regex::new(&pattern).map_err(|_| miette::miette!("Invalid glob pattern '{glob}'"))
+}
+
+fn print_packages_as_table(
+    packages: &Vec<PackageToOutput>,
+    with_dependents: bool,
+) -> io::Result<()> {
+    let mut writer = tabwriter::TabWriter::new(stdout());
+
+    let header_style = console::Style::new().bold();
+    write!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}\t",
         header_style.apply_to("Package"),
         header_style.apply_to("Version"),
         header_style.apply_to("Build"),
         header_style.apply_to("Size"),
         header_style.apply_to("Kind"),
-        header_style.apply_to("Source")
     )?;
+    if with_dependents {
+        write!(writer, "{}\t", header_style.apply_to("Dependents"))?;
+    }
+    writeln!(writer, "{}", header_style.apply_to("Source"))?;
 
     for package in packages {
         if package.is_explicit {
@@ -253,13 +802,25 @@ fn print_packages_as_table(packages: &Vec<PackageToOutput>) -> io::Result<()> {
             .map(|size| human_bytes(size as f64))
             .unwrap_or_default();
 
-        writeln!(
+        write!(
             writer,
-            "\t{}\t{}\t{}\t{}\t{}{}",
+            "\t{}\t{}\t{}\t{}\t",
             &package.version,
             package.build.as_deref().unwrap_or(""),
             size_human,
             &package.kind,
+        )?;
+
+        // Packages that nothing depends on are candidates for removal
+        match package.dependents {
+            Some(0) => write!(writer, "{}\t", console::style(0).fg(Color::Yellow))?,
+            Some(count) => write!(writer, "{count}\t")?,
+            None => {}
+        }
+
+        writeln!(
+            writer,
+            "{}{}",
             package.source.as_deref().unwrap_or(""),
             if package.is_editable {
                 format!(" {}", console::style("(editable)").fg(Color::Yellow))
@@ -272,6 +833,47 @@ fn print_packages_as_table(packages: &Vec<PackageToOutput>) -> io::Result<()> {
     writer.flush()
 }
 
+/// Prints only the given fields of the packages as a table, in the order of the fields.
+fn print_fields_as_table(packages: &[PackageToOutput], fields: &[ListField]) -> io::Result<()> {
+    let mut writer = tabwriter::TabWriter::new(stdout());
+    let header_style = console::Style::new().bold();
+    writeln!(
+        writer,
+        "{}",
+        fields
+            .iter()
+            .map(|field| header_style.apply_to(field.header()))
+            .format("\t")
+    )?;
+    for package in packages {
+        writeln!(
+            writer,
+            "{}",
+            fields
+                .iter()
+                .map(|field| field.table_value(package))
+                .format("\t")
+        )?;
+    }
+    writer.flush()
+}
+
+/// Returns the given fields of every package, in the order of the fields.
+fn select_fields(
+    packages: &[PackageToOutput],
+    fields: &[ListField],
+) -> Vec<IndexMap<&'static str, serde_json::Value>> {
+    packages
+        .iter()
+        .map(|package| {
+            fields
+                .iter()
+                .map(|field| (field.json_key(), field.json_value(package)))
+                .collect()
+        })
+        .collect()
+}
+
 fn json_packages(packages: &Vec<PackageToOutput>, json_pretty: bool) {
     let json_string = if json_pretty {
         serde_json::to_string_pretty(&packages)
@@ -283,6 +885,25 @@ fn json_packages(packages: &Vec<PackageToOutput>, json_pretty: bool) {
     println!("{}", json_string);
 }
 
+fn installed_package_to_output(
+    record: &PrefixRecord,
+    project_dependency_names: &[String],
+) -> PackageToOutput {
+    let package_record = &record.repodata_record.package_record;
+    let name = package_record.name.as_normalized().to_string();
+    PackageToOutput {
+        is_explicit: project_dependency_names.contains(&name),
+        name,
+        version: package_record.version.to_string(),
+        build: Some(package_record.build.clone()),
+        size_bytes: package_record.size,
+        kind: "conda".to_string(),
+        source: Some(record.repodata_record.file_name.clone()),
+        is_editable: false,
+        dependents: None,
+    }
+}
+
 fn create_package_to_output<'a, 'b>(
     p: &'b Package,
     project_dependency_names: &'a [String],
@@ -344,5 +965,144 @@ fn create_package_to_output<'a, 'b>(
         source,
         is_explicit,
         is_editable,
+        dependents: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        duplicate_packages, glob_to_regex, package_drift, select_fields, ListField,
+        LockedEnvironment, PackageDrift, PackageToOutput,
+    };
+    use rattler_conda_types::{PackageName, PackageRecord, Version};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_package_drift() {
+        let record = |name: &str, version: &str, build: &str| {
+            PackageRecord::new(
+                PackageName::new_unchecked(name),
+                Version::from_str(version).unwrap(),
+                build.to_string(),
+            )
+        };
+        let locked = [
+            record("python", "3.12.0", "h1_0"),
+            record("numpy", "1.26.0", "py312_0"),
+            record("pip", "24.0", "pyhd8ed1ab_0"),
+        ];
+        let installed = [
+            record("python", "3.12.0", "h1_0"),
+            record("numpy", "1.26.4", "py312_0"),
+            record("requests", "2.31.0", "pyhd8ed1ab_0"),
+        ];
+
+        let drift = package_drift(locked.iter(), installed.iter());
+        assert_eq!(
+            drift,
+            vec![
+                PackageDrift {
+                    name: String::from("numpy"),
+                    locked: Some(String::from("1.26.0 py312_0")),
+                    installed: Some(String::from("1.26.4 py312_0")),
+                },
+                PackageDrift {
+                    name: String::from("pip"),
+                    locked: Some(String::from("24.0 pyhd8ed1ab_0")),
+                    installed: None,
+                },
+                PackageDrift {
+                    name: String::from("requests"),
+                    locked: None,
+                    installed: Some(String::from("2.31.0 pyhd8ed1ab_0")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_packages() {
+        let environment =
+            |name: &str, solve_group: Option<&str>, packages: &[(&str, &str)]| LockedEnvironment {
+                name: name.to_string(),
+                solve_group: solve_group.map(ToString::to_string),
+                packages: packages
+                    .iter()
+                    .map(|(name, version)| ("conda", name.to_string(), version.to_string()))
+                    .collect(),
+            };
+        let environments = [
+            environment(
+                "default",
+                Some("prod"),
+                &[("python", "3.12.0"), ("numpy", "1.26.4")],
+            ),
+            environment(
+                "test",
+                Some("prod"),
+                &[("python", "3.11.8"), ("numpy", "1.26.4")],
+            ),
+            environment("py39", None, &[("python", "3.9.18"), ("numpy", "1.24.0")]),
+        ];
+
+        let duplicates = duplicate_packages(&environments, false);
+        assert_eq!(
+            duplicates.iter().map(|p| &p.name).collect::<Vec<_>>(),
+            vec!["numpy", "python"]
+        );
+        assert_eq!(duplicates[0].versions["1.26.4"], vec!["default", "test"]);
+        assert_eq!(duplicates[1].versions.len(), 3);
+
+        // The environment without a solve group is not compared.
+        let duplicates = duplicate_packages(&environments, true);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "python");
+        assert_eq!(duplicates[0].solve_group.as_deref(), Some("prod"));
+        assert_eq!(duplicates[0].versions["3.12.0"], vec!["default"]);
+        assert_eq!(duplicates[0].versions["3.11.8"], vec!["test"]);
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let pattern = glob_to_regex("py*").unwrap();
+        assert!(pattern.is_match("python"));
+        assert!(pattern.is_match("py"));
+        assert!(!pattern.is_match("cpython"));
+
+        let pattern = glob_to_regex("lib?").unwrap();
+        assert!(pattern.is_match("libz"));
+        assert!(!pattern.is_match("libzz"));
+
+        let pattern = glob_to_regex("lib[!c]*").unwrap();
+        assert!(pattern.is_match("libzlib"));
+        assert!(!pattern.is_match("libcurl"));
+
+        let pattern = glob_to_regex("ca-certificates").unwrap();
+        assert!(pattern.is_match("ca-certificates"));
+        assert!(!pattern.is_match("ca_certificates"));
+
+        let pattern = glob_to_regex("python.app").unwrap();
+        assert!(!pattern.is_match("pythonXapp"));
+    }
+
+    #[test]
+    fn test_select_fields() {
+        let package = PackageToOutput {
+            name: String::from("numpy"),
+            version: String::from("1.26.4"),
+            build: Some(String::from("py311_0")),
+            size_bytes: Some(1024),
+            kind: String::from("conda"),
+            source: None,
+            is_explicit: true,
+            is_editable: false,
+            dependents: None,
+        };
+        let selected = select_fields(&[package], &[ListField::Version, ListField::Name]);
+        assert_eq!(
+            serde_json::to_string(&selected).unwrap(),
+            r#"[{"version":"1.26.4","name":"numpy"}]"#
+        );
     }
 }
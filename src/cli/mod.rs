@@ -12,6 +12,9 @@ use tracing_subscriber::{filter::LevelFilter, util::SubscriberInitExt, EnvFilter
 
 pub mod add;
 pub mod completion;
+pub mod config;
+pub mod doctor;
+pub mod export;
 pub mod global;
 pub mod info;
 pub mod init;
@@ -26,6 +29,7 @@ pub mod shell;
 pub mod shell_hook;
 pub mod task;
 pub mod tree;
+pub mod update;
 pub mod upload;
 
 #[derive(Parser, Debug)]
@@ -85,6 +89,10 @@ pub enum Command {
     List(list::Args),
     #[clap(visible_alias = "t")]
     Tree(tree::Args),
+    Update(update::Args),
+    Export(export::Args),
+    Doctor(doctor::Args),
+    Config(config::Args),
 }
 
 #[derive(Parser, Debug, Default, Copy, Clone)]
@@ -246,6 +254,10 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::SelfUpdate(cmd) => self_update::execute(cmd).await,
         Command::List(cmd) => list::execute(cmd).await,
         Command::Tree(cmd) => tree::execute(cmd).await,
+        Command::Update(cmd) => update::execute(cmd).await,
+        Command::Export(cmd) => export::execute(cmd).await,
+        Command::Doctor(cmd) => doctor::execute(cmd).await,
+        Command::Config(cmd) => config::execute(cmd),
     }
 }
 
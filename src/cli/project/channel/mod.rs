@@ -1,6 +1,7 @@
 pub mod add;
 pub mod list;
 pub mod remove;
+pub mod set_priority;
 
 use crate::Project;
 use clap::Parser;
@@ -29,6 +30,8 @@ pub enum Command {
     /// Remove channel(s) from the project file and updates the lockfile.
     #[clap(visible_alias = "rm")]
     Remove(remove::Args),
+    /// Move a channel to another position in the project file, changing its priority.
+    SetPriority(set_priority::Args),
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
@@ -38,5 +41,6 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         Command::Add(args) => add::execute(project, args).await,
         Command::List(args) => list::execute(project, args),
         Command::Remove(args) => remove::execute(project, args).await,
+        Command::SetPriority(args) => set_priority::execute(project, args),
     }
 }
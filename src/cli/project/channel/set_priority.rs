@@ -0,0 +1,46 @@
+use crate::project::manifest::FeatureName;
+use crate::Project;
+use clap::Parser;
+use miette::IntoDiagnostic;
+use rattler_conda_types::Channel;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// The channel name or URL
+    pub channel: String,
+
+    /// The zero based position to move the channel to, the first channel has the highest
+    /// priority.
+    pub index: usize,
+
+    /// The name of the feature of which the channel is moved.
+    #[clap(long, short)]
+    pub feature: Option<String>,
+}
+
+pub fn execute(mut project: Project, args: Args) -> miette::Result<()> {
+    let feature_name = args
+        .feature
+        .map_or(FeatureName::Default, FeatureName::Named);
+
+    let channel =
+        Channel::from_str(&args.channel, project.config().channel_config()).into_diagnostic()?;
+
+    // Move the channel in the manifest, changing the order of the channels makes the lock-file
+    // out of date so it is updated by the next command that needs it.
+    project
+        .manifest
+        .set_channel_position(&channel, args.index, &feature_name)?;
+    project.save()?;
+
+    // Report back to the user
+    eprintln!(
+        "{}Moved {} ({}) to position {}, the lock-file will be updated on the next install",
+        console::style(console::Emoji("✔ ", "")).green(),
+        args.channel,
+        channel.base_url(),
+        args.index
+    );
+
+    Ok(())
+}
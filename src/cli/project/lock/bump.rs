@@ -0,0 +1,248 @@
+use crate::cli::update::update_environments;
+use crate::lock_file::LockFileDiff;
+use crate::project::has_features::HasFeatures;
+use crate::project::manifest::EnvironmentName;
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use rattler_conda_types::{Platform, Version};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Only print the changelog, without writing the lock file.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// A version change of an explicit dependency, merged over the environments in which it changed
+/// the same way.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct BumpRow {
+    package: String,
+    before: Option<String>,
+    after: Option<String>,
+    change: &'static str,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let diff = update_environments(&project, &project.environments(), args.dry_run).await?;
+
+    // The names of the conda and pypi dependencies in the manifest, per environment and platform.
+    let explicit = |environment: &str, platform: &str| -> HashSet<(&'static str, String)> {
+        let Some(environment) = EnvironmentName::from_str(environment)
+            .ok()
+            .and_then(|name| project.environment(&name))
+        else {
+            return HashSet::new();
+        };
+        let platform = Platform::from_str(platform).ok();
+        let conda = environment
+            .dependencies(None, platform)
+            .names()
+            .map(|name| ("conda", name.as_normalized().to_string()))
+            .collect_vec();
+        let pypi = environment
+            .pypi_dependencies(platform)
+            .names()
+            .map(|name| ("pypi", name.as_normalized().to_string()))
+            .collect_vec();
+        conda.into_iter().chain(pypi).collect()
+    };
+
+    print!("{}", changelog(&diff, explicit));
+
+    if args.dry_run {
+        eprintln!("The lock-file was not updated because of `--dry-run`");
+    } else if !diff.is_empty() {
+        eprintln!(
+            "{}Updated the lock-file",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+    }
+    Ok(())
+}
+
+/// Renders the version changes of the explicit dependencies as a Markdown table with the columns
+/// of the dependency bump pull requests: package, before, after, change type and environments.
+fn changelog(
+    diff: &LockFileDiff,
+    explicit: impl Fn(&str, &str) -> HashSet<(&'static str, String)>,
+) -> String {
+    let mut rows: BTreeMap<BumpRow, BTreeSet<&str>> = BTreeMap::new();
+    for (environment, platforms) in &diff.environments {
+        for (platform, platform_diff) in platforms {
+            let dependencies = explicit(environment, platform);
+            let is_explicit =
+                |kind: &'static str, name: &str| dependencies.contains(&(kind, name.to_string()));
+            let changes = platform_diff
+                .added
+                .iter()
+                .filter(|package| is_explicit(package.kind, &package.name))
+                .map(|package| (package.kind, &package.name, None, Some(&package.version)))
+                .chain(
+                    platform_diff
+                        .removed
+                        .iter()
+                        .filter(|package| is_explicit(package.kind, &package.name))
+                        .map(|package| (package.kind, &package.name, Some(&package.version), None)),
+                )
+                .chain(
+                    platform_diff
+                        .changed
+                        .iter()
+                        .filter(|package| is_explicit(package.kind, &package.name))
+                        .map(|package| {
+                            (
+                                package.kind,
+                                &package.name,
+                                Some(&package.before),
+                                Some(&package.after),
+                            )
+                        }),
+                );
+            for (kind, name, before, after) in changes {
+                // The version of a conda package is followed by its build string.
+                let before = before.map(|version| version_only(version).to_string());
+                let after = after.map(|version| version_only(version).to_string());
+                let row = BumpRow {
+                    package: if kind == "pypi" {
+                        format!("{name} (pypi)")
+                    } else {
+                        name.clone()
+                    },
+                    change: change_type(before.as_deref(), after.as_deref()),
+                    before,
+                    after,
+                };
+                rows.entry(row).or_default().insert(environment.as_str());
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return String::from("No explicit dependencies were updated.\n");
+    }
+
+    let mut table = String::from(
+        "| Package | Before | After | Change | Environments |\n| --- | --- | --- | --- | --- |\n",
+    );
+    for (row, environments) in rows {
+        writeln!(
+            table,
+            "| {} | {} | {} | {} | {} |",
+            row.package,
+            row.before.as_deref().unwrap_or("-"),
+            row.after.as_deref().unwrap_or("-"),
+            row.change,
+            environments.iter().join(", ")
+        )
+        .expect("writing to a string never fails");
+    }
+    table
+}
+
+fn version_only(version: &str) -> &str {
+    version
+        .split_once(' ')
+        .map_or(version, |(version, _)| version)
+}
+
+/// Classifies a version change by the first version segment that changed.
+fn change_type(before: Option<&str>, after: Option<&str>) -> &'static str {
+    let (before, after) = match (before, after) {
+        (None, _) => return "added",
+        (_, None) => return "removed",
+        (Some(before), Some(after)) => (before, after),
+    };
+    if before == after {
+        return "build";
+    }
+    let (Ok(before), Ok(after)) = (Version::from_str(before), Version::from_str(after)) else {
+        return "changed";
+    };
+    if after < before {
+        return "downgrade";
+    }
+    match (before.as_major_minor(), after.as_major_minor()) {
+        (Some((major, _)), Some((new_major, _))) if major != new_major => "major",
+        (Some((_, minor)), Some((_, new_minor))) if minor != new_minor => "minor",
+        _ => "patch",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock_file::{ChangedPackage, LockedPackageVersion, PlatformDiff};
+
+    #[test]
+    fn test_change_type() {
+        assert_eq!(change_type(None, Some("1.0")), "added");
+        assert_eq!(change_type(Some("1.0"), None), "removed");
+        assert_eq!(change_type(Some("1.2.3"), Some("2.0.0")), "major");
+        assert_eq!(change_type(Some("1.2.3"), Some("1.3.0")), "minor");
+        assert_eq!(change_type(Some("1.2.3"), Some("1.2.4")), "patch");
+        assert_eq!(change_type(Some("1.2.3"), Some("1.2.3")), "build");
+        assert_eq!(change_type(Some("1.2.3"), Some("1.1.0")), "downgrade");
+    }
+
+    #[test]
+    fn test_changelog() {
+        let platform_diff = PlatformDiff {
+            added: vec![LockedPackageVersion {
+                name: String::from("requests"),
+                kind: "pypi",
+                version: String::from("2.32.0"),
+            }],
+            removed: vec![],
+            changed: vec![
+                ChangedPackage {
+                    name: String::from("python"),
+                    kind: "conda",
+                    before: String::from("3.11.9 h_0"),
+                    after: String::from("3.12.3 h_0"),
+                },
+                // Not a dependency in the manifest.
+                ChangedPackage {
+                    name: String::from("openssl"),
+                    kind: "conda",
+                    before: String::from("3.2.0 h_0"),
+                    after: String::from("3.3.0 h_0"),
+                },
+            ],
+        };
+        let diff = LockFileDiff {
+            environments: BTreeMap::from([
+                (
+                    String::from("default"),
+                    BTreeMap::from([(String::from("linux-64"), platform_diff.clone())]),
+                ),
+                (
+                    String::from("test"),
+                    BTreeMap::from([(String::from("linux-64"), platform_diff)]),
+                ),
+            ]),
+        };
+
+        let explicit = |_: &str, _: &str| {
+            HashSet::from([
+                ("conda", String::from("python")),
+                ("pypi", String::from("requests")),
+            ])
+        };
+        assert_eq!(
+            changelog(&diff, explicit),
+            "| Package | Before | After | Change | Environments |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | python | 3.11.9 | 3.12.3 | minor | default, test |\n\
+             | requests (pypi) | - | 2.32.0 | added | default, test |\n"
+        );
+        assert_eq!(
+            changelog(&LockFileDiff::default(), explicit),
+            "No explicit dependencies were updated.\n"
+        );
+    }
+}
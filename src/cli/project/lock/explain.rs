@@ -0,0 +1,335 @@
+use crate::lock_file::load_lock_file;
+use crate::project::has_features::HasFeatures;
+use crate::project::SpecType;
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_conda_types::version_spec::{EqualityOperator, LogicalOperator, RangeOperator};
+use rattler_conda_types::{
+    MatchSpec, PackageName, PackageRecord, ParseStrictness, Platform, Version, VersionSpec,
+};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The conda package to explain the locked version of.
+    pub package: String,
+
+    /// The environment to look at, if none is provided the default environment is used.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+
+    /// The platform to look at, defaults to the current platform.
+    #[arg(long, short)]
+    pub platform: Option<Platform>,
+
+    /// Print the explanation as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Why a package is locked at its version.
+#[derive(Debug, Serialize)]
+struct Explanation {
+    package: String,
+    version: String,
+    build: String,
+    environment: String,
+    platform: Platform,
+    /// The specs of the manifest that name the package.
+    manifest: Vec<ManifestConstraint>,
+    /// The specs of the locked packages that name the package.
+    transitive: Vec<TransitiveConstraint>,
+    /// The transitive constraint that limits the version the most.
+    tightest: Option<TransitiveConstraint>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestConstraint {
+    spec: String,
+    table: String,
+    /// Why the package is pinned, for a constraint of the `[pins]` table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct TransitiveConstraint {
+    /// The locked package that declares the constraint.
+    dependent: String,
+    dependent_version: String,
+    spec: String,
+    /// Either `depends` or `constrains`.
+    kind: &'static str,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+    let name = PackageName::from_str(&args.package).into_diagnostic()?;
+
+    let lock_file = load_lock_file(&project).await?;
+    let locked_records = lock_file
+        .environment(environment.name().as_str())
+        .map(|env| env.conda_repodata_records_for_platform(platform))
+        .transpose()
+        .into_diagnostic()?
+        .flatten()
+        .unwrap_or_default();
+    let records = locked_records
+        .iter()
+        .map(|record| &record.package_record)
+        .collect_vec();
+    let Some(locked) = records.iter().find(|record| record.name == name) else {
+        miette::bail!(
+            "'{}' is not locked in the environment '{}' for platform '{}'",
+            name.as_source(),
+            environment.name().fancy_display(),
+            platform
+        );
+    };
+
+    let manifest = [SpecType::Run, SpecType::Host, SpecType::Build]
+        .into_iter()
+        .flat_map(|spec_type| {
+            environment
+                .dependencies(Some(spec_type), Some(platform))
+                .iter()
+                .filter(|(dependency, _)| **dependency == name)
+                .flat_map(|(_, specs)| specs.iter().map(ToString::to_string).collect_vec())
+                .map(|spec| ManifestConstraint {
+                    spec,
+                    table: spec_type.name().to_string(),
+                    reason: None,
+                })
+                .collect_vec()
+        })
+        .chain(project.pins().get(&name).map(|pin| ManifestConstraint {
+            spec: pin.version.to_string(),
+            table: String::from("pins"),
+            reason: pin.reason.clone(),
+        }))
+        .collect_vec();
+
+    let transitive = transitive_constraints(&records, &name);
+    let explanation = Explanation {
+        package: name.as_source().to_string(),
+        version: locked.version.to_string(),
+        build: locked.build.clone(),
+        environment: environment.name().to_string(),
+        platform,
+        manifest,
+        tightest: tightest_constraint(&transitive).cloned(),
+        transitive,
+    };
+
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&explanation).into_diagnostic()?
+        );
+    } else {
+        print_explanation(&explanation);
+    }
+    Ok(())
+}
+
+fn print_explanation(explanation: &Explanation) {
+    println!(
+        "{} {} {} is locked in the environment '{}' for platform '{}'",
+        console::style(&explanation.package).bold(),
+        console::style(&explanation.version).green(),
+        explanation.build,
+        explanation.environment,
+        explanation.platform
+    );
+
+    println!("\n{}", console::style("Manifest constraints:").bold());
+    if explanation.manifest.is_empty() {
+        println!("  none, the package is a transitive dependency");
+    }
+    for constraint in &explanation.manifest {
+        match &constraint.reason {
+            Some(reason) => println!(
+                "  {} {} ({}): {}",
+                explanation.package,
+                constraint.spec,
+                constraint.table,
+                console::style(reason).italic()
+            ),
+            None => println!(
+                "  {} {} ({})",
+                explanation.package, constraint.spec, constraint.table
+            ),
+        }
+    }
+
+    println!("\n{}", console::style("Transitive constraints:").bold());
+    if explanation.transitive.is_empty() {
+        println!("  none");
+    }
+    for constraint in &explanation.transitive {
+        println!(
+            "  {} {}: {} ({})",
+            constraint.dependent, constraint.dependent_version, constraint.spec, constraint.kind
+        );
+    }
+
+    if let Some(tightest) = &explanation.tightest {
+        println!(
+            "\n{} {} from {} {}",
+            console::style("Tightest transitive constraint:").bold(),
+            console::style(&tightest.spec).yellow(),
+            tightest.dependent,
+            tightest.dependent_version
+        );
+    }
+}
+
+/// Returns the `depends` and `constrains` entries of the locked packages that name the package.
+fn transitive_constraints(
+    records: &[&PackageRecord],
+    name: &PackageName,
+) -> Vec<TransitiveConstraint> {
+    let mut constraints = Vec::new();
+    for record in records.iter().sorted_by_key(|r| r.name.as_normalized()) {
+        let depends = record.depends.iter().map(|spec| (spec, "depends"));
+        let constrains = record.constrains.iter().map(|spec| (spec, "constrains"));
+        for (spec, kind) in depends.chain(constrains) {
+            let Ok(match_spec) = MatchSpec::from_str(spec, ParseStrictness::Lenient) else {
+                continue;
+            };
+            if match_spec.name.as_ref() == Some(name) {
+                constraints.push(TransitiveConstraint {
+                    dependent: record.name.as_source().to_string(),
+                    dependent_version: record.version.to_string(),
+                    spec: spec.clone(),
+                    kind,
+                });
+            }
+        }
+    }
+    constraints
+}
+
+/// Returns the constraint that limits the version the most. The solver prefers the newest
+/// versions, so the constraint with the lowest upper bound is the tightest. Without upper bounds
+/// the constraint with the highest lower bound is the tightest.
+fn tightest_constraint(constraints: &[TransitiveConstraint]) -> Option<&TransitiveConstraint> {
+    constraints
+        .iter()
+        .filter_map(|constraint| {
+            let match_spec =
+                MatchSpec::from_str(&constraint.spec, ParseStrictness::Lenient).ok()?;
+            let (lower, upper) = match_spec
+                .version
+                .as_ref()
+                .map(version_bounds)
+                .unwrap_or_default();
+            (lower.is_some() || upper.is_some()).then_some((constraint, lower, upper))
+        })
+        .min_by(|(_, a_lower, a_upper), (_, b_lower, b_upper)| {
+            match (a_upper, b_upper) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(|| b_lower.cmp(a_lower))
+        })
+        .map(|(constraint, _, _)| constraint)
+}
+
+/// Returns the lower and upper bound of the versions that the spec allows, as far as they can be
+/// determined.
+fn version_bounds(spec: &VersionSpec) -> (Option<Version>, Option<Version>) {
+    match spec {
+        VersionSpec::Exact(EqualityOperator::Equals, version) => {
+            (Some(version.clone()), Some(version.clone()))
+        }
+        VersionSpec::Range(RangeOperator::Greater | RangeOperator::GreaterEquals, version) => {
+            (Some(version.clone()), None)
+        }
+        VersionSpec::Range(RangeOperator::Less | RangeOperator::LessEquals, version) => {
+            (None, Some(version.clone()))
+        }
+        VersionSpec::Group(LogicalOperator::And, specs) => {
+            let bounds = specs.iter().map(version_bounds).collect_vec();
+            (
+                bounds.iter().filter_map(|(lower, _)| lower.clone()).max(),
+                bounds.iter().filter_map(|(_, upper)| upper.clone()).min(),
+            )
+        }
+        VersionSpec::Group(LogicalOperator::Or, specs) => {
+            let bounds = specs.iter().map(version_bounds).collect_vec();
+            let lower = bounds
+                .iter()
+                .map(|(lower, _)| lower.clone())
+                .collect::<Option<Vec<_>>>()
+                .and_then(|lowers| lowers.into_iter().min());
+            let upper = bounds
+                .iter()
+                .map(|(_, upper)| upper.clone())
+                .collect::<Option<Vec<_>>>()
+                .and_then(|uppers| uppers.into_iter().max());
+            (lower, upper)
+        }
+        _ => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(name: &str, version: &str, depends: &[&str]) -> PackageRecord {
+        let mut record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            Version::from_str(version).unwrap(),
+            String::from("0"),
+        );
+        record.depends = depends.iter().map(ToString::to_string).collect();
+        record
+    }
+
+    #[test]
+    fn test_transitive_constraints() {
+        let numpy = record("numpy", "1.26.4", &["python >=3.9"]);
+        let scipy = record("scipy", "1.11.4", &["numpy >=1.23.5,<2.0a0", "python"]);
+        let pandas = record("pandas", "2.1.0", &["numpy >=1.24"]);
+        let mut numba = record("numba", "0.59.0", &["numpy >=1.22,<1.27"]);
+        numba.constrains = vec![String::from("numpy <1.26.5")];
+        let records = vec![&numpy, &scipy, &pandas, &numba];
+
+        let constraints = transitive_constraints(&records, &PackageName::new_unchecked("numpy"));
+        assert_eq!(
+            constraints
+                .iter()
+                .map(|c| format!("{}: {} ({})", c.dependent, c.spec, c.kind))
+                .collect_vec(),
+            vec![
+                "numba: numpy >=1.22,<1.27 (depends)",
+                "numba: numpy <1.26.5 (constrains)",
+                "pandas: numpy >=1.24 (depends)",
+                "scipy: numpy >=1.23.5,<2.0a0 (depends)",
+            ]
+        );
+        assert_eq!(
+            tightest_constraint(&constraints).unwrap().spec,
+            "numpy <1.26.5"
+        );
+
+        // Without upper bounds the highest lower bound is the tightest.
+        let constraints = transitive_constraints(
+            &[&pandas, &record("xarray", "2024.1", &["numpy >=1.23"])],
+            &PackageName::new_unchecked("numpy"),
+        );
+        assert_eq!(
+            tightest_constraint(&constraints).unwrap().dependent,
+            "pandas"
+        );
+    }
+}
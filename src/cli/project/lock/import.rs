@@ -0,0 +1,447 @@
+use crate::lock_file::{
+    load_lock_file, merge_lock_files, remove_environments, sort_conda_records, sort_pypi_records,
+    write_lock_file, OutdatedEnvironments, PypiRecord,
+};
+use crate::Project;
+use clap::Parser;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use pep508_rs::Requirement;
+use rattler_conda_types::{
+    Channel, ChannelConfig, NoArchType, PackageName, PackageRecord, Platform, RepoDataRecord,
+    Version,
+};
+use rattler_digest::{parse_digest_from_hex, Md5, Md5Hash, Sha256, Sha256Hash};
+use rattler_lock::{
+    LockFile, PackageHashes, PypiPackageData, PypiPackageEnvironmentData, UrlOrPath,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use url::Url;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The conda-lock file to import.
+    #[arg(default_value = "conda-lock.yml")]
+    pub input: PathBuf,
+
+    /// The environment to import the packages into, defaults to the default environment.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+}
+
+/// The root of a conda-lock v1 lock-file, only the fields that are imported.
+#[derive(Debug, Deserialize)]
+struct CondaLock {
+    version: u32,
+    metadata: CondaLockMetadata,
+    #[serde(default)]
+    package: Vec<CondaLockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CondaLockMetadata {
+    #[serde(default)]
+    channels: Vec<CondaLockChannel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CondaLockChannel {
+    url: String,
+    #[serde(default)]
+    used_env_vars: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CondaLockPackage {
+    name: String,
+    version: String,
+    manager: String,
+    platform: String,
+    #[serde(default)]
+    dependencies: IndexMap<String, String>,
+    url: String,
+    #[serde(default)]
+    hash: CondaLockHash,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CondaLockHash {
+    md5: Option<String>,
+    sha256: Option<String>,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+
+    let contents = std::fs::read_to_string(&args.input)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read '{}'", args.input.display()))?;
+    let conda_lock: CondaLock = serde_yaml::from_str(&contents)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("'{}' is not a conda-lock file", args.input.display()))?;
+
+    let (imported, unmapped) = from_conda_lock(
+        conda_lock,
+        environment.name().as_str(),
+        project.config().channel_config(),
+    )?;
+    let imported_count: usize =
+        imported
+            .environment(environment.name().as_str())
+            .map_or(0, |locked| {
+                locked
+                    .platforms()
+                    .map(|platform| locked.packages(platform).into_iter().flatten().count())
+                    .sum()
+            });
+    for message in &unmapped {
+        eprintln!(
+            "{}{message}",
+            console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+        );
+    }
+
+    // Keep the other environments of the current lock-file.
+    let lock_file = if project.has_lock_file() {
+        let current = load_lock_file(&project).await?;
+        merge_lock_files(&[
+            remove_environments(&current, &[environment.name().to_string()])?,
+            imported,
+        ])?
+    } else {
+        imported
+    };
+
+    let outdated = OutdatedEnvironments::from_project_and_lock_file(&project, &lock_file);
+    let outdated_platforms = outdated
+        .conda
+        .get(&environment)
+        .into_iter()
+        .chain(outdated.pypi.get(&environment))
+        .flatten()
+        .map(Platform::as_str)
+        .sorted()
+        .dedup()
+        .collect_vec();
+    if !outdated_platforms.is_empty() {
+        miette::bail!(
+            help = "import a conda-lock file that was created from the same dependencies, or solve the environment with `pixi install`",
+            "the imported packages don't satisfy the manifest of environment '{}' on {}, the lock-file was not written",
+            environment.name().fancy_display(),
+            outdated_platforms.join(", ")
+        );
+    }
+
+    write_lock_file(&project, &lock_file)?;
+    eprintln!(
+        "{}Imported {} packages of '{}' into the environment '{}'",
+        console::style(console::Emoji("✔ ", "")).green(),
+        imported_count,
+        args.input.display(),
+        environment.name().fancy_display()
+    );
+    Ok(())
+}
+
+/// Converts the packages of a conda-lock v1 file into a lock-file with a single environment. Also
+/// returns a description of every package or field that could not be imported.
+fn from_conda_lock(
+    conda_lock: CondaLock,
+    environment_name: &str,
+    channel_config: &ChannelConfig,
+) -> miette::Result<(LockFile, Vec<String>)> {
+    if conda_lock.version != 1 {
+        miette::bail!(
+            "only version 1 of the conda-lock format can be imported, found version {}",
+            conda_lock.version
+        );
+    }
+
+    let mut builder = LockFile::builder();
+    let channels = conda_lock
+        .metadata
+        .channels
+        .into_iter()
+        .map(|channel| {
+            let url = Channel::from_str(&channel.url, channel_config)
+                .into_diagnostic()?
+                .base_url()
+                .to_string();
+            Ok(rattler_lock::Channel {
+                url,
+                used_env_vars: channel.used_env_vars,
+            })
+        })
+        .collect::<miette::Result<Vec<_>>>()?;
+    builder.set_channels(environment_name, channels);
+
+    let mut unmapped = Vec::new();
+    let mut platforms: BTreeMap<Platform, (Vec<RepoDataRecord>, Vec<PypiRecord>)> = BTreeMap::new();
+    for package in &conda_lock.package {
+        let Ok(platform) = Platform::from_str(&package.platform) else {
+            unmapped.push(format!(
+                "skipped '{}' because of the unknown platform '{}'",
+                package.name, package.platform
+            ));
+            continue;
+        };
+        let (conda_records, pypi_records) = platforms.entry(platform).or_default();
+        let result = match package.manager.as_str() {
+            "conda" => conda_record(package).map(|record| {
+                if record.package_record.noarch.is_python() {
+                    unmapped.push(format!(
+                        "imported '{}' for {platform} as a `noarch: python` package, the noarch type is guessed from its python dependency",
+                        package.name
+                    ));
+                }
+                conda_records.push(record)
+            }),
+            "pip" => pypi_record(package).map(|record| pypi_records.push(record)),
+            manager => Err(format!("the package manager '{manager}' is not supported")),
+        };
+        match result {
+            Err(reason) => unmapped.push(format!(
+                "skipped '{}' for {platform}: {reason}",
+                package.name
+            )),
+            // pixi has no categories, packages of all categories end up in the environment.
+            Ok(()) => {
+                if let Some(category) = package.category.as_deref().filter(|c| *c != "main") {
+                    unmapped.push(format!(
+                        "imported '{}' for {platform} as a regular package, the '{category}' category is not kept",
+                        package.name
+                    ));
+                }
+            }
+        }
+    }
+
+    for (platform, (mut conda_records, mut pypi_records)) in platforms {
+        sort_conda_records(&mut conda_records);
+        sort_pypi_records(&mut pypi_records);
+        for record in conda_records {
+            builder.add_conda_package(environment_name, platform, record.into());
+        }
+        for (pkg_data, pkg_env_data) in pypi_records {
+            builder.add_pypi_package(environment_name, platform, pkg_data, pkg_env_data);
+        }
+    }
+
+    Ok((builder.finish(), unmapped))
+}
+
+/// Converts a conda package of a conda-lock file to a record. conda-lock doesn't store the build
+/// string, it is taken from the file name of the package.
+fn conda_record(package: &CondaLockPackage) -> Result<RepoDataRecord, String> {
+    let url = Url::parse(&package.url).map_err(|err| format!("invalid url: {err}"))?;
+    let mut segments = url.path_segments().into_iter().flatten().rev();
+    let file_name = segments.next().unwrap_or_default().to_string();
+    let subdir = segments
+        .next()
+        .unwrap_or(package.platform.as_str())
+        .to_string();
+
+    let build = file_name
+        .strip_suffix(".conda")
+        .or_else(|| file_name.strip_suffix(".tar.bz2"))
+        .and_then(|stem| stem.strip_prefix(&format!("{}-{}-", package.name, package.version)))
+        .ok_or_else(|| {
+            format!("the file name '{file_name}' doesn't match the name and version of the package")
+        })?;
+
+    let mut package_record = PackageRecord::new(
+        PackageName::from_str(&package.name).map_err(|err| err.to_string())?,
+        Version::from_str(&package.version).map_err(|err| err.to_string())?,
+        build.to_string(),
+    );
+    // The build number is not recorded either, by convention it ends the build string.
+    package_record.build_number = build
+        .rsplit('_')
+        .next()
+        .and_then(|number| number.parse().ok())
+        .unwrap_or(0);
+    package_record.depends = package
+        .dependencies
+        .iter()
+        .map(|(name, spec)| match spec.as_str() {
+            "" | "*" => name.clone(),
+            spec => format!("{name} {spec}"),
+        })
+        .collect();
+    // The noarch type is not recorded either, a noarch package that depends on python is most
+    // likely a `noarch: python` package whose files have to be installed into site-packages.
+    if subdir == "noarch" && package.dependencies.contains_key("python") {
+        package_record.noarch = NoArchType::python();
+    }
+    package_record.subdir = subdir;
+    (package_record.md5, package_record.sha256) = digests(&package.hash)?;
+
+    let channel = url
+        .join("..")
+        .map_err(|err| format!("invalid url: {err}"))?
+        .to_string();
+    Ok(RepoDataRecord {
+        package_record,
+        file_name,
+        url,
+        channel,
+    })
+}
+
+/// Converts a pip package of a conda-lock file to a pypi record.
+fn pypi_record(package: &CondaLockPackage) -> Result<PypiRecord, String> {
+    let url = Url::parse(&package.url).map_err(|err| format!("invalid url: {err}"))?;
+    let requires_dist = package
+        .dependencies
+        .iter()
+        .map(|(name, spec)| {
+            let requirement = match spec.as_str() {
+                "" | "*" => name.clone(),
+                spec if spec.contains("://") => format!("{name} @ {spec}"),
+                spec => format!("{name} {spec}"),
+            };
+            Requirement::from_str(&requirement)
+                .map_err(|err| format!("invalid dependency '{requirement}': {err}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let hash = match digests(&package.hash)? {
+        (Some(md5), Some(sha256)) => Some(PackageHashes::Md5Sha256(md5, sha256)),
+        (Some(md5), None) => Some(PackageHashes::Md5(md5)),
+        (None, Some(sha256)) => Some(PackageHashes::Sha256(sha256)),
+        (None, None) => None,
+    };
+    let data = PypiPackageData {
+        name: package.name.parse().map_err(|err| format!("{err}"))?,
+        version: package.version.parse().map_err(|err| format!("{err}"))?,
+        requires_dist,
+        requires_python: None,
+        editable: false,
+        url_or_path: UrlOrPath::Url(url),
+        hash,
+    };
+    Ok((data, PypiPackageEnvironmentData::default()))
+}
+
+fn digests(hash: &CondaLockHash) -> Result<(Option<Md5Hash>, Option<Sha256Hash>), String> {
+    let md5 = hash
+        .md5
+        .as_deref()
+        .map(|hex| parse_digest_from_hex::<Md5>(hex).ok_or(format!("invalid md5 hash '{hex}'")))
+        .transpose()?;
+    let sha256 = hash
+        .sha256
+        .as_deref()
+        .map(|hex| {
+            parse_digest_from_hex::<Sha256>(hex).ok_or(format!("invalid sha256 hash '{hex}'"))
+        })
+        .transpose()?;
+    Ok((md5, sha256))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::default_channel_config;
+
+    #[test]
+    fn test_from_conda_lock() {
+        let conda_lock: CondaLock = serde_yaml::from_str(
+            r#"
+version: 1
+metadata:
+  channels:
+  - url: conda-forge
+    used_env_vars: []
+  platforms:
+  - linux-64
+package:
+- name: libzlib
+  version: 1.2.13
+  manager: conda
+  platform: linux-64
+  dependencies:
+    libgcc-ng: '>=12'
+  url: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  hash:
+    md5: f36c115f1ee199da648e0597ec2047ad
+    sha256: 370c7c5893b737596fd6ca0d9190c9715d89d888b8c88537ae1ef168c25e82e4
+  category: main
+  optional: false
+- name: six
+  version: 1.16.0
+  manager: conda
+  platform: linux-64
+  dependencies:
+    python: ''
+  url: https://conda.anaconda.org/conda-forge/noarch/six-1.16.0-pyh6c4a22f_0.tar.bz2
+  hash:
+    md5: e5f25f8dbc060e9a8d912e432202afc2
+  category: main
+  optional: false
+- name: idna
+  version: '3.7'
+  manager: pip
+  platform: linux-64
+  dependencies: {}
+  url: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+  hash:
+    sha256: 82fee1fc78add43492d3a1898bfa6d8a904cc97d8427f683ed8e798d07761aa0
+  category: main
+  optional: false
+- name: left-pad
+  version: 1.0.0
+  manager: npm
+  platform: linux-64
+  url: https://registry.npmjs.org/left-pad/-/left-pad-1.0.0.tgz
+"#,
+        )
+        .unwrap();
+
+        let (lock_file, unmapped) =
+            from_conda_lock(conda_lock, "default", &default_channel_config()).unwrap();
+        assert_eq!(
+            unmapped,
+            vec![
+                "imported 'six' for linux-64 as a `noarch: python` package, the noarch type is guessed from its python dependency",
+                "skipped 'left-pad' for linux-64: the package manager 'npm' is not supported"
+            ]
+        );
+
+        let environment = lock_file.environment("default").unwrap();
+        assert_eq!(
+            environment.channels()[0].url,
+            "https://conda.anaconda.org/conda-forge/"
+        );
+        let records = environment
+            .conda_repodata_records_for_platform(Platform::Linux64)
+            .unwrap()
+            .unwrap();
+        let libzlib = &records[0].package_record;
+        assert!(!libzlib.noarch.is_python());
+        assert_eq!(libzlib.build, "hd590300_5");
+        assert_eq!(libzlib.build_number, 5);
+        assert_eq!(libzlib.subdir, "linux-64");
+        assert_eq!(libzlib.depends, vec!["libgcc-ng >=12"]);
+        assert!(libzlib.md5.is_some());
+        assert_eq!(
+            records[0].channel,
+            "https://conda.anaconda.org/conda-forge/"
+        );
+
+        let six = &records[1].package_record;
+        assert_eq!(six.subdir, "noarch");
+        assert!(six.noarch.is_python());
+
+        let pypi_packages = environment
+            .pypi_packages_for_platform(Platform::Linux64)
+            .unwrap();
+        assert_eq!(pypi_packages[0].0.name.to_string(), "idna");
+    }
+}
@@ -0,0 +1,82 @@
+use crate::lock_file::{load_lock_file, minimize_lock_file, write_lock_file};
+use crate::project::has_features::HasFeatures;
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_conda_types::Platform;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Parser, Debug)]
+pub struct Args {}
+
+pub async fn execute(project: Project, _args: Args) -> miette::Result<()> {
+    if !project.has_lock_file() {
+        miette::bail!(
+            "there is no lock-file at '{}' to minimize",
+            project.lock_file_path().display()
+        );
+    }
+
+    // The environments and platforms that are still declared in the manifest.
+    let declared: HashMap<String, HashSet<Platform>> = project
+        .environments()
+        .into_iter()
+        .map(|environment| (environment.name().to_string(), environment.platforms()))
+        .collect();
+
+    let lock_file = load_lock_file(&project).await?;
+    let mut removed_environments = Vec::new();
+    let mut removed_platforms = Vec::new();
+    for (name, environment) in lock_file.environments() {
+        match declared.get(name) {
+            None => removed_environments.push(name.to_string()),
+            Some(platforms) => removed_platforms.extend(
+                environment
+                    .platforms()
+                    .filter(|platform| !platforms.contains(platform))
+                    .map(|platform| (name.to_string(), platform)),
+            ),
+        }
+    }
+
+    if removed_environments.is_empty() && removed_platforms.is_empty() {
+        eprintln!(
+            "{}The lock-file only contains environments and platforms of the manifest",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+        return Ok(());
+    }
+
+    write_lock_file(&project, &minimize_lock_file(&lock_file, &declared)?)?;
+
+    // The lock files of removed environments are no longer written, so remove them.
+    if project.lock_file_per_environment() {
+        for name in &removed_environments {
+            let path = project.environment_lock_file_path(name);
+            if path.is_file() {
+                std::fs::remove_file(path).into_diagnostic()?;
+            }
+        }
+    }
+
+    for name in removed_environments.iter().sorted() {
+        eprintln!(
+            "{}Removed the environment '{}'",
+            console::style(console::Emoji("✔ ", "")).green(),
+            console::style(name).bold(),
+        );
+    }
+    for (name, platform) in removed_platforms
+        .iter()
+        .sorted_by_key(|(name, platform)| (name.clone(), platform.as_str()))
+    {
+        eprintln!(
+            "{}Removed the platform '{}' of the environment '{}'",
+            console::style(console::Emoji("✔ ", "")).green(),
+            console::style(platform).bold(),
+            console::style(name).bold(),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+pub mod bump;
+pub mod explain;
+pub mod import;
+pub mod minimize;
+pub mod prune_cache;
+pub mod stats;
+pub mod touch;
+pub mod upgrade_channels;
+pub mod verify_hashes;
+pub mod which;
+
+use crate::Project;
+use clap::Parser;
+use std::path::PathBuf;
+
+/// Commands to inspect and manage the lock file of the project.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The path to 'pixi.toml' or 'pyproject.toml'
+    #[clap(long, global = true)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// The subcommand to execute
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Parser, Debug)]
+pub enum Command {
+    /// Verify the installed packages against the hashes recorded in the lock file.
+    VerifyHashes(verify_hashes::Args),
+
+    /// Mark a hand-edited lock file as up-to-date with the manifest without re-solving.
+    Touch(touch::Args),
+
+    /// Remove the environments and platforms that are no longer in the manifest from the lock
+    /// file, without re-solving.
+    Minimize(minimize::Args),
+
+    /// Explain which constraints of the manifest and of the locked packages determined the locked
+    /// version of a package.
+    Explain(explain::Args),
+
+    /// Show the number of packages and their download size per environment and platform.
+    Stats(stats::Args),
+
+    /// Solve the project again and move the locked packages to a higher priority channel that
+    /// provides the same version, keeping the locked versions where possible.
+    UpgradeChannels(upgrade_channels::Args),
+
+    /// Import the packages of a conda-lock file into the lock file, without re-solving.
+    Import(import::Args),
+
+    /// Remove the packages from the package cache that are not locked in any environment.
+    PruneCache(prune_cache::Args),
+
+    /// Update all environments and print a Markdown changelog of the version changes of the
+    /// dependencies in the manifest.
+    Bump(bump::Args),
+
+    /// Show which installed package provides a file in the prefix of an environment.
+    Which(which::Args),
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
+
+    match args.command {
+        Command::VerifyHashes(args) => verify_hashes::execute(project, args).await?,
+        Command::Touch(args) => touch::execute(project, args).await?,
+        Command::Minimize(args) => minimize::execute(project, args).await?,
+        Command::Explain(args) => explain::execute(project, args).await?,
+        Command::Stats(args) => stats::execute(project, args).await?,
+        Command::UpgradeChannels(args) => upgrade_channels::execute(project, args).await?,
+        Command::Import(args) => import::execute(project, args).await?,
+        Command::PruneCache(args) => prune_cache::execute(project, args).await?,
+        Command::Bump(args) => bump::execute(project, args).await?,
+        Command::Which(args) => which::execute(project, args).await?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,309 @@
+use crate::cli::global::common::bin_env_dir;
+use crate::config::get_cache_dir;
+use crate::lock_file::load_lock_file;
+use crate::Project;
+use clap::Parser;
+use human_bytes::human_bytes;
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_lock::{LockFile, Package};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Additional lock files whose packages are kept, e.g. the lock files of other projects that
+    /// share the package cache.
+    #[arg(long = "keep-lock-file", value_name = "LOCK_FILE")]
+    pub keep_lock_files: Vec<PathBuf>,
+
+    /// Only show which packages would be removed, without removing them.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Remove the packages. The package cache is shared by all projects, so this removes the
+    /// packages of other projects that are not kept with `--keep-lock-file`.
+    #[arg(long, conflicts_with = "dry_run")]
+    pub force: bool,
+}
+
+/// A package in the cache that isn't referenced by any of the lock files.
+struct UnusedEntry {
+    path: PathBuf,
+    size_bytes: u64,
+    /// The acquired cache lock (`<entry>.lock`) of the package, it is held until the package is
+    /// removed. `None` if another process holds the lock.
+    lock: Option<CacheLock>,
+}
+
+/// The lock of an entry of the package cache, an entry without a lock file can't be in use.
+struct CacheLock {
+    _lock_file: Option<fslock::LockFile>,
+}
+
+impl CacheLock {
+    /// Tries to acquire the lock file of a cache entry, returns `None` if it is held by another
+    /// process.
+    fn try_acquire(lock_path: &Path) -> miette::Result<Option<Self>> {
+        if !lock_path.is_file() {
+            return Ok(Some(Self { _lock_file: None }));
+        }
+        let mut lock = fslock::LockFile::open(lock_path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to open '{}'", lock_path.display()))?;
+        let acquired = lock.try_lock().into_diagnostic()?;
+        Ok(acquired.then_some(Self {
+            _lock_file: Some(lock),
+        }))
+    }
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    if !project.has_lock_file() {
+        miette::bail!(
+            "there is no lock-file at '{}'",
+            project.lock_file_path().display()
+        );
+    }
+
+    if !args.dry_run && !args.force {
+        miette::bail!(
+            help = "run with `--dry-run` to see which packages would be removed, and with `--force` to remove them",
+            "the package cache is shared by all projects, pruning it removes the packages of the other projects that are not kept with `--keep-lock-file`"
+        );
+    }
+
+    let mut referenced = referenced_cache_keys(&load_lock_file(&project).await?);
+    referenced.extend(global_cache_keys()?);
+    for path in &args.keep_lock_files {
+        let lock_file = LockFile::from_path(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read the lock file '{}'", path.display()))?;
+        referenced.extend(referenced_cache_keys(&lock_file));
+    }
+
+    let cache_dir = get_cache_dir()?.join("pkgs");
+    if !cache_dir.is_dir() {
+        eprintln!(
+            "{}The package cache at '{}' is empty",
+            console::style(console::Emoji("✔ ", "")).green(),
+            cache_dir.display()
+        );
+        return Ok(());
+    }
+
+    let mut unused = unused_cache_entries(&cache_dir, &referenced)?;
+    unused.retain(|entry| {
+        let in_use = entry.lock.is_none();
+        if in_use {
+            eprintln!(
+                "{}Skipping {}, it is in use by another process",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                entry.path.display()
+            );
+        }
+        !in_use
+    });
+    let freed: u64 = unused.iter().map(|entry| entry.size_bytes).sum();
+    for entry in &unused {
+        if args.dry_run {
+            println!(
+                "would remove {} ({})",
+                entry.path.display(),
+                human_bytes(entry.size_bytes as f64)
+            );
+            continue;
+        }
+        let removed = if entry.path.is_dir() {
+            fs::remove_dir_all(&entry.path)
+        } else {
+            fs::remove_file(&entry.path)
+        };
+        removed
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to remove '{}'", entry.path.display()))?;
+        tracing::info!("removed {}", entry.path.display());
+    }
+
+    if args.dry_run {
+        eprintln!(
+            "{}Pruning would remove {} cached packages and free {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            unused.len(),
+            human_bytes(freed as f64)
+        );
+    } else {
+        eprintln!(
+            "{}Removed {} cached packages, freed {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            unused.len(),
+            human_bytes(freed as f64)
+        );
+    }
+    Ok(())
+}
+
+/// Returns the names of the package cache entries of the conda packages in the lock file. The
+/// package cache stores a package as `<name>-<version>-<build>`.
+fn referenced_cache_keys(lock_file: &LockFile) -> HashSet<String> {
+    let mut keys = HashSet::new();
+    for (_, environment) in lock_file.environments() {
+        for platform in environment.platforms() {
+            for package in environment.packages(platform).into_iter().flatten() {
+                if let Package::Conda(conda) = package {
+                    let record = conda.package_record();
+                    keys.insert(format!(
+                        "{}-{}-{}",
+                        record.name.as_normalized(),
+                        record.version,
+                        record.build
+                    ));
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Returns the names of the package cache entries of the packages that are installed in the
+/// environments of `pixi global`, the `conda-meta` records are named like the cache entries.
+fn global_cache_keys() -> miette::Result<HashSet<String>> {
+    let mut keys = HashSet::new();
+    let Some(env_dir) = bin_env_dir() else {
+        return Ok(keys);
+    };
+    for env in fs::read_dir(env_dir).into_iter().flatten() {
+        let conda_meta = env.into_diagnostic()?.path().join("conda-meta");
+        for record in fs::read_dir(conda_meta).into_iter().flatten() {
+            let file_name = record.into_diagnostic()?.file_name();
+            if let Some(key) = file_name.to_string_lossy().strip_suffix(".json") {
+                keys.insert(key.to_string());
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Returns the entries of the package cache that are not referenced. The lock file that the cache
+/// keeps next to an entry (`<entry>.lock`) is kept, the entry is only returned with its lock
+/// acquired, see [`CacheLock`].
+fn unused_cache_entries(
+    cache_dir: &Path,
+    referenced: &HashSet<String>,
+) -> miette::Result<Vec<UnusedEntry>> {
+    let mut unused = Vec::new();
+    for entry in fs::read_dir(cache_dir)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read '{}'", cache_dir.display()))?
+    {
+        let entry = entry.into_diagnostic()?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if file_name.starts_with('.') || file_name.ends_with(".lock") {
+            continue;
+        }
+        if referenced.contains(&file_name) {
+            continue;
+        }
+        unused.push(UnusedEntry {
+            size_bytes: size_on_disk(&entry.path())?,
+            lock: CacheLock::try_acquire(&cache_dir.join(format!("{file_name}.lock")))?,
+            path: entry.path(),
+        });
+    }
+    unused.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(unused)
+}
+
+/// Returns the size of a file or of all the files in a directory.
+fn size_on_disk(path: &Path) -> miette::Result<u64> {
+    let metadata = fs::symlink_metadata(path).into_diagnostic()?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    fs::read_dir(path)
+        .into_diagnostic()?
+        .try_fold(0, |acc, entry| {
+            Ok(acc + size_on_disk(&entry.into_diagnostic()?.path())?)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_unused_cache_entries() {
+        let lock_file = LockFile::from_str(
+            r#"
+version: 4
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+      - pypi: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+packages:
+- kind: conda
+  name: libzlib
+  version: 1.2.13
+  build: hd590300_5
+  build_number: 5
+  subdir: linux-64
+  url: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  sha256: 370c7c5893b737596fd6ca0d9190c9715d89d888b8c88537ae1ef168c25e82e4
+  md5: f36c115f1ee199da648e0597ec2047ad
+  size: 61588
+  timestamp: 1686575217516
+- kind: pypi
+  name: idna
+  version: '3.7'
+  url: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+  sha256: 82fee1fc78add43492d3a1898bfa6d8a904cc97d8427f683ed8e798d07761aa0
+  requires_python: '>=3.5'
+"#,
+        )
+        .unwrap();
+        let referenced = referenced_cache_keys(&lock_file);
+        assert_eq!(
+            referenced,
+            HashSet::from([String::from("libzlib-1.2.13-hd590300_5")])
+        );
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        for key in ["libzlib-1.2.13-hd590300_5", "libzlib-1.2.11-h166bdaf_1025"] {
+            fs::create_dir_all(cache_dir.path().join(key).join("lib")).unwrap();
+            fs::write(cache_dir.path().join(key).join("lib/libz.so"), [0u8; 10]).unwrap();
+            fs::write(cache_dir.path().join(format!("{key}.lock")), [0u8; 2]).unwrap();
+        }
+
+        let unused = unused_cache_entries(cache_dir.path(), &referenced).unwrap();
+        assert_eq!(unused.len(), 1);
+        assert_eq!(
+            unused[0].path,
+            cache_dir.path().join("libzlib-1.2.11-h166bdaf_1025")
+        );
+        assert_eq!(unused[0].size_bytes, 10);
+        assert!(unused[0].lock.is_some());
+
+        // An entry whose lock is held by another process is in use.
+        assert!(CacheLock::try_acquire(
+            &cache_dir.path().join("libzlib-1.2.11-h166bdaf_1025.lock")
+        )
+        .unwrap()
+        .is_none());
+        drop(unused);
+        assert!(CacheLock::try_acquire(
+            &cache_dir.path().join("libzlib-1.2.11-h166bdaf_1025.lock")
+        )
+        .unwrap()
+        .is_some());
+        assert!(
+            CacheLock::try_acquire(&cache_dir.path().join("missing.lock"))
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use crate::lock_file::load_lock_file;
+use crate::Project;
+use clap::Parser;
+use human_bytes::human_bytes;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_conda_types::Platform;
+use rattler_lock::{LockFile, Package};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::{stdout, Write};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Print the statistics as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The size and composition of a lock file.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct LockStats {
+    environments: Vec<PlatformStats>,
+    /// The distinct packages of the lock file, a package that is locked in several environments
+    /// or platforms is counted once.
+    total: PackageCounts,
+    /// The distinct channels of all environments.
+    channels: Vec<String>,
+}
+
+/// The packages of a single environment and platform.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct PlatformStats {
+    environment: String,
+    platform: Platform,
+    #[serde(flatten)]
+    packages: PackageCounts,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+struct PackageCounts {
+    conda: usize,
+    pypi: usize,
+    /// The download size of the conda packages, the lock file doesn't record the size of pypi
+    /// packages.
+    size_bytes: u64,
+}
+
+impl PackageCounts {
+    fn add(&mut self, package: &Package) {
+        match package {
+            Package::Conda(conda) => {
+                self.conda += 1;
+                self.size_bytes += conda.package_record().size.unwrap_or_default();
+            }
+            Package::Pypi(_) => self.pypi += 1,
+        }
+    }
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    if !project.has_lock_file() {
+        miette::bail!(
+            "there is no lock-file at '{}'",
+            project.lock_file_path().display()
+        );
+    }
+    let stats = lock_stats(&load_lock_file(&project).await?);
+
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).into_diagnostic()?
+        );
+        return Ok(());
+    }
+
+    let mut writer = tabwriter::TabWriter::new(stdout());
+    let header_style = console::Style::new().bold();
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}",
+        header_style.apply_to("Environment"),
+        header_style.apply_to("Platform"),
+        header_style.apply_to("Conda"),
+        header_style.apply_to("PyPI"),
+        header_style.apply_to("Size"),
+    )
+    .into_diagnostic()?;
+    for platform in &stats.environments {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}",
+            platform.environment,
+            platform.platform,
+            platform.packages.conda,
+            platform.packages.pypi,
+            human_bytes(platform.packages.size_bytes as f64),
+        )
+        .into_diagnostic()?;
+    }
+    writer.flush().into_diagnostic()?;
+
+    println!(
+        "\n{} {} distinct packages ({} conda, {} pypi), {} download size of the conda packages",
+        console::style("Total:").bold(),
+        stats.total.conda + stats.total.pypi,
+        stats.total.conda,
+        stats.total.pypi,
+        human_bytes(stats.total.size_bytes as f64),
+    );
+    println!(
+        "{} {} ({})",
+        console::style("Channels:").bold(),
+        stats.channels.len(),
+        stats.channels.join(", ")
+    );
+    Ok(())
+}
+
+/// Aggregates the packages of the lock file per environment and platform and in total.
+fn lock_stats(lock_file: &LockFile) -> LockStats {
+    let mut environments = Vec::new();
+    let mut total = PackageCounts::default();
+    let mut seen = BTreeSet::new();
+    let mut channels = BTreeSet::new();
+    for (name, environment) in lock_file
+        .environments()
+        .sorted_by_key(|(name, _)| name.to_string())
+    {
+        channels.extend(environment.channels().iter().map(|c| c.url.clone()));
+        for platform in environment.platforms().sorted_by_key(|p| p.as_str()) {
+            let mut packages = PackageCounts::default();
+            for package in environment.packages(platform).into_iter().flatten() {
+                packages.add(&package);
+                if seen.insert(package_key(&package)) {
+                    total.add(&package);
+                }
+            }
+            environments.push(PlatformStats {
+                environment: name.to_string(),
+                platform,
+                packages,
+            });
+        }
+    }
+    LockStats {
+        environments,
+        total,
+        channels: channels.into_iter().collect(),
+    }
+}
+
+/// Identifies a package across the environments and platforms of the lock file.
+fn package_key(package: &Package) -> String {
+    match package {
+        Package::Conda(conda) => {
+            let record = conda.package_record();
+            format!(
+                "conda:{}/{}-{}-{}",
+                record.subdir,
+                record.name.as_normalized(),
+                record.version,
+                record.build
+            )
+        }
+        Package::Pypi(_) => format!("pypi:{}=={}", package.name(), package.version()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_lock_stats() {
+        let lock_file = LockFile::from_str(
+            r#"
+version: 4
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+      - pypi: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+  test:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    - url: https://conda.anaconda.org/bioconda/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+packages:
+- kind: conda
+  name: libzlib
+  version: 1.2.13
+  build: hd590300_5
+  build_number: 5
+  subdir: linux-64
+  url: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  sha256: 370c7c5893b737596fd6ca0d9190c9715d89d888b8c88537ae1ef168c25e82e4
+  md5: f36c115f1ee199da648e0597ec2047ad
+  size: 61588
+  timestamp: 1686575217516
+- kind: pypi
+  name: idna
+  version: '3.7'
+  url: https://files.pythonhosted.org/packages/idna-3.7-py3-none-any.whl
+  sha256: 82fee1fc78add43492d3a1898bfa6d8a904cc97d8427f683ed8e798d07761aa0
+  requires_python: '>=3.5'
+"#,
+        )
+        .unwrap();
+
+        let stats = lock_stats(&lock_file);
+        assert_eq!(
+            stats.environments,
+            vec![
+                PlatformStats {
+                    environment: String::from("default"),
+                    platform: Platform::Linux64,
+                    packages: PackageCounts {
+                        conda: 1,
+                        pypi: 1,
+                        size_bytes: 61588,
+                    },
+                },
+                PlatformStats {
+                    environment: String::from("test"),
+                    platform: Platform::Linux64,
+                    packages: PackageCounts {
+                        conda: 1,
+                        pypi: 0,
+                        size_bytes: 61588,
+                    },
+                },
+            ]
+        );
+        // The package that is shared by both environments is counted once.
+        assert_eq!(
+            stats.total,
+            PackageCounts {
+                conda: 1,
+                pypi: 1,
+                size_bytes: 61588,
+            }
+        );
+        assert_eq!(stats.channels.len(), 2);
+    }
+}
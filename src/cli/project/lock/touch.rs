@@ -0,0 +1,95 @@
+use crate::lock_file::{load_lock_file, touch_lock_file};
+use crate::project::has_features::HasFeatures;
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use rattler_lock::LockFile;
+use std::collections::HashSet;
+
+#[derive(Parser, Debug)]
+pub struct Args {}
+
+pub async fn execute(project: Project, _args: Args) -> miette::Result<()> {
+    if !project.has_lock_file() {
+        miette::bail!(
+            "there is no lock-file at '{}' to touch",
+            project.lock_file_path().display()
+        );
+    }
+
+    eprintln!(
+        "{}pixi will trust the current lock-file as is and no longer check it against the manifest until either of them changes. \
+        Make sure the lock-file is correct, an incorrect lock-file results in broken environments.",
+        console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+    );
+
+    let lock_file = load_lock_file(&project).await?;
+    let missing = missing_dependencies(&project, &lock_file);
+    if !missing.is_empty() {
+        miette::bail!(
+            help = "add the packages to the lock-file or run `pixi update` to re-solve",
+            "the lock-file is missing dependencies that are declared in the manifest:\n{}",
+            missing
+                .iter()
+                .map(|missing| format!("  - {missing}"))
+                .join("\n")
+        );
+    }
+
+    touch_lock_file(&project)?;
+    eprintln!(
+        "{}Marked the lock-file as up-to-date with the manifest",
+        console::style(console::Emoji("✔ ", "")).green(),
+    );
+    Ok(())
+}
+
+/// Returns a description of every dependency of the manifest that is not present in the lock-file.
+fn missing_dependencies(project: &Project, lock_file: &LockFile) -> Vec<String> {
+    let mut missing = Vec::new();
+    for environment in project.environments() {
+        let Some(locked_environment) = lock_file.environment(environment.name().as_str()) else {
+            missing.push(format!(
+                "the environment '{}'",
+                environment.name().fancy_display()
+            ));
+            continue;
+        };
+
+        for platform in environment
+            .platforms()
+            .into_iter()
+            .sorted_by_key(|platform| platform.as_str())
+        {
+            let locked_names: HashSet<String> = locked_environment
+                .packages(platform)
+                .into_iter()
+                .flatten()
+                .map(|package| match package.as_conda() {
+                    Some(conda) => conda.package_record().name.as_normalized().to_string(),
+                    None => package.name().to_string(),
+                })
+                .collect();
+
+            let conda_names = environment
+                .dependencies(None, Some(platform))
+                .names()
+                .map(|name| name.as_normalized().to_string())
+                .collect_vec();
+            let pypi_names = environment
+                .pypi_dependencies(Some(platform))
+                .names()
+                .map(|name| name.as_normalized().to_string())
+                .collect_vec();
+            for name in conda_names.into_iter().chain(pypi_names).unique() {
+                if !locked_names.contains(&name) {
+                    missing.push(format!(
+                        "'{name}' in environment '{}' for platform '{platform}'",
+                        environment.name().fancy_display()
+                    ));
+                }
+            }
+        }
+    }
+    missing
+}
@@ -0,0 +1,184 @@
+use crate::environment::LockFileUsage;
+use crate::lock_file::{load_lock_file, UpdateLockFileOptions};
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_conda_types::Platform;
+use rattler_lock::LockFile;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Only show the packages that would change channel, without writing the lock file.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print the changes as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// A locked conda package that is taken from another channel.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct ChannelChange {
+    environment: String,
+    platform: Platform,
+    package: String,
+    version: String,
+    from: String,
+    to: String,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    // Solve all environments again, preferring the locked packages from the highest priority
+    // channel that provides them.
+    let previous_lock_file = load_lock_file(&project).await?;
+    let lock_file = project
+        .up_to_date_lock_file(UpdateLockFileOptions {
+            lock_file_usage: LockFileUsage::Update,
+            no_install: true,
+            existing_lock_file: Some(previous_lock_file.clone()),
+            dry_run: args.dry_run,
+            upgrade_channels: true,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+
+    let changes = channel_changes(&previous_lock_file, &lock_file)?;
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&changes).into_diagnostic()?
+        );
+    } else if changes.is_empty() {
+        eprintln!("No packages changed channel");
+    } else {
+        for change in &changes {
+            println!(
+                "{} {} {} {}: {} -> {}",
+                change.environment,
+                change.platform,
+                console::style(&change.package).bold(),
+                change.version,
+                change.from,
+                console::style(&change.to).green(),
+            );
+        }
+    }
+
+    if args.dry_run {
+        eprintln!("The lock file was not updated because of `--dry-run`");
+    } else if !changes.is_empty() {
+        eprintln!(
+            "{}Moved {} packages to another channel",
+            console::style(console::Emoji("✔ ", "")).green(),
+            changes.len()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the conda packages that are locked from another channel than in the previous lock file,
+/// per environment and platform.
+fn channel_changes(previous: &LockFile, current: &LockFile) -> miette::Result<Vec<ChannelChange>> {
+    let mut changes = Vec::new();
+    for (name, environment) in current
+        .environments()
+        .sorted_by_key(|(name, _)| name.to_string())
+    {
+        let Some(previous_environment) = previous.environment(name) else {
+            continue;
+        };
+        for platform in environment.platforms().sorted_by_key(|p| p.as_str()) {
+            let previous_channels: HashMap<_, _> = previous_environment
+                .conda_repodata_records_for_platform(platform)
+                .into_diagnostic()?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|record| (record.package_record.name.clone(), record.channel))
+                .collect();
+            for record in environment
+                .conda_repodata_records_for_platform(platform)
+                .into_diagnostic()?
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_by(|a, b| a.package_record.name.cmp(&b.package_record.name))
+            {
+                match previous_channels.get(&record.package_record.name) {
+                    Some(previous_channel) if *previous_channel != record.channel => {
+                        changes.push(ChannelChange {
+                            environment: name.to_string(),
+                            platform,
+                            package: record.package_record.name.as_source().to_string(),
+                            version: record.package_record.version.to_string(),
+                            from: previous_channel.clone(),
+                            to: record.channel,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const LOCK_FILE: &str = r#"
+version: 4
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+packages:
+- kind: conda
+  name: libzlib
+  version: 1.2.13
+  build: hd590300_5
+  build_number: 5
+  subdir: linux-64
+  url: https://conda.anaconda.org/conda-forge/linux-64/libzlib-1.2.13-hd590300_5.conda
+  sha256: 370c7c5893b737596fd6ca0d9190c9715d89d888b8c88537ae1ef168c25e82e4
+  md5: f36c115f1ee199da648e0597ec2047ad
+"#;
+
+    #[test]
+    fn test_channel_changes() {
+        let previous = LockFile::from_str(LOCK_FILE).unwrap();
+        assert!(channel_changes(&previous, &previous).unwrap().is_empty());
+
+        let current = LockFile::from_str(
+            &LOCK_FILE
+                .replace(
+                    "- url: https://conda.anaconda.org/conda-forge/",
+                    "- url: https://prefix.dev/internal/",
+                )
+                .replace(
+                    "https://conda.anaconda.org/conda-forge/linux-64/",
+                    "https://prefix.dev/internal/linux-64/",
+                ),
+        )
+        .unwrap();
+        assert_eq!(
+            channel_changes(&previous, &current).unwrap(),
+            vec![ChannelChange {
+                environment: String::from("default"),
+                platform: Platform::Linux64,
+                package: String::from("libzlib"),
+                version: String::from("1.2.13"),
+                from: String::from("https://conda.anaconda.org/conda-forge/"),
+                to: String::from("https://prefix.dev/internal/"),
+            }]
+        );
+    }
+}
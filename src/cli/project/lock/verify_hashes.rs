@@ -0,0 +1,281 @@
+use crate::lock_file::load_lock_file;
+use crate::prefix::Prefix;
+use crate::project::has_features::HasFeatures;
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_conda_types::prefix_record::PathType;
+use rattler_conda_types::{PackageRecord, PrefixRecord};
+use rattler_digest::{compute_file_digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The environment to verify, if none is provided the default environment is used.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = environment.best_platform();
+    if !environment.platforms().contains(&platform) {
+        miette::bail!(
+            "the environment '{}' does not support the current platform '{}'",
+            environment.name().fancy_display(),
+            platform
+        );
+    }
+
+    let lock_file = load_lock_file(&project).await?;
+    let locked_records = lock_file
+        .environment(environment.name().as_str())
+        .map(|env| env.conda_repodata_records_for_platform(platform))
+        .transpose()
+        .into_diagnostic()?
+        .flatten()
+        .unwrap_or_default();
+
+    let prefix = Prefix::new(environment.dir());
+    let installed_records = if prefix.root().is_dir() {
+        prefix.find_installed_packages(None).await?
+    } else {
+        Vec::new()
+    };
+    let installed_records: HashMap<_, _> = installed_records
+        .into_iter()
+        .map(|r| {
+            (
+                r.repodata_record
+                    .package_record
+                    .name
+                    .as_normalized()
+                    .to_string(),
+                r,
+            )
+        })
+        .collect();
+
+    let mut mismatches = Vec::new();
+    let mut verified = 0;
+    for locked in locked_records
+        .iter()
+        .sorted_by(|a, b| a.package_record.name.cmp(&b.package_record.name))
+    {
+        let name = locked.package_record.name.as_normalized();
+        let Some(installed) = installed_records.get(name) else {
+            eprintln!(
+                "{}skipping '{}', it is not installed",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                locked.package_record.name.as_source()
+            );
+            continue;
+        };
+
+        mismatches.extend(verify_archive_hash(
+            &locked.package_record,
+            &installed.repodata_record.package_record,
+        ));
+        mismatches.extend(
+            verify_installed_files(prefix.root(), installed)
+                .into_iter()
+                .map(|msg| format!("'{name}': {msg}")),
+        );
+        verified += 1;
+    }
+
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            eprintln!(
+                "{}{}",
+                console::style(console::Emoji("✘ ", "")).red(),
+                mismatch
+            );
+        }
+        miette::bail!(
+            "found {} hash mismatches in environment '{}'",
+            mismatches.len(),
+            environment.name().fancy_display()
+        );
+    }
+
+    eprintln!(
+        "{}Verified the hashes of {} installed packages in environment '{}'",
+        console::style(console::Emoji("✔ ", "")).green(),
+        verified,
+        environment.name().fancy_display()
+    );
+
+    Ok(())
+}
+
+/// Compares the hash of the archive a package was installed from with the hash in the lock file.
+/// The sha256 is compared if the lock file records one, the md5 otherwise.
+fn verify_archive_hash(locked: &PackageRecord, installed: &PackageRecord) -> Option<String> {
+    let name = locked.name.as_normalized();
+    let format_hash = |hash: Option<String>| hash.unwrap_or_else(|| "<unknown>".to_string());
+    if locked.sha256.is_some() {
+        (installed.sha256 != locked.sha256).then(|| {
+            format!(
+                "'{name}' was installed from an archive with sha256 {} but the lock file records {}",
+                format_hash(installed.sha256.map(|h| format!("{h:x}"))),
+                format_hash(locked.sha256.map(|h| format!("{h:x}"))),
+            )
+        })
+    } else if locked.md5.is_some() {
+        (installed.md5 != locked.md5).then(|| {
+            format!(
+                "'{name}' was installed from an archive with md5 {} but the lock file records {}",
+                format_hash(installed.md5.map(|h| format!("{h:x}"))),
+                format_hash(locked.md5.map(|h| format!("{h:x}"))),
+            )
+        })
+    } else {
+        None
+    }
+}
+
+/// Recomputes the hashes of the files that were linked into the prefix for the given package
+/// and returns a description of every file that doesn't match the recorded hash.
+fn verify_installed_files(prefix_root: &Path, record: &PrefixRecord) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    for entry in record.paths_data.paths.iter() {
+        if matches!(entry.path_type, PathType::SoftLink | PathType::Directory) {
+            continue;
+        }
+
+        // Files that contained a prefix placeholder are rewritten during linking, their hash
+        // in the prefix differs from the hash in the package.
+        let Some(expected) = entry.sha256_in_prefix.or(entry.sha256) else {
+            continue;
+        };
+
+        let path = prefix_root.join(&entry.relative_path);
+        match compute_file_digest::<Sha256>(&path) {
+            Ok(actual) if actual == expected => {}
+            Ok(actual) => mismatches.push(format!(
+                "{} has sha256 {:x} but {:x} was expected",
+                entry.relative_path.display(),
+                actual,
+                expected
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                mismatches.push(format!("{} is missing", entry.relative_path.display()))
+            }
+            Err(e) => mismatches.push(format!(
+                "{} could not be read: {e}",
+                entry.relative_path.display()
+            )),
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_conda_types::{PackageName, Version};
+    use rattler_digest::{compute_bytes_digest, parse_digest_from_hex, Md5};
+    use std::str::FromStr;
+
+    fn sha256_hex(content: &str) -> String {
+        format!("{:x}", compute_bytes_digest::<Sha256>(content.as_bytes()))
+    }
+
+    #[test]
+    fn test_verify_archive_hash() {
+        let record = |sha256: Option<&str>, md5: Option<&str>| {
+            let mut record = PackageRecord::new(
+                PackageName::from_str("foo").unwrap(),
+                Version::from_str("1.0").unwrap(),
+                String::from("h_0"),
+            );
+            record.sha256 = sha256.map(|hash| parse_digest_from_hex::<Sha256>(hash).unwrap());
+            record.md5 = md5.map(|hash| parse_digest_from_hex::<Md5>(hash).unwrap());
+            record
+        };
+        let (a, b) = (sha256_hex("a"), sha256_hex("b"));
+        let md5 = "f36c115f1ee199da648e0597ec2047ad";
+
+        assert_eq!(
+            verify_archive_hash(&record(Some(&a), None), &record(Some(&a), None)),
+            None
+        );
+        assert_eq!(
+            verify_archive_hash(&record(Some(&a), None), &record(Some(&b), None)),
+            Some(format!(
+                "'foo' was installed from an archive with sha256 {b} but the lock file records {a}"
+            ))
+        );
+        // The md5 is only compared if the lock file doesn't record a sha256.
+        assert_eq!(
+            verify_archive_hash(&record(Some(&a), Some(md5)), &record(Some(&a), None)),
+            None
+        );
+        assert_eq!(
+            verify_archive_hash(&record(None, Some(md5)), &record(Some(&a), None)),
+            Some(format!(
+                "'foo' was installed from an archive with md5 <unknown> but the lock file records {md5}"
+            ))
+        );
+        assert_eq!(
+            verify_archive_hash(&record(None, None), &record(Some(&a), None)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_installed_files() {
+        let prefix = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(prefix.path().join("conda-meta")).unwrap();
+        std::fs::create_dir_all(prefix.path().join("bin")).unwrap();
+        std::fs::write(prefix.path().join("bin/foo"), "foo\n").unwrap();
+        std::fs::write(prefix.path().join("bin/bar"), "changed\n").unwrap();
+        std::fs::write(prefix.path().join("bin/script"), "#!/prefix/bin/sh\n").unwrap();
+        std::fs::write(
+            prefix.path().join("conda-meta/foo-1.0-h_0.json"),
+            format!(
+                r#"{{
+                "name": "foo",
+                "version": "1.0",
+                "build": "h_0",
+                "build_number": 0,
+                "subdir": "linux-64",
+                "fn": "foo-1.0-h_0.conda",
+                "url": "https://conda.anaconda.org/conda-forge/linux-64/foo-1.0-h_0.conda",
+                "channel": "https://conda.anaconda.org/conda-forge/",
+                "files": ["bin/foo", "bin/bar", "bin/gone", "bin/script", "bin/link"],
+                "paths_data": {{"paths_version": 1, "paths": [
+                    {{"_path": "bin/foo", "path_type": "hardlink", "sha256": "{foo}"}},
+                    {{"_path": "bin/bar", "path_type": "hardlink", "sha256": "{bar}"}},
+                    {{"_path": "bin/gone", "path_type": "hardlink", "sha256": "{foo}"}},
+                    {{"_path": "bin/script", "path_type": "hardlink", "sha256": "{foo}", "sha256_in_prefix": "{script}"}},
+                    {{"_path": "bin/link", "path_type": "softlink", "sha256": "{foo}"}}
+                ]}}
+            }}"#,
+                foo = sha256_hex("foo\n"),
+                bar = sha256_hex("bar\n"),
+                script = sha256_hex("#!/prefix/bin/sh\n"),
+            ),
+        )
+        .unwrap();
+
+        let records = Prefix::new(prefix.path())
+            .find_installed_packages(None)
+            .await
+            .unwrap();
+        assert_eq!(
+            verify_installed_files(prefix.path(), &records[0]),
+            vec![
+                format!(
+                    "bin/bar has sha256 {} but {} was expected",
+                    sha256_hex("changed\n"),
+                    sha256_hex("bar\n")
+                ),
+                String::from("bin/gone is missing"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use crate::prefix::Prefix;
+use crate::Project;
+use clap::Parser;
+use miette::IntoDiagnostic;
+use rattler_conda_types::PrefixRecord;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The path of the file, relative to the prefix of the environment, e.g. `bin/python`. An
+    /// absolute path inside the prefix is also accepted.
+    pub path: PathBuf,
+
+    /// The environment to look in, if none is provided the default environment is used.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+}
+
+/// The package that installed a file in the prefix.
+#[derive(Debug, PartialEq, Eq)]
+struct Owner {
+    kind: &'static str,
+    name: String,
+    version: String,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let prefix = Prefix::new(environment.dir());
+    if !prefix.root().is_dir() {
+        miette::bail!(
+            help = "install it with `pixi install`",
+            "the environment '{}' is not installed",
+            environment.name().fancy_display()
+        );
+    }
+
+    let path = relative_to_prefix(prefix.root(), &args.path)?;
+    let records = prefix.find_installed_packages(None).await?;
+    let owner = match conda_owner(&records, &path) {
+        Some(owner) => Some(owner),
+        None => pypi_owner(prefix.root(), &path)?,
+    };
+
+    match owner {
+        Some(owner) => println!(
+            "{} is installed by the {} package {} {}",
+            path.display(),
+            owner.kind,
+            console::style(&owner.name).bold(),
+            owner.version
+        ),
+        None => println!("{} is not managed by pixi", path.display()),
+    }
+    Ok(())
+}
+
+/// Returns the path relative to the prefix, an absolute path has to be inside the prefix.
+fn relative_to_prefix(prefix_root: &Path, path: &Path) -> miette::Result<PathBuf> {
+    if !path.is_absolute() {
+        return Ok(normalize(path));
+    }
+    let prefix_root = dunce::canonicalize(prefix_root).into_diagnostic()?;
+    let path = dunce::canonicalize(path).unwrap_or_else(|_| normalize(path));
+    match path.strip_prefix(&prefix_root) {
+        Ok(relative) => Ok(relative.to_path_buf()),
+        Err(_) => miette::bail!(
+            "'{}' is not inside the prefix '{}'",
+            path.display(),
+            prefix_root.display()
+        ),
+    }
+}
+
+/// Returns the conda package whose `conda-meta` record lists the file.
+fn conda_owner(records: &[PrefixRecord], path: &Path) -> Option<Owner> {
+    records
+        .iter()
+        .find(|record| {
+            record.files.iter().any(|file| file == path)
+                || record
+                    .paths_data
+                    .paths
+                    .iter()
+                    .any(|entry| entry.relative_path == path)
+        })
+        .map(|record| {
+            let package = &record.repodata_record.package_record;
+            Owner {
+                kind: "conda",
+                name: package.name.as_source().to_string(),
+                version: format!("{} {}", package.version, package.build),
+            }
+        })
+}
+
+/// Returns the pypi package whose `RECORD` in the `site-packages` of the prefix lists the file.
+fn pypi_owner(prefix_root: &Path, path: &Path) -> miette::Result<Option<Owner>> {
+    for site_packages in site_packages_dirs(prefix_root) {
+        let relative_site_packages = site_packages
+            .strip_prefix(prefix_root)
+            .expect("the site-packages are inside the prefix");
+        for entry in fs::read_dir(&site_packages).into_diagnostic()? {
+            let entry = entry.into_diagnostic()?;
+            let dir_name = entry.file_name().to_string_lossy().into_owned();
+            let Some((name, version)) = dir_name
+                .strip_suffix(".dist-info")
+                .and_then(|stem| stem.split_once('-'))
+            else {
+                continue;
+            };
+            let Ok(record) = fs::read_to_string(entry.path().join("RECORD")) else {
+                continue;
+            };
+            // The paths in the `RECORD` are relative to the `site-packages`, scripts are
+            // recorded as e.g. `../../../bin/black`.
+            let owns_path = record
+                .lines()
+                .filter_map(|line| line.split(',').next())
+                .filter(|file| !file.is_empty())
+                .any(|file| normalize(&relative_site_packages.join(file)) == path);
+            if owns_path {
+                return Ok(Some(Owner {
+                    kind: "pypi",
+                    name: name.to_string(),
+                    version: version.to_string(),
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the `site-packages` directories of the python installations in the prefix.
+fn site_packages_dirs(prefix_root: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let windows = prefix_root.join("Lib").join("site-packages");
+    if windows.is_dir() {
+        dirs.push(windows);
+    }
+    for entry in fs::read_dir(prefix_root.join("lib")).into_iter().flatten() {
+        let Ok(entry) = entry else { continue };
+        let site_packages = entry.path().join("site-packages");
+        if entry.file_name().to_string_lossy().starts_with("python") && site_packages.is_dir() {
+            dirs.push(site_packages);
+        }
+    }
+    dirs
+}
+
+/// Resolves the `.` and `..` components of a path without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_owner() {
+        let prefix = tempfile::tempdir().unwrap();
+        fs::create_dir_all(prefix.path().join("conda-meta")).unwrap();
+        fs::write(
+            prefix.path().join("conda-meta/python-3.12.3-h_0.json"),
+            r#"{
+                "name": "python",
+                "version": "3.12.3",
+                "build": "h_0",
+                "build_number": 0,
+                "subdir": "linux-64",
+                "fn": "python-3.12.3-h_0.conda",
+                "url": "https://conda.anaconda.org/conda-forge/linux-64/python-3.12.3-h_0.conda",
+                "channel": "https://conda.anaconda.org/conda-forge/",
+                "files": ["bin/python3.12"],
+                "paths_data": {"paths_version": 1, "paths": []}
+            }"#,
+        )
+        .unwrap();
+        let dist_info = prefix
+            .path()
+            .join("lib/python3.12/site-packages/black-24.4.2.dist-info");
+        fs::create_dir_all(&dist_info).unwrap();
+        fs::write(
+            dist_info.join("RECORD"),
+            "../../../bin/black,sha256=abc,42\nblack/__init__.py,sha256=def,1\n",
+        )
+        .unwrap();
+
+        let records = Prefix::new(prefix.path())
+            .find_installed_packages(None)
+            .await
+            .unwrap();
+        assert_eq!(
+            conda_owner(&records, Path::new("bin/python3.12")),
+            Some(Owner {
+                kind: "conda",
+                name: String::from("python"),
+                version: String::from("3.12.3 h_0"),
+            })
+        );
+        assert_eq!(conda_owner(&records, Path::new("bin/black")), None);
+
+        let black = Some(Owner {
+            kind: "pypi",
+            name: String::from("black"),
+            version: String::from("24.4.2"),
+        });
+        assert_eq!(
+            pypi_owner(prefix.path(), Path::new("bin/black")).unwrap(),
+            black
+        );
+        assert_eq!(
+            pypi_owner(
+                prefix.path(),
+                Path::new("lib/python3.12/site-packages/black/__init__.py")
+            )
+            .unwrap(),
+            black
+        );
+        assert_eq!(
+            pypi_owner(prefix.path(), Path::new("bin/ruff")).unwrap(),
+            None
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::path::PathBuf;
 
 pub mod channel;
 pub mod description;
+pub mod lock;
 pub mod platform;
 pub mod version;
 
@@ -10,6 +11,7 @@ pub mod version;
 pub enum Command {
     Channel(channel::Args),
     Description(description::Args),
+    Lock(lock::Args),
     Platform(platform::Args),
     Version(version::Args),
 }
@@ -28,6 +30,7 @@ pub async fn execute(cmd: Args) -> miette::Result<()> {
     match cmd.command {
         Command::Channel(args) => channel::execute(args).await?,
         Command::Description(args) => description::execute(args).await?,
+        Command::Lock(args) => lock::execute(args).await?,
         Command::Platform(args) => platform::execute(args).await?,
         Command::Version(args) => version::execute(args).await?,
     };
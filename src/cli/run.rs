@@ -1,12 +1,16 @@
-use std::collections::hash_map::Entry;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::convert::identity;
+use std::ffi::OsString;
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 use std::{collections::HashMap, path::PathBuf, string::String};
 
 use crate::config::ConfigCli;
 use clap::Parser;
+use deno_task_shell::{execute_with_pipes, ShellPipeReader, ShellPipeWriter, ShellState};
 use dialoguer::theme::ColorfulTheme;
+use futures::{FutureExt, StreamExt};
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::{miette, Context, Diagnostic, IntoDiagnostic};
 use rattler_conda_types::Platform;
@@ -15,8 +19,10 @@ use crate::activation::get_environment_variables;
 use crate::environment::verify_prefix_location_unchanged;
 use crate::project::errors::UnsupportedPlatformError;
 use crate::task::{
-    AmbiguousTask, CanSkip, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory,
-    SearchEnvironments, TaskAndEnvironment, TaskGraph, TaskName,
+    run_on_changes, AmbiguousTask, CanSkip, ChangedFiles, DetachedTasks, ExecutableTask,
+    FailedToParseShellScript, FileWatcher, InvalidWorkingDirectory, OutputFilter, ParallelOutput,
+    SearchEnvironments, ShellNotFound, StopOutcome, TaskAndEnvironment, TaskArg,
+    TaskDisambiguation, TaskGraph, TaskId, TaskName, TaskOutput, REPRODUCIBLE_ENV,
 };
 use crate::Project;
 
@@ -29,12 +35,21 @@ use crate::project::Environment;
 use thiserror::Error;
 use tracing::Level;
 
+/// What to do when a task exits with a non-zero exit code.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnFailure {
+    /// Print the environment, the key environment variables and the resolved command of the task
+    /// to stderr.
+    Dump,
+}
+
 /// Runs task in project.
 #[derive(Parser, Debug, Default)]
 #[clap(trailing_var_arg = true, arg_required_else_help = true)]
+#[clap(group(clap::ArgGroup::new("parallel_output").args(["interleave", "serialize"])))]
 pub struct Args {
     /// The task you want to run in the projects environment.
-    #[arg(required = true)]
+    #[arg(required_unless_present_any = ["stop", "print_completions"])]
     pub task: Vec<String>,
 
     /// The path to 'pixi.toml' or 'pyproject.toml'
@@ -49,14 +64,182 @@ pub struct Args {
 
     #[clap(flatten)]
     pub config: ConfigCli,
+
+    /// Only run tasks whose `inputs` changed compared to a git reference. Tasks without
+    /// `inputs` are always run.
+    #[arg(long)]
+    pub changed_only: bool,
+
+    /// The git reference to compare against when using `--changed-only`. Defaults to `HEAD`.
+    #[arg(long, requires = "changed_only")]
+    pub since: Option<String>,
+
+    /// Run the tasks in this directory instead of their configured `cwd`. Relative paths are
+    /// resolved against the current directory.
+    #[arg(long)]
+    pub cwd: Option<PathBuf>,
+
+    /// Start the task in the background and return immediately. The output of the task is
+    /// written to a log file in the `.pixi` directory.
+    #[arg(long, conflicts_with = "stop")]
+    pub detach: bool,
+
+    /// Stop a task that was started with `--detach`, including all processes it started.
+    #[arg(long, value_name = "TASK", conflicts_with = "task")]
+    pub stop: Option<String>,
+
+    /// Treat every argument as the name of a task and run all of them in order. Dependencies
+    /// shared by the tasks are only run once.
+    #[arg(long)]
+    pub each: bool,
+
+    /// Like `--each`, but keep running the other tasks if some of them fail.
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Only print the lines of the output of the tasks that match this regex. The exit code of
+    /// the tasks is not affected.
+    #[arg(long, value_name = "REGEX")]
+    pub grep: Option<regex::bytes::Regex>,
+
+    /// Also filter the stderr of the tasks with `--grep`, otherwise it is passed through untouched.
+    #[arg(long, requires = "grep")]
+    pub grep_stderr: bool,
+
+    /// Print the names of the tasks that can be run, one per line, for use by the shell
+    /// completions. When a task and the values of its preceding arguments are given, the
+    /// `choices` of its next argument are printed instead. Only the manifest is read, nothing is
+    /// solved or installed.
+    #[arg(long, hide = true)]
+    pub print_completions: bool,
+
+    /// Run the independent `depends-on` tasks in parallel and stream their output live, every
+    /// line is prefixed with the name of the task.
+    #[arg(long, conflicts_with = "serialize")]
+    pub interleave: bool,
+
+    /// Run the independent `depends-on` tasks in parallel and buffer their output, the output of
+    /// every task is printed in order once they finished.
+    #[arg(long)]
+    pub serialize: bool,
+
+    /// The maximum number of independent `depends-on` tasks that run at the same time with
+    /// `--interleave` or `--serialize`, defaults to the number of CPUs. Requires one of them.
+    #[arg(long, value_name = "N", requires = "parallel_output")]
+    pub max_parallel: Option<NonZeroUsize>,
+
+    /// Redirect the stderr of the tasks into their stdout, like `2>&1`. The exit code of the
+    /// tasks is not affected.
+    #[arg(long)]
+    pub merge_stderr: bool,
+
+    /// Run the activation scripts of the environment again instead of reusing the activation
+    /// environment of a previous run.
+    #[arg(long)]
+    pub no_activation_cache: bool,
+
+    /// Print debugging context to stderr when a task exits with a non-zero exit code. The values
+    /// of environment variables that look like secrets are redacted.
+    #[arg(long, value_enum, value_name = "ACTION")]
+    pub on_failure: Option<OnFailure>,
+
+    /// Run the tasks again whenever the files that match these comma separated globs change, a
+    /// run that is still in progress is cancelled. Without globs the `inputs` of the tasks are
+    /// watched.
+    #[arg(
+        long,
+        value_name = "GLOBS",
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ',',
+        conflicts_with_all = ["detach", "stop"]
+    )]
+    pub watch: Option<Vec<String>>,
+
+    /// Set `PYTHONHASHSEED=0`, `SOURCE_DATE_EPOCH` and `TZ=UTC` in the environment of the tasks,
+    /// like `reproducible-env = true` in the manifest. The `env` of a task overrides them.
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 /// CLI entry point for `pixi run`
 /// When running the sigints are ignored and child can react to them. As it pleases.
 pub async fn execute(args: Args) -> miette::Result<()> {
+    // Split 'task' into arguments if it's a single string, supporting commands like:
+    // `"test 1 == 0 || echo failed"` or `"echo foo && echo bar"` or `"echo 'Hello World'"`
+    // This prevents shell interpretation of pixi run inputs.
+    // Use as-is if 'task' already contains multiple elements.
+    // With `--each` or `--keep-going` every argument is a task of its own.
+    let each_task = args.each || args.keep_going;
+    let mut task_args = if args.task.len() == 1 && !each_task {
+        shlex::split(args.task[0].as_str())
+            .ok_or(miette!("Could not split task, assuming non valid task"))?
+    } else {
+        args.task
+    };
+    tracing::debug!("Task parsed from run command: {:?}", task_args);
+
+    // Make sure the working directory override exists before anything is executed.
+    let cwd_override = args
+        .cwd
+        .map(|cwd| {
+            let abs_path = std::env::current_dir().into_diagnostic()?.join(&cwd);
+            if !abs_path.is_dir() {
+                return Err(InvalidWorkingDirectory {
+                    path: cwd.to_string_lossy().to_string(),
+                }
+                .into());
+            }
+            Ok::<_, miette::Report>(abs_path)
+        })
+        .transpose()?;
+
+    // A task of a nested project can be targeted with `<path-to-project>:<task>`, unless a
+    // manifest was explicitly specified.
+    let mut manifest_path = args.manifest_path.clone();
+    if manifest_path.is_none() {
+        if let Some((nested_manifest, task_name)) = task_args
+            .first()
+            .and_then(|task| crate::project::split_nested_project_task(task))
+        {
+            tracing::debug!(
+                "Running task '{task_name}' of {}",
+                nested_manifest.display()
+            );
+            task_args[0] = task_name.to_string();
+            manifest_path = Some(nested_manifest);
+        }
+    }
+
     // Load the project
     let project =
-        Project::load_or_else_discover(args.manifest_path.as_deref())?.with_cli_config(args.config);
+        Project::load_or_else_discover(manifest_path.as_deref())?.with_cli_config(args.config);
+
+    if args.print_completions {
+        return print_task_completions(&project, args.environment.as_deref(), &task_args);
+    }
+
+    let detached_tasks = DetachedTasks::new(project.detached_tasks_folder());
+
+    // Stop a task that is running in the background.
+    if let Some(task) = args.stop {
+        match detached_tasks.stop(&task)? {
+            StopOutcome::Stopped(pid) => eprintln!(
+                "{}Stopped task '{}' (pid {pid})",
+                console::style(console::Emoji("✔ ", "")).green(),
+                console::style(&task).bold(),
+            ),
+            StopOutcome::Stale(pid) => eprintln!(
+                "{}Task '{}' was no longer running (pid {pid}), removed its pid file",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                console::style(&task).bold(),
+            ),
+            StopOutcome::NotRunning => miette::bail!(
+                "the task '{task}' is not running in the background, it can be started with `pixi run --detach {task}`"
+            ),
+        }
+        return Ok(());
+    }
 
     // Sanity check of prefix location
     verify_prefix_location_unchanged(project.default_environment().dir().as_path()).await?;
@@ -79,128 +262,655 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             .into_diagnostic()?;
     }
 
+    let search_environment = SearchEnvironments::from_opt_env(
+        &project,
+        explicit_environment.clone(),
+        explicit_environment
+            .as_ref()
+            .map(|e| e.best_platform())
+            .or(Some(Platform::current())),
+    )
+    .with_disambiguate_fn(disambiguate_task_interactive);
+
+    // Start the task in a `pixi run` process of its own that keeps running in the background, or
+    // that is started again whenever the watched files change.
+    if args.detach || args.watch.is_some() {
+        let run_args = respawn_args(&std::env::args_os().collect_vec());
+
+        let pixi = std::env::current_exe().into_diagnostic()?;
+        if let Some(globs) = args.watch {
+            let globs = if globs.is_empty() {
+                task_inputs(&project, &search_environment, &task_args, each_task)?
+            } else {
+                globs
+            };
+            eprintln!(
+                "{}Watching {} for changes, press Ctrl-C to stop",
+                console::Emoji("👀 ", ""),
+                globs.join(", ")
+            );
+            let watcher = FileWatcher::new(project.root(), globs)
+                .await
+                .into_diagnostic()?;
+            return run_on_changes(watcher, &pixi, run_args).await;
+        }
+
+        let task_name = &task_args[0];
+        let detached = detached_tasks.spawn(task_name, &pixi, run_args)?;
+        eprintln!(
+            "{}Started task '{}' in the background (pid {}), its output is written to '{}'",
+            console::style(console::Emoji("✔ ", "")).green(),
+            console::style(task_name).bold(),
+            detached.pid,
+            detached.log_file.display()
+        );
+        eprintln!("Stop it with `pixi run --stop {task_name}`");
+        return Ok(());
+    }
+
     // Ensure that the lock-file is up-to-date.
-    let mut lock_file = project
+    let lock_file = project
         .up_to_date_lock_file(UpdateLockFileOptions {
             lock_file_usage: args.lock_file_usage.into(),
             ..UpdateLockFileOptions::default()
         })
         .await?;
 
-    // Split 'task' into arguments if it's a single string, supporting commands like:
-    // `"test 1 == 0 || echo failed"` or `"echo foo && echo bar"` or `"echo 'Hello World'"`
-    // This prevents shell interpretation of pixi run inputs.
-    // Use as-is if 'task' already contains multiple elements.
-    let task_args = if args.task.len() == 1 {
-        shlex::split(args.task[0].as_str())
-            .ok_or(miette!("Could not split task, assuming non valid task"))?
+    // Construct a task graph from the input arguments. With `--each` or `--keep-going` every
+    // argument is a task of its own, otherwise the arguments form a single task invocation.
+    let task_graphs = if each_task {
+        task_args
+            .iter()
+            .map(|task| {
+                let graph =
+                    TaskGraph::from_cmd_args(&project, &search_environment, vec![task.clone()])?;
+                Ok::<_, miette::Report>((task.clone(), graph))
+            })
+            .collect::<Result<Vec<_>, _>>()?
     } else {
-        args.task
+        let name = task_args.first().cloned().unwrap_or_default();
+        vec![(
+            name,
+            TaskGraph::from_cmd_args(&project, &search_environment, task_args)?,
+        )]
     };
-    tracing::debug!("Task parsed from run command: {:?}", task_args);
 
-    // Construct a task graph from the input arguments
-    let search_environment = SearchEnvironments::from_opt_env(
+    // The hooks around the tasks.
+    let pre_run_graph = hook_task_graph(
         &project,
-        explicit_environment.clone(),
-        explicit_environment
-            .as_ref()
-            .map(|e| e.best_platform())
-            .or(Some(Platform::current())),
-    )
-    .with_disambiguate_fn(disambiguate_task_interactive);
+        &search_environment,
+        project.pre_run_task(),
+        &task_graphs,
+    )?;
+    let post_run_graph = hook_task_graph(
+        &project,
+        &search_environment,
+        project.post_run_task(),
+        &task_graphs,
+    )?;
 
-    let task_graph = TaskGraph::from_cmd_args(&project, &search_environment, task_args)?;
+    // Determine which files changed if we only want to run tasks with changed inputs.
+    let since = args.since.unwrap_or_else(|| String::from("HEAD"));
+    let changed_files = if args.changed_only {
+        Some(ChangedFiles::since(project.root(), &since)?)
+    } else {
+        None
+    };
 
-    tracing::info!("Task graph: {}", task_graph);
+    let output_filter = args.grep.map(|regex| OutputFilter {
+        regex,
+        stderr: args.grep_stderr,
+    });
+    let activation_cache = !args.no_activation_cache;
+    let reproducible = args.reproducible || project.reproducible_env();
+    let parallel_output = if args.interleave {
+        Some(ParallelOutput::Interleave)
+    } else if args.serialize {
+        Some(ParallelOutput::Serialize)
+    } else {
+        None
+    };
+    let max_parallel = args
+        .max_parallel
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
 
-    // Traverse the task graph in topological order and execute each individual task.
-    let mut task_idx = 0;
-    let mut task_envs = HashMap::new();
-    for task_id in task_graph.topological_order() {
-        let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
+    let mut runner = TaskRunner {
+        project: &project,
+        explicit_environment,
+        lock_file,
+        task_envs: HashMap::new(),
+        finished_tasks: HashMap::new(),
+        task_idx: 0,
+        cwd_override,
+        changed_files,
+        since,
+        output_filter,
+        merge_stderr: args.merge_stderr,
+        activation_cache,
+        reproducible,
+        parallel_output,
+        max_parallel,
+        keep_going: args.keep_going,
+        on_failure: args.on_failure,
+    };
 
-        // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
-        // instantiate a prefix for an alias.
-        if !executable_task.task().is_executable() {
-            continue;
-        }
+    if let Some(pre_run_graph) = &pre_run_graph {
+        runner.run_hook("pre-run-task", pre_run_graph).await?;
+    }
 
-        // Showing which command is being run if the level and type allows it.
-        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
-            if task_idx > 0 {
-                // Add a newline between task outputs
-                eprintln!();
+    // Traverse the task graphs in topological order and execute each individual task.
+    let mut failed_tasks = Vec::new();
+
+    // A failure that stops running the tasks, it is reported after the post-run task ran.
+    let mut stopped = None;
+    'graphs: for (task_name, task_graph) in &task_graphs {
+        tracing::info!("Task graph: {}", task_graph);
+        for layer in runner.layers(task_graph) {
+            match runner.run_layer(task_graph, layer).await? {
+                LayerOutcome::Succeeded => {}
+                LayerOutcome::Failed => {
+                    failed_tasks.push(task_name.clone());
+                    break;
+                }
+                outcome => {
+                    stopped = Some(outcome);
+                    break 'graphs;
+                }
             }
+        }
+    }
+
+    // The post-run task also runs when one of the tasks failed.
+    if let Some(post_run_graph) = &post_run_graph {
+        runner.run_hook("post-run-task", post_run_graph).await?;
+    }
+    match stopped {
+        Some(LayerOutcome::Error(err)) => return Err(err.into()),
+        Some(LayerOutcome::ExitCode(code)) => std::process::exit(code),
+        _ => {}
+    }
+
+    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+
+    if args.keep_going {
+        eprintln!();
+        let task_names = task_graphs
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect_vec();
+        return report_keep_going(&task_names, &failed_tasks);
+    }
+
+    Ok(())
+}
+
+/// Prints whether each of the tasks of `--keep-going` succeeded, fails if any of them failed.
+fn report_keep_going(task_names: &[&str], failed_tasks: &[String]) -> miette::Result<()> {
+    for task_name in task_names {
+        if failed_tasks.iter().any(|failed| failed == task_name) {
             eprintln!(
-                "{}{}{} in {}{}{}",
-                console::Emoji("✨ ", ""),
-                console::style("Pixi task (").bold(),
-                console::style(executable_task.name().unwrap_or("unnamed"))
-                    .green()
-                    .bold(),
-                executable_task
-                    .run_environment
-                    .name()
-                    .fancy_display()
-                    .bold(),
-                console::style("): ").bold(),
-                executable_task.display_command(),
+                "{}Task '{}' failed",
+                console::style(console::Emoji("❌ ", "")).red().bold(),
+                console::style(task_name).bold()
+            );
+        } else {
+            eprintln!(
+                "{}Task '{}' succeeded",
+                console::style(console::Emoji("✔ ", "")).green(),
+                console::style(task_name).bold()
             );
         }
+    }
+    if !failed_tasks.is_empty() {
+        miette::bail!(
+            "{} of {} tasks failed: {}",
+            failed_tasks.len(),
+            task_names.len(),
+            failed_tasks.iter().format(", ")
+        );
+    }
+    Ok(())
+}
 
-        // check task cache
-        let task_cache = match executable_task
-            .can_skip(&lock_file)
-            .await
-            .into_diagnostic()?
-        {
-            CanSkip::No(cache) => cache,
-            CanSkip::Yes => {
-                eprintln!(
-                    "Task '{}' can be skipped (cache hit) 🚀",
-                    console::style(executable_task.name().unwrap_or("")).bold()
-                );
-                task_idx += 1;
+/// How the tasks of a layer of a task graph finished.
+enum LayerOutcome {
+    /// The tasks succeeded, were skipped or were allowed to fail.
+    Succeeded,
+    /// A task failed with `--keep-going`, the other task graphs still run.
+    Failed,
+    /// A task failed with this exit code, no other task is run.
+    ExitCode(i32),
+    /// A task could not be executed, no other task is run.
+    Error(TaskExecutionError),
+}
+
+/// Runs the layers of the task graphs of a `pixi run` and the hooks around them. The task
+/// environments are shared by all tasks, they are computed once an environment is needed.
+struct TaskRunner<'p> {
+    project: &'p Project,
+    explicit_environment: Option<Environment<'p>>,
+    lock_file: LockFileDerivedData<'p>,
+    task_envs: HashMap<Environment<'p>, HashMap<String, String>>,
+
+    /// Whether a named task succeeded, so tasks shared by multiple task graphs and the hooks only
+    /// run once.
+    finished_tasks: HashMap<(String, EnvironmentName), bool>,
+
+    /// The number of tasks that ran so far, the output of the tasks is separated by an empty line.
+    task_idx: usize,
+
+    cwd_override: Option<PathBuf>,
+    changed_files: Option<ChangedFiles>,
+    since: String,
+    output_filter: Option<OutputFilter>,
+    merge_stderr: bool,
+    activation_cache: bool,
+    reproducible: bool,
+    parallel_output: Option<ParallelOutput>,
+    max_parallel: usize,
+    keep_going: bool,
+    on_failure: Option<OnFailure>,
+}
+
+impl<'p> TaskRunner<'p> {
+    /// Returns the layers of the task graph. Without a parallel output mode every task is a layer
+    /// of its own and the tasks run one after the other.
+    fn layers(&self, task_graph: &TaskGraph<'p>) -> Vec<Vec<TaskId>> {
+        match self.parallel_output {
+            Some(_) => task_graph.parallel_layers(),
+            None => task_graph
+                .topological_order()
+                .into_iter()
+                .map(|task_id| vec![task_id])
+                .collect_vec(),
+        }
+    }
+
+    /// Records whether a named task succeeded.
+    fn mark_finished(&mut self, finished_key: Option<(String, EnvironmentName)>, succeeded: bool) {
+        if let Some(key) = finished_key {
+            self.finished_tasks.insert(key, succeeded);
+        }
+    }
+
+    /// Computes the command environment of the environment if no task needed it yet. We lazily
+    /// compute the task environment because we only need the environment if a task is actually
+    /// executed.
+    async fn ensure_task_env(&mut self, environment: &Environment<'p>) -> miette::Result<()> {
+        if !self.task_envs.contains_key(environment) {
+            let command_env = get_task_env(
+                &mut self.lock_file,
+                environment,
+                self.activation_cache,
+                self.reproducible,
+            )
+            .await?;
+            self.task_envs.insert(environment.clone(), command_env);
+        }
+        Ok(())
+    }
+
+    /// Runs the tasks of a layer of the task graph. The tasks run at the same time, at most
+    /// `max_parallel` at once, and the next layer only starts once all tasks of this layer
+    /// finished.
+    async fn run_layer(
+        &mut self,
+        task_graph: &TaskGraph<'p>,
+        layer: Vec<TaskId>,
+    ) -> miette::Result<LayerOutcome> {
+        // The tasks of this layer that have to be executed.
+        let mut pending = Vec::new();
+        for task_id in layer {
+            let executable_task = ExecutableTask::from_task_graph(task_graph, task_id)
+                .with_working_directory(self.cwd_override.clone())
+                .with_reproducible_env(self.reproducible);
+
+            // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
+            // instantiate a prefix for an alias.
+            if !executable_task.task().is_executable() {
                 continue;
             }
-        };
 
-        // If we don't have a command environment yet, we need to compute it. We lazily compute the
-        // task environment because we only need the environment if a task is actually executed.
-        let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
-            Entry::Occupied(env) => env.into_mut(),
-            Entry::Vacant(entry) => {
-                let command_env =
-                    get_task_env(&mut lock_file, &executable_task.run_environment).await?;
-                entry.insert(command_env)
+            // Skip tasks that already ran as part of a previous task graph.
+            let finished_key = executable_task.name().map(|name| {
+                (
+                    name.to_string(),
+                    executable_task.run_environment.name().clone(),
+                )
+            });
+            if let Some(key) = &finished_key {
+                match self.finished_tasks.get(key) {
+                    Some(true) => continue,
+                    Some(false) => return Ok(LayerOutcome::Failed),
+                    None => {}
+                }
             }
-        };
 
-        // Execute the task itself within the command environment. If one of the tasks failed with
-        // a non-zero exit code, we exit this parent process with the same code.
-        match execute_task(&executable_task, task_env).await {
-            Ok(_) => {
-                task_idx += 1;
+            // The header of a serialized task is printed together with its output. The index of
+            // the task counts the tasks of this layer that were already started, the index is
+            // only advanced once the tasks of the layer finished.
+            if self.parallel_output != Some(ParallelOutput::Serialize) {
+                print_task_header(&executable_task, self.task_idx + pending.len());
+            }
+
+            // Skip the task if none of its inputs changed since the requested git reference.
+            if let (Some(changed_files), Some(inputs)) = (
+                &self.changed_files,
+                executable_task
+                    .task()
+                    .as_execute()
+                    .and_then(|e| e.inputs.as_ref()),
+            ) {
+                if !changed_files.matches_any(inputs)? {
+                    eprintln!(
+                        "Task '{}' can be skipped (no inputs changed since '{}') 🚀",
+                        console::style(executable_task.name().unwrap_or("")).bold(),
+                        self.since
+                    );
+                    self.mark_finished(finished_key, true);
+                    self.task_idx += 1;
+                    continue;
+                }
+            }
+
+            // check task cache
+            let task_cache = match executable_task
+                .can_skip(&self.lock_file)
+                .await
+                .into_diagnostic()?
+            {
+                CanSkip::No(cache) => cache,
+                CanSkip::Yes => {
+                    eprintln!(
+                        "Task '{}' can be skipped (cache hit) 🚀",
+                        console::style(executable_task.name().unwrap_or("")).bold()
+                    );
+                    self.mark_finished(finished_key, true);
+                    self.task_idx += 1;
+                    continue;
+                }
+            };
+
+            self.ensure_task_env(&executable_task.run_environment)
+                .await?;
+
+            // Read the secrets of the task before anything runs, a missing secret is an error.
+            let keyring_env = with_keyring_env(
+                &executable_task,
+                &self.task_envs[&executable_task.run_environment],
+            )?;
+
+            let output = self
+                .parallel_output
+                .map(|mode| mode.task_output(executable_task.name().unwrap_or("unnamed")));
+            pending.push((
+                task_id,
+                executable_task,
+                task_cache,
+                finished_key,
+                output,
+                keyring_env,
+            ));
+        }
+
+        // Execute the tasks of the layer at the same time. If one of the tasks failed with a
+        // non-zero exit code, we exit this parent process with the same code.
+        let results = futures::stream::iter(pending.iter().map(
+            |(_, executable_task, _, _, output, keyring_env)| {
+                execute_task(
+                    executable_task,
+                    keyring_env
+                        .as_ref()
+                        .unwrap_or(&self.task_envs[&executable_task.run_environment]),
+                    self.output_filter.as_ref(),
+                    output.as_ref(),
+                    self.merge_stderr,
+                )
+            },
+        ))
+        .buffered(self.max_parallel)
+        .collect::<Vec<_>>()
+        .await;
+
+        // Print the buffered output of the tasks in order.
+        if self.parallel_output == Some(ParallelOutput::Serialize) {
+            for (idx, (_, executable_task, _, _, output, _)) in pending.iter().enumerate() {
+                print_task_header(executable_task, self.task_idx + idx);
+                if let Some(output) = output {
+                    output.print_buffered().into_diagnostic()?;
+                }
             }
-            Err(TaskExecutionError::NonZeroExitCode(code)) => {
-                if code == 127 {
-                    command_not_found(&project, explicit_environment);
+        }
+
+        // Handle the result of every task of the layer before stopping at a failure, so the
+        // tasks that succeeded next to a failed one are still cached.
+        let mut exit_code = None;
+        let mut error = None;
+        let mut layer_failed = false;
+        for ((task_id, executable_task, task_cache, finished_key, _, _), result) in
+            pending.into_iter().zip(results)
+        {
+            match result {
+                Ok(_) => {}
+                Err(TaskExecutionError::NonZeroExitCode(code))
+                    if task_graph[task_id].allow_failure =>
+                {
+                    eprintln!(
+                        "{}Task '{}' failed with exit code {} but is allowed to fail, continuing",
+                        console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                        console::style(executable_task.name().unwrap_or("")).bold(),
+                        code
+                    );
+                    self.mark_finished(finished_key, true);
+                    self.task_idx += 1;
+                    continue;
+                }
+                Err(TaskExecutionError::NonZeroExitCode(code)) => {
+                    self.report_failure(&executable_task, code);
+                    if self.keep_going {
+                        eprintln!(
+                            "{}Task '{}' failed with exit code {}, continuing with the next task",
+                            console::style(console::Emoji("❌ ", "")).red().bold(),
+                            console::style(executable_task.name().unwrap_or("")).bold(),
+                            code
+                        );
+                        self.mark_finished(finished_key, false);
+                        self.task_idx += 1;
+                    } else {
+                        exit_code.get_or_insert(code);
+                    }
+                    layer_failed = true;
+                    continue;
+                }
+                Err(err) => {
+                    error.get_or_insert(err);
+                    layer_failed = true;
+                    continue;
                 }
-                std::process::exit(code);
             }
-            Err(err) => return Err(err.into()),
+            self.mark_finished(finished_key, true);
+            self.task_idx += 1;
+
+            // Update the task cache with the new hash
+            executable_task
+                .save_cache(&self.lock_file, task_cache)
+                .await
+                .into_diagnostic()?;
         }
 
-        // Update the task cache with the new hash
-        executable_task
-            .save_cache(&lock_file, task_cache)
+        Ok(match (error, exit_code) {
+            (Some(err), _) => LayerOutcome::Error(err),
+            (None, Some(code)) => LayerOutcome::ExitCode(code),
+            (None, None) if layer_failed => LayerOutcome::Failed,
+            (None, None) => LayerOutcome::Succeeded,
+        })
+    }
+
+    /// Prints the hints about a task that exited with a non-zero exit code.
+    fn report_failure(&self, executable_task: &ExecutableTask<'p>, code: i32) {
+        if code == 127 {
+            command_not_found(self.project, self.explicit_environment.clone());
+        }
+        if self.on_failure == Some(OnFailure::Dump) {
+            dump_failure_context(
+                executable_task,
+                &self.task_envs[&executable_task.run_environment],
+            );
+        }
+    }
+
+    /// Runs the task graph of a `pre-run-task` or `post-run-task`, a failing hook is an error.
+    /// Tasks that already ran as part of `finished_tasks` or that can be skipped by the task cache
+    /// are not run again.
+    async fn run_hook(&mut self, kind: &str, task_graph: &TaskGraph<'p>) -> miette::Result<()> {
+        tracing::info!("Task graph of the {kind}: {}", task_graph);
+        for task_id in task_graph.topological_order() {
+            let executable_task = ExecutableTask::from_task_graph(task_graph, task_id)
+                .with_reproducible_env(self.reproducible);
+            if !executable_task.task().is_executable() {
+                continue;
+            }
+            let finished_key = executable_task.name().map(|name| {
+                (
+                    name.to_string(),
+                    executable_task.run_environment.name().clone(),
+                )
+            });
+            if finished_key
+                .as_ref()
+                .is_some_and(|key| self.finished_tasks.contains_key(key))
+            {
+                continue;
+            }
+            if tracing::enabled!(Level::WARN) {
+                eprintln!(
+                    "{}{}{}): {}",
+                    console::Emoji("✨ ", ""),
+                    console::style(format!("Pixi {kind} (")).bold(),
+                    console::style(executable_task.name().unwrap_or("unnamed"))
+                        .green()
+                        .bold(),
+                    executable_task.display_command(),
+                );
+            }
+
+            let task_cache = match executable_task
+                .can_skip(&self.lock_file)
+                .await
+                .into_diagnostic()?
+            {
+                CanSkip::No(cache) => cache,
+                CanSkip::Yes => {
+                    eprintln!(
+                        "Task '{}' can be skipped (cache hit) 🚀",
+                        console::style(executable_task.name().unwrap_or("")).bold()
+                    );
+                    self.mark_finished(finished_key, true);
+                    continue;
+                }
+            };
+
+            self.ensure_task_env(&executable_task.run_environment)
+                .await?;
+            let task_env = &self.task_envs[&executable_task.run_environment];
+            let keyring_env = with_keyring_env(&executable_task, task_env)?;
+            match execute_task(
+                &executable_task,
+                keyring_env.as_ref().unwrap_or(task_env),
+                self.output_filter.as_ref(),
+                None,
+                self.merge_stderr,
+            )
             .await
-            .into_diagnostic()?;
+            {
+                Ok(_) => {}
+                Err(TaskExecutionError::NonZeroExitCode(code)) => miette::bail!(
+                    "the {kind} '{}' failed with exit code {code}",
+                    executable_task.name().unwrap_or("unnamed")
+                ),
+                Err(err) => return Err(err.into()),
+            }
+            self.mark_finished(finished_key, true);
+            executable_task
+                .save_cache(&self.lock_file, task_cache)
+                .await
+                .into_diagnostic()?;
+        }
+        Ok(())
     }
+}
 
-    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
-    Ok(())
+/// Returns the arguments of this `pixi run` without `--detach` and `--watch`, to run the tasks in
+/// a `pixi run` process of its own. The options are looked up in the definition of the command
+/// line, so all other arguments are passed on unchanged. The process is started in the same
+/// directory, so the project and the relative paths resolve the same way.
+fn respawn_args(argv: &[OsString]) -> Vec<OsString> {
+    let mut command = <super::Args as clap::CommandFactory>::command();
+    command.build();
+    let argv = argv.get(1..).unwrap_or_default();
+    let Some(subcommand) = first_positional(&command, argv) else {
+        return Vec::new();
+    };
+    let run_command = command
+        .find_subcommand(argv[subcommand].to_string_lossy().as_ref())
+        .expect("this is the `run` subcommand");
+
+    let run_argv = &argv[subcommand + 1..];
+    let options_end = first_positional(run_command, run_argv).unwrap_or(run_argv.len());
+    let is_respawn_option = |arg: &OsString| {
+        let arg = arg.to_string_lossy();
+        arg == "--detach" || arg == "--watch" || arg.starts_with("--watch=")
+    };
+    argv[..=subcommand]
+        .iter()
+        .chain(
+            run_argv[..options_end]
+                .iter()
+                .filter(|arg| !is_respawn_option(arg)),
+        )
+        .chain(&run_argv[options_end..])
+        .cloned()
+        .collect()
+}
+
+/// Returns the index of the first positional argument or `--` in `args`, the arguments before it
+/// are the options of the command and their values.
+fn first_positional(command: &clap::Command, args: &[OsString]) -> Option<usize> {
+    // An option whose value has to be attached with `=`, like `--watch`, never takes the next
+    // argument.
+    let takes_value = |arg: Option<&clap::Arg>| {
+        arg.is_some_and(|arg| arg.get_action().takes_values() && !arg.is_require_equals_set())
+    };
+    let mut idx = 0;
+    while let Some(arg) = args.get(idx) {
+        let arg = arg.to_string_lossy();
+        if arg == "--" || !arg.starts_with('-') || arg == "-" {
+            return Some(idx);
+        }
+        // The value of an option is the next argument unless it is attached, e.g. `--cwd=src` or
+        // `-edev`.
+        let value_is_next = if let Some(long) = arg.strip_prefix("--") {
+            !long.contains('=')
+                && takes_value(
+                    command
+                        .get_arguments()
+                        .find(|option| option.get_long() == Some(long)),
+                )
+        } else {
+            let mut shorts = arg.chars().skip(1);
+            shorts.next().is_some_and(|short| {
+                shorts.next().is_none()
+                    && takes_value(
+                        command
+                            .get_arguments()
+                            .find(|option| option.get_short() == Some(short)),
+                    )
+            })
+        };
+        idx += if value_is_next { 2 } else { 1 };
+    }
+    None
 }
 
 /// Called when a command was not found.
@@ -230,11 +940,240 @@ fn command_not_found<'p>(project: &'p Project, explicit_environment: Option<Envi
     }
 }
 
+/// Environment variables whose name contains one of these words are printed without their value.
+const SECRET_ENV_MARKERS: [&str; 3] = ["TOKEN", "SECRET", "PASSWORD"];
+
+/// Returns the environment variables that are printed for a failed task: the variables of the
+/// activation that describe the environment, `PATH` and the `env` of the task, sorted by name.
+/// The values of variables that look like secrets are redacted.
+fn failure_env_vars<'a>(
+    command_env: &'a HashMap<String, String>,
+    task_env: Option<&'a IndexMap<String, String>>,
+) -> Vec<(&'a str, &'a str)> {
+    let is_key = |key: &str| {
+        key.starts_with("PIXI_") || key.starts_with("CONDA_") || key.eq_ignore_ascii_case("PATH")
+    };
+    // The `env` of the task overrides the variables of the command environment.
+    let env_vars: BTreeMap<&str, &str> = command_env
+        .iter()
+        .filter(|(key, _)| is_key(key))
+        .chain(task_env.into_iter().flatten())
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    env_vars
+        .into_iter()
+        .map(|(key, value)| {
+            let upper = key.to_uppercase();
+            if SECRET_ENV_MARKERS
+                .iter()
+                .any(|marker| upper.contains(marker))
+            {
+                (key, "<redacted>")
+            } else {
+                (key, value)
+            }
+        })
+        .collect()
+}
+
+/// Prints the environment, the key environment variables and the resolved command of a failed
+/// task to stderr.
+fn dump_failure_context(
+    executable_task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+) {
+    eprintln!("{}", console::style("Failed task context:").bold());
+    eprintln!(
+        "  environment: {}",
+        executable_task.run_environment.name().fancy_display()
+    );
+    if let Ok(cwd) = executable_task.working_directory() {
+        eprintln!("  working directory: {}", cwd.display());
+    }
+    if let Some(command) = executable_task.full_command() {
+        eprintln!("  command: {command}");
+    }
+    eprintln!("  environment variables:");
+    for (key, value) in failure_env_vars(command_env, executable_task.task().env()) {
+        eprintln!("    {key}={value}");
+    }
+}
+
+/// Prints which task is being run if the level and type allows it.
+fn print_task_header(executable_task: &ExecutableTask<'_>, task_idx: usize) {
+    if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
+        if task_idx > 0 {
+            // Add a newline between task outputs
+            eprintln!();
+        }
+        eprintln!(
+            "{}{}{} in {}{}{}",
+            console::Emoji("✨ ", ""),
+            console::style("Pixi task (").bold(),
+            console::style(executable_task.name().unwrap_or("unnamed"))
+                .green()
+                .bold(),
+            executable_task
+                .run_environment
+                .name()
+                .fancy_display()
+                .bold(),
+            console::style("): ").bold(),
+            executable_task.display_command(),
+        );
+    }
+}
+
+/// Returns the `inputs` of the tasks that are run and of the tasks they depend on, these are
+/// watched by `--watch` when no globs are given.
+fn task_inputs<'p, D: TaskDisambiguation<'p>>(
+    project: &'p Project,
+    search_environment: &SearchEnvironments<'p, D>,
+    task_args: &[String],
+    each_task: bool,
+) -> miette::Result<Vec<String>> {
+    let task_graphs = if each_task {
+        task_args
+            .iter()
+            .map(|task| TaskGraph::from_cmd_args(project, search_environment, vec![task.clone()]))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![TaskGraph::from_cmd_args(
+            project,
+            search_environment,
+            task_args.to_vec(),
+        )?]
+    };
+    let inputs = task_graphs
+        .iter()
+        .flat_map(|task_graph| {
+            task_graph.topological_order().into_iter().flat_map(|id| {
+                ExecutableTask::from_task_graph(task_graph, id)
+                    .task()
+                    .as_execute()
+                    .and_then(|execute| execute.inputs.clone())
+                    .unwrap_or_default()
+            })
+        })
+        .unique()
+        .collect_vec();
+    if inputs.is_empty() {
+        miette::bail!(
+            help = "pass the files to watch, e.g. `--watch=src/**,tests/**`, or add `inputs` to the task",
+            "the tasks don't have `inputs` to watch"
+        );
+    }
+    Ok(inputs)
+}
+
+/// Builds the task graph of a `pre-run-task` or `post-run-task` of the project. The hook has to be
+/// a task of the project, it is never interpreted as a command. Returns `None` if there is no
+/// hook or if it already runs as one of the tasks in `task_graphs`.
+fn hook_task_graph<'p, D: TaskDisambiguation<'p>>(
+    project: &'p Project,
+    search_environment: &SearchEnvironments<'p, D>,
+    hook: Option<&str>,
+    task_graphs: &[(String, TaskGraph<'p>)],
+) -> miette::Result<Option<TaskGraph<'p>>> {
+    let Some(hook) = hook else {
+        return Ok(None);
+    };
+
+    // A hook that is one of the tasks that are run, or one of their dependencies, already runs as
+    // part of them.
+    let is_running = task_graphs.iter().any(|(_, task_graph)| {
+        task_graph
+            .topological_order()
+            .into_iter()
+            .any(|id| task_graph[id].name.as_ref().map(|name| name.as_str()) == Some(hook))
+    });
+    if is_running {
+        return Ok(None);
+    }
+
+    let name = TaskName::from(hook);
+    let is_task = project.environments().iter().any(|environment| {
+        environment
+            .tasks(Some(Platform::current()))
+            .is_ok_and(|tasks| tasks.contains_key(&name))
+    });
+    if !is_task {
+        miette::bail!(
+            help = "add the task to the manifest or remove the hook from the `[project]` table",
+            "the hook '{hook}' is not a task of the project"
+        );
+    }
+    Ok(Some(TaskGraph::from_cmd_args(
+        project,
+        search_environment,
+        vec![hook.to_string()],
+    )?))
+}
+
+/// Prints the names of the tasks that can be run in the given environment, or in any environment
+/// that is usable on the current platform. This only reads the manifest so it is fast enough to
+/// be called on every tab completion.
+fn print_task_completions(
+    project: &Project,
+    environment: Option<&str>,
+    task_args: &[String],
+) -> miette::Result<()> {
+    let environments = match environment {
+        Some(name) => {
+            let name = EnvironmentName::from_str(name)?;
+            vec![project
+                .environment(&name)
+                .ok_or_else(|| miette::miette!("unknown environment '{name}'"))?]
+        }
+        None => project
+            .environments()
+            .into_iter()
+            .filter(|env| verify_current_platform_has_required_virtual_packages(env).is_ok())
+            .collect(),
+    };
+
+    // Complete the next argument of the task, tasks without arguments or arguments without
+    // `choices` have nothing to complete.
+    if let Some((task_name, values)) = task_args.split_first() {
+        let task_name = TaskName::from(task_name.as_str());
+        let task = environments
+            .iter()
+            .find_map(|env| env.task(&task_name, Some(Platform::current())).ok());
+        if let Some(task) = task {
+            for choice in argument_choices(task.args(), values.len()) {
+                println!("{choice}");
+            }
+        }
+        return Ok(());
+    }
+
+    let tasks: HashSet<TaskName> = environments
+        .iter()
+        .flat_map(|env| env.get_filtered_tasks())
+        .collect();
+    for task in tasks.iter().sorted() {
+        println!("{}", task.as_str());
+    }
+    Ok(())
+}
+
+/// Returns the values that are allowed for the argument at `position`, the arguments are bound in
+/// the order in which they are declared.
+fn argument_choices(args: &[TaskArg], position: usize) -> &[String] {
+    args.get(position)
+        .and_then(|arg| arg.choices.as_deref())
+        .unwrap_or_default()
+}
+
 /// Determine the environment variables to use when executing a command. The method combines the
-/// activation environment with the system environment variables.
+/// activation environment with the system environment variables. With `activation_cache` the
+/// activation environment of a previous run is reused if the prefix did not change. With
+/// `reproducible` the [`REPRODUCIBLE_ENV`] variables are added on top of the activation.
 pub async fn get_task_env<'p>(
     lock_file_derived_data: &mut LockFileDerivedData<'p>,
     environment: &Environment<'p>,
+    activation_cache: bool,
+    reproducible: bool,
 ) -> miette::Result<HashMap<String, String>> {
     // Make sure the system requirements are met
     verify_current_platform_has_required_virtual_packages(environment).into_diagnostic()?;
@@ -242,9 +1181,22 @@ pub async fn get_task_env<'p>(
     // Ensure there is a valid prefix
     lock_file_derived_data.prefix(environment).await?;
 
+    get_installed_task_env(environment, activation_cache, reproducible).await
+}
+
+/// Like [`get_task_env`] for an environment that is already installed, the prefix is not updated.
+pub async fn get_installed_task_env(
+    environment: &Environment<'_>,
+    activation_cache: bool,
+    reproducible: bool,
+) -> miette::Result<HashMap<String, String>> {
     // Get environment variables from the activation
-    let activation_env = await_in_progress("activating environment", |_| {
-        crate::activation::run_activation(environment)
+    let activation_env = await_in_progress("activating environment", |_| async {
+        if activation_cache {
+            crate::activation::run_activation_cached(environment).await
+        } else {
+            crate::activation::run_activation(environment).await
+        }
     })
     .await
     .wrap_err("failed to activate environment")?;
@@ -253,9 +1205,14 @@ pub async fn get_task_env<'p>(
     let environment_variables = get_environment_variables(environment);
 
     // Concatenate with the system environment variables
+    let reproducible_env = REPRODUCIBLE_ENV
+        .iter()
+        .filter(|_| reproducible)
+        .map(|(name, value)| (name.to_string(), value.to_string()));
     Ok(std::env::vars()
         .chain(activation_env)
         .chain(environment_variables)
+        .chain(reproducible_env)
         .collect())
 }
 
@@ -272,17 +1229,65 @@ enum TaskExecutionError {
 
     #[error(transparent)]
     UnsupportedPlatformError(#[from] UnsupportedPlatformError),
+
+    #[error(transparent)]
+    ShellNotFound(#[from] ShellNotFound),
+
+    #[error("failed to start the shell of the task")]
+    FailedToStartShell(#[source] std::io::Error),
+
+    #[error("the `nice` and `ulimit` of a task are only applied to tasks with a `shell` on Unix")]
+    #[diagnostic(help("run the task in a shell, e.g. `shell = \"sh\"`"))]
+    ResourceLimitsWithoutShell,
 }
 
-/// Called to execute a single command.
+/// Returns the command environment extended with the secrets of the `env-from-keyring` of the
+/// task, or `None` if the task doesn't read any secrets.
+fn with_keyring_env(
+    task: &ExecutableTask,
+    command_env: &HashMap<String, String>,
+) -> miette::Result<Option<HashMap<String, String>>> {
+    let secrets = task.keyring_env()?;
+    if secrets.is_empty() {
+        return Ok(None);
+    }
+    let mut command_env = command_env.clone();
+    command_env.extend(secrets);
+    Ok(Some(command_env))
+}
+
+/// Called to execute a single command. When an output filter is given only the matching lines of
+/// the output are printed. Tasks that run in parallel write their output to a [`TaskOutput`].
+/// With `merge_stderr` the stderr of the task is written to its stdout.
 ///
 /// This function is called from [`execute`].
 async fn execute_task<'p>(
     task: &ExecutableTask<'p>,
     command_env: &HashMap<String, String>,
+    output_filter: Option<&OutputFilter>,
+    output: Option<&TaskOutput>,
+    merge_stderr: bool,
 ) -> Result<(), TaskExecutionError> {
-    let Some(script) = task.as_deno_script()? else {
-        return Ok(());
+    // A task with a shell of its own is not run in the deno task shell. The shell is looked up
+    // before anything is executed.
+    let shell_command = task.as_shell_command(command_env)?;
+    // The deno task shell runs inside of pixi, so on Unix the limits can only be set in the
+    // process of a `shell`. On Windows the priority class of pixi is inherited by the processes
+    // the deno task shell starts.
+    if cfg!(unix) && shell_command.is_none() && !task.task().resource_limits().is_empty() {
+        return Err(TaskExecutionError::ResourceLimitsWithoutShell);
+    }
+    #[cfg(windows)]
+    let _priority_class = match shell_command {
+        Some(_) => None,
+        None => task.task().resource_limits().enter_priority_class(),
+    };
+    let script = match shell_command {
+        Some(_) => None,
+        None => match task.as_deno_script()? {
+            Some(script) => Some(script),
+            None => return Ok(()),
+        },
     };
     let cwd = task.working_directory()?;
 
@@ -293,10 +1298,90 @@ async fn execute_task<'p>(
     // some other command we might want to revaluate this.
     let ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
 
-    let execute_future =
-        deno_task_shell::execute(script, command_env.clone(), &cwd, Default::default());
+    let execute_future = match (shell_command, script) {
+        (Some(mut command), _) => {
+            command.current_dir(&cwd);
+            let output_filter = output_filter.cloned();
+            let output = output.cloned();
+            tokio::task::spawn_blocking(move || match (output, output_filter) {
+                (Some(output), output_filter) => {
+                    output.status(&mut command, output_filter.as_ref(), merge_stderr)
+                }
+                (None, Some(output_filter)) => output_filter.status(&mut command, merge_stderr),
+                (None, None) if merge_stderr => command.stderr(std::io::stdout()).status(),
+                (None, None) => command.status(),
+            })
+            .map(|result| match result {
+                Ok(Ok(status)) => Ok(status.code().unwrap_or(1)),
+                Ok(Err(err)) => Err(TaskExecutionError::FailedToStartShell(err)),
+                Err(err) => Err(TaskExecutionError::FailedToStartShell(err.into())),
+            })
+            .boxed_local()
+        }
+        (None, Some(script)) => match (output, output_filter) {
+            (Some(output), output_filter) => {
+                let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+                let (stdout, stderr, handles) = output.shell_pipes(output_filter, merge_stderr);
+                async move {
+                    let code =
+                        execute_with_pipes(script, state, ShellPipeReader::stdin(), stdout, stderr)
+                            .await;
+                    // Wait until all the output is written.
+                    for handle in handles {
+                        let _ = handle.await;
+                    }
+                    Ok(code)
+                }
+                .boxed_local()
+            }
+            (None, Some(output_filter)) => {
+                let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+                let (stdout, stdout_handle) = output_filter.shell_pipe(std::io::stdout());
+                let (stderr, stderr_handle) = if merge_stderr {
+                    // Merged stderr is part of stdout, so it is filtered like stdout.
+                    let (stderr, handle) = output_filter.shell_pipe(std::io::stdout());
+                    (stderr, Some(handle))
+                } else if output_filter.stderr {
+                    let (stderr, handle) = output_filter.shell_pipe(std::io::stderr());
+                    (stderr, Some(handle))
+                } else {
+                    (ShellPipeWriter::stderr(), None)
+                };
+                async move {
+                    let code =
+                        execute_with_pipes(script, state, ShellPipeReader::stdin(), stdout, stderr)
+                            .await;
+                    // Wait until all the filtered output is written.
+                    let _ = stdout_handle.await;
+                    if let Some(handle) = stderr_handle {
+                        let _ = handle.await;
+                    }
+                    Ok(code)
+                }
+                .boxed_local()
+            }
+            (None, None) if merge_stderr => {
+                let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+                execute_with_pipes(
+                    script,
+                    state,
+                    ShellPipeReader::stdin(),
+                    ShellPipeWriter::stdout(),
+                    ShellPipeWriter::stdout(),
+                )
+                .map(Ok)
+                .boxed_local()
+            }
+            (None, None) => {
+                deno_task_shell::execute(script, command_env.clone(), &cwd, Default::default())
+                    .map(Ok)
+                    .boxed_local()
+            }
+        },
+        (None, None) => unreachable!("a task without a script returns early"),
+    };
     let status_code = tokio::select! {
-        code = execute_future => code,
+        code = execute_future => code?,
         // This should never exit
         _ = ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
     };
@@ -339,3 +1424,154 @@ fn disambiguate_task_interactive<'p>(
         .map_or(None, identity)
         .map(|idx| problem.environments[idx].clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_keep_going() {
+        let tasks = ["lint", "test", "docs"];
+        assert!(report_keep_going(&tasks, &[]).is_ok());
+        let failed = vec![String::from("lint"), String::from("docs")];
+        assert_eq!(
+            report_keep_going(&tasks, &failed).unwrap_err().to_string(),
+            "2 of 3 tasks failed: lint, docs"
+        );
+    }
+
+    #[test]
+    fn test_respawn_args() {
+        let argv = |args: &str| args.split(' ').map(OsString::from).collect_vec();
+        assert_eq!(
+            respawn_args(&argv(
+                "pixi --color always r --detach -e dev --cwd src --watch=src/** --each build test"
+            )),
+            argv("--color always r -e dev --cwd src --each build test")
+        );
+        // The arguments of the task are passed on unchanged.
+        assert_eq!(
+            respawn_args(&argv(
+                "pixi run --watch --max-parallel 2 --serialize serve --detach"
+            )),
+            argv("run --max-parallel 2 --serialize serve --detach")
+        );
+        assert_eq!(
+            respawn_args(&argv("pixi run --detach -- echo --watch")),
+            argv("run -- echo --watch")
+        );
+    }
+
+    #[test]
+    fn test_argument_choices() {
+        let args = vec![
+            TaskArg {
+                name: String::from("env"),
+                choices: Some(vec![String::from("staging"), String::from("prod")]),
+                default: None,
+            },
+            TaskArg {
+                name: String::from("tag"),
+                choices: None,
+                default: Some(String::from("latest")),
+            },
+        ];
+        assert_eq!(argument_choices(&args, 0), ["staging", "prod"]);
+        assert!(argument_choices(&args, 1).is_empty());
+        assert!(argument_choices(&args, 2).is_empty());
+        assert!(argument_choices(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn test_max_parallel_requires_parallel_output() {
+        assert!(Args::try_parse_from(["run", "--max-parallel", "2", "test"]).is_err());
+        for flag in ["--interleave", "--serialize"] {
+            let args = Args::try_parse_from(["run", flag, "--max-parallel", "2", "test"]).unwrap();
+            assert_eq!(args.max_parallel, NonZeroUsize::new(2));
+        }
+    }
+
+    #[test]
+    fn test_hook_task_graph() {
+        let project = Project::from_str(
+            std::path::Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+        pre-run-task = "setup"
+
+        [tasks]
+        setup = "echo setup"
+        build = { cmd = "echo build", depends-on = ["setup"] }
+        lint = "echo lint"
+        "#,
+        )
+        .unwrap();
+        let search_environment = SearchEnvironments::from_opt_env(&project, None, None);
+        let task_graphs = |task: &str| {
+            vec![(
+                task.to_string(),
+                TaskGraph::from_cmd_args(&project, &search_environment, vec![task.to_string()])
+                    .unwrap(),
+            )]
+        };
+        let hook_tasks = |task: &str| {
+            hook_task_graph(
+                &project,
+                &search_environment,
+                project.pre_run_task(),
+                &task_graphs(task),
+            )
+            .unwrap()
+            .map(|graph| {
+                graph
+                    .topological_order()
+                    .into_iter()
+                    .filter_map(|id| graph[id].name.as_ref())
+                    .map(|name| name.as_str().to_string())
+                    .collect_vec()
+            })
+        };
+
+        assert_eq!(hook_tasks("lint"), Some(vec![String::from("setup")]));
+        // A hook that already runs as a dependency of the task, or as the task itself, is not
+        // run again.
+        assert_eq!(hook_tasks("build"), None);
+        assert_eq!(hook_tasks("setup"), None);
+
+        assert!(hook_task_graph(
+            &project,
+            &search_environment,
+            Some("missing"),
+            &task_graphs("lint")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_failure_env_vars() {
+        let command_env = HashMap::from([
+            (String::from("PIXI_ENVIRONMENT_NAME"), String::from("test")),
+            (String::from("PATH"), String::from("/env/bin")),
+            (String::from("HOME"), String::from("/home/user")),
+            (String::from("CONDA_TOKEN"), String::from("abc")),
+        ]);
+        let task_env = IndexMap::from([
+            (String::from("API_Password"), String::from("hunter2")),
+            (String::from("LEVEL"), String::from("debug")),
+            (String::from("PATH"), String::from("/task/bin")),
+        ]);
+        assert_eq!(
+            failure_env_vars(&command_env, Some(&task_env)),
+            vec![
+                ("API_Password", "<redacted>"),
+                ("CONDA_TOKEN", "<redacted>"),
+                ("LEVEL", "debug"),
+                ("PATH", "/task/bin"),
+                ("PIXI_ENVIRONMENT_NAME", "test"),
+            ]
+        );
+    }
+}
@@ -5,7 +5,7 @@ use std::{cmp::Ordering, path::PathBuf};
 use clap::Parser;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use miette::IntoDiagnostic;
+use miette::{IntoDiagnostic, WrapErr};
 use rattler_conda_types::{Channel, PackageName, Platform, RepoDataRecord};
 use rattler_repodata_gateway::sparse::SparseRepoData;
 use regex::Regex;
@@ -27,9 +27,10 @@ pub struct Args {
     pub package: String,
 
     /// Channel to specifically search package, defaults to
-    /// project channels or conda-forge
+    /// project channels or conda-forge. Can be used multiple times, only the given channels are
+    /// searched.
     #[clap(short, long)]
-    channel: Option<Vec<String>>,
+    pub channel: Option<Vec<String>>,
 
     /// The path to 'pixi.toml' or 'pyproject.toml'
     #[arg(long)]
@@ -44,6 +45,30 @@ pub struct Args {
     limit: Option<usize>,
 }
 
+/// Resolves the channels that are passed on the command line. Channels that match the name of a
+/// project channel resolve to that channel, all other channels are resolved in the same way as
+/// project channels are.
+fn resolve_channels(
+    names: &[String],
+    project_channels: &[Channel],
+    config: &Config,
+) -> miette::Result<Vec<Channel>> {
+    names
+        .iter()
+        .map(|name| {
+            if let Some(channel) = project_channels
+                .iter()
+                .find(|c| c.name() == name || c.canonical_name() == *name)
+            {
+                return Ok(channel.clone());
+            }
+            Channel::from_str(name, config.channel_config())
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to parse channel '{name}'"))
+        })
+        .collect()
+}
+
 /// fetch packages from `repo_data` based on `filter_func`
 fn search_package_by_filter<F>(
     package: &PackageName,
@@ -94,9 +119,10 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     let channels = match (args.channel, project.as_ref()) {
         // if user passes channels through the channel flag
         (Some(c), Some(p)) => {
-            let channels = p.config().compute_channels(&c).into_diagnostic()?;
+            let project_channels: Vec<_> = p.channels().into_iter().cloned().collect();
+            let channels = resolve_channels(&c, &project_channels, p.config())?;
             eprintln!(
-                "Using channels from arguments ({}): {:?}",
+                "Using channels from arguments ({}): {}",
                 p.name(),
                 channels.iter().map(|c| c.name()).join(", ")
             );
@@ -104,9 +130,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         }
         // No project -> use the global config
         (Some(c), None) => {
-            let channels = Config::load_global()
-                .compute_channels(&c)
-                .into_diagnostic()?;
+            let channels = resolve_channels(&c, &[], &Config::load_global())?;
             eprintln!(
                 "Using channels from arguments: {}",
                 channels.iter().map(|c| c.name()).join(", ")
@@ -154,6 +178,18 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         .await?,
     );
 
+    // A channel without any repodata for the searched platforms does not exist.
+    if let Some(unknown) = channels
+        .iter()
+        .find(|channel| !repo_data.keys().any(|(c, _)| c == *channel))
+    {
+        miette::bail!(
+            "channel '{}' could not be found at {}",
+            unknown.name(),
+            unknown.base_url()
+        );
+    }
+
     // When package name filter contains * (wildcard), it will search and display a list of packages matching this filter
     if package_name_filter.contains('*') {
         let package_name_without_filter = package_name_filter.replace('*', "");
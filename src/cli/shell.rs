@@ -2,13 +2,13 @@ use crate::activation::get_activation_env;
 use crate::config::ConfigCliPrompt;
 use crate::{prompt, Project};
 use clap::Parser;
-use miette::IntoDiagnostic;
+use miette::{IntoDiagnostic, WrapErr};
 use rattler_conda_types::Platform;
 use rattler_shell::activation::PathModificationBehavior;
 use rattler_shell::shell::{CmdExe, PowerShell, Shell, ShellEnum, ShellScript};
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[cfg(target_family = "unix")]
 use crate::unix::PtySession;
@@ -30,8 +30,32 @@ pub struct Args {
     #[arg(long, short)]
     environment: Option<String>,
 
+    /// Activate the environment that was installed in this directory with `pixi install --prefix`
+    #[arg(long)]
+    prefix: Option<PathBuf>,
+
     #[clap(flatten)]
     config: ConfigCliPrompt,
+
+    /// Start the shell even if it is started from within another pixi shell. The environment of
+    /// the outer shell is deactivated in the new shell and is back after exiting it.
+    #[arg(long)]
+    force: bool,
+
+    /// Run this program with its arguments in the activated environment instead of starting a
+    /// shell, and exit with its exit code. Unlike `pixi run` the tasks of the project are not
+    /// consulted and the arguments are passed verbatim.
+    #[arg(last = true, value_name = "PROGRAM")]
+    command: Vec<String>,
+}
+
+/// Removes the directories inside of `prefix` from a `PATH` like variable, so the binaries of an
+/// outer environment are no longer found in a nested shell.
+fn remove_prefix_from_path(path: &str, prefix: &Path) -> String {
+    let paths = std::env::split_paths(path).filter(|p| !p.starts_with(prefix));
+    std::env::join_paths(paths)
+        .map(|joined| joined.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string())
 }
 
 fn start_powershell(
@@ -201,10 +225,44 @@ async fn start_nu_shell(
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
-    let project =
+    let mut project =
         Project::load_or_else_discover(args.manifest_path.as_deref())?.with_cli_config(args.config);
+    if let Some(prefix) = &args.prefix {
+        let name = project
+            .environment_from_name_or_env_var(args.environment.clone())?
+            .name()
+            .clone();
+        project = project.with_prefix_override(name, prefix)?;
+    }
     let environment = project.environment_from_name_or_env_var(args.environment)?;
 
+    // Starting a shell from within another pixi shell stacks the activations, which is rarely
+    // intended.
+    let outer_prefix = if std::env::var("PIXI_IN_SHELL").is_ok() {
+        let outer = match (
+            std::env::var("PIXI_PROJECT_NAME"),
+            std::env::var("PIXI_ENVIRONMENT_NAME"),
+        ) {
+            (Ok(project_name), Ok(env_name)) => format!(" of '{project_name}:{env_name}'"),
+            _ => String::new(),
+        };
+        if !args.force && args.command.is_empty() {
+            miette::bail!(
+                help = "exit the current shell first, or use `pixi shell --force` to start a nested shell anyway",
+                "already inside a pixi shell{outer}"
+            );
+        }
+        if args.command.is_empty() {
+            eprintln!(
+                "{}starting a nested pixi shell inside the pixi shell{outer}, exit it to return to the outer shell",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+            );
+        }
+        std::env::var_os("CONDA_PREFIX").map(PathBuf::from)
+    } else {
+        None
+    };
+
     verify_current_platform_has_required_virtual_packages(&environment).into_diagnostic()?;
 
     let prompt_name = match environment.name() {
@@ -214,8 +272,34 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
     // Get the environment variables we need to set activate the environment in the shell.
     let env = get_activation_env(&environment, args.lock_file_usage.into()).await?;
+
+    // Deactivate the environment of the outer shell by removing its directories from the path.
+    let env = match outer_prefix {
+        Some(outer_prefix) if outer_prefix != environment.dir() => {
+            let mut env = env.clone();
+            for (key, value) in env.iter_mut() {
+                if key.eq_ignore_ascii_case("PATH") {
+                    *value = remove_prefix_from_path(value, &outer_prefix);
+                }
+            }
+            env
+        }
+        _ => env.clone(),
+    };
+    let env = &env;
     tracing::debug!("Pixi environment activation:\n{:?}", env);
 
+    // Run the program directly in the activated environment, without a shell in between.
+    if let Some((program, program_args)) = args.command.split_first() {
+        let status = std::process::Command::new(program)
+            .args(program_args)
+            .envs(env)
+            .status()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to start '{program}'"))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
     // Start the shell as the last part of the activation script based on the default shell.
     let interactive_shell: ShellEnum = ShellEnum::from_parent_process()
         .or_else(ShellEnum::from_env)
@@ -228,7 +312,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             ShellEnum::Bash(_) => prompt::get_bash_hook(prompt_name.as_str()),
             ShellEnum::Zsh(_) => prompt::get_zsh_hook(prompt_name.as_str()),
             ShellEnum::Fish(_) => prompt::get_fish_prompt(prompt_name.as_str()),
-            ShellEnum::Xonsh(_) => prompt::get_xonsh_prompt(),
+            ShellEnum::Xonsh(_) => prompt::get_xonsh_prompt(prompt_name.as_str()),
             ShellEnum::CmdExe(_) => prompt::get_cmd_prompt(prompt_name.as_str()),
         }
     } else {
@@ -267,3 +351,27 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_prefix_from_path() {
+        let outer = std::env::temp_dir().join("outer");
+        let inner = std::env::temp_dir().join("inner");
+        let path = std::env::join_paths([
+            outer.join("bin"),
+            inner.join("bin"),
+            PathBuf::from("/usr/bin"),
+        ])
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+        let expected = std::env::join_paths([inner.join("bin"), PathBuf::from("/usr/bin")])
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        assert_eq!(remove_prefix_from_path(&path, &outer), expected);
+    }
+}
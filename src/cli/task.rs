@@ -1,17 +1,19 @@
 use crate::project::manifest::EnvironmentName;
 use crate::project::manifest::FeatureName;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
-use crate::task::{quote, Alias, CmdArgs, Execute, Task, TaskName};
+use crate::task::{quote, Alias, CmdArgs, Execute, Task, TaskArg, TaskDependency, TaskName};
 use crate::Project;
 use clap::Parser;
 use indexmap::IndexMap;
 use itertools::Itertools;
+use miette::IntoDiagnostic;
 use rattler_conda_types::Platform;
+use serde::Serialize;
 use std::collections::HashSet;
 use std::error::Error;
 use std::path::PathBuf;
 use std::str::FromStr;
-use toml_edit::{Array, Item, Table, Value};
+use toml_edit::{Array, InlineTable, Item, Table, Value};
 
 #[derive(Parser, Debug)]
 pub enum Operation {
@@ -31,6 +33,13 @@ pub enum Operation {
     /// List all tasks
     #[clap(visible_alias = "ls", alias = "l")]
     List(ListArgs),
+
+    /// Print the definition of a single task
+    Get(GetArgs),
+
+    /// Move a task to another feature
+    #[clap(alias = "mv")]
+    Move(MoveArgs),
 }
 
 #[derive(Parser, Debug)]
@@ -90,6 +99,35 @@ fn parse_key_val(s: &str) -> Result<(String, String), Box<dyn Error + Send + Syn
     Ok((key, value))
 }
 
+#[derive(Parser, Debug, Clone)]
+#[clap(arg_required_else_help = true)]
+pub struct MoveArgs {
+    /// The name of the task to move
+    pub name: TaskName,
+
+    /// The feature the task is moved from, either `default` or `feature:<name>`
+    #[arg(long, default_value = "default", value_parser = parse_feature_name)]
+    pub from: FeatureName,
+
+    /// The feature the task is moved to, either `default` or `feature:<name>`
+    #[arg(long, value_parser = parse_feature_name)]
+    pub to: FeatureName,
+
+    /// The platform specific tasks table the task is moved between
+    #[arg(long, short)]
+    pub platform: Option<Platform>,
+}
+
+/// Parse a feature name, `default` refers to the default feature and both `feature:<name>` and
+/// `<name>` refer to a named feature.
+fn parse_feature_name(s: &str) -> Result<FeatureName, String> {
+    let name = s.strip_prefix("feature:").unwrap_or(s);
+    if name.is_empty() {
+        return Err(format!("invalid feature `{}`", s));
+    }
+    Ok(FeatureName::from(name))
+}
+
 #[derive(Parser, Debug, Clone)]
 #[clap(arg_required_else_help = true)]
 pub struct AliasArgs {
@@ -116,9 +154,125 @@ pub struct ListArgs {
     pub environment: Option<String>,
 }
 
+#[derive(Parser, Debug, Clone)]
+#[clap(arg_required_else_help = true)]
+pub struct GetArgs {
+    /// The name of the task
+    pub name: TaskName,
+
+    /// The environment to get the task from.
+    /// If not specified, the task is looked up in all environments.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+
+    /// Whether to output in json format
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The definition of a task in a specific environment, as printed by `pixi task get`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct TaskInfo {
+    name: String,
+    environments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cmd: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    env: IndexMap<String, String>,
+}
+
+impl TaskInfo {
+    fn new(name: &TaskName, task: &Task) -> Self {
+        Self {
+            name: name.as_str().to_string(),
+            environments: Vec::new(),
+            cmd: task.as_single_command().map(|cmd| cmd.into_owned()),
+            depends_on: task
+                .depends_on()
+                .iter()
+                .map(|dependency| dependency.task.as_str().to_string())
+                .collect(),
+            cwd: task.working_directory().map(PathBuf::from),
+            env: task.env().cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Returns true if both describe the same task definition, regardless of the environments.
+    fn same_definition(&self, other: &Self) -> bool {
+        self.cmd == other.cmd
+            && self.depends_on == other.depends_on
+            && self.cwd == other.cwd
+            && self.env == other.env
+    }
+
+    fn print(&self) {
+        println!(
+            "{} {}",
+            console::style("Task:").bold(),
+            console::style(&self.name).green().bold()
+        );
+        println!("  environments: {}", self.environments.join(", "));
+        if let Some(cmd) = &self.cmd {
+            println!("  cmd: {cmd}");
+        }
+        if !self.depends_on.is_empty() {
+            println!("  depends-on: {}", self.depends_on.join(", "));
+        }
+        if let Some(cwd) = &self.cwd {
+            println!("  cwd: {}", cwd.display());
+        }
+        if !self.env.is_empty() {
+            println!(
+                "  env: {}",
+                self.env
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .join(", ")
+            );
+        }
+    }
+}
+
+/// Returns the task dependencies that cannot be resolved, as the environment, the task and the
+/// task it depends on.
+fn broken_task_dependencies(
+    project: &Project,
+    platform: Option<Platform>,
+) -> HashSet<(String, TaskName, TaskName)> {
+    let mut broken = HashSet::new();
+    for environment in project.environments() {
+        let Ok(tasks) = environment.tasks(platform) else {
+            continue;
+        };
+        for (name, task) in tasks.iter() {
+            for dependency in task.depends_on() {
+                let applies = platform.map_or(true, |p| dependency.matches_platform(p));
+                if applies && !tasks.contains_key(&dependency.task) {
+                    broken.insert((
+                        environment.name().to_string(),
+                        (*name).clone(),
+                        dependency.task.clone(),
+                    ));
+                }
+            }
+        }
+    }
+    broken
+}
+
 impl From<AddArgs> for Task {
     fn from(value: AddArgs) -> Self {
-        let depends_on = value.depends_on.unwrap_or_default();
+        let depends_on = value
+            .depends_on
+            .unwrap_or_default()
+            .into_iter()
+            .map(TaskDependency::from)
+            .collect_vec();
 
         // Convert the arguments into a single string representation
         let cmd_args = if value.commands.len() == 1 {
@@ -156,6 +310,11 @@ impl From<AddArgs> for Task {
                 outputs: None,
                 cwd,
                 env,
+                env_from_keyring: None,
+                args: vec![],
+                shell: None,
+                nice: None,
+                ulimit: None,
             })
         }
     }
@@ -164,7 +323,11 @@ impl From<AddArgs> for Task {
 impl From<AliasArgs> for Task {
     fn from(value: AliasArgs) -> Self {
         Self::Alias(Alias {
-            depends_on: value.depends_on,
+            depends_on: value
+                .depends_on
+                .into_iter()
+                .map(TaskDependency::from)
+                .collect(),
         })
     }
 }
@@ -265,6 +428,46 @@ pub fn execute(args: Args) -> miette::Result<()> {
                 );
             }
         }
+        Operation::Move(args) => {
+            let broken_before = broken_task_dependencies(&project, args.platform);
+            project
+                .manifest
+                .move_task(args.name.clone(), args.platform, &args.from, &args.to)?;
+
+            // Make sure every `depends-on` that could be resolved before still resolves in the
+            // environments after the move.
+            let broken = broken_task_dependencies(&project, args.platform)
+                .difference(&broken_before)
+                .cloned()
+                .sorted()
+                .collect_vec();
+            if !broken.is_empty() {
+                miette::bail!(
+                    help = "add the feature to these environments or move the tasks they depend on as well",
+                    "moving task `{}` to the `{}` feature breaks {}",
+                    args.name.as_str(),
+                    args.to,
+                    broken
+                        .iter()
+                        .map(|(environment, task, dependency)| format!(
+                            "`{}` depending on `{}` in environment `{}`",
+                            task.as_str(),
+                            dependency.as_str(),
+                            environment
+                        ))
+                        .join(", ")
+                );
+            }
+
+            project.save()?;
+            eprintln!(
+                "{}Moved task `{}` from the `{}` feature to the `{}` feature",
+                console::style(console::Emoji("✔ ", "+")).green(),
+                args.name.fancy_display().bold(),
+                args.from.fancy_display(),
+                args.to.fancy_display(),
+            );
+        }
         Operation::Alias(args) => {
             let name = &args.alias;
             let task: Task = args.clone().into();
@@ -325,6 +528,56 @@ pub fn execute(args: Args) -> miette::Result<()> {
                 println!("{}", formatted);
             }
         }
+        Operation::Get(args) => {
+            let environments = match args.environment {
+                Some(name) => {
+                    let name = EnvironmentName::from_str(name.as_str())?;
+                    vec![project
+                        .environment(&name)
+                        .ok_or_else(|| miette::miette!("unknown environment '{name}'"))?]
+                }
+                None => project
+                    .environments()
+                    .into_iter()
+                    .filter(|env| {
+                        verify_current_platform_has_required_virtual_packages(env).is_ok()
+                    })
+                    .collect(),
+            };
+
+            // Collect the distinct definitions of the task over all requested environments.
+            let mut definitions: Vec<TaskInfo> = Vec::new();
+            for environment in environments {
+                let Ok(task) = environment.task(&args.name, Some(Platform::current())) else {
+                    continue;
+                };
+                let info = TaskInfo::new(&args.name, task);
+                let environment_name = environment.name().as_str().to_string();
+                match definitions.iter_mut().find(|d| d.same_definition(&info)) {
+                    Some(existing) => existing.environments.push(environment_name),
+                    None => definitions.push(TaskInfo {
+                        environments: vec![environment_name],
+                        ..info
+                    }),
+                }
+            }
+
+            if definitions.is_empty() {
+                miette::bail!("task '{}' could not be found", args.name.fancy_display());
+            }
+
+            if args.json {
+                let json = serde_json::to_string_pretty(&definitions).into_diagnostic()?;
+                println!("{}", json);
+            } else {
+                for (idx, definition) in definitions.iter().enumerate() {
+                    if idx > 0 {
+                        println!();
+                    }
+                    definition.print();
+                }
+            }
+        }
     };
 
     Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
@@ -349,11 +602,7 @@ impl From<Task> for Item {
                     table.insert(
                         "depends-on",
                         Value::Array(Array::from_iter(
-                            process
-                                .depends_on
-                                .into_iter()
-                                .map(String::from)
-                                .map(Value::from),
+                            process.depends_on.into_iter().map(dependency_to_value),
                         )),
                     );
                 }
@@ -363,6 +612,34 @@ impl From<Task> for Item {
                 if let Some(env) = process.env {
                     table.insert("env", Value::InlineTable(env.into_iter().collect()));
                 }
+                if let Some(env) = process.env_from_keyring {
+                    table.insert(
+                        "env-from-keyring",
+                        Value::InlineTable(env.into_iter().collect()),
+                    );
+                }
+                if !process.args.is_empty() {
+                    table.insert(
+                        "args",
+                        Value::Array(Array::from_iter(process.args.into_iter().map(arg_to_value))),
+                    );
+                }
+                if let Some(shell) = process.shell {
+                    table.insert("shell", shell.into());
+                }
+                if let Some(nice) = process.nice {
+                    table.insert("nice", i64::from(nice).into());
+                }
+                if let Some(ulimit) = process.ulimit {
+                    let mut limits = Table::new().into_inline_table();
+                    if let Some(memory) = ulimit.memory {
+                        limits.insert("memory", (memory as i64).into());
+                    }
+                    if let Some(open_files) = ulimit.open_files {
+                        limits.insert("open-files", (open_files as i64).into());
+                    }
+                    table.insert("ulimit", Value::InlineTable(limits));
+                }
                 Item::Value(Value::InlineTable(table))
             }
             Task::Alias(alias) => {
@@ -370,11 +647,7 @@ impl From<Task> for Item {
                 table.insert(
                     "depends-on",
                     Value::Array(Array::from_iter(
-                        alias
-                            .depends_on
-                            .into_iter()
-                            .map(String::from)
-                            .map(Value::from),
+                        alias.depends_on.into_iter().map(dependency_to_value),
                     )),
                 );
                 Item::Value(Value::InlineTable(table))
@@ -383,3 +656,34 @@ impl From<Task> for Item {
         }
     }
 }
+
+/// Converts a task argument to its representation in the manifest.
+fn arg_to_value(arg: TaskArg) -> Value {
+    let mut table = InlineTable::new();
+    table.insert("name", arg.name.into());
+    if let Some(choices) = arg.choices {
+        table.insert("choices", Value::Array(Array::from_iter(choices)));
+    }
+    if let Some(default) = arg.default {
+        table.insert("default", default.into());
+    }
+    Value::InlineTable(table)
+}
+
+/// Converts a task dependency to its representation in the manifest. Dependencies without
+/// conditions are written as plain task names.
+fn dependency_to_value(dependency: TaskDependency) -> Value {
+    if dependency.platform.is_none() && !dependency.allow_failure {
+        return Value::from(String::from(dependency.task));
+    }
+
+    let mut table = InlineTable::new();
+    table.insert("task", String::from(dependency.task).into());
+    if let Some(platform) = dependency.platform {
+        table.insert("platform", platform.to_string().into());
+    }
+    if dependency.allow_failure {
+        table.insert("allow-failure", true.into());
+    }
+    Value::InlineTable(table)
+}
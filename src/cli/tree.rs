@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use clap::Parser;
 use console::Color;
 use itertools::Itertools;
+use pep508_rs::MarkerEnvironment;
 use rattler_conda_types::Platform;
 
 use crate::lock_file::UpdateLockFileOptions;
@@ -264,6 +265,24 @@ fn print_dependency_tree(
     Ok(())
 }
 
+/// Print the dependency tree rooted at a single package. Returns `false` if the package is not
+/// part of the dependency map.
+pub(crate) fn print_package_subtree(
+    dep_map: &HashMap<String, Package>,
+    direct_deps: &Vec<String>,
+    package_name: &str,
+) -> bool {
+    let Some(pkg) = dep_map.get(package_name) else {
+        return false;
+    };
+
+    print_package(String::new(), pkg, direct_deps.contains(&pkg.name), false);
+
+    let mut visited_pkgs = vec![pkg.name.clone()];
+    print_dependency_leaf(pkg, String::new(), dep_map, &mut visited_pkgs, direct_deps);
+    true
+}
+
 /// Recursively print top down dependency tree nodes
 fn print_dependency_leaf(
     pkg: &Package,
@@ -344,7 +363,7 @@ fn print_package(prefix: String, package: &Package, direct: bool, visited: bool)
 }
 
 /// Extract the direct Conda and PyPI dependencies from the environment
-fn direct_dependencies(
+pub(crate) fn direct_dependencies(
     environment: &crate::project::Environment<'_>,
     platform: &Platform,
     dep_map: &HashMap<String, Package>,
@@ -386,7 +405,7 @@ enum PackageSource {
 }
 
 #[derive(Debug, Clone)]
-struct Package {
+pub(crate) struct Package {
     name: String,
     version: String,
     dependencies: Vec<String>,
@@ -395,7 +414,25 @@ struct Package {
 }
 
 /// Builds a hashmap of dependencies, with names, versions, and what they depend on
-fn generate_dependency_map(locked_deps: &Vec<rattler_lock::Package>) -> HashMap<String, Package> {
+pub(crate) fn generate_dependency_map(
+    locked_deps: &Vec<rattler_lock::Package>,
+) -> HashMap<String, Package> {
+    dependency_map(locked_deps, None)
+}
+
+/// Like [`generate_dependency_map`], but the dependencies of pypi packages that have environment
+/// markers are included if the markers apply to the `marker_environment`.
+pub(crate) fn generate_dependency_map_with_markers(
+    locked_deps: &Vec<rattler_lock::Package>,
+    marker_environment: &MarkerEnvironment,
+) -> HashMap<String, Package> {
+    dependency_map(locked_deps, Some(marker_environment))
+}
+
+fn dependency_map(
+    locked_deps: &Vec<rattler_lock::Package>,
+    marker_environment: Option<&MarkerEnvironment>,
+) -> HashMap<String, Package> {
     let mut package_dependencies_map = HashMap::new();
 
     for package in locked_deps {
@@ -436,17 +473,28 @@ fn generate_dependency_map(locked_deps: &Vec<rattler_lock::Package>) -> HashMap<
                 .as_dist_info_name()
                 .into_owned();
 
+            let extras = pypi_package
+                .data()
+                .environment
+                .extras
+                .iter()
+                .cloned()
+                .collect_vec();
             let mut dependencies = Vec::new();
             for p in pypi_package.data().package.requires_dist.iter() {
-                if let Some(markers) = &p.marker {
-                    tracing::info!(
+                match (&p.marker, marker_environment) {
+                    (None, _) => dependencies.push(p.name.as_dist_info_name().into_owned()),
+                    (Some(_), Some(marker_environment)) => {
+                        if p.evaluate_markers(marker_environment, &extras) {
+                            dependencies.push(p.name.as_dist_info_name().into_owned())
+                        }
+                    }
+                    (Some(markers), None) => tracing::info!(
                         "Extra and environment markers currently cannot be parsed on {} which is specified by {}, skipping. {:?}",
                         p.name,
                         name,
                         markers
-                    );
-                } else {
-                    dependencies.push(p.name.as_dist_info_name().into_owned())
+                    ),
                 }
             }
             package_dependencies_map.insert(
@@ -464,6 +512,14 @@ fn generate_dependency_map(locked_deps: &Vec<rattler_lock::Package>) -> HashMap<
     package_dependencies_map
 }
 
+/// Returns for every package the number of packages in the map that directly depend on it.
+pub(crate) fn dependents_count(dep_map: &HashMap<String, Package>) -> HashMap<String, usize> {
+    invert_dep_map(dep_map)
+        .into_iter()
+        .map(|(name, package)| (name, package.needed_by.iter().unique().count()))
+        .collect()
+}
+
 /// Given a map of dependencies, invert it so that it has what a package is needed by,
 /// rather than what it depends on
 fn invert_dep_map(dep_map: &HashMap<String, Package>) -> HashMap<String, Package> {
@@ -479,3 +535,78 @@ fn invert_dep_map(dep_map: &HashMap<String, Package>) -> HashMap<String, Package
 
     inverted_deps
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pypi_marker_env::determine_marker_environment;
+    use rattler_conda_types::{PackageName, PackageRecord, Version};
+    use rattler_lock::{LockFile, PypiPackageData, PypiPackageEnvironmentData, UrlOrPath};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_dependents_count_with_markers() {
+        let mut builder = LockFile::builder();
+        for (name, requires_dist) in [
+            (
+                "black",
+                vec![
+                    "click",
+                    "colorama ; sys_platform == 'win32'",
+                    "tomli ; python_version < '3.11'",
+                ],
+            ),
+            ("click", vec!["colorama ; platform_system == 'Linux'"]),
+            ("colorama", vec![]),
+            ("tomli", vec![]),
+        ] {
+            let data = PypiPackageData {
+                name: name.parse().unwrap(),
+                version: "1.0".parse().unwrap(),
+                requires_dist: requires_dist
+                    .into_iter()
+                    .map(|requirement| requirement.parse().unwrap())
+                    .collect(),
+                requires_python: None,
+                editable: false,
+                url_or_path: UrlOrPath::Url(
+                    format!("https://example.com/{name}-1.0-py3-none-any.whl")
+                        .parse()
+                        .unwrap(),
+                ),
+                hash: None,
+            };
+            builder.add_pypi_package(
+                "default",
+                Platform::Linux64,
+                data,
+                PypiPackageEnvironmentData::default(),
+            );
+        }
+        let lock_file = builder.finish();
+        let locked_deps = lock_file
+            .environment("default")
+            .unwrap()
+            .packages(Platform::Linux64)
+            .unwrap()
+            .collect_vec();
+
+        // Without a marker environment the dependencies with markers are skipped.
+        let dependents = dependents_count(&generate_dependency_map(&locked_deps));
+        assert_eq!(dependents.get("colorama").copied().unwrap_or_default(), 0);
+
+        let python = PackageRecord::new(
+            PackageName::new_unchecked("python"),
+            Version::from_str("3.12.3").unwrap(),
+            "h_0".to_string(),
+        );
+        let marker_environment = determine_marker_environment(Platform::Linux64, &python).unwrap();
+        let dependents = dependents_count(&generate_dependency_map_with_markers(
+            &locked_deps,
+            &marker_environment,
+        ));
+        assert_eq!(dependents.get("click").copied(), Some(1));
+        assert_eq!(dependents.get("colorama").copied(), Some(1));
+        assert_eq!(dependents.get("tomli").copied().unwrap_or_default(), 0);
+    }
+}
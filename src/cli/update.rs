@@ -0,0 +1,113 @@
+use crate::config::ConfigCli;
+use crate::environment::LockFileUsage;
+use crate::lock_file::{load_lock_file, remove_environments, LockFileDiff, UpdateLockFileOptions};
+use crate::project::manifest::EnvironmentName;
+use crate::project::Environment;
+use crate::Project;
+use clap::Parser;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Update the lock-file by solving environments again, without the constraints of the currently
+/// locked versions.
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// The path to 'pixi.toml' or 'pyproject.toml'
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub config: ConfigCli,
+
+    /// The environment(s) to update, all environments are updated if none are given.
+    #[arg(long, short)]
+    pub environment: Vec<String>,
+
+    /// Only show the packages that would change, without writing the lock-file.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print the changes as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project =
+        Project::load_or_else_discover(args.manifest_path.as_deref())?.with_cli_config(args.config);
+
+    // Determine the environments to update.
+    let environments = if args.environment.is_empty() {
+        project.environments()
+    } else {
+        args.environment
+            .iter()
+            .map(|name| {
+                let name = EnvironmentName::from_str(name)?;
+                project
+                    .environment(&name)
+                    .ok_or_else(|| miette::miette!("unknown environment '{name}'"))
+            })
+            .collect::<miette::Result<Vec<_>>>()?
+    };
+    let diff = update_environments(&project, &environments, args.dry_run).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff).into_diagnostic()?);
+    } else {
+        diff.print();
+    }
+
+    if args.dry_run {
+        eprintln!("The lock-file was not updated because of `--dry-run`");
+    } else if !diff.is_empty() {
+        eprintln!(
+            "{}Updated the lock-file",
+            console::style(console::Emoji("✔ ", "")).green(),
+        );
+    }
+
+    Project::warn_on_discovered_from_env(args.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Solves the environments again without their locked packages and returns the changes to the
+/// lock-file. The lock-file is only written if `dry_run` is false.
+pub async fn update_environments(
+    project: &Project,
+    environments: &[Environment<'_>],
+    dry_run: bool,
+) -> miette::Result<LockFileDiff> {
+    // Environments in the same solve group are solved together, so they are updated together.
+    let environment_names = environments
+        .iter()
+        .flat_map(|environment| match environment.solve_group() {
+            Some(solve_group) => solve_group.environments().collect_vec(),
+            None => vec![environment.clone()],
+        })
+        .map(|environment| environment.name().to_string())
+        .unique()
+        .collect_vec();
+
+    // Solve the environments without their locked packages.
+    let previous_lock_file = load_lock_file(project).await?;
+    let lock_file = project
+        .up_to_date_lock_file(UpdateLockFileOptions {
+            lock_file_usage: LockFileUsage::Update,
+            no_install: true,
+            existing_lock_file: Some(remove_environments(
+                &previous_lock_file,
+                &environment_names,
+            )?),
+            dry_run,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+
+    Ok(LockFileDiff::from_lock_files(
+        &previous_lock_file,
+        &lock_file,
+    ))
+}
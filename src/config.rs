@@ -10,6 +10,7 @@ use std::process::{Command, Stdio};
 use url::Url;
 
 use crate::consts;
+use crate::install::LinkMethod;
 use crate::util::default_channel_config;
 
 /// Determines the default author based on the default git author. Both the name and the email
@@ -59,12 +60,14 @@ pub fn home_path() -> Option<PathBuf> {
 /// Returns the default cache directory.
 /// Most important is the `PIXI_CACHE_DIR` environment variable.
 /// - If that is not set, the `RATTLER_CACHE_DIR` environment variable is used.
+/// - If that is not set, the `cache-dir` of the global configuration is used.
 /// - If that is not set, `XDG_CACHE_HOME/pixi` is used when the directory exists.
 /// - If that is not set, the default cache directory of [`rattler::default_cache_dir`] is used.
 pub fn get_cache_dir() -> miette::Result<PathBuf> {
     std::env::var("PIXI_CACHE_DIR")
         .map(PathBuf::from)
         .or_else(|_| std::env::var("RATTLER_CACHE_DIR").map(PathBuf::from))
+        .or_else(|_| Config::load_global().cache_dir.ok_or(()))
         .or_else(|_| {
             let xdg_cache_pixi_dir = std::env::var_os("XDG_CACHE_HOME")
                 .map_or_else(
@@ -121,6 +124,23 @@ pub struct RepodataConfig {
     pub disable_zstd: Option<bool>,
 }
 
+#[derive(Clone, Default, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConcurrencyConfig {
+    /// The maximum number of environments that are solved at the same time.
+    #[serde(default)]
+    pub solves: Option<usize>,
+}
+
+impl ConcurrencyConfig {
+    /// Merge the given ConcurrencyConfig into the current one.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            solves: other.solves.or(self.solves),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum KeyringProvider {
@@ -212,6 +232,34 @@ pub struct Config {
     #[serde(default)]
     #[serde(rename = "pypi-config")]
     pub pypi_config: PyPIConfig,
+
+    /// The directory in which the package and repodata caches are stored.
+    #[serde(default)]
+    #[serde(rename = "cache-dir")]
+    cache_dir: Option<PathBuf>,
+
+    /// Limits on the number of concurrent operations.
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+
+    /// If set to true, the repodata and package caches are not read from. Fetched data is still
+    /// written to the caches. This can only be set from the command line.
+    #[serde(skip)]
+    no_cache: bool,
+
+    /// If set to true, nothing is fetched from the network, the repodata and packages have to be
+    /// in the caches. This can only be set from the command line.
+    #[serde(skip)]
+    offline: bool,
+
+    /// How the files of packages are linked into the prefix, all methods are tried when not set.
+    /// This can only be set from the command line.
+    #[serde(skip)]
+    link_method: Option<LinkMethod>,
+
+    /// The proxy to use for http(s) requests. The `proxy` of the project takes precedence.
+    #[serde(default)]
+    proxy: Option<Url>,
 }
 
 impl Default for Config {
@@ -226,6 +274,12 @@ impl Default for Config {
             channel_config: default_channel_config(),
             repodata_config: None,
             pypi_config: PyPIConfig::default(),
+            cache_dir: None,
+            concurrency: ConcurrencyConfig::default(),
+            no_cache: false,
+            offline: false,
+            link_method: None,
+            proxy: None,
         }
     }
 }
@@ -264,8 +318,9 @@ impl Config {
         Ok(config)
     }
 
-    /// Load the global config file from the home directory (~/.pixi/config.toml)
-    pub fn load_global() -> Config {
+    /// The locations of the global config files, in the order in which they are loaded. Later
+    /// files take precedence over earlier ones.
+    pub fn global_config_locations() -> Vec<PathBuf> {
         #[cfg(target_os = "windows")]
         let base_path = PathBuf::from("C:\\ProgramData");
         #[cfg(not(target_os = "windows"))]
@@ -276,14 +331,21 @@ impl Config {
             |p| Some(PathBuf::from(p)),
         );
 
-        let global_locations = vec![
+        vec![
             Some(base_path.join("pixi").join(consts::CONFIG_FILE)),
             xdg_config_home.map(|d| d.join("pixi").join(consts::CONFIG_FILE)),
             dirs::config_dir().map(|d| d.join("pixi").join(consts::CONFIG_FILE)),
             home_path().map(|d| d.join(consts::CONFIG_FILE)),
-        ];
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Load the global config file from the home directory (~/.pixi/config.toml)
+    pub fn load_global() -> Config {
         let mut merged_config = Config::default();
-        for location in global_locations.into_iter().flatten() {
+        for location in Self::global_config_locations() {
             if location.exists() {
                 tracing::info!("Loading global config from {}", location.display());
                 let global_config = fs::read_to_string(&location).unwrap_or_default();
@@ -357,6 +419,12 @@ impl Config {
             channel_config: other.channel_config,
             repodata_config: other.repodata_config.or(self.repodata_config),
             pypi_config: other.pypi_config.merge(self.pypi_config),
+            cache_dir: other.cache_dir.or(self.cache_dir),
+            concurrency: self.concurrency.merge(other.concurrency),
+            no_cache: other.no_cache || self.no_cache,
+            offline: other.offline || self.offline,
+            link_method: other.link_method.or(self.link_method),
+            proxy: other.proxy.or(self.proxy),
         }
     }
 
@@ -382,6 +450,68 @@ impl Config {
         self.change_ps1.unwrap_or(true)
     }
 
+    /// Retrieve the value for the no_cache field (defaults to false).
+    pub fn no_cache(&self) -> bool {
+        self.no_cache
+    }
+
+    /// Bypass the repodata and package caches.
+    #[must_use]
+    pub fn with_no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+
+    /// Retrieve the value for the offline field (defaults to false).
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Only use the repodata and package caches, without any network access.
+    #[must_use]
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Retrieve the value for the link_method field.
+    pub fn link_method(&self) -> Option<LinkMethod> {
+        self.link_method
+    }
+
+    /// Only link the files of packages into the prefix with the given method.
+    #[must_use]
+    pub fn with_link_method(mut self, link_method: Option<LinkMethod>) -> Self {
+        self.link_method = link_method;
+        self
+    }
+
+    /// Retrieve the value for the proxy field.
+    pub fn proxy(&self) -> Option<&Url> {
+        self.proxy.as_ref()
+    }
+
+    /// Use the given proxy for http(s) requests. When `None` is passed the configured proxy is
+    /// kept.
+    #[must_use]
+    pub fn with_proxy(mut self, proxy: Option<Url>) -> Self {
+        self.proxy = proxy.or(self.proxy);
+        self
+    }
+
+    /// Retrieve the value for the cache_dir field.
+    pub fn cache_dir(&self) -> Option<&PathBuf> {
+        self.cache_dir.as_ref()
+    }
+
+    /// Retrieve the maximum number of concurrent solves (defaults to the number of available
+    /// cores minus two, with a maximum of four).
+    pub fn max_concurrent_solves(&self) -> usize {
+        self.concurrency
+            .solves
+            .unwrap_or_else(default_max_concurrent_solves)
+    }
+
     /// Retrieve the value for the auth_file field.
     pub fn authentication_override_file(&self) -> Option<&PathBuf> {
         self.authentication_override_file.as_ref()
@@ -416,6 +546,12 @@ impl Config {
     }
 }
 
+/// Returns the default number of concurrent solves.
+fn default_max_concurrent_solves() -> usize {
+    let available_parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+    (available_parallelism.saturating_sub(2)).min(4).max(1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -513,6 +649,29 @@ mod tests {
         insta::assert_snapshot!(debug);
     }
 
+    #[test]
+    fn test_parse_cache_dir_concurrency_and_proxy() {
+        let toml = r#"
+            cache-dir = "/path/to/cache"
+            proxy = "http://proxy.example.com:8080"
+
+            [concurrency]
+            solves = 2
+        "#;
+        let config = Config::from_toml(toml, &PathBuf::from("")).unwrap();
+        assert_eq!(config.cache_dir(), Some(&PathBuf::from("/path/to/cache")));
+        assert_eq!(config.max_concurrent_solves(), 2);
+
+        // The proxy of the project takes precedence, but does not remove the configured one.
+        let configured = Url::parse("http://proxy.example.com:8080").unwrap();
+        let project = Url::parse("http://project-proxy.example.com").unwrap();
+        assert_eq!(config.clone().with_proxy(None).proxy(), Some(&configured));
+        assert_eq!(
+            config.with_proxy(Some(project.clone())).proxy(),
+            Some(&project)
+        );
+    }
+
     #[test]
     fn test_parse_kebab_and_snake_case() {
         let toml = r#"
@@ -5,14 +5,21 @@ use url::Url;
 pub const PROJECT_MANIFEST: &str = "pixi.toml";
 pub const PYPROJECT_MANIFEST: &str = "pyproject.toml";
 pub const PROJECT_LOCK_FILE: &str = "pixi.lock";
+pub const PYPI_BUILD_LOCK_FILE: &str = "pixi-build.lock";
 pub const PIXI_DIR: &str = ".pixi";
 pub const CONFIG_FILE: &str = "config.toml";
+pub const GLOBAL_MANIFEST: &str = "pixi-global.toml";
 pub const PREFIX_FILE_NAME: &str = "pixi_env_prefix";
+pub const INCOMPLETE_PREFIX_FILE_NAME: &str = "pixi_incomplete";
 pub const ENVIRONMENTS_DIR: &str = "envs";
 pub const SOLVE_GROUP_ENVIRONMENTS_DIR: &str = "solve-group-envs";
 pub const PYPI_DEPENDENCIES: &str = "pypi-dependencies";
 pub const TASK_CACHE_DIR: &str = "task-cache-v0";
+pub const DETACHED_TASKS_DIR: &str = "detached-tasks";
+pub const ACTIVATION_CACHE_DIR: &str = "activation-cache-v0";
 pub const PIXI_UV_INSTALLER: &str = "uv-pixi";
+pub const LOCK_FILE_STAMP: &str = "# pixi-lock-touched: ";
+pub const LOCK_FILE_VIRTUAL_PACKAGE_OVERRIDES: &str = "# pixi-virtual-package-overrides: ";
 
 pub const ONE_TIME_MESSAGES_DIR: &str = "one-time-messages";
 
@@ -4,7 +4,7 @@ use crate::project::grouped_environment::GroupedEnvironmentName;
 use crate::project::has_features::HasFeatures;
 use crate::project::manifest::pypi_options::PypiOptions;
 use crate::{
-    consts, install, install_pypi,
+    config, consts, install, install_pypi,
     lock_file::UpdateLockFileOptions,
     prefix::Prefix,
     progress,
@@ -17,6 +17,7 @@ use crate::{
 };
 use dialoguer::theme::ColorfulTheme;
 use indexmap::IndexMap;
+use itertools::Itertools;
 use miette::{IntoDiagnostic, WrapErr};
 use rattler::{
     install::{PythonInfo, Transaction},
@@ -27,7 +28,12 @@ use rattler_lock::{PypiPackageData, PypiPackageEnvironmentData};
 use rattler_repodata_gateway::sparse::SparseRepoData;
 use reqwest_middleware::ClientWithMiddleware;
 use std::convert::identity;
-use std::{collections::HashMap, io::ErrorKind, path::Path, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    path::Path,
+    sync::Arc,
+};
 
 /// Verify the location of the prefix folder is not changed so the applied prefix path is still valid.
 /// Errors when there is a file system error or the path does not align with the defined prefix.
@@ -208,36 +214,59 @@ impl LockFileUsage {
 pub async fn get_up_to_date_prefix(
     environment: &Environment<'_>,
     lock_file_usage: LockFileUsage,
-    mut no_install: bool,
+    no_install: bool,
     existing_repo_data: IndexMap<(Channel, Platform), SparseRepoData>,
+) -> miette::Result<Prefix> {
+    get_up_to_date_prefix_with_options(
+        environment,
+        UpdateLockFileOptions {
+            existing_repo_data,
+            lock_file_usage,
+            no_install,
+            ..UpdateLockFileOptions::default()
+        },
+    )
+    .await
+}
+
+/// Like [`get_up_to_date_prefix`] but with full control over how the lock-file is updated.
+pub async fn get_up_to_date_prefix_with_options(
+    environment: &Environment<'_>,
+    options: UpdateLockFileOptions,
+) -> miette::Result<Prefix> {
+    get_up_to_date_prefix_excluding(environment, options, &HashSet::new()).await
+}
+
+/// Like [`get_up_to_date_prefix_with_options`] but the packages with the given normalized names
+/// are not installed in the prefix. The lock-file still contains them.
+pub async fn get_up_to_date_prefix_excluding(
+    environment: &Environment<'_>,
+    mut options: UpdateLockFileOptions,
+    excluded: &HashSet<String>,
 ) -> miette::Result<Prefix> {
     let current_platform = environment.best_platform();
     let project = environment.project();
 
     // Do not install if the platform is not supported
-    if !no_install && !environment.platforms().contains(&current_platform) {
+    if !options.no_install && !environment.platforms().contains(&current_platform) {
         tracing::warn!("Not installing dependency on current platform: ({current_platform}) as it is not part of this project's supported platforms.");
-        no_install = true;
+        options.no_install = true;
     }
+    let no_install = options.no_install;
 
     // Make sure the project is in a sane state
     sanity_check_project(project).await?;
 
     // Ensure that the lock-file is up-to-date
-    let mut lock_file = project
-        .up_to_date_lock_file(UpdateLockFileOptions {
-            existing_repo_data,
-            lock_file_usage,
-            no_install,
-            ..UpdateLockFileOptions::default()
-        })
-        .await?;
+    let mut lock_file = project.up_to_date_lock_file(options).await?;
 
     // Get the locked environment from the lock-file.
     if no_install {
         Ok(Prefix::new(environment.dir()))
-    } else {
+    } else if excluded.is_empty() {
         lock_file.prefix(environment).await
+    } else {
+        lock_file.prefix_excluding(environment, excluded).await
     }
 }
 
@@ -256,6 +285,7 @@ pub async fn update_prefix_pypi(
     environment_variables: &HashMap<String, String>,
     lock_file_dir: &Path,
     platform: Platform,
+    allow_build_lock_updates: bool,
 ) -> miette::Result<()> {
     // Remove python packages from a previous python distribution if the python version changed.
 
@@ -277,6 +307,8 @@ pub async fn update_prefix_pypi(
                 pypi_options,
                 environment_variables,
                 platform,
+                environment_name,
+                allow_build_lock_updates,
             )
         },
     )
@@ -346,7 +378,33 @@ pub async fn update_prefix_conda(
     installed_packages: Vec<PrefixRecord>,
     repodata_records: &[RepoDataRecord],
     platform: Platform,
+    no_cache: bool,
+    offline: bool,
+    link_method: Option<install::LinkMethod>,
 ) -> miette::Result<PythonStatus> {
+    // If a previous update of the prefix was interrupted, packages might only be partially linked.
+    // Their records are removed so they are linked again by the transaction.
+    let incomplete_marker = install::incomplete_prefix_marker(prefix.root());
+    let installed_packages = if incomplete_marker.is_file() {
+        let (installed_packages, incomplete_packages) =
+            install::split_incomplete_packages(prefix.root(), installed_packages);
+        if !incomplete_packages.is_empty() {
+            eprintln!(
+                "{}the previous installation of '{}' did not finish, repairing {}",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                environment_name.fancy_display(),
+                incomplete_packages
+                    .iter()
+                    .map(|record| record.repodata_record.package_record.name.as_source())
+                    .join(", ")
+            );
+            install::remove_incomplete_package_records(prefix.root(), &incomplete_packages)?;
+        }
+        installed_packages
+    } else {
+        installed_packages
+    };
+
     // Construct a transaction to bring the environment up to date with the lock-file content
     let transaction = Transaction::from_current_and_desired(
         installed_packages.clone(),
@@ -356,8 +414,31 @@ pub async fn update_prefix_conda(
     )
     .into_diagnostic()?;
 
+    // Without caching the packages of the transaction are removed from the shared package cache
+    // and downloaded into it again. A package cache of its own is used, so packages that were
+    // already fetched by this process are not taken from memory either. Offline, the cache is the
+    // only source of the packages, so nothing is removed.
+    let package_cache = if no_cache && !offline {
+        let pkgs_dir = config::get_cache_dir()?.join("pkgs");
+        install::evict_from_package_cache(&pkgs_dir, &transaction)?;
+        Arc::new(PackageCache::new(pkgs_dir))
+    } else {
+        package_cache
+    };
+
+    // Without network access all the packages have to come from the package cache.
+    if offline {
+        install::ensure_in_package_cache(&config::get_cache_dir()?.join("pkgs"), &transaction)?;
+    }
+
     // Execute the transaction if there is work to do
     if !transaction.operations.is_empty() {
+        // Mark the prefix as incomplete until all the operations succeeded.
+        if let Some(conda_meta) = incomplete_marker.parent() {
+            std::fs::create_dir_all(conda_meta).into_diagnostic()?;
+        }
+        std::fs::write(&incomplete_marker, "").into_diagnostic()?;
+
         // Execute the operations that are returned by the solver.
         progress::await_in_progress(
             format!(
@@ -372,12 +453,16 @@ pub async fn update_prefix_conda(
                     prefix.root().to_path_buf(),
                     authenticated_client,
                     pb,
+                    link_method,
                 )
                 .await
             },
         )
         .await?;
     }
+    if incomplete_marker.is_file() {
+        std::fs::remove_file(&incomplete_marker).into_diagnostic()?;
+    }
 
     // Mark the location of the prefix
     create_prefix_location_file(prefix.root())?;
@@ -1,8 +1,10 @@
+use crate::consts;
 use crate::progress::{
-    default_progress_style, finished_progress_style, global_multi_progress,
-    ProgressBarMessageFormatter,
+    default_progress_style, emit_progress_event, finished_progress_style, global_multi_progress,
+    ProgressBarMessageFormatter, ProgressEvent,
 };
 use crate::utils::reqwest::default_retry_policy;
+use clap::ValueEnum;
 use futures::future::ready;
 use futures::{stream, FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use indicatif::ProgressBar;
@@ -11,7 +13,7 @@ use miette::{IntoDiagnostic, WrapErr};
 use rattler::install::{
     link_package, unlink_package, InstallDriver, InstallOptions, Transaction, TransactionOperation,
 };
-use rattler::package_cache::PackageCache;
+use rattler::package_cache::{CacheKey, PackageCache};
 use rattler_conda_types::prefix_record::{Link, LinkType};
 use rattler_conda_types::{PrefixRecord, RepoDataRecord};
 use reqwest_middleware::ClientWithMiddleware;
@@ -20,7 +22,76 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-/// Executes the transaction on the given environment.
+/// How the files of the packages in the package cache are materialized in a prefix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinkMethod {
+    /// Hard link the files, the files share their contents with the package cache.
+    Hardlink,
+    /// Copy the files from the package cache.
+    Copy,
+    /// Symlink the files to the package cache. Files that have to be patched for the prefix are
+    /// still copied.
+    Symlink,
+    /// Reflink (copy-on-write) the files, this requires a filesystem that supports it.
+    Reflink,
+}
+
+impl LinkMethod {
+    /// Returns the installation options that only allow this way of linking. Files that can't be
+    /// linked this way, e.g. files with a prefix placeholder, are copied.
+    fn install_options(self, mut options: InstallOptions) -> InstallOptions {
+        options.allow_hard_links = Some(self == LinkMethod::Hardlink);
+        options.allow_symbolic_links = Some(self == LinkMethod::Symlink);
+        options.allow_ref_links = Some(self == LinkMethod::Reflink);
+        options
+    }
+
+    /// Links a test file from `source_dir` into `target_dir` to check whether this method is
+    /// supported between the two directories, e.g. hard links don't work across filesystems.
+    fn probe(self, source_dir: &Path, target_dir: &Path) -> std::io::Result<()> {
+        if self == LinkMethod::Copy {
+            return Ok(());
+        }
+        std::fs::create_dir_all(source_dir)?;
+        std::fs::create_dir_all(target_dir)?;
+        let source = tempfile::NamedTempFile::new_in(source_dir)?;
+        let target = target_dir.join(format!(".pixi-link-probe-{}", std::process::id()));
+        let result = match self {
+            LinkMethod::Hardlink => std::fs::hard_link(source.path(), &target),
+            #[cfg(unix)]
+            LinkMethod::Symlink => std::os::unix::fs::symlink(source.path(), &target),
+            #[cfg(windows)]
+            LinkMethod::Symlink => std::os::windows::fs::symlink_file(source.path(), &target),
+            LinkMethod::Reflink => reflink_copy::reflink(source.path(), &target),
+            LinkMethod::Copy => unreachable!(),
+        };
+        let _ = std::fs::remove_file(&target);
+        result
+    }
+
+    /// Returns this method if it is supported between the package cache and the prefix, otherwise
+    /// warns and falls back to copying the files.
+    pub fn supported_or_copy(self, package_cache_dir: &Path, target_prefix: &Path) -> Self {
+        match self.probe(package_cache_dir, target_prefix) {
+            Ok(()) => self,
+            Err(err) => {
+                eprintln!(
+                    "{}cannot {} files from '{}' to '{}' ({err}), copying them instead",
+                    console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                    self.to_possible_value()
+                        .expect("no variant is skipped")
+                        .get_name(),
+                    package_cache_dir.display(),
+                    target_prefix.display(),
+                );
+                LinkMethod::Copy
+            }
+        }
+    }
+}
+
+/// Executes the transaction on the given environment. When a `link_method` is given, only that
+/// method is used to link the files of the packages into the prefix.
 pub async fn execute_transaction(
     package_cache: Arc<PackageCache>,
     transaction: &Transaction<PrefixRecord, RepoDataRecord>,
@@ -28,23 +99,30 @@ pub async fn execute_transaction(
     target_prefix: PathBuf,
     download_client: ClientWithMiddleware,
     top_level_progress: ProgressBar,
+    link_method: Option<LinkMethod>,
 ) -> miette::Result<()> {
     // Create an install driver which helps limit the number of concurrent filesystem operations
     let install_driver = InstallDriver::new(100, Some(prefix_records), true);
 
     // Define default installation options.
-    let install_options = InstallOptions {
+    let mut install_options = InstallOptions {
         python_info: transaction.python_info.clone(),
         platform: Some(transaction.platform),
         ..Default::default()
     };
+    if let Some(link_method) = link_method {
+        let package_cache_dir = crate::config::get_cache_dir()?.join("pkgs");
+        install_options = link_method
+            .supported_or_copy(&package_cache_dir, &target_prefix)
+            .install_options(install_options);
+    }
 
     // Create a progress bars for downloads.
     let multi_progress = global_multi_progress();
     let total_packages_to_download = transaction
         .operations
         .iter()
-        .filter(|op| op.record_to_install().is_some())
+        .filter(|op| op.record_to_install().is_some() && relink_package_dir(op).is_none())
         .count();
     let download_pb = if total_packages_to_download > 0 {
         let pb = multi_progress
@@ -193,8 +271,18 @@ async fn execute_operation(
         ready(Ok(())).right_future()
     };
 
-    // Create a future to download the package
-    let cached_package_dir_fut = if let Some(install_record) = install_record {
+    // Create a future to download the package. Packages that only need to be relinked are linked
+    // from the directory they were previously extracted to, without fetching them again.
+    let cached_package_dir_fut = if let (Some(install_record), Some(package_dir)) =
+        (install_record, relink_package_dir(op))
+    {
+        tracing::debug!(
+            "relinking {} from {}",
+            install_record.package_record.name.as_source(),
+            package_dir.display()
+        );
+        ready(Ok(Some((install_record.clone(), package_dir)))).left_future()
+    } else if let Some(install_record) = install_record {
         async {
             let task = if let Some(pb) = download_pb {
                 Some(
@@ -204,6 +292,15 @@ async fn execute_operation(
             } else {
                 None
             };
+            let package_name = install_record.package_record.name.as_source();
+            let package_size = install_record
+                .package_record
+                .size
+                .or(install_record.package_record.legacy_bz2_size);
+            emit_progress_event(ProgressEvent::DownloadStarted {
+                package: package_name,
+                bytes: package_size,
+            });
 
             // Make sure the package is available in the package cache.
             let result = package_cache
@@ -217,6 +314,12 @@ async fn execute_operation(
                 .await
                 .into_diagnostic()
                 .with_context(|| format!("failed to download package {}", install_record.url));
+            if result.is_ok() {
+                emit_progress_event(ProgressEvent::DownloadFinished {
+                    package: package_name,
+                    bytes: package_size,
+                });
+            }
 
             // Increment the download progress bar.
             if let Some(task) = task {
@@ -230,8 +333,9 @@ async fn execute_operation(
             result
         }
         .left_future()
+        .right_future()
     } else {
-        ready(Ok(None)).right_future()
+        ready(Ok(None)).right_future().right_future()
     };
 
     // Await removal and downloading concurrently
@@ -239,6 +343,15 @@ async fn execute_operation(
 
     // If there is a package to install, do that now.
     if let Some((record, package_dir)) = install_package {
+        let package_name = record.package_record.name.as_source().to_string();
+        let package_size = record
+            .package_record
+            .size
+            .or(record.package_record.legacy_bz2_size);
+        emit_progress_event(ProgressEvent::LinkStarted {
+            package: &package_name,
+            bytes: package_size,
+        });
         link_pb
             .wrap(
                 record.package_record.name.as_source().to_string(),
@@ -251,6 +364,10 @@ async fn execute_operation(
                 ),
             )
             .await?;
+        emit_progress_event(ProgressEvent::LinkFinished {
+            package: &package_name,
+            bytes: package_size,
+        });
     }
 
     // Increment the link progress bar since we finished a step!
@@ -262,6 +379,65 @@ async fn execute_operation(
     Ok(())
 }
 
+/// Returns the path of the file in `conda-meta` that marks a prefix in which the linking of packages
+/// did not finish. The file is created before a transaction is executed and removed once it
+/// succeeded, so it only remains if the transaction was interrupted or failed.
+pub fn incomplete_prefix_marker(prefix: &Path) -> PathBuf {
+    prefix
+        .join("conda-meta")
+        .join(consts::INCOMPLETE_PREFIX_FILE_NAME)
+}
+
+/// Splits the installed packages into the packages that are completely linked into the prefix and
+/// the packages of which files are missing. Packages of which the linking was interrupted have a
+/// record in `conda-meta` without all their files.
+pub fn split_incomplete_packages(
+    prefix: &Path,
+    installed_packages: Vec<PrefixRecord>,
+) -> (Vec<PrefixRecord>, Vec<PrefixRecord>) {
+    installed_packages.into_iter().partition(|record| {
+        record
+            .files
+            .iter()
+            .all(|file| prefix.join(file).symlink_metadata().is_ok())
+    })
+}
+
+/// Removes the `conda-meta` records of the incomplete packages, so the next transaction links them
+/// again as if they were never installed.
+pub fn remove_incomplete_package_records(
+    prefix: &Path,
+    incomplete_packages: &[PrefixRecord],
+) -> miette::Result<()> {
+    for record in incomplete_packages {
+        let path = prefix.join("conda-meta").join(record.file_name());
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(err)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to remove '{}'", path.display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns the directory that contains the extracted contents of a package that is reinstalled, if
+/// it still exists. The transaction reinstalls packages that need to be relinked without changing
+/// the package itself, e.g. `noarch: python` packages when the python minor version changed, those
+/// can be linked from the previously extracted directory instead of fetching them again.
+fn relink_package_dir(op: &TransactionOperation<PrefixRecord, RepoDataRecord>) -> Option<PathBuf> {
+    match op {
+        TransactionOperation::Reinstall(record) => record
+            .extracted_package_dir
+            .as_ref()
+            .filter(|dir| dir.is_dir())
+            .cloned(),
+        _ => None,
+    }
+}
+
 /// Install a package into the environment and write a `conda-meta` file that contains information
 /// about how the file was linked.
 async fn install_package_to_environment(
@@ -333,3 +509,256 @@ async fn remove_package_from_environment(
         .await
         .into_diagnostic()
 }
+
+/// Returns an error listing the packages that the transaction has to install but that are not in
+/// the package cache at `cache_dir`. Used when installing offline, the packages can't be
+/// downloaded. Packages that are relinked from their extracted directory don't need the cache.
+pub fn ensure_in_package_cache(
+    cache_dir: &Path,
+    transaction: &Transaction<PrefixRecord, RepoDataRecord>,
+) -> miette::Result<()> {
+    let missing = transaction
+        .operations
+        .iter()
+        .filter(|op| relink_package_dir(op).is_none())
+        .filter_map(|op| op.record_to_install())
+        .map(|record| CacheKey::from(&record.package_record).to_string())
+        .filter(|key| !cache_dir.join(key).is_dir())
+        .sorted()
+        .collect_vec();
+    if !missing.is_empty() {
+        miette::bail!(
+            help = "install the environment once without `--offline` to populate the package cache",
+            "the required {} {} not in the package cache and can't be downloaded offline",
+            if missing.len() == 1 {
+                "package"
+            } else {
+                "packages"
+            },
+            if missing.len() == 1 {
+                format!("'{}' is", missing[0])
+            } else {
+                format!(
+                    "{} are",
+                    missing.iter().map(|key| format!("'{key}'")).join(", ")
+                )
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Removes the packages that the transaction installs from the package cache at `cache_dir`, so
+/// they are downloaded and extracted into the cache again. Used when installing without the
+/// cache, the fetched packages still refresh the shared package cache.
+pub fn evict_from_package_cache(
+    cache_dir: &Path,
+    transaction: &Transaction<PrefixRecord, RepoDataRecord>,
+) -> miette::Result<()> {
+    for record in transaction
+        .operations
+        .iter()
+        .filter_map(|op| op.record_to_install())
+    {
+        let path = cache_dir.join(CacheKey::from(&record.package_record).to_string());
+        match std::fs::remove_dir_all(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(err)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to remove '{}'", path.display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_conda_types::{NoArchType, PackageName, PackageRecord, Platform, Version};
+    use std::str::FromStr;
+
+    fn repodata_record(name: &str, version: &str, noarch: NoArchType) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            Version::from_str(version).unwrap(),
+            String::from("0"),
+        );
+        package_record.subdir = if noarch.is_none() {
+            Platform::Linux64.to_string()
+        } else {
+            Platform::NoArch.to_string()
+        };
+        package_record.noarch = noarch;
+        RepoDataRecord {
+            url: url::Url::parse(&format!(
+                "https://example.com/{}/{name}-{version}-0.conda",
+                package_record.subdir
+            ))
+            .unwrap(),
+            file_name: format!("{name}-{version}-0.conda"),
+            channel: String::from("https://example.com"),
+            package_record,
+        }
+    }
+
+    fn prefix_record(record: RepoDataRecord, extracted_package_dir: &Path) -> PrefixRecord {
+        PrefixRecord {
+            repodata_record: record,
+            package_tarball_full_path: None,
+            extracted_package_dir: Some(extracted_package_dir.to_path_buf()),
+            files: vec![],
+            paths_data: Vec::<rattler_conda_types::prefix_record::PathsEntry>::new().into(),
+            requested_spec: None,
+            link: None,
+        }
+    }
+
+    #[test]
+    fn test_link_method_install_options() {
+        let options = LinkMethod::Copy.install_options(InstallOptions::default());
+        assert_eq!(options.allow_hard_links, Some(false));
+        assert_eq!(options.allow_symbolic_links, Some(false));
+        assert_eq!(options.allow_ref_links, Some(false));
+
+        let options = LinkMethod::Hardlink.install_options(InstallOptions::default());
+        assert_eq!(options.allow_hard_links, Some(true));
+        assert_eq!(options.allow_symbolic_links, Some(false));
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("pkgs");
+        let prefix = dir.path().join("env");
+        assert_eq!(
+            LinkMethod::Hardlink.supported_or_copy(&cache_dir, &prefix),
+            LinkMethod::Hardlink
+        );
+        assert_eq!(std::fs::read_dir(&prefix).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_noarch_python_is_relinked_after_python_change() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let noarch_dir = cache_dir.path().join("requests-2.31.0-0");
+        std::fs::create_dir_all(&noarch_dir).unwrap();
+
+        let python_311 = repodata_record("python", "3.11.9", NoArchType::none());
+        let python_312 = repodata_record("python", "3.12.3", NoArchType::none());
+        let requests = repodata_record("requests", "2.31.0", NoArchType::python());
+
+        let installed = vec![
+            prefix_record(python_311, &cache_dir.path().join("python-3.11.9-0")),
+            prefix_record(requests.clone(), &noarch_dir),
+        ];
+        let transaction = Transaction::from_current_and_desired(
+            installed,
+            vec![python_312, requests],
+            Platform::Linux64,
+        )
+        .unwrap();
+
+        // The noarch package is reinstalled from the directory it was extracted to before.
+        let relinked = transaction
+            .operations
+            .iter()
+            .filter_map(|op| Some((op.record_to_install()?, relink_package_dir(op)?)))
+            .map(|(record, dir)| (record.package_record.name.as_source().to_string(), dir))
+            .collect::<Vec<_>>();
+        assert_eq!(relinked, vec![(String::from("requests"), noarch_dir)]);
+
+        // Only the python package itself has to be fetched.
+        let fetched = transaction
+            .operations
+            .iter()
+            .filter(|op| relink_package_dir(op).is_none())
+            .filter_map(|op| op.record_to_install())
+            .map(|record| record.package_record.name.as_source().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(fetched, vec![String::from("python")]);
+    }
+
+    #[test]
+    fn test_incomplete_package_is_relinked() {
+        let prefix = tempfile::tempdir().unwrap();
+        let conda_meta = prefix.path().join("conda-meta");
+        std::fs::create_dir_all(prefix.path().join("bin")).unwrap();
+        std::fs::create_dir_all(&conda_meta).unwrap();
+        std::fs::write(prefix.path().join("bin/python"), "").unwrap();
+
+        let python = repodata_record("python", "3.12.3", NoArchType::none());
+        let requests = repodata_record("requests", "2.31.0", NoArchType::python());
+        let mut installed_python = prefix_record(python.clone(), prefix.path());
+        installed_python.files = vec![PathBuf::from("bin/python")];
+        // The linking of requests was interrupted, its files are missing.
+        let mut installed_requests = prefix_record(requests.clone(), prefix.path());
+        installed_requests.files = vec![PathBuf::from("lib/requests/__init__.py")];
+        let record_path = conda_meta.join(installed_requests.file_name());
+        std::fs::write(&record_path, "{}").unwrap();
+
+        let (complete, incomplete) =
+            split_incomplete_packages(prefix.path(), vec![installed_python, installed_requests]);
+        assert_eq!(complete.len(), 1);
+        assert_eq!(incomplete.len(), 1);
+        remove_incomplete_package_records(prefix.path(), &incomplete).unwrap();
+        assert!(!record_path.exists());
+
+        // The transaction links the incomplete package again and leaves python untouched.
+        let transaction = Transaction::from_current_and_desired(
+            complete,
+            vec![python, requests],
+            Platform::Linux64,
+        )
+        .unwrap();
+        let installed = transaction
+            .operations
+            .iter()
+            .filter_map(|op| op.record_to_install())
+            .map(|record| record.package_record.name.as_source().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(installed, vec![String::from("requests")]);
+    }
+
+    #[test]
+    fn test_ensure_in_package_cache() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(cache_dir.path().join("python-3.12.3-0")).unwrap();
+
+        let python = repodata_record("python", "3.12.3", NoArchType::none());
+        let requests = repodata_record("requests", "2.31.0", NoArchType::python());
+        let transaction =
+            Transaction::from_current_and_desired(vec![], vec![python.clone()], Platform::Linux64)
+                .unwrap();
+        ensure_in_package_cache(cache_dir.path(), &transaction).unwrap();
+
+        let transaction = Transaction::from_current_and_desired(
+            vec![],
+            vec![python, requests],
+            Platform::Linux64,
+        )
+        .unwrap();
+        let err = ensure_in_package_cache(cache_dir.path(), &transaction).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'requests-2.31.0-0' is not in the package cache"));
+    }
+
+    #[test]
+    fn test_evict_from_package_cache() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        for key in ["python-3.12.3-0", "zlib-1.3-0"] {
+            std::fs::create_dir_all(cache_dir.path().join(key)).unwrap();
+        }
+
+        let python = repodata_record("python", "3.12.3", NoArchType::none());
+        let requests = repodata_record("requests", "2.31.0", NoArchType::python());
+        let transaction = Transaction::from_current_and_desired(
+            vec![],
+            vec![python, requests],
+            Platform::Linux64,
+        )
+        .unwrap();
+        evict_from_package_cache(cache_dir.path(), &transaction).unwrap();
+        assert!(!cache_dir.path().join("python-3.12.3-0").exists());
+        assert!(cache_dir.path().join("zlib-1.3-0").is_dir());
+    }
+}
@@ -20,15 +20,19 @@ use url::Url;
 use uv_cache::{ArchiveTarget, ArchiveTimestamp, Cache};
 use uv_configuration::{ConfigSettings, SetupPyStrategy};
 use uv_resolver::InMemoryIndex;
-use uv_types::HashStrategy;
+use uv_types::{BuildContext, HashStrategy};
 
-use crate::consts::{PIXI_UV_INSTALLER, PROJECT_MANIFEST};
-use crate::lock_file::UvResolutionContext;
-use crate::project::manifest::SystemRequirements;
+use crate::consts::{self, PIXI_UV_INSTALLER, PROJECT_MANIFEST};
+use crate::lock_file::{
+    build_backend, build_system, read_pyproject_toml_from_archive, PypiBuildEnvironment,
+    PypiBuildLock, UvResolutionContext,
+};
+use crate::project::manifest::{EnvironmentName, SystemRequirements};
 
 use crate::pypi_tags::{get_pypi_tags, is_python_record};
 use distribution_types::{
-    CachedDist, Dist, IndexUrl, InstalledDist, LocalEditable, LocalEditables, Name, ParsedGitUrl,
+    CachedDist, Dist, DistributionMetadata, IndexUrl, InstalledDist, LocalEditable, LocalEditables,
+    Name, ParsedGitUrl, ResolvedDist, SourceDist, VersionOrUrl,
 };
 use install_wheel_rs::linker::LinkMode;
 
@@ -40,7 +44,7 @@ use std::path::Path;
 use std::str::FromStr;
 use std::time::Duration;
 
-use uv_client::{Connectivity, FlatIndexClient, RegistryClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClient, RegistryClientBuilder};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::RegistryWheelIndex;
 use uv_installer::{Downloader, ResolvedEditable, SitePackages};
@@ -723,6 +727,198 @@ async fn resolve_editables(
     })
 }
 
+/// Reads the `pyproject.toml` of a locked source distribution, returns `None` if the source
+/// distribution doesn't have one.
+async fn read_source_pyproject_toml(
+    pkg: &PypiPackageData,
+    lock_file_dir: &Path,
+    client: &reqwest::Client,
+) -> miette::Result<Option<String>> {
+    match &pkg.url_or_path {
+        UrlOrPath::Path(path) => {
+            let path = lock_file_dir.join(path);
+            if path.is_dir() {
+                let pyproject_toml = path.join("pyproject.toml");
+                if !pyproject_toml.is_file() {
+                    return Ok(None);
+                }
+                return std::fs::read_to_string(pyproject_toml)
+                    .map(Some)
+                    .into_diagnostic();
+            }
+            let archive = std::fs::read(&path).into_diagnostic()?;
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            read_pyproject_toml_from_archive(&file_name, &archive)
+        }
+        UrlOrPath::Url(url) => {
+            let url = strip_direct_scheme(url);
+            let archive = client
+                .get(url.as_ref().clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .into_diagnostic()?
+                .bytes()
+                .await
+                .into_diagnostic()?;
+            let file_name = url
+                .path_segments()
+                .and_then(|segments| segments.last())
+                .unwrap_or_default();
+            read_pyproject_toml_from_archive(file_name, &archive)
+        }
+    }
+}
+
+/// Returns true if the locked package is a wheel instead of a source distribution.
+fn is_wheel(pkg: &PypiPackageData) -> bool {
+    match &pkg.url_or_path {
+        UrlOrPath::Url(url) => url.path().ends_with(".whl"),
+        UrlOrPath::Path(path) => path.extension().is_some_and(|ext| ext == "whl"),
+    }
+}
+
+/// Checks the build environments of the source distributions that are about to be built against
+/// the build environments that were recorded in the build lock file, see [`PypiBuildLock`]. The
+/// build environment of a source distribution that was not built before is recorded. A changed
+/// build environment is an error if the lock files may not be updated, otherwise it replaces the
+/// recorded one.
+#[allow(clippy::too_many_arguments)]
+async fn lock_build_environments(
+    lock_file_dir: &Path,
+    environment_name: &EnvironmentName,
+    platform: Platform,
+    allow_updates: bool,
+    python_packages: &[&CombinedPypiPackageData],
+    remote: &[Dist],
+    client: &reqwest::Client,
+    build_dispatch: &BuildDispatch<'_>,
+) -> miette::Result<()> {
+    let path = PypiBuildLock::path(lock_file_dir);
+    let mut build_lock = PypiBuildLock::from_path(&path)?;
+    let original_build_lock = build_lock.clone();
+
+    // Forget the build environments of the packages that are no longer built from source.
+    build_lock.retain_packages(environment_name.as_str(), platform, |name| {
+        python_packages
+            .iter()
+            .any(|(pkg, _)| pkg.name.as_ref() == name && !pkg.editable && !is_wheel(pkg))
+    });
+
+    for dist in remote {
+        // The revision of a git source is already locked, editables are built from the project.
+        let Dist::Source(source) = dist else {
+            continue;
+        };
+        if matches!(source, SourceDist::Git(_)) {
+            continue;
+        }
+        let Some((pkg, _)) = python_packages
+            .iter()
+            .find(|(pkg, _)| &pkg.name == dist.name() && !pkg.editable)
+        else {
+            continue;
+        };
+
+        let pyproject_toml = match read_source_pyproject_toml(pkg, lock_file_dir, client).await {
+            Ok(pyproject_toml) => pyproject_toml,
+            Err(err) => {
+                tracing::warn!(
+                    "failed to read the build system of '{}', its build environment is not locked: {err}",
+                    pkg.name
+                );
+                continue;
+            }
+        };
+        let build_system = build_system(pyproject_toml.as_deref())?;
+
+        // Resolve the build dependencies the same way as the build of the source distribution.
+        let resolution = build_dispatch
+            .resolve(&build_system.requires)
+            .await
+            .map_err(|err| {
+                miette!(
+                    "failed to resolve the build dependencies of '{}': {err}",
+                    pkg.name
+                )
+            })?;
+        let requires = resolution
+            .into_distributions()
+            .filter_map(|dist| match dist {
+                ResolvedDist::Installable(dist) => Some(dist),
+                ResolvedDist::Installed(_) => None,
+            })
+            .map(|dist| match dist.version_or_url() {
+                VersionOrUrl::Version(version) => format!("{}=={version}", dist.name()),
+                VersionOrUrl::Url(url) => format!("{} @ {url}", dist.name()),
+            })
+            .sorted()
+            .collect_vec();
+        let build = PypiBuildEnvironment {
+            build_backend: build_backend(&build_system),
+            requires,
+        };
+
+        let name = pkg.name.as_ref();
+        let version = pkg.version.to_string();
+        match build_lock.get(environment_name.as_str(), platform, name, &version) {
+            Some(locked) if locked == &build => continue,
+            Some(locked) if !allow_updates => miette::bail!(
+                help = format!(
+                    "run `pixi install` without `--frozen` or `--locked` to update '{}'",
+                    consts::PYPI_BUILD_LOCK_FILE
+                ),
+                "the build environment of '{name}' changed since it was locked\n{}",
+                describe_build_environment_change(locked, &build)
+            ),
+            Some(locked) => eprintln!(
+                "{}the build environment of '{name}' changed since it was locked\n{}",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+                describe_build_environment_change(locked, &build)
+            ),
+            None => {}
+        }
+        build_lock.insert(environment_name.as_str(), platform, name, &version, build);
+    }
+
+    if build_lock != original_build_lock {
+        build_lock.to_path(&path)?;
+    }
+    Ok(())
+}
+
+/// Describes the difference between a locked and a new build environment.
+fn describe_build_environment_change(
+    locked: &PypiBuildEnvironment,
+    build: &PypiBuildEnvironment,
+) -> String {
+    let mut lines = Vec::new();
+    if locked.build_backend != build.build_backend {
+        lines.push(format!(
+            "  build backend: {} -> {}",
+            locked.build_backend, build.build_backend
+        ));
+    }
+    for removed in locked
+        .requires
+        .iter()
+        .filter(|r| !build.requires.contains(r))
+    {
+        lines.push(format!("  - {removed}"));
+    }
+    for added in build
+        .requires
+        .iter()
+        .filter(|r| !locked.requires.contains(r))
+    {
+        lines.push(format!("  + {added}"));
+    }
+    lines.join("\n")
+}
+
 /// Installs and/or remove python distributions.
 // TODO: refactor arguments in struct
 #[allow(clippy::too_many_arguments)]
@@ -737,6 +933,8 @@ pub async fn update_python_distributions(
     pypi_options: &PypiOptions,
     environment_variables: &HashMap<String, String>,
     platform: Platform,
+    environment_name: &EnvironmentName,
+    allow_build_lock_updates: bool,
 ) -> miette::Result<()> {
     let start = std::time::Instant::now();
     let Some(python_info) = status.current_info() else {
@@ -759,13 +957,15 @@ pub async fn update_python_distributions(
         .ok_or_else(|| miette::miette!("could not resolve pypi dependencies because no python interpreter is added to the dependencies of the project.\nMake sure to add a python interpreter to the [dependencies] section of the {PROJECT_MANIFEST}, or run:\n\n\tpixi add python"))?;
     let tags = get_pypi_tags(platform, system_requirements, &python_record.package_record)?;
 
-    let index_locations = pypi_options.to_index_locations();
+    let index_locations = pypi_options
+        .with_stored_credentials(&uv_context.auth_storage)
+        .to_index_locations();
     let registry_client = Arc::new(
         RegistryClientBuilder::new(uv_context.cache.clone())
             .client(uv_context.client.clone())
             .index_urls(index_locations.index_urls())
             .keyring(uv_context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(uv_context.connectivity)
             .build(),
     );
 
@@ -911,6 +1111,18 @@ pub async fn update_python_distributions(
     let wheels = if remote.is_empty() {
         Vec::new()
     } else {
+        lock_build_environments(
+            lock_file_dir,
+            environment_name,
+            platform,
+            allow_build_lock_updates,
+            &python_packages,
+            &remote,
+            &uv_context.client,
+            &build_dispatch,
+        )
+        .await?;
+
         let start = std::time::Instant::now();
 
         let options = UvReporterOptions::new()
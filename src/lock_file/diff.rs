@@ -0,0 +1,265 @@
+//! Computes which packages were added, removed or changed between two lock-files, per environment
+//! and platform.
+
+use itertools::Itertools;
+use rattler_conda_types::Platform;
+use rattler_lock::{LockFile, Package};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// A package that was added to or removed from a lock-file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockedPackageVersion {
+    pub name: String,
+    pub kind: &'static str,
+    pub version: String,
+}
+
+/// A package whose version or build differs between two lock-files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedPackage {
+    pub name: String,
+    pub kind: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+/// The differences of a single environment and platform.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct PlatformDiff {
+    pub added: Vec<LockedPackageVersion>,
+    pub removed: Vec<LockedPackageVersion>,
+    pub changed: Vec<ChangedPackage>,
+}
+
+impl PlatformDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The differences between two lock-files. Environments and platforms without changes are
+/// omitted.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct LockFileDiff {
+    pub environments: BTreeMap<String, BTreeMap<String, PlatformDiff>>,
+}
+
+impl LockFileDiff {
+    /// Computes the differences from the `previous` to the `current` lock-file.
+    pub fn from_lock_files(previous: &LockFile, current: &LockFile) -> Self {
+        let environment_names: BTreeSet<&str> = previous
+            .environments()
+            .chain(current.environments())
+            .map(|(name, _)| name)
+            .collect();
+
+        let mut environments = BTreeMap::new();
+        for name in environment_names {
+            let platforms: HashSet<Platform> = [previous, current]
+                .into_iter()
+                .filter_map(|lock_file| lock_file.environment(name))
+                .flat_map(|environment| environment.platforms().collect_vec())
+                .collect();
+
+            let mut platform_diffs = BTreeMap::new();
+            for platform in platforms {
+                let diff = diff_packages(
+                    locked_packages(previous, name, platform),
+                    locked_packages(current, name, platform),
+                );
+                if !diff.is_empty() {
+                    platform_diffs.insert(platform.to_string(), diff);
+                }
+            }
+            if !platform_diffs.is_empty() {
+                environments.insert(name.to_string(), platform_diffs);
+            }
+        }
+
+        Self { environments }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.environments.is_empty()
+    }
+
+    /// Prints a human readable summary of the differences.
+    pub fn print(&self) {
+        if self.is_empty() {
+            println!("No packages were added, removed or changed");
+            return;
+        }
+
+        for (environment, platforms) in &self.environments {
+            for (platform, diff) in platforms {
+                println!(
+                    "{} ({})",
+                    console::style(environment).magenta().bold(),
+                    console::style(platform).yellow()
+                );
+                for package in &diff.added {
+                    println!(
+                        "  {} {} {} ({})",
+                        console::style("+").green(),
+                        package.name,
+                        package.version,
+                        package.kind
+                    );
+                }
+                for package in &diff.removed {
+                    println!(
+                        "  {} {} {} ({})",
+                        console::style("-").red(),
+                        package.name,
+                        package.version,
+                        package.kind
+                    );
+                }
+                for package in &diff.changed {
+                    println!(
+                        "  {} {} {} -> {} ({})",
+                        console::style("~").yellow(),
+                        package.name,
+                        package.before,
+                        package.after,
+                        package.kind
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Returns the version of every package in the lock-file for the environment and platform, keyed
+/// by the kind and name of the package. The version of a conda package includes its build string.
+fn locked_packages(
+    lock_file: &LockFile,
+    environment: &str,
+    platform: Platform,
+) -> BTreeMap<(&'static str, String), String> {
+    lock_file
+        .environment(environment)
+        .and_then(|environment| environment.packages(platform))
+        .into_iter()
+        .flatten()
+        .map(|package| match &package {
+            Package::Conda(conda) => {
+                let record = conda.package_record();
+                (
+                    ("conda", record.name.as_normalized().to_string()),
+                    format!("{} {}", record.version, record.build),
+                )
+            }
+            Package::Pypi(pypi) => (
+                ("pypi", pypi.data().package.name.to_string()),
+                pypi.data().package.version.to_string(),
+            ),
+        })
+        .collect()
+}
+
+fn diff_packages(
+    mut previous: BTreeMap<(&'static str, String), String>,
+    current: BTreeMap<(&'static str, String), String>,
+) -> PlatformDiff {
+    let mut diff = PlatformDiff::default();
+    for ((kind, name), after) in current {
+        match previous.remove(&(kind, name.clone())) {
+            None => diff.added.push(LockedPackageVersion {
+                name,
+                kind,
+                version: after,
+            }),
+            Some(before) if before != after => diff.changed.push(ChangedPackage {
+                name,
+                kind,
+                before,
+                after,
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.removed = previous
+        .into_iter()
+        .map(|((kind, name), version)| LockedPackageVersion {
+            name,
+            kind,
+            version,
+        })
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_conda_types::{PackageName, PackageRecord, RepoDataRecord, Version};
+    use std::str::FromStr;
+
+    fn record(name: &str, version: &str, build: &str) -> RepoDataRecord {
+        let file_name = format!("{name}-{version}-{build}.conda");
+        RepoDataRecord {
+            package_record: PackageRecord::new(
+                PackageName::new_unchecked(name),
+                Version::from_str(version).unwrap(),
+                build.to_string(),
+            ),
+            url: url::Url::parse(&format!("https://example.com/linux-64/{file_name}")).unwrap(),
+            file_name,
+            channel: String::from("https://example.com"),
+        }
+    }
+
+    fn lock_file(records: Vec<RepoDataRecord>) -> LockFile {
+        let mut builder = LockFile::builder();
+        for record in records {
+            builder.add_conda_package("default", Platform::Linux64, record.into());
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn test_lock_file_diff() {
+        let previous = lock_file(vec![
+            record("python", "3.11.0", "h_0"),
+            record("numpy", "1.26.4", "py311_0"),
+            record("openssl", "3.0.0", "0"),
+        ]);
+        let current = lock_file(vec![
+            record("python", "3.12.0", "h_0"),
+            record("numpy", "1.26.4", "py311_0"),
+            record("zlib", "1.3", "0"),
+        ]);
+
+        let diff = LockFileDiff::from_lock_files(&previous, &current);
+        let platform_diff = &diff.environments["default"]["linux-64"];
+        assert_eq!(
+            platform_diff.added,
+            vec![LockedPackageVersion {
+                name: String::from("zlib"),
+                kind: "conda",
+                version: String::from("1.3 0"),
+            }]
+        );
+        assert_eq!(
+            platform_diff.removed,
+            vec![LockedPackageVersion {
+                name: String::from("openssl"),
+                kind: "conda",
+                version: String::from("3.0.0 0"),
+            }]
+        );
+        assert_eq!(
+            platform_diff.changed,
+            vec![ChangedPackage {
+                name: String::from("python"),
+                kind: "conda",
+                before: String::from("3.11.0 h_0"),
+                after: String::from("3.12.0 h_0"),
+            }]
+        );
+
+        assert!(LockFileDiff::from_lock_files(&current, &current).is_empty());
+    }
+}
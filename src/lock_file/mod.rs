@@ -1,25 +1,44 @@
 #![deny(dead_code)]
 
+mod diff;
 mod outdated;
 mod package_identifier;
+mod pypi_build;
 mod records_by_name;
 mod resolve;
 mod satisfiability;
+mod touch;
 mod update;
+mod virtual_package_overrides;
 
+use crate::consts;
+use crate::project::virtual_packages::virtual_package_overrides;
 use crate::Project;
+use itertools::Itertools;
 use miette::{IntoDiagnostic, WrapErr};
-use rattler_conda_types::RepoDataRecord;
-use rattler_lock::{LockFile, PypiPackageData, PypiPackageEnvironmentData};
+use rattler_conda_types::{Platform, RepoDataRecord};
+use rattler_lock::{LockFile, LockFileBuilder, PypiPackageData, PypiPackageEnvironmentData};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
+pub use diff::{ChangedPackage, LockFileDiff, LockedPackageVersion, PlatformDiff};
 pub use outdated::OutdatedEnvironments;
 pub use package_identifier::PypiPackageIdentifier;
+pub use pypi_build::{
+    build_backend, build_system, read_pyproject_toml_from_archive, PypiBuildEnvironment,
+    PypiBuildLock,
+};
 pub use records_by_name::{PypiRecordsByName, RepoDataRecordsByName};
 pub use resolve::{
-    conda::resolve_conda, pypi::resolve_pypi, uv_resolution_context::UvResolutionContext,
+    conda::{channel_equivalent_records, resolve_conda, CondaSolveOptions, SolveStrategy},
+    pypi::resolve_pypi,
+    uv_resolution_context::UvResolutionContext,
 };
 pub use satisfiability::{verify_environment_satisfiability, verify_platform_satisfiability};
-pub use update::{LockFileDerivedData, UpdateLockFileOptions};
+pub use touch::{is_lock_file_touched, touch_lock_file};
+pub use update::{conda_solve_options, LockFileDerivedData, UpdateLockFileOptions};
+pub use virtual_package_overrides::virtual_package_overrides_changed;
 
 /// A list of conda packages that are locked for a specific platform.
 pub type LockedCondaPackages = Vec<RepoDataRecord>;
@@ -32,23 +51,404 @@ pub type LockedPypiPackages = Vec<PypiRecord>;
 pub type PypiRecord = (PypiPackageData, PypiPackageEnvironmentData);
 
 /// Loads the lockfile for the specified project or returns a dummy one if none could be found.
+///
+/// If the project writes a lock file per environment, the lock files of all environments are
+/// merged into a single lock-file.
 pub async fn load_lock_file(project: &Project) -> miette::Result<LockFile> {
-    let lock_file_path = project.lock_file_path();
-    if lock_file_path.is_file() {
-        // Spawn a background task because loading the file might be IO bound.
-        tokio::task::spawn_blocking(move || {
-            LockFile::from_path(&lock_file_path)
+    let lock_file_paths = existing_lock_files(project);
+    let per_environment = project.lock_file_per_environment();
+
+    // Spawn a background task because loading the files might be IO bound.
+    tokio::task::spawn_blocking(move || {
+        let lock_files = lock_file_paths
+            .iter()
+            .map(|path| read_lock_file(path))
+            .collect::<miette::Result<Vec<_>>>()?;
+        if per_environment {
+            merge_lock_files(&lock_files)
+        } else {
+            Ok(lock_files.into_iter().next().unwrap_or_default())
+        }
+    })
+    .await
+    .unwrap_or_else(|e| Err(e).into_diagnostic())
+}
+
+/// Returns the lock-files of the project that exist. With a lock file per environment, the lock
+/// files of environments that were not locked yet are skipped. If none of the environments has a
+/// lock file yet, the single lock file of the project is used instead so the locked versions are
+/// kept when switching to a lock file per environment.
+fn existing_lock_files(project: &Project) -> Vec<PathBuf> {
+    let lock_file_paths = project
+        .lock_file_paths()
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect_vec();
+    let single_lock_file = project.lock_file_path();
+    if lock_file_paths.is_empty()
+        && project.lock_file_per_environment()
+        && single_lock_file.is_file()
+    {
+        return vec![single_lock_file];
+    }
+    lock_file_paths
+}
+
+fn read_lock_file(path: &Path) -> miette::Result<LockFile> {
+    LockFile::from_path(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to load lock file from `{}`", path.display()))
+}
+
+/// Writes the lock-file of the project to disk. If the project writes a lock file per
+/// environment, every environment is written to its own file. The `CONDA_OVERRIDE_*` variables
+/// that are set are recorded in every lock-file.
+pub(crate) fn write_lock_file(project: &Project, lock_file: &LockFile) -> miette::Result<()> {
+    let overrides = virtual_package_overrides();
+    if !project.lock_file_per_environment() {
+        let path = project.lock_file_path();
+        lock_file
+            .to_path(&path)
+            .into_diagnostic()
+            .wrap_err("failed to write lock-file to disk")?;
+        return virtual_package_overrides::record(&path, &overrides);
+    }
+
+    for (environment, environment_lock_file) in split_lock_file(lock_file)? {
+        let path = project.environment_lock_file_path(&environment);
+        environment_lock_file
+            .to_path(&path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write lock-file '{}' to disk", path.display()))?;
+        virtual_package_overrides::record(&path, &overrides)?;
+    }
+
+    // The environments are now locked in their own lock files, so the single lock file of the
+    // project is outdated.
+    let single_lock_file = project.lock_file_path();
+    if single_lock_file.is_file() {
+        std::fs::remove_file(&single_lock_file)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to remove '{}'", single_lock_file.display()))?;
+        eprintln!(
+            "{}Removed '{}', the project writes a lock-file per environment",
+            console::style(console::Emoji("✔ ", "")).green(),
+            consts::PROJECT_LOCK_FILE
+        );
+    }
+
+    remove_stale_lock_files(project.root(), &project.lock_file_paths())
+}
+
+/// Removes the `pixi.<environment>.lock` files in the root of the project that don't belong to
+/// one of the `lock_file_paths`, e.g. of an environment that was removed from the manifest.
+///
+/// Only the lock files that pixi wrote for an environment are removed, that is a lock-file that
+/// only locks the environment in its name. Other files that match the pattern are left alone.
+fn remove_stale_lock_files(root: &Path, lock_file_paths: &[PathBuf]) -> miette::Result<()> {
+    for entry in std::fs::read_dir(root).into_diagnostic()? {
+        let path = entry.into_diagnostic()?.path();
+        let Some(environment) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("pixi."))
+            .and_then(|name| name.strip_suffix(".lock"))
+            .filter(|environment| !environment.is_empty())
+        else {
+            continue;
+        };
+        if !path.is_file() || lock_file_paths.contains(&path) {
+            continue;
+        }
+        let was_locked = LockFile::from_path(&path).is_ok_and(|lock_file| {
+            lock_file
+                .environments()
+                .map(|(name, _)| name)
+                .eq([environment])
+        });
+        if was_locked {
+            std::fs::remove_file(&path)
                 .into_diagnostic()
-                .wrap_err_with(|| {
-                    format!(
-                        "Failed to load lock file from `{}`",
-                        lock_file_path.display()
-                    )
-                })
+                .wrap_err_with(|| format!("failed to remove '{}'", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns a copy of the lock-file without the given environments, so they are solved again
+/// without being constrained by the currently locked versions.
+pub fn remove_environments(
+    lock_file: &LockFile,
+    environments: &[String],
+) -> miette::Result<LockFile> {
+    let mut builder = LockFile::builder();
+    for (name, environment) in lock_file.environments() {
+        if !environments.iter().any(|removed| removed == name) {
+            add_locked_environment(&mut builder, name, &environment)?;
+        }
+    }
+    Ok(builder.finish())
+}
+
+/// Returns a copy of the lock-file with only the environments and platforms in `declared`, which
+/// maps the name of an environment to its platforms. The remaining packages are copied as is,
+/// nothing is solved again.
+pub fn minimize_lock_file(
+    lock_file: &LockFile,
+    declared: &HashMap<String, HashSet<Platform>>,
+) -> miette::Result<LockFile> {
+    let mut builder = LockFile::builder();
+    for (name, environment) in lock_file.environments() {
+        if let Some(platforms) = declared.get(name) {
+            add_locked_platforms(&mut builder, name, &environment, |platform| {
+                platforms.contains(&platform)
+            })?;
+        }
+    }
+    Ok(builder.finish())
+}
+
+/// Splits a lock-file into a lock-file per environment.
+fn split_lock_file(lock_file: &LockFile) -> miette::Result<Vec<(String, LockFile)>> {
+    lock_file
+        .environments()
+        .map(|(name, environment)| {
+            let mut builder = LockFile::builder();
+            add_locked_environment(&mut builder, name, &environment)?;
+            Ok((name.to_string(), builder.finish()))
         })
-        .await
-        .unwrap_or_else(|e| Err(e).into_diagnostic())
-    } else {
-        Ok(LockFile::default())
+        .collect()
+}
+
+/// Merges the environments of multiple lock-files into a single lock-file.
+pub fn merge_lock_files(lock_files: &[LockFile]) -> miette::Result<LockFile> {
+    let mut builder = LockFile::builder();
+    for lock_file in lock_files {
+        for (name, environment) in lock_file.environments() {
+            add_locked_environment(&mut builder, name, &environment)?;
+        }
+    }
+    Ok(builder.finish())
+}
+
+/// Copies the channels, indexes and packages of a locked environment into a lock-file builder.
+fn add_locked_environment(
+    builder: &mut LockFileBuilder,
+    name: &str,
+    environment: &rattler_lock::Environment<'_>,
+) -> miette::Result<()> {
+    add_locked_platforms(builder, name, environment, |_| true)
+}
+
+/// Copies the channels and indexes of a locked environment and the packages of the platforms for
+/// which `include` returns true into a lock-file builder.
+fn add_locked_platforms(
+    builder: &mut LockFileBuilder,
+    name: &str,
+    environment: &rattler_lock::Environment<'_>,
+    include: impl Fn(Platform) -> bool,
+) -> miette::Result<()> {
+    builder.set_channels(name, environment.channels().iter().cloned());
+    if let Some(indexes) = environment.pypi_indexes() {
+        builder.set_pypi_indexes(name, indexes.clone());
+    }
+    for platform in environment
+        .platforms()
+        .filter(|platform| include(*platform))
+    {
+        let records = environment
+            .conda_repodata_records_for_platform(platform)
+            .into_diagnostic()?
+            .unwrap_or_default();
+        for record in records {
+            builder.add_conda_package(name, platform, record.into());
+        }
+        for (pkg_data, pkg_env_data) in environment
+            .pypi_packages_for_platform(platform)
+            .unwrap_or_default()
+        {
+            builder.add_pypi_package(name, platform, pkg_data, pkg_env_data);
+        }
+    }
+    Ok(())
+}
+
+/// Sorts conda records by name, version, build string and subdir. This ensures that the order of
+/// the packages in the lock-file does not depend on the order in which they were solved, which
+/// keeps diffs of the lock-file clean.
+pub(crate) fn sort_conda_records(records: &mut [RepoDataRecord]) {
+    records.sort_by(|a, b| {
+        let (a, b) = (&a.package_record, &b.package_record);
+        a.name
+            .as_normalized()
+            .cmp(b.name.as_normalized())
+            .then_with(|| a.version.partial_cmp(&b.version).unwrap_or(Ordering::Equal))
+            .then_with(|| a.build.cmp(&b.build))
+            .then_with(|| a.subdir.cmp(&b.subdir))
+    });
+}
+
+/// Sorts pypi records by name and version. See [`sort_conda_records`].
+pub(crate) fn sort_pypi_records(records: &mut [PypiRecord]) {
+    records.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_conda_types::{PackageName, PackageRecord, Version};
+    use std::str::FromStr;
+
+    fn record(name: &str, version: &str, build: &str, subdir: Platform) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            Version::from_str(version).unwrap(),
+            build.to_string(),
+        );
+        package_record.subdir = subdir.to_string();
+        let file_name = format!("{name}-{version}-{build}.conda");
+        RepoDataRecord {
+            package_record,
+            url: url::Url::parse(&format!("https://example.com/{subdir}/{file_name}")).unwrap(),
+            file_name,
+            channel: String::from("https://example.com"),
+        }
+    }
+
+    fn render_lock_file(mut records: Vec<RepoDataRecord>, path: &std::path::Path) -> String {
+        sort_conda_records(&mut records);
+        let mut builder = LockFile::builder();
+        for record in records {
+            builder.add_conda_package("default", Platform::Linux64, record.into());
+        }
+        builder.finish().to_path(path).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_split_and_merge_lock_file() {
+        let mut builder = LockFile::builder();
+        for (environment, name) in [("default", "python"), ("test", "pytest")] {
+            builder.add_conda_package(
+                environment,
+                Platform::Linux64,
+                record(name, "1.0", "0", Platform::Linux64).into(),
+            );
+        }
+        let lock_file = builder.finish();
+
+        let split = split_lock_file(&lock_file).unwrap();
+        assert_eq!(
+            split
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["default", "test"]
+        );
+        for (name, environment_lock_file) in &split {
+            assert_eq!(environment_lock_file.environments().count(), 1);
+            assert!(environment_lock_file.environment(name).is_some());
+        }
+
+        let lock_files = split
+            .into_iter()
+            .map(|(_, lock_file)| lock_file)
+            .collect::<Vec<_>>();
+        let merged = merge_lock_files(&lock_files).unwrap();
+        assert!(LockFileDiff::from_lock_files(&lock_file, &merged).is_empty());
+
+        let removed = remove_environments(&lock_file, &[String::from("test")]).unwrap();
+        assert!(removed.environment("default").is_some());
+        assert!(removed.environment("test").is_none());
+    }
+
+    #[test]
+    fn test_remove_stale_lock_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["pixi.lock", "pixi-build.lock", "pixi.notes.lock"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        for (file_name, environment) in [
+            ("pixi.default.lock", "default"),
+            ("pixi.removed.lock", "removed"),
+            ("pixi.other.lock", "default"),
+        ] {
+            let mut builder = LockFile::builder();
+            builder.add_conda_package(
+                environment,
+                Platform::Linux64,
+                record("python", "1.0", "0", Platform::Linux64).into(),
+            );
+            builder
+                .finish()
+                .to_path(&dir.path().join(file_name))
+                .unwrap();
+        }
+
+        remove_stale_lock_files(dir.path(), &[dir.path().join("pixi.default.lock")]).unwrap();
+        let mut remaining = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            vec![
+                "pixi-build.lock",
+                "pixi.default.lock",
+                "pixi.lock",
+                "pixi.notes.lock",
+                "pixi.other.lock"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minimize_lock_file() {
+        let mut builder = LockFile::builder();
+        for (environment, platform) in [
+            ("default", Platform::Linux64),
+            ("default", Platform::Win64),
+            ("removed", Platform::Linux64),
+        ] {
+            builder.add_conda_package(
+                environment,
+                platform,
+                record("python", "1.0", "0", platform).into(),
+            );
+        }
+        let lock_file = builder.finish();
+
+        let declared = HashMap::from([(
+            String::from("default"),
+            HashSet::from([Platform::Linux64, Platform::OsxArm64]),
+        )]);
+        let minimized = minimize_lock_file(&lock_file, &declared).unwrap();
+        assert!(minimized.environment("removed").is_none());
+        let default = minimized.environment("default").unwrap();
+        assert_eq!(
+            default.platforms().collect::<Vec<_>>(),
+            vec![Platform::Linux64]
+        );
+    }
+
+    #[test]
+    fn test_lock_file_order_is_deterministic() {
+        let records = vec![
+            record("foo", "1.0", "a", Platform::Linux64),
+            record("bar", "2.0", "b", Platform::NoArch),
+            record("bar", "10.0", "a", Platform::Linux64),
+            record("baz", "1.0", "b", Platform::Linux64),
+            record("baz", "1.0", "a", Platform::NoArch),
+            record("baz", "1.0", "a", Platform::Linux64),
+        ];
+        let mut shuffled = records.clone();
+        shuffled.reverse();
+        shuffled.swap(1, 4);
+
+        let dir = tempfile::tempdir().unwrap();
+        let first = render_lock_file(records, &dir.path().join("first.lock"));
+        let second = render_lock_file(shuffled, &dir.path().join("second.lock"));
+        assert_eq!(first, second);
     }
 }
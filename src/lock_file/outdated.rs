@@ -1,4 +1,7 @@
-use super::{verify_environment_satisfiability, verify_platform_satisfiability};
+use super::{
+    is_lock_file_touched, verify_environment_satisfiability, verify_platform_satisfiability,
+    virtual_package_overrides_changed,
+};
 use crate::lock_file::satisfiability::EnvironmentUnsat;
 use crate::project::has_features::HasFeatures;
 use crate::{consts, project::Environment, project::SolveGroup, Project};
@@ -25,9 +28,48 @@ pub struct OutdatedEnvironments<'p> {
 }
 
 impl<'p> OutdatedEnvironments<'p> {
+    /// Returns all the environments of the project for all their platforms as outdated, the
+    /// content of the lock-file is disregarded so everything is solved again.
+    pub fn all(project: &'p Project) -> Self {
+        let environments = project.environments();
+        let all_platforms =
+            |environment: &Environment<'p>| (environment.clone(), environment.platforms());
+        Self {
+            conda: environments.iter().map(all_platforms).collect(),
+            pypi: environments
+                .iter()
+                .filter(|environment| environment.has_pypi_dependencies())
+                .map(all_platforms)
+                .collect(),
+            disregard_locked_content: environments.into_iter().collect(),
+        }
+    }
+
     /// Constructs a new instance of this struct by examining the project and lock-file and finding
     /// any mismatches.
+    ///
+    /// A lock-file that was solved with other `CONDA_OVERRIDE_*` variables is solved again for
+    /// all environments, the locked packages are still preferred.
+    ///
+    /// A lock-file that was touched with `pixi project lock touch` is trusted as long as neither
+    /// the manifest nor the lock-file changed since, no environment is outdated in that case.
     pub fn from_project_and_lock_file(project: &'p Project, lock_file: &LockFile) -> Self {
+        if virtual_package_overrides_changed(project) {
+            tracing::info!("the lock-file was solved with other `CONDA_OVERRIDE_*` variables");
+            return Self {
+                disregard_locked_content: HashSet::new(),
+                ..Self::all(project)
+            };
+        }
+        if is_lock_file_touched(project) {
+            tracing::info!("the lock-file was touched, trusting it without checking the manifest");
+            return Self {
+                conda: HashMap::new(),
+                pypi: HashMap::new(),
+                disregard_locked_content: HashSet::new(),
+            };
+        }
+
         let mut outdated_conda: HashMap<_, HashSet<_>> = HashMap::new();
         let mut outdated_pypi: HashMap<_, HashSet<_>> = HashMap::new();
         let mut disregard_locked_content = HashSet::new();
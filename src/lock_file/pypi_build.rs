@@ -0,0 +1,350 @@
+//! The lock-file records the source distributions of pypi packages, but building a source
+//! distribution resolves its `build-system.requires` again. To make these builds reproducible,
+//! the build backend and the pinned build dependencies that were used to build a package are
+//! recorded in a separate lock file next to the lock-file of the project. A later build of the
+//! same package is checked against the recorded build environment.
+
+use crate::consts;
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_conda_types::Platform;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// The version of the format of the build lock file.
+const BUILD_LOCK_VERSION: u32 = 1;
+
+/// The build backend that is used for a source distribution without a `build-system` table, see
+/// PEP 517.
+const LEGACY_BUILD_BACKEND: &str = "setuptools.build_meta:__legacy__";
+
+/// The build environment of a pypi package that is built from source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PypiBuildEnvironment {
+    /// The build backend of the source distribution, e.g. `setuptools.build_meta`.
+    pub build_backend: String,
+
+    /// The pinned build dependencies, e.g. `setuptools==69.5.1`, sorted by name.
+    pub requires: Vec<String>,
+}
+
+/// A build environment that was used to build a package for a platform of an environment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LockedBuildEnvironment {
+    environment: String,
+    platform: Platform,
+    name: String,
+    version: String,
+    #[serde(flatten)]
+    build: PypiBuildEnvironment,
+}
+
+impl LockedBuildEnvironment {
+    fn is_package(&self, environment: &str, platform: Platform, name: &str) -> bool {
+        self.environment == environment && self.platform == platform && self.name == name
+    }
+}
+
+/// The build environments of the pypi packages of a project that are built from source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PypiBuildLock {
+    version: u32,
+    #[serde(default)]
+    packages: Vec<LockedBuildEnvironment>,
+}
+
+impl Default for PypiBuildLock {
+    fn default() -> Self {
+        Self {
+            version: BUILD_LOCK_VERSION,
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl PypiBuildLock {
+    /// Returns the path of the build lock file of the project in the given root.
+    pub fn path(project_root: &Path) -> std::path::PathBuf {
+        project_root.join(consts::PYPI_BUILD_LOCK_FILE)
+    }
+
+    /// Reads the build lock file, or returns an empty one if the file does not exist.
+    pub fn from_path(path: &Path) -> miette::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to parse '{}'", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to read '{}'", path.display())),
+        }
+    }
+
+    /// Writes the build lock file, the file is removed if no build environment is recorded.
+    pub fn to_path(&self, path: &Path) -> miette::Result<()> {
+        if self.packages.is_empty() {
+            return match std::fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to remove '{}'", path.display())),
+                _ => Ok(()),
+            };
+        }
+        let content = serde_yaml::to_string(self).into_diagnostic()?;
+        std::fs::write(path, content)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write '{}'", path.display()))
+    }
+
+    /// Returns the build environment that was recorded for a version of a package.
+    pub fn get(
+        &self,
+        environment: &str,
+        platform: Platform,
+        name: &str,
+        version: &str,
+    ) -> Option<&PypiBuildEnvironment> {
+        self.packages
+            .iter()
+            .find(|locked| {
+                locked.is_package(environment, platform, name) && locked.version == version
+            })
+            .map(|locked| &locked.build)
+    }
+
+    /// Records the build environment of a version of a package, this replaces the build
+    /// environment of any other version of the package.
+    pub fn insert(
+        &mut self,
+        environment: &str,
+        platform: Platform,
+        name: &str,
+        version: &str,
+        build: PypiBuildEnvironment,
+    ) {
+        self.packages
+            .retain(|locked| !locked.is_package(environment, platform, name));
+        self.packages.push(LockedBuildEnvironment {
+            environment: environment.to_string(),
+            platform,
+            name: name.to_string(),
+            version: version.to_string(),
+            build,
+        });
+        self.packages.sort_by(|a, b| {
+            (&a.environment, a.platform.as_str(), &a.name).cmp(&(
+                &b.environment,
+                b.platform.as_str(),
+                &b.name,
+            ))
+        });
+    }
+
+    /// Removes the build environments of the packages of a platform of an environment that are
+    /// no longer built from source.
+    pub fn retain_packages(
+        &mut self,
+        environment: &str,
+        platform: Platform,
+        keep: impl Fn(&str) -> bool,
+    ) {
+        self.packages.retain(|locked| {
+            locked.environment != environment || locked.platform != platform || keep(&locked.name)
+        });
+    }
+}
+
+/// Returns the `build-system` table of a `pyproject.toml`, a source distribution without one is
+/// built with the legacy setuptools backend.
+pub fn build_system(pyproject_toml: Option<&str>) -> miette::Result<pyproject_toml::BuildSystem> {
+    let build_system = match pyproject_toml {
+        Some(content) => {
+            pyproject_toml::PyProjectToml::new(content)
+                .into_diagnostic()
+                .wrap_err("failed to parse the pyproject.toml of the source distribution")?
+                .build_system
+        }
+        None => None,
+    };
+    Ok(match build_system {
+        Some(build_system) => build_system,
+        None => pyproject_toml::BuildSystem {
+            requires: vec![
+                "setuptools>=40.8.0".parse().expect("valid requirement"),
+                "wheel".parse().expect("valid requirement"),
+            ],
+            build_backend: Some(LEGACY_BUILD_BACKEND.to_string()),
+            backend_path: None,
+        },
+    })
+}
+
+/// Returns the build backend of a `build-system` table.
+pub fn build_backend(build_system: &pyproject_toml::BuildSystem) -> String {
+    build_system
+        .build_backend
+        .clone()
+        .unwrap_or_else(|| LEGACY_BUILD_BACKEND.to_string())
+}
+
+/// Reads the top-level `pyproject.toml` from a `.tar.gz` or `.zip` source distribution. Returns
+/// `None` if the archive doesn't contain one.
+pub fn read_pyproject_toml_from_archive(
+    file_name: &str,
+    archive: &[u8],
+) -> miette::Result<Option<String>> {
+    // The files of a source distribution are in a `{name}-{version}` directory.
+    let is_pyproject_toml = |path: &Path| {
+        path.components().count() == 2 && path.file_name() == Some("pyproject.toml".as_ref())
+    };
+
+    let mut content = String::new();
+    if file_name.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(Cursor::new(archive)).into_diagnostic()?;
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).into_diagnostic()?;
+            if file
+                .enclosed_name()
+                .is_some_and(|path| is_pyproject_toml(path.as_ref()))
+            {
+                file.read_to_string(&mut content).into_diagnostic()?;
+                return Ok(Some(content));
+            }
+        }
+    } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(Cursor::new(archive)));
+        for entry in archive.entries().into_diagnostic()? {
+            let mut entry = entry.into_diagnostic()?;
+            if is_pyproject_toml(&entry.path().into_diagnostic()?) {
+                entry.read_to_string(&mut content).into_diagnostic()?;
+                return Ok(Some(content));
+            }
+        }
+    } else {
+        miette::bail!("unsupported source distribution format '{file_name}'");
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(backend: &str, requires: &[&str]) -> PypiBuildEnvironment {
+        PypiBuildEnvironment {
+            build_backend: backend.to_string(),
+            requires: requires.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_lock_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = PypiBuildLock::path(dir.path());
+        assert_eq!(
+            PypiBuildLock::from_path(&path).unwrap(),
+            PypiBuildLock::default()
+        );
+
+        let mut lock = PypiBuildLock::default();
+        let setuptools = build(
+            "setuptools.build_meta",
+            &["setuptools==69.5.1", "wheel==0.43.0"],
+        );
+        lock.insert(
+            "default",
+            Platform::Linux64,
+            "foo",
+            "1.0.0",
+            setuptools.clone(),
+        );
+        lock.insert(
+            "default",
+            Platform::Linux64,
+            "bar",
+            "2.0.0",
+            build("hatchling.build", &["hatchling==1.24.2"]),
+        );
+        // A new version of a package replaces the old one.
+        lock.insert(
+            "default",
+            Platform::Linux64,
+            "foo",
+            "1.1.0",
+            setuptools.clone(),
+        );
+        lock.to_path(&path).unwrap();
+
+        let lock = PypiBuildLock::from_path(&path).unwrap();
+        assert_eq!(lock.packages.len(), 2);
+        assert_eq!(lock.packages[0].name, "bar");
+        assert_eq!(
+            lock.get("default", Platform::Linux64, "foo", "1.1.0"),
+            Some(&setuptools)
+        );
+        assert_eq!(lock.get("default", Platform::Linux64, "foo", "1.0.0"), None);
+        assert_eq!(lock.get("default", Platform::Win64, "foo", "1.1.0"), None);
+
+        // The file is removed once nothing is recorded.
+        let mut lock = lock;
+        lock.retain_packages("default", Platform::Linux64, |_| false);
+        lock.to_path(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_build_system() {
+        let hatchling = build_system(Some(
+            r#"
+            [build-system]
+            requires = ["hatchling>=1.8"]
+            build-backend = "hatchling.build"
+            "#,
+        ))
+        .unwrap();
+        assert_eq!(build_backend(&hatchling), "hatchling.build");
+        assert_eq!(hatchling.requires.len(), 1);
+
+        // Without a `build-system` the legacy setuptools backend is used.
+        let legacy = build_system(Some("[project]\nname = \"foo\"\n")).unwrap();
+        assert_eq!(build_backend(&legacy), LEGACY_BUILD_BACKEND);
+        assert_eq!(
+            build_backend(&build_system(None).unwrap()),
+            LEGACY_BUILD_BACKEND
+        );
+    }
+
+    #[test]
+    fn test_read_pyproject_toml_from_archive() {
+        let pyproject_toml = "[build-system]\nrequires = [\"flit_core\"]\n";
+
+        let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (path, content) in [
+            ("foo-1.0.0/tests/pyproject.toml", "[tool.other]\n"),
+            ("foo-1.0.0/pyproject.toml", pyproject_toml),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        let archive = archive.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(
+            read_pyproject_toml_from_archive("foo-1.0.0.tar.gz", &archive)
+                .unwrap()
+                .as_deref(),
+            Some(pyproject_toml)
+        );
+        assert!(read_pyproject_toml_from_archive("foo-1.0.0.tar.bz2", &archive).is_err());
+    }
+}
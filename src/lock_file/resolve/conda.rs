@@ -1,19 +1,189 @@
-use miette::IntoDiagnostic;
-use rattler_conda_types::{GenericVirtualPackage, MatchSpec, RepoDataRecord};
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_conda_types::{
+    GenericVirtualPackage, MatchSpec, PackageName, PackageRecord, ParseStrictness, RepoDataRecord,
+    Version,
+};
 use rattler_solve::{resolvo, ChannelPriority, SolverImpl};
 
 use crate::lock_file::LockedCondaPackages;
+use crate::project::manifest::PythonImplementation;
+
+/// The track feature that is added to package variants that do not provide any of the preferred
+/// features. The solver deprioritizes packages with track features.
+const NOT_PREFERRED_TRACK_FEATURE: &str = "__pixi_not_preferred";
+
+/// The versions that the solver prefers when several versions satisfy the specs.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SolveStrategy {
+    /// Prefer the highest versions of all packages.
+    #[default]
+    Highest,
+    /// Prefer the lowest versions of all packages.
+    Lowest,
+    /// Prefer the lowest versions of the direct dependencies, the other packages get their highest
+    /// versions.
+    LowestDirect,
+}
+
+/// The options that restrict and steer the selection of the conda packages, besides the specs.
+#[derive(Debug, Clone, Default)]
+pub struct CondaSolveOptions {
+    /// Package variants that provide one of these features are preferred over variants of the
+    /// same package that do not.
+    pub preferred_features: Vec<String>,
+
+    /// Package variants that provide one of these features are only selected when no variant of
+    /// the same package without them fits the environment.
+    pub avoided_features: Vec<String>,
+
+    /// Bound the versions of the packages that end up in the environment, without requiring them
+    /// to be installed.
+    pub constraints: Vec<MatchSpec>,
+
+    /// Packages that never end up in the environment.
+    pub excludes: Vec<PackageName>,
+
+    /// Packages with one of these licenses are never selected, see [`disallowed_license`].
+    pub disallowed_licenses: Vec<String>,
+
+    /// Only select the python builds of this implementation.
+    pub python_implementation: Option<PythonImplementation>,
+
+    /// Only select the `noarch: python` packages that support this version of python, see
+    /// [`effective_noarch_python_min`].
+    pub noarch_python_min: Option<Version>,
+
+    /// The versions that the solver prefers, a strategy other than [`SolveStrategy::Highest`]
+    /// ignores the locked packages, see [`strategy_preferred_records`].
+    pub strategy: SolveStrategy,
+}
+
+/// The locked and the available records from which the solver selects the packages.
+struct Candidates {
+    locked: Vec<RepoDataRecord>,
+    available: Vec<Vec<RepoDataRecord>>,
+}
+
+impl Candidates {
+    /// Removes the locked and the available records for which `keep` returns false.
+    fn retain(&mut self, keep: impl Fn(&PackageRecord) -> bool) {
+        self.locked.retain(|record| keep(&record.package_record));
+        for records in &mut self.available {
+            records.retain(|record| keep(&record.package_record));
+        }
+    }
+}
 
 /// Solves the conda package environment for the given input. This function is async because it
 /// spawns a background task for the solver. Since solving is a CPU intensive task we do not want to
 /// block the main task.
+///
+/// Specs that name a `subdir` only select packages from that subdir, specs that pin a `sha256` or
+/// `md5` hash only select the record with that hash. The other restrictions and preferences are
+/// described by the [`CondaSolveOptions`].
 pub async fn resolve_conda(
     specs: Vec<MatchSpec>,
     virtual_packages: Vec<GenericVirtualPackage>,
     locked_packages: Vec<RepoDataRecord>,
     available_packages: Vec<Vec<RepoDataRecord>>,
+    options: CondaSolveOptions,
 ) -> miette::Result<LockedCondaPackages> {
     tokio::task::spawn_blocking(move || {
+        let CondaSolveOptions {
+            preferred_features,
+            avoided_features,
+            constraints,
+            excludes,
+            disallowed_licenses,
+            python_implementation,
+            noarch_python_min,
+            strategy,
+        } = options;
+
+        // An excluded package can never be part of the environment, so requiring one directly is
+        // an error.
+        if let Some(spec) = specs
+            .iter()
+            .find(|spec| spec.name.as_ref().is_some_and(|name| excludes.contains(name)))
+        {
+            miette::bail!(
+                help = "remove the dependency or remove the package from the `exclude` of the environment",
+                "the dependency '{spec}' is excluded from the environment"
+            );
+        }
+
+        let mut candidates = Candidates {
+            locked: locked_packages,
+            available: available_packages,
+        };
+
+        // Remove all the records of excluded packages.
+        if !excludes.is_empty() {
+            candidates.retain(|record| !excludes.contains(&record.name));
+        }
+
+        // Only keep the records with the pinned hash of a package.
+        if specs.iter().any(|s| s.sha256.is_some() || s.md5.is_some()) {
+            check_hash_pins(&candidates.available, &specs)?;
+            candidates.retain(|record| matches_hash_pins(&specs, record));
+        }
+
+        // Remove all the records with a disallowed license.
+        if !disallowed_licenses.is_empty() {
+            check_licenses(&candidates.available, &specs, &disallowed_licenses)?;
+            candidates.retain(|record| disallowed_license(record, &disallowed_licenses).is_none());
+        }
+
+        // Remove all the python builds of other implementations.
+        if let Some(implementation) = python_implementation {
+            check_python_implementation(&candidates.available, implementation)?;
+            candidates.retain(|record| implementation.matches(record));
+        }
+
+        // Remove all the noarch python records that do not support the minimum python.
+        let noarch_python_min = effective_noarch_python_min(noarch_python_min, &specs);
+        if let Some(python) = &noarch_python_min {
+            candidates.retain(|record| supports_python(record, python));
+        }
+
+        // Remove all the records that do not satisfy the constraints.
+        if !constraints.is_empty() {
+            candidates.retain(|record| satisfies_constraints(&constraints, record));
+        }
+
+        // Remove all the records that are not part of the subdir requested by a spec.
+        if specs.iter().any(|s| s.subdir.is_some()) {
+            check_subdirs(&candidates.available, &specs)?;
+            candidates.retain(|record| in_requested_subdir(&specs, record));
+        }
+
+        let Candidates {
+            locked: locked_packages,
+            available: available_packages,
+        } = candidates;
+
+        // Apply the feature preferences to the available packages.
+        let mut original_records = HashMap::new();
+        let available_packages = if preferred_features.is_empty() && avoided_features.is_empty() {
+            available_packages
+        } else {
+            apply_feature_preferences(
+                available_packages,
+                &preferred_features,
+                &avoided_features,
+                &mut original_records,
+            )
+        };
+
+        // The solver prefers the locked packages, the strategy replaces them with its own
+        // preferences.
+        let locked_packages =
+            strategy_preferred_records(strategy, &specs, &available_packages)
+                .unwrap_or(locked_packages);
+
         // Construct a solver task that we can start solving.
         let task = rattler_solve::SolverTask {
             specs,
@@ -25,8 +195,43 @@ pub async fn resolve_conda(
             channel_priority: ChannelPriority::Strict,
         };
 
-        // Solve the task
-        resolvo::Solver.solve(task).into_diagnostic()
+        // Solve the task, a conflict might be caused by a dependency on an excluded package or on a
+        // package with a disallowed license.
+        let records = resolvo::Solver.solve(task).into_diagnostic();
+        let records = if excludes.is_empty() {
+            records
+        } else {
+            records.wrap_err_with(|| {
+                format!(
+                    "the environment excludes {}, which might be required by one of the dependencies",
+                    excludes.iter().map(|name| format!("'{}'", name.as_source())).join(", ")
+                )
+            })
+        };
+        let records = match &noarch_python_min {
+            None => records,
+            Some(python) => records.wrap_err_with(|| {
+                format!(
+                    "the noarch python packages have to support python {python} (`noarch-python-min`), which might not be possible for one of the dependencies"
+                )
+            }),
+        };
+        let records = if disallowed_licenses.is_empty() {
+            records?
+        } else {
+            records.wrap_err_with(|| {
+                format!(
+                    "packages with the licenses {} are disallowed, which might be required by one of the dependencies",
+                    disallowed_licenses.iter().map(|license| format!("'{license}'")).join(", ")
+                )
+            })?
+        };
+
+        // Make sure the records that end up in the lock-file are the original ones.
+        Ok(records
+            .into_iter()
+            .map(|record| original_records.remove(&record.url).unwrap_or(record))
+            .collect())
     })
     .await
     .unwrap_or_else(|e| match e.try_into_panic() {
@@ -34,3 +239,1158 @@ pub async fn resolve_conda(
         Err(_err) => Err(miette::miette!("cancelled")),
     })
 }
+
+/// Replaces the locked records by their equivalent, with the same name, version and build, from
+/// the highest priority channel that provides the package. The `available_packages` are ordered
+/// by channel priority. Records without an equivalent in that channel are kept, so only the
+/// packages that are available unchanged move to another channel.
+pub fn channel_equivalent_records(
+    locked_packages: Vec<RepoDataRecord>,
+    available_packages: &[Vec<RepoDataRecord>],
+) -> Vec<RepoDataRecord> {
+    locked_packages
+        .into_iter()
+        .map(|locked| {
+            let locked_record = &locked.package_record;
+            let Some(channel) = available_packages
+                .iter()
+                .flatten()
+                .find(|record| record.package_record.name == locked_record.name)
+                .map(|record| &record.channel)
+            else {
+                return locked;
+            };
+            if *channel == locked.channel {
+                return locked;
+            }
+            available_packages
+                .iter()
+                .flatten()
+                .find(|record| {
+                    record.channel == *channel
+                        && record.package_record.name == locked_record.name
+                        && record.package_record.version == locked_record.version
+                        && record.package_record.build == locked_record.build
+                })
+                .cloned()
+                .unwrap_or(locked)
+        })
+        .collect()
+}
+
+/// Returns true if the record satisfies the constraints for its package. Packages without a
+/// constraint are left untouched.
+fn satisfies_constraints(constraints: &[MatchSpec], record: &PackageRecord) -> bool {
+    constraints
+        .iter()
+        .filter(|spec| spec.name.as_ref() == Some(&record.name))
+        .all(|spec| spec.matches(record))
+}
+
+/// Returns the minimum python that the noarch python packages have to support. An explicit python
+/// spec takes precedence: if it doesn't allow the minimum, e.g. `python 3.11.*` with a minimum of
+/// `3.9`, the noarch packages are resolved against the explicit python instead.
+pub(crate) fn effective_noarch_python_min(
+    noarch_python_min: Option<Version>,
+    specs: &[MatchSpec],
+) -> Option<Version> {
+    let min = noarch_python_min?;
+    let excluded_by = specs
+        .iter()
+        .filter(|spec| spec.name.as_ref().is_some_and(is_python))
+        .find(|spec| spec.version.as_ref().is_some_and(|v| !v.matches(&min)));
+    match excluded_by {
+        Some(spec) => {
+            tracing::info!("ignoring `noarch-python-min = \"{min}\"` because of the explicit dependency '{spec}'");
+            None
+        }
+        None => Some(min),
+    }
+}
+
+fn is_python(name: &PackageName) -> bool {
+    name.as_normalized() == "python"
+}
+
+/// Returns true if the record is not a `noarch: python` package or if its python dependency
+/// allows the given version of python.
+pub(crate) fn supports_python(record: &PackageRecord, python: &Version) -> bool {
+    !record.noarch.is_python()
+        || record
+            .depends
+            .iter()
+            .filter_map(|dep| MatchSpec::from_str(dep, ParseStrictness::Lenient).ok())
+            .filter(|spec| spec.name.as_ref().is_some_and(is_python))
+            .all(|spec| spec.version.as_ref().map_or(true, |v| v.matches(python)))
+}
+
+/// Returns the disallowed license that applies to the record, if any.
+///
+/// The `license` of the record is an SPDX expression, a disallowed license applies to an
+/// identifier in it that is equal to it, ignoring case. The `-only` and `-or-later` suffixes and a
+/// trailing `+` of an identifier are ignored, so `GPL-3.0` also applies to `GPL-3.0-or-later`.
+/// An `AND` expression is disallowed if one of its operands is, an `OR` expression only if all of
+/// its options are. A license that is not a valid expression is disallowed if any of its
+/// identifiers is.
+///
+/// The `license_family` of the record is disallowed if it is equal to a disallowed license, it
+/// is ignored if the `license` offers a choice between licenses with `OR`.
+pub(crate) fn disallowed_license<'a>(
+    record: &PackageRecord,
+    disallowed_licenses: &'a [String],
+) -> Option<&'a String> {
+    let tokens = record
+        .license
+        .as_deref()
+        .map(|license| {
+            license
+                .replace('(', " ( ")
+                .replace(')', " ) ")
+                .split_whitespace()
+                .map(String::from)
+                .collect_vec()
+        })
+        .unwrap_or_default();
+
+    let mut expression = LicenseExpression {
+        tokens: &tokens,
+        position: 0,
+        disallowed_licenses,
+    };
+    let disallowed = match expression.or_expression() {
+        Some(disallowed) if expression.position == tokens.len() => disallowed,
+        _ => tokens
+            .iter()
+            .filter(|token| !is_license_operator(token))
+            .find_map(|id| matching_license(id, disallowed_licenses)),
+    };
+
+    let has_choice = tokens.iter().any(|token| token.eq_ignore_ascii_case("OR"));
+    disallowed.or_else(|| {
+        record
+            .license_family
+            .as_deref()
+            .filter(|_| !has_choice)
+            .and_then(|family| matching_license(family, disallowed_licenses))
+    })
+}
+
+/// Returns the disallowed license that is equal to the license identifier, if any.
+fn matching_license<'a>(id: &str, disallowed_licenses: &'a [String]) -> Option<&'a String> {
+    let id = id.trim_end_matches('+');
+    let id = id
+        .strip_suffix("-only")
+        .or_else(|| id.strip_suffix("-or-later"))
+        .unwrap_or(id);
+    disallowed_licenses
+        .iter()
+        .find(|disallowed| id.eq_ignore_ascii_case(disallowed))
+}
+
+/// Returns true if the token of a license expression is an operator or a parenthesis.
+fn is_license_operator(token: &str) -> bool {
+    ["AND", "OR", "WITH", "(", ")"]
+        .iter()
+        .any(|operator| token.eq_ignore_ascii_case(operator))
+}
+
+/// A parser of the tokens of an SPDX license expression, that evaluates which disallowed license
+/// applies to the expression. Every method returns `None` if the expression is not valid.
+struct LicenseExpression<'t, 'a> {
+    tokens: &'t [String],
+    position: usize,
+    disallowed_licenses: &'a [String],
+}
+
+impl<'t, 'a> LicenseExpression<'t, 'a> {
+    /// Advances past the next token if it is equal to `token`, ignoring case.
+    fn eat(&mut self, token: &str) -> bool {
+        let matches = self
+            .tokens
+            .get(self.position)
+            .is_some_and(|next| next.eq_ignore_ascii_case(token));
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    /// `or-expression = and-expression ("OR" and-expression)*`
+    fn or_expression(&mut self) -> Option<Option<&'a String>> {
+        let mut disallowed = self.and_expression()?;
+        while self.eat("OR") {
+            if self.and_expression()?.is_none() {
+                disallowed = None;
+            }
+        }
+        Some(disallowed)
+    }
+
+    /// `and-expression = license ("AND" license)*`
+    fn and_expression(&mut self) -> Option<Option<&'a String>> {
+        let mut disallowed = self.license()?;
+        while self.eat("AND") {
+            disallowed = disallowed.or(self.license()?);
+        }
+        Some(disallowed)
+    }
+
+    /// `license = "(" or-expression ")" | identifier ("WITH" identifier)?`, the exception of a
+    /// `WITH` doesn't change whether the license is disallowed.
+    fn license(&mut self) -> Option<Option<&'a String>> {
+        if self.eat("(") {
+            let disallowed = self.or_expression()?;
+            return self.eat(")").then_some(disallowed);
+        }
+        let id = self.identifier()?;
+        if self.eat("WITH") {
+            self.identifier()?;
+        }
+        Some(matching_license(id, self.disallowed_licenses))
+    }
+
+    fn identifier(&mut self) -> Option<&'t str> {
+        let id = self.tokens.get(self.position)?;
+        if is_license_operator(id) {
+            return None;
+        }
+        self.position += 1;
+        Some(id.as_str())
+    }
+}
+
+/// Returns an error if a spec only has candidates with a disallowed license.
+fn check_licenses(
+    available_packages: &[Vec<RepoDataRecord>],
+    specs: &[MatchSpec],
+    disallowed_licenses: &[String],
+) -> miette::Result<()> {
+    for spec in specs {
+        let mut candidates = available_packages
+            .iter()
+            .flatten()
+            .filter(|record| spec.matches(&record.package_record))
+            .peekable();
+        if candidates.peek().is_none() {
+            continue;
+        }
+
+        let licenses = candidates
+            .map(|record| disallowed_license(&record.package_record, disallowed_licenses))
+            .collect::<Option<Vec<_>>>();
+        if let Some(licenses) = licenses {
+            miette::bail!(
+                help = "remove the dependency or the license from the `disallowed-licenses` of the project",
+                "all candidates for the dependency '{spec}' have a disallowed license: {}",
+                licenses.into_iter().unique().join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns an error if python is available, but not for the given implementation.
+fn check_python_implementation(
+    available_packages: &[Vec<RepoDataRecord>],
+    implementation: PythonImplementation,
+) -> miette::Result<()> {
+    let mut pythons = available_packages
+        .iter()
+        .flatten()
+        .filter(|record| record.package_record.name.as_normalized() == "python")
+        .peekable();
+    if pythons.peek().is_some()
+        && !pythons.any(|record| implementation.matches(&record.package_record))
+    {
+        miette::bail!(
+            help = "use channels that provide {implementation} builds of python, or remove `python-implementation` from the project",
+            "no {implementation} builds of python are available in the channels of the environment"
+        );
+    }
+    Ok(())
+}
+
+/// Returns true if the record has the hashes that the spec pins, a spec without hashes matches
+/// every record.
+fn matches_hash_pin(spec: &MatchSpec, record: &PackageRecord) -> bool {
+    spec.sha256
+        .as_ref()
+        .map_or(true, |sha256| record.sha256.as_ref() == Some(sha256))
+        && spec
+            .md5
+            .as_ref()
+            .map_or(true, |md5| record.md5.as_ref() == Some(md5))
+}
+
+/// Returns true if the record has the hashes that the specs pin for its package.
+fn matches_hash_pins(specs: &[MatchSpec], record: &PackageRecord) -> bool {
+    specs
+        .iter()
+        .filter(|spec| spec.name.as_ref() == Some(&record.name))
+        .all(|spec| matches_hash_pin(spec, record))
+}
+
+/// Returns an error if no record of a package has the hash that a spec pins.
+fn check_hash_pins(
+    available_packages: &[Vec<RepoDataRecord>],
+    specs: &[MatchSpec],
+) -> miette::Result<()> {
+    for spec in specs {
+        let Some(name) = &spec.name else {
+            continue;
+        };
+        if spec.sha256.is_none() && spec.md5.is_none() {
+            continue;
+        }
+        let pinned = available_packages.iter().flatten().any(|record| {
+            &record.package_record.name == name && matches_hash_pin(spec, &record.package_record)
+        });
+        if !pinned {
+            let hash = spec
+                .sha256
+                .as_ref()
+                .map(|sha256| format!("sha256 {sha256:x}"))
+                .into_iter()
+                .chain(spec.md5.as_ref().map(|md5| format!("md5 {md5:x}")))
+                .join(" and ");
+            miette::bail!(
+                help = "make sure the hash is correct and that the package is available in the channels and platforms of the environment",
+                "no package '{}' with {hash} is available",
+                name.as_source()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the record is part of the subdirs that the specs request for its package.
+fn in_requested_subdir(specs: &[MatchSpec], record: &PackageRecord) -> bool {
+    specs
+        .iter()
+        .filter(|spec| spec.name.as_ref() == Some(&record.name))
+        .filter_map(|spec| spec.subdir.as_deref())
+        .all(|subdir| subdir == record.subdir)
+}
+
+/// Returns an error if no candidates remain for a spec that requests a subdir. This happens when
+/// a package is not available in the requested subdir or when the subdir is not fetched for the
+/// channels of the project.
+fn check_subdirs(
+    available_packages: &[Vec<RepoDataRecord>],
+    specs: &[MatchSpec],
+) -> miette::Result<()> {
+    for spec in specs {
+        let (Some(name), Some(subdir)) = (&spec.name, &spec.subdir) else {
+            continue;
+        };
+        let candidates = available_packages
+            .iter()
+            .flatten()
+            .filter(|record| &record.package_record.name == name);
+        if candidates
+            .clone()
+            .any(|record| &record.package_record.subdir == subdir)
+        {
+            continue;
+        }
+
+        let other_subdirs = candidates
+            .map(|record| record.package_record.subdir.as_str())
+            .unique()
+            .sorted()
+            .collect_vec();
+        if other_subdirs.is_empty() {
+            miette::bail!(
+                "no candidates for '{}' in subdir '{subdir}'",
+                name.as_source()
+            );
+        }
+        miette::bail!(
+            "no candidates for '{}' in subdir '{subdir}', candidates are only available in: {}",
+            name.as_source(),
+            other_subdirs.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Returns true if the record provides one of the given features.
+fn provides_feature(record: &RepoDataRecord, features: &[String]) -> bool {
+    let record = &record.package_record;
+    record
+        .features
+        .iter()
+        .flat_map(|f| f.split([' ', ',']))
+        .chain(record.track_features.iter().map(String::as_str))
+        .any(|f| features.iter().any(|preferred| preferred == f))
+}
+
+/// Modifies the track features of the available packages such that the solver prefers the
+/// variants that provide one of the `preferred_features` and that do not provide one of the
+/// `avoided_features`. Only packages for which at least one variant provides a preferred feature
+/// and the variants that provide an avoided feature are modified. The original records of all
+/// modified packages are stored in `original_records` so they can be restored after solving.
+fn apply_feature_preferences(
+    available_packages: Vec<Vec<RepoDataRecord>>,
+    preferred_features: &[String],
+    avoided_features: &[String],
+    original_records: &mut HashMap<url::Url, RepoDataRecord>,
+) -> Vec<Vec<RepoDataRecord>> {
+    // Determine the names of the packages that have a variant with a preferred feature.
+    let preferred_names: Vec<PackageName> = available_packages
+        .iter()
+        .flatten()
+        .filter(|record| provides_feature(record, preferred_features))
+        .map(|record| record.package_record.name.clone())
+        .collect();
+
+    available_packages
+        .into_iter()
+        .map(|records| {
+            records
+                .into_iter()
+                .map(|record| {
+                    let preferred = preferred_names.contains(&record.package_record.name);
+                    let avoided = provides_feature(&record, avoided_features);
+                    if !preferred && !avoided {
+                        return record;
+                    }
+
+                    let mut modified = record.clone();
+                    let track_features = &mut modified.package_record.track_features;
+                    let provides_preferred = provides_feature(&record, preferred_features);
+                    if provides_preferred {
+                        track_features.retain(|f| !preferred_features.contains(f));
+                    }
+                    if avoided || (preferred && !provides_preferred) {
+                        track_features.push(NOT_PREFERRED_TRACK_FEATURE.to_string());
+                    }
+                    original_records.insert(record.url.clone(), record);
+                    modified
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the records the solver should prefer for the strategy, or `None` for
+/// [`SolveStrategy::Highest`]. The solver already selects the highest versions, the lowest
+/// strategies prefer the lowest version of each package that matches the specs.
+///
+/// This is best-effort: the solver has no strategy of its own and the preferred records are only
+/// a soft preference like the locked packages. A preferred record that conflicts with the rest of
+/// the environment is ignored, and the solver then selects the highest version of that package
+/// rather than the next-lowest one.
+fn strategy_preferred_records(
+    strategy: SolveStrategy,
+    specs: &[MatchSpec],
+    available_packages: &[Vec<RepoDataRecord>],
+) -> Option<Vec<RepoDataRecord>> {
+    let direct_only = match strategy {
+        SolveStrategy::Highest => return None,
+        SolveStrategy::Lowest => false,
+        SolveStrategy::LowestDirect => true,
+    };
+
+    let mut lowest: HashMap<&PackageName, &RepoDataRecord> = HashMap::new();
+    for record in available_packages.iter().flatten() {
+        let name = &record.package_record.name;
+        let direct_specs = specs
+            .iter()
+            .filter(|spec| spec.name.as_ref() == Some(name))
+            .collect_vec();
+        if direct_only && direct_specs.is_empty() {
+            continue;
+        }
+        if !direct_specs
+            .iter()
+            .all(|spec| spec.matches(&record.package_record))
+        {
+            continue;
+        }
+
+        // The lowest version wins, of the builds of that version the highest build number.
+        let is_lower = lowest.get(name).map_or(true, |current| {
+            let (current, new) = (&current.package_record, &record.package_record);
+            new.version
+                .cmp(&current.version)
+                .then_with(|| current.build_number.cmp(&new.build_number))
+                .is_lt()
+        });
+        if is_lower {
+            lowest.insert(name, record);
+        }
+    }
+    Some(lowest.into_values().cloned().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rattler_conda_types::{NoArchType, PackageRecord, ParseStrictness, Version};
+    use std::str::FromStr;
+
+    fn record(
+        name: &str,
+        build: &str,
+        build_number: u64,
+        features: Option<&str>,
+        track: &[&str],
+    ) -> RepoDataRecord {
+        versioned_record(name, "1.0", build, build_number, features, track)
+    }
+
+    fn versioned_record(
+        name: &str,
+        version: &str,
+        build: &str,
+        build_number: u64,
+        features: Option<&str>,
+        track: &[&str],
+    ) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            PackageName::new_unchecked(name),
+            Version::from_str(version).unwrap(),
+            build.to_string(),
+        );
+        package_record.build_number = build_number;
+        package_record.features = features.map(ToString::to_string);
+        package_record.track_features = track.iter().map(ToString::to_string).collect();
+        RepoDataRecord {
+            package_record,
+            file_name: format!("{name}-{version}-{build}.conda"),
+            url: url::Url::parse(&format!(
+                "https://example.com/{name}-{version}-{build}.conda"
+            ))
+            .unwrap(),
+            channel: String::from("https://example.com"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preferred_feature_steers_variant() {
+        let available = vec![vec![
+            // The MKL variant has a higher build number and is selected by default.
+            record("numpy", "py_mkl", 1, None, &[]),
+            record("numpy", "py_openblas", 0, Some("nomkl"), &[]),
+            record("nomkl", "0", 0, None, &["nomkl"]),
+        ]];
+        let specs = vec![MatchSpec::from_str("numpy", ParseStrictness::Strict).unwrap()];
+
+        let records = resolve_conda(
+            specs.clone(),
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(records[0].package_record.build, "py_mkl");
+
+        let records = resolve_conda(
+            specs,
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions {
+                preferred_features: vec!["nomkl".into()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].package_record.build, "py_openblas");
+        assert!(records[0].package_record.track_features.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_avoided_feature_steers_variant() {
+        let available = vec![vec![
+            // The MKL variant has a higher build number and is selected by default.
+            record("numpy", "py_mkl", 1, Some("mkl"), &[]),
+            record("numpy", "py_openblas", 0, None, &[]),
+        ]];
+        let specs = vec![MatchSpec::from_str("numpy", ParseStrictness::Strict).unwrap()];
+
+        let records = resolve_conda(
+            specs.clone(),
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                avoided_features: vec!["mkl".into()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].package_record.build, "py_openblas");
+
+        // An avoided variant is still selected if it is the only one that fits.
+        let specs = vec![MatchSpec::from_str("numpy * py_mkl", ParseStrictness::Strict).unwrap()];
+        let records = resolve_conda(
+            specs,
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions {
+                avoided_features: vec!["mkl".into()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records[0].package_record.build, "py_mkl");
+        assert!(records[0].package_record.track_features.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_solve_strategy() {
+        let mut app_1 = versioned_record("app", "1.0", "0", 0, None, &[]);
+        app_1.package_record.depends = vec![String::from("libfoo >=1")];
+        let mut app_2 = versioned_record("app", "2.0", "0", 0, None, &[]);
+        app_2.package_record.depends = vec![String::from("libfoo >=1")];
+        let available = vec![vec![
+            app_1,
+            app_2,
+            versioned_record("app", "3.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "1.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "2.0", "0", 0, None, &[]),
+        ]];
+        let specs = vec![MatchSpec::from_str("app >=2", ParseStrictness::Strict).unwrap()];
+
+        let solve = |strategy| {
+            let (specs, available) = (specs.clone(), available.clone());
+            async move {
+                let records = resolve_conda(
+                    specs,
+                    vec![],
+                    vec![],
+                    available,
+                    CondaSolveOptions {
+                        strategy,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+                records
+                    .iter()
+                    .map(|r| {
+                        format!(
+                            "{} {}",
+                            r.package_record.name.as_normalized(),
+                            r.package_record.version
+                        )
+                    })
+                    .sorted()
+                    .collect_vec()
+            }
+        };
+
+        assert_eq!(solve(SolveStrategy::Highest).await, vec!["app 3.0"]);
+        // Only the direct dependency is minimized, the transitive one gets its highest version.
+        assert_eq!(
+            solve(SolveStrategy::LowestDirect).await,
+            vec!["app 2.0", "libfoo 2.0"]
+        );
+        assert_eq!(
+            solve(SolveStrategy::Lowest).await,
+            vec!["app 2.0", "libfoo 1.0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_solve_strategy_conflict() {
+        let mut app = versioned_record("app", "1.0", "0", 0, None, &[]);
+        app.package_record.depends = vec![String::from("libfoo >=2")];
+        let available = vec![vec![
+            app,
+            versioned_record("libfoo", "1.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "2.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "3.0", "0", 0, None, &[]),
+        ]];
+        let specs = vec![
+            MatchSpec::from_str("app", ParseStrictness::Strict).unwrap(),
+            MatchSpec::from_str("libfoo", ParseStrictness::Strict).unwrap(),
+        ];
+
+        // The preferred lowest version of `libfoo` conflicts with `app`, the solve still succeeds
+        // but the preference is dropped. The next-lowest version is not guaranteed.
+        let records = resolve_conda(
+            specs,
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions {
+                strategy: SolveStrategy::Lowest,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let libfoo = records
+            .iter()
+            .find(|r| r.package_record.name.as_normalized() == "libfoo")
+            .unwrap();
+        assert!(*libfoo.package_record.version.version() >= Version::from_str("2").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_constraints() {
+        let available = vec![vec![
+            versioned_record("app", "1.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "1.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "2.0", "0", 0, None, &[]),
+        ]];
+        let specs = vec![MatchSpec::from_str("libfoo", ParseStrictness::Strict).unwrap()];
+
+        // The constraint bounds the version of a package that is pulled in.
+        let records = resolve_conda(
+            specs.clone(),
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                constraints: vec![
+                    MatchSpec::from_str("libfoo <2", ParseStrictness::Strict).unwrap()
+                ],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].package_record.version.to_string(), "1.0");
+
+        // A constraint on a package that is not part of the environment has no effect.
+        let records = resolve_conda(
+            specs.clone(),
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                constraints: vec![MatchSpec::from_str("app >=2", ParseStrictness::Strict).unwrap()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].package_record.version.to_string(), "2.0");
+
+        // A constraint that conflicts with the requirements is an error.
+        assert!(resolve_conda(
+            specs,
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions {
+                constraints: vec![
+                    MatchSpec::from_str("libfoo >=3", ParseStrictness::Strict).unwrap()
+                ],
+                ..Default::default()
+            }
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_excludes() {
+        let mut app = versioned_record("app", "1.0", "0", 0, None, &[]);
+        app.package_record.depends = vec![String::from("cuda-toolkit")];
+        let available = vec![vec![
+            app,
+            versioned_record("cuda-toolkit", "12.0", "0", 0, None, &[]),
+            versioned_record("libfoo", "1.0", "0", 0, None, &[]),
+        ]];
+        let excludes = vec![PackageName::new_unchecked("cuda-toolkit")];
+
+        // An exclude does not affect packages that do not depend on it.
+        let records = resolve_conda(
+            vec![MatchSpec::from_str("libfoo", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                excludes: excludes.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+
+        // A package that requires an excluded package cannot be installed.
+        let err = resolve_conda(
+            vec![MatchSpec::from_str("app", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                excludes: excludes.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("'cuda-toolkit'"));
+
+        // Requiring an excluded package directly is an error.
+        let err = resolve_conda(
+            vec![MatchSpec::from_str("cuda-toolkit", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions {
+                excludes: excludes,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the dependency 'cuda-toolkit' is excluded from the environment"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subdir_restriction() {
+        let mut noarch = versioned_record("libfoo", "1.0", "0", 0, None, &[]);
+        noarch.package_record.subdir = "noarch".to_string();
+        let mut native = versioned_record("libfoo", "2.0", "0", 0, None, &[]);
+        native.package_record.subdir = "linux-64".to_string();
+        let available = vec![vec![noarch, native]];
+        let spec_in_subdir = |subdir: &str| {
+            let mut spec = MatchSpec::from_str("libfoo", ParseStrictness::Strict).unwrap();
+            spec.subdir = Some(subdir.to_string());
+            spec
+        };
+
+        // Without a subdir the highest version is selected.
+        let records = resolve_conda(
+            vec![MatchSpec::from_str("libfoo", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(records[0].package_record.version.to_string(), "2.0");
+
+        // The subdir restricts the candidates.
+        let records = resolve_conda(
+            vec![spec_in_subdir("noarch")],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].package_record.subdir, "noarch");
+
+        // A subdir without candidates is an error.
+        let err = resolve_conda(
+            vec![spec_in_subdir("osx-64")],
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no candidates for 'libfoo' in subdir 'osx-64', candidates are only available in: linux-64, noarch"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_licenses() {
+        let mut gpl = versioned_record("readline", "8.2", "0", 0, None, &[]);
+        gpl.package_record.license = Some("GPL-3.0-only".to_string());
+        gpl.package_record.license_family = Some("GPL".to_string());
+        let mut permissive = versioned_record("libedit", "3.1", "0", 0, None, &[]);
+        permissive.package_record.license = Some("BSD-2-Clause".to_string());
+        let mut app_gpl = versioned_record("app", "2.0", "0", 0, None, &[]);
+        app_gpl.package_record.depends = vec![String::from("readline")];
+        let mut app_permissive = versioned_record("app", "1.0", "0", 0, None, &[]);
+        app_permissive.package_record.depends = vec![String::from("libedit")];
+        let available = vec![vec![gpl, permissive, app_gpl, app_permissive]];
+        let disallowed = vec!["GPL-3.0".to_string()];
+
+        // Without restrictions the highest version, which depends on the GPL package, is selected.
+        let records = resolve_conda(
+            vec![MatchSpec::from_str("app", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(records
+            .iter()
+            .any(|r| r.package_record.name.as_normalized() == "readline"));
+
+        // The GPL package is rejected and the variant with a permissive dependency is selected.
+        let records = resolve_conda(
+            vec![MatchSpec::from_str("app", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                disallowed_licenses: disallowed.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let names = records
+            .iter()
+            .map(|r| r.package_record.name.as_normalized().to_string())
+            .sorted()
+            .collect_vec();
+        assert_eq!(names, vec!["app", "libedit"]);
+
+        // Requiring a package that only has disallowed candidates is an error.
+        let err = resolve_conda(
+            vec![MatchSpec::from_str("readline", ParseStrictness::Strict).unwrap()],
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions {
+                disallowed_licenses: disallowed,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "all candidates for the dependency 'readline' have a disallowed license: GPL-3.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_python_implementation() {
+        let available = vec![vec![
+            versioned_record("python", "3.10.13", "0_73_pypy", 0, None, &[]),
+            versioned_record("python", "3.12.0", "hab00c5b_0_cpython", 0, None, &[]),
+        ]];
+        let specs = vec![MatchSpec::from_str("python", ParseStrictness::Strict).unwrap()];
+
+        let records = resolve_conda(
+            specs.clone(),
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions {
+                python_implementation: Some(PythonImplementation::PyPy),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(records[0].package_record.build, "0_73_pypy");
+
+        // Requesting PyPy when only CPython builds are available is an error.
+        let err = resolve_conda(
+            specs,
+            vec![],
+            vec![],
+            vec![available[0][1..].to_vec()],
+            CondaSolveOptions {
+                python_implementation: Some(PythonImplementation::PyPy),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no pypy builds of python are available in the channels of the environment"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hash_pins() {
+        let sha256 =
+            |hex: &str| rattler_digest::parse_digest_from_hex::<rattler_digest::Sha256>(hex);
+        let mut old = versioned_record("foo", "1.0", "0", 0, None, &[]);
+        old.package_record.sha256 =
+            sha256("1111111111111111111111111111111111111111111111111111111111111111");
+        let mut new = versioned_record("foo", "1.1", "0", 0, None, &[]);
+        new.package_record.sha256 =
+            sha256("2222222222222222222222222222222222222222222222222222222222222222");
+        let available = vec![vec![old, new]];
+
+        // The pinned record is selected even though a newer version is available.
+        let records = resolve_conda(
+            vec![MatchSpec::from_str(
+                "foo[sha256=1111111111111111111111111111111111111111111111111111111111111111]",
+                ParseStrictness::Strict,
+            )
+            .unwrap()],
+            vec![],
+            vec![],
+            available.clone(),
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].package_record.version.to_string(), "1.0");
+
+        // A hash that matches no record is an error.
+        let err = resolve_conda(
+            vec![MatchSpec::from_str(
+                "foo[sha256=3333333333333333333333333333333333333333333333333333333333333333]",
+                ParseStrictness::Strict,
+            )
+            .unwrap()],
+            vec![],
+            vec![],
+            available,
+            CondaSolveOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no package 'foo' with sha256 3333333333333333333333333333333333333333333333333333333333333333 is available"
+        );
+    }
+
+    #[test]
+    fn test_disallowed_license_matching() {
+        let mut record = PackageRecord::new(
+            PackageName::new_unchecked("foo"),
+            Version::from_str("1.0").unwrap(),
+            "0".to_string(),
+        );
+        record.license = Some("(MIT OR GPL-2.0+) AND LGPL-2.1-or-later".to_string());
+        record.license_family = Some("GPL2".to_string());
+
+        let disallowed = |licenses: &[&str]| {
+            let licenses = licenses.iter().map(ToString::to_string).collect_vec();
+            disallowed_license(&record, &licenses).cloned()
+        };
+        // MIT can be chosen instead of the GPL, but the LGPL is required.
+        assert_eq!(disallowed(&["gpl-2.0"]), None);
+        assert_eq!(disallowed(&["MIT", "GPL-2.0"]), Some("MIT".to_string()));
+        assert_eq!(disallowed(&["LGPL-2.1"]), Some("LGPL-2.1".to_string()));
+        // The license family is ignored if the license offers a choice.
+        assert_eq!(disallowed(&["GPL2"]), None);
+        assert_eq!(disallowed(&["GPL-3.0", "Apache-2.0"]), None);
+
+        record.license = Some("GPL-3.0-only WITH GCC-exception-3.1".to_string());
+        record.license_family = Some("GPL".to_string());
+        let disallowed = |licenses: &[&str]| {
+            let licenses = licenses.iter().map(ToString::to_string).collect_vec();
+            disallowed_license(&record, &licenses).cloned()
+        };
+        assert_eq!(disallowed(&["GPL-3.0"]), Some("GPL-3.0".to_string()));
+        assert_eq!(disallowed(&["GPL"]), Some("GPL".to_string()));
+        assert_eq!(disallowed(&["GCC-exception-3.1"]), None);
+
+        // A license that is not a valid expression is disallowed if any identifier is.
+        record.license = Some("MIT OR (GPL-3.0".to_string());
+        let disallowed = |licenses: &[&str]| {
+            let licenses = licenses.iter().map(ToString::to_string).collect_vec();
+            disallowed_license(&record, &licenses).cloned()
+        };
+        assert_eq!(disallowed(&["GPL-3.0"]), Some("GPL-3.0".to_string()));
+    }
+
+    #[test]
+    fn test_channel_equivalent_records() {
+        let in_channel = |name: &str, version: &str, channel: &str| {
+            let mut record = versioned_record(name, version, "0", 0, None, &[]);
+            record.channel = channel.to_string();
+            record
+        };
+        let locked = vec![
+            in_channel("openssl", "3.2.1", "https://example.com/old"),
+            in_channel("zlib", "1.3", "https://example.com/old"),
+            in_channel("libfoo", "1.0", "https://example.com/old"),
+        ];
+        // The new channel has the highest priority, it provides the same openssl and a different
+        // zlib. libfoo is only available in the old channel.
+        let available = vec![
+            vec![
+                in_channel("openssl", "3.2.1", "https://example.com/new"),
+                in_channel("zlib", "1.4", "https://example.com/new"),
+            ],
+            locked.clone(),
+        ];
+
+        let channels = channel_equivalent_records(locked, &available)
+            .into_iter()
+            .map(|record| record.channel)
+            .collect_vec();
+        assert_eq!(
+            channels,
+            vec![
+                "https://example.com/new",
+                "https://example.com/old",
+                "https://example.com/old"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_noarch_python_min() {
+        let noarch = |version: &str, python: &str| {
+            let mut record = versioned_record("black", version, "pyhd8ed1ab_0", 0, None, &[]);
+            record.package_record.noarch = NoArchType::python();
+            record.package_record.depends = vec![format!("python {python}")];
+            record
+        };
+        let available = vec![vec![
+            noarch("23.0", ">=3.8"),
+            noarch("24.0", ">=3.10"),
+            versioned_record("python", "3.9.18", "0", 0, None, &[]),
+            versioned_record("python", "3.12.0", "0", 0, None, &[]),
+        ]];
+        let solve = |python: &str| {
+            let specs = vec![
+                MatchSpec::from_str("black", ParseStrictness::Strict).unwrap(),
+                MatchSpec::from_str(python, ParseStrictness::Strict).unwrap(),
+            ];
+            resolve_conda(
+                specs,
+                vec![],
+                vec![],
+                available.clone(),
+                CondaSolveOptions {
+                    noarch_python_min: Some(Version::from_str("3.9").unwrap()),
+                    ..Default::default()
+                },
+            )
+        };
+        let black_version = |records: LockedCondaPackages| {
+            records
+                .into_iter()
+                .find(|r| r.package_record.name.as_normalized() == "black")
+                .unwrap()
+                .package_record
+                .version
+                .to_string()
+        };
+
+        // The noarch package has to support the minimum python.
+        assert_eq!(black_version(solve("python >=3.9").await.unwrap()), "23.0");
+
+        // An explicit python that excludes the minimum takes precedence.
+        assert_eq!(black_version(solve("python 3.12.*").await.unwrap()), "24.0");
+    }
+}
@@ -3,6 +3,7 @@ use crate::consts::PROJECT_MANIFEST;
 use crate::lock_file::resolve::resolver_provider::CondaResolverProvider;
 use crate::project::manifest::pypi_options::PypiOptions;
 use crate::project::manifest::python::RequirementOrEditable;
+use crate::utils::reqwest::strip_credentials;
 use crate::uv_reporter::{UvReporter, UvReporterOptions};
 use std::collections::HashMap;
 
@@ -39,10 +40,10 @@ use rattler_lock::{
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
-use uv_configuration::{ConfigSettings, Constraints, NoBuild, Overrides, SetupPyStrategy};
+use uv_configuration::{ConfigSettings, Constraints, Overrides, SetupPyStrategy};
 
 use url::Url;
-use uv_client::{Connectivity, FlatIndexClient, RegistryClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClient, RegistryClientBuilder};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::DistributionDatabase;
 use uv_interpreter::Interpreter;
@@ -183,6 +184,13 @@ pub async fn resolve_pypi(
     // Solve python packages
     pb.set_message("resolving pypi dependencies");
 
+    // Packages that are restricted to wheels or sdists must not use the other format.
+    let context = context.with_distribution_formats(
+        dependencies
+            .iter()
+            .flat_map(|(name, requirements)| requirements.iter().map(move |req| (name, req))),
+    );
+
     // Determine which pypi packages are already installed as conda package.
     let conda_python_packages = locked_conda_records
         .iter()
@@ -265,7 +273,9 @@ pub async fn resolve_pypi(
 
     tracing::debug!("[Resolve] Using Python Interpreter: {:?}", interpreter);
 
-    let index_locations = pypi_options.to_index_locations();
+    let index_locations = pypi_options
+        .with_stored_credentials(&context.auth_storage)
+        .to_index_locations();
 
     // TODO: create a cached registry client per index_url set?
     let registry_client = Arc::new(
@@ -273,7 +283,7 @@ pub async fn resolve_pypi(
             .client(context.client.clone())
             .index_urls(index_locations.index_urls())
             .keyring(context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(context.connectivity)
             .build(),
     );
     // Resolve the flat indexes from `--find-links`.
@@ -373,7 +383,7 @@ pub async fn resolve_pypi(
         &context.hash_strategy,
         options.exclude_newer,
         build_dispatch.no_binary(),
-        &NoBuild::None,
+        &context.no_build,
     );
     let provider = CondaResolverProvider {
         fallback: fallback_provider,
@@ -590,6 +600,12 @@ async fn lock_pypi_packages<'a>(
             }
         };
 
+        // Never write the credentials of an index to the lock-file.
+        let mut pypi_package_data = pypi_package_data;
+        if let UrlOrPath::Url(url) = &pypi_package_data.url_or_path {
+            pypi_package_data.url_or_path = UrlOrPath::Url(strip_credentials(url));
+        }
+
         // TODO: Store extras in the lock-file
         locked_packages.push((pypi_package_data, PypiPackageEnvironmentData::default()));
     }
@@ -1,12 +1,17 @@
 use std::sync::Arc;
 
 use miette::{Context, IntoDiagnostic};
-use uv_cache::Cache;
+use rattler_networking::AuthenticationStorage;
+use uv_cache::{Cache, Refresh, Timestamp};
+use uv_client::Connectivity;
 use uv_configuration::{NoBinary, NoBuild};
+use uv_normalize::PackageName;
 use uv_types::{HashStrategy, InFlight};
 
 use crate::{
     config::{self, get_cache_dir},
+    project::manifest::{DistributionFormat, PyPiRequirement},
+    utils::reqwest::authentication_storage,
     Project,
 };
 
@@ -20,6 +25,10 @@ pub struct UvResolutionContext {
     pub hash_strategy: HashStrategy,
     pub client: reqwest::Client,
     pub keyring_provider: uv_configuration::KeyringProviderType,
+    /// The storage from which the credentials of the pypi indexes are read.
+    pub auth_storage: Arc<AuthenticationStorage>,
+    /// Whether the pypi indexes can be reached, offline only the uv cache is used.
+    pub connectivity: Connectivity,
 }
 
 impl UvResolutionContext {
@@ -32,6 +41,13 @@ impl UvResolutionContext {
         .into_diagnostic()
         .context("failed to create uv cache")?;
 
+        // Revalidate all cached pypi metadata and distributions if caching is disabled.
+        let cache = if project.config().no_cache() {
+            cache.with_refresh(Refresh::All(Timestamp::now()))
+        } else {
+            cache
+        };
+
         let keyring_provider = match project.config().pypi_config().use_keyring() {
             config::KeyringProvider::Subprocess => {
                 tracing::info!("using uv keyring (subprocess) provider");
@@ -43,6 +59,10 @@ impl UvResolutionContext {
             }
         };
 
+        let auth_storage = authentication_storage(project.config())
+            .into_diagnostic()
+            .context("failed to load the authentication storage")?;
+
         let in_flight = Arc::new(InFlight::default());
         Ok(Self {
             cache,
@@ -52,6 +72,52 @@ impl UvResolutionContext {
             hash_strategy: HashStrategy::None,
             client: project.client().clone(),
             keyring_provider,
+            auth_storage: Arc::new(auth_storage),
+            connectivity: if project.config().offline() {
+                Connectivity::Offline
+            } else {
+                Connectivity::Online
+            },
         })
     }
+
+    /// Restricts the packages whose requirement only allows wheels or sdists to that
+    /// distribution format, so that resolving or installing them fails if no matching
+    /// distribution is available.
+    pub fn with_distribution_formats<'a>(
+        mut self,
+        requirements: impl IntoIterator<Item = (&'a PackageName, &'a PyPiRequirement)>,
+    ) -> Self {
+        let mut wheel_only = Vec::new();
+        let mut sdist_only = Vec::new();
+        for (name, requirement) in requirements {
+            match requirement.format() {
+                Some(DistributionFormat::Wheel) => wheel_only.push(name.clone()),
+                Some(DistributionFormat::Sdist) => sdist_only.push(name.clone()),
+                None => {}
+            }
+        }
+
+        if !wheel_only.is_empty() {
+            self.no_build = match self.no_build {
+                NoBuild::All => NoBuild::All,
+                NoBuild::None => NoBuild::Packages(wheel_only),
+                NoBuild::Packages(mut packages) => {
+                    packages.extend(wheel_only);
+                    NoBuild::Packages(packages)
+                }
+            };
+        }
+        if !sdist_only.is_empty() {
+            self.no_binary = match self.no_binary {
+                NoBinary::All => NoBinary::All,
+                NoBinary::None => NoBinary::Packages(sdist_only),
+                NoBinary::Packages(mut packages) => {
+                    packages.extend(sdist_only);
+                    NoBinary::Packages(packages)
+                }
+            };
+        }
+        self
+    }
 }
@@ -1,7 +1,9 @@
+use super::resolve::conda::{disallowed_license, effective_noarch_python_min, supports_python};
 use super::{PypiRecord, PypiRecordsByName, RepoDataRecordsByName};
 use crate::project::grouped_environment::GroupedEnvironment;
 use crate::project::has_features::HasFeatures;
 use crate::project::manifest::python::{AsPep508Error, RequirementOrEditable};
+use crate::project::manifest::PythonImplementation;
 use crate::{project::Environment, pypi_marker_env::determine_marker_environment};
 use distribution_types::ParsedGitUrl;
 use itertools::Itertools;
@@ -10,7 +12,7 @@ use pep440_rs::VersionSpecifiers;
 use pep508_rs::{Requirement, VersionOrUrl};
 use rattler_conda_types::ParseStrictness::Lenient;
 use rattler_conda_types::{
-    GenericVirtualPackage, MatchSpec, ParseMatchSpecError, Platform, RepoDataRecord,
+    GenericVirtualPackage, MatchSpec, ParseMatchSpecError, Platform, RepoDataRecord, Version,
 };
 use rattler_lock::{ConversionError, Package, PypiPackageData, PypiSourceTreeHashable, UrlOrPath};
 use requirements_txt::EditableRequirement;
@@ -104,6 +106,26 @@ pub enum PlatformUnsat {
 
     #[error("the path '{0}, cannot be canonicalized")]
     FailedToCanonicalizePath(PathBuf, #[source] std::io::Error),
+
+    #[error("failed to load the constraints of the project")]
+    FailedToLoadConstraints(#[source] Box<dyn Diagnostic + Send + Sync>),
+
+    #[error("the locked package '{0}' does not satisfy the constraint '{1}'")]
+    UnsatisfiedConstraint(String, MatchSpec),
+
+    #[error("the locked package '{0}' is excluded from the environment")]
+    ExcludedPackage(String),
+
+    #[error("the locked package '{0}' has the disallowed license '{1}'")]
+    DisallowedLicense(String, String),
+
+    #[error("the locked package '{0}' is not a build for the python implementation '{1}'")]
+    PythonImplementationMismatch(String, PythonImplementation),
+
+    #[error(
+        "the locked noarch python package '{0}' does not support python {1} (`noarch-python-min`)"
+    )]
+    NoarchPythonMinMismatch(String, Version),
 }
 
 impl PlatformUnsat {
@@ -218,6 +240,78 @@ pub fn verify_platform_satisfiability(
         }
     }
 
+    // Verify that the locked conda packages satisfy the constraints of the project.
+    let constraints = environment
+        .project()
+        .constraints()
+        .map_err(|e| PlatformUnsat::FailedToLoadConstraints(e.into()))?;
+    for record in &conda_packages {
+        let record = &record.package_record;
+        if let Some(constraint) = constraints
+            .iter()
+            .filter(|spec| spec.name.as_ref() == Some(&record.name))
+            .find(|spec| !spec.matches(record))
+        {
+            return Err(PlatformUnsat::UnsatisfiedConstraint(
+                record.name.as_source().to_string(),
+                constraint.clone(),
+            ));
+        }
+    }
+
+    // Check that none of the locked packages is excluded from the environment.
+    if let Some(record) = conda_packages
+        .iter()
+        .find(|record| environment.excludes().contains(&record.package_record.name))
+    {
+        return Err(PlatformUnsat::ExcludedPackage(
+            record.package_record.name.as_source().to_string(),
+        ));
+    }
+
+    // Check that none of the locked packages has a disallowed license.
+    let disallowed_licenses = environment.project().disallowed_licenses();
+    if let Some((record, license)) = conda_packages.iter().find_map(|record| {
+        disallowed_license(&record.package_record, disallowed_licenses).map(|l| (record, l))
+    }) {
+        return Err(PlatformUnsat::DisallowedLicense(
+            record.package_record.name.as_source().to_string(),
+            license.clone(),
+        ));
+    }
+
+    // Check that the locked python builds are of the requested implementation.
+    if let Some(implementation) = environment.project().python_implementation() {
+        if let Some(record) = conda_packages
+            .iter()
+            .find(|record| !implementation.matches(&record.package_record))
+        {
+            return Err(PlatformUnsat::PythonImplementationMismatch(
+                record.package_record.name.as_source().to_string(),
+                implementation,
+            ));
+        }
+    }
+
+    // Check that the locked noarch python packages support the minimum python.
+    let specs = environment
+        .dependencies(None, Some(platform))
+        .into_match_specs()
+        .collect_vec();
+    let noarch_python_min =
+        effective_noarch_python_min(environment.project().noarch_python_min().cloned(), &specs);
+    if let Some(python) = noarch_python_min {
+        if let Some(record) = conda_packages
+            .iter()
+            .find(|record| !supports_python(&record.package_record, &python))
+        {
+            return Err(PlatformUnsat::NoarchPythonMinMismatch(
+                record.package_record.name.as_source().to_string(),
+                python,
+            ));
+        }
+    }
+
     // Create a lookup table from package name to package record. Returns an error if we find a
     // duplicate entry for a record
     let repodata_records_by_name = match RepoDataRecordsByName::from_unique_iter(conda_packages) {
@@ -733,6 +827,13 @@ impl MatchesMatchspec for RepoDataRecord {
             }
         }
 
+        // Check the subdir
+        if let Some(subdir) = &spec.subdir {
+            if &self.package_record.subdir != subdir {
+                return false;
+            }
+        }
+
         true
     }
 }
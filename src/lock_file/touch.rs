@@ -0,0 +1,145 @@
+//! A lock-file that was edited by hand can be marked as up-to-date with the manifest by "touching"
+//! it. This stores a stamp with the hashes of the manifest and the lock-file in a comment on the
+//! first line of the lock-file, so the stamp is committed together with the lock-file. As long as
+//! neither file changes, the lock-file is trusted as is and not checked against the manifest.
+//!
+//! Writing the lock-file after a solve drops the stamp again.
+
+use super::existing_lock_files;
+use crate::{consts, Project};
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_digest::{compute_bytes_digest, Sha256};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The hashes of the manifest and the lock-file at the time the lock-file was touched.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LockFileStamp {
+    manifest_hash: String,
+    lock_file_hash: String,
+}
+
+/// Hashes the content of a file, line endings are normalized so a checkout on Windows results in
+/// the same hash.
+fn hash_content(content: &str) -> String {
+    let content = content.replace("\r\n", "\n");
+    format!("{:x}", compute_bytes_digest::<Sha256>(content.as_bytes()))
+}
+
+/// Splits the content of a lock-file into its stamp, if any, and the rest of the content.
+fn split_stamp(content: &str) -> (Option<&str>, &str) {
+    match content.strip_prefix(consts::LOCK_FILE_STAMP) {
+        Some(rest) => match rest.split_once('\n') {
+            Some((stamp, rest)) => (Some(stamp.trim_end()), rest),
+            None => (Some(rest.trim_end()), ""),
+        },
+        None => (None, content),
+    }
+}
+
+fn read_file(path: &Path) -> miette::Result<String> {
+    std::fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read '{}'", path.display()))
+}
+
+/// Marks the current lock-file of the project as up-to-date with the manifest by writing a stamp
+/// into every lock-file of the project.
+pub fn touch_lock_file(project: &Project) -> miette::Result<()> {
+    let manifest_hash = hash_content(&read_file(&project.manifest_path())?);
+    for path in existing_lock_files(project) {
+        let content = read_file(&path)?;
+        let (_, content) = split_stamp(&content);
+        let stamp = LockFileStamp {
+            manifest_hash: manifest_hash.clone(),
+            lock_file_hash: hash_content(content),
+        };
+        let stamped = format!(
+            "{}{}\n{content}",
+            consts::LOCK_FILE_STAMP,
+            serde_json::to_string(&stamp).into_diagnostic()?
+        );
+        std::fs::write(&path, stamped)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Returns true if the lock-file was touched and neither the manifest nor the lock-file changed
+/// since.
+pub fn is_lock_file_touched(project: &Project) -> bool {
+    let Ok(manifest) = std::fs::read_to_string(project.manifest_path()) else {
+        return false;
+    };
+    let manifest_hash = hash_content(&manifest);
+    let lock_files = existing_lock_files(project);
+    !lock_files.is_empty()
+        && lock_files.iter().all(|path| {
+            let Ok(content) = std::fs::read_to_string(path) else {
+                return false;
+            };
+            let (Some(stamp), content) = split_stamp(&content) else {
+                return false;
+            };
+            serde_json::from_str::<LockFileStamp>(stamp).is_ok_and(|stamp| {
+                stamp.manifest_hash == manifest_hash
+                    && stamp.lock_file_hash == hash_content(content)
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touch_lock_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join(consts::PROJECT_MANIFEST);
+        std::fs::write(
+            &manifest_path,
+            r#"
+            [project]
+            name = "foo"
+            channels = []
+            platforms = []
+            "#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(consts::PROJECT_LOCK_FILE), "version: 5\n").unwrap();
+        let project = Project::load(&manifest_path).unwrap();
+
+        assert!(!is_lock_file_touched(&project));
+        touch_lock_file(&project).unwrap();
+        assert!(is_lock_file_touched(&project));
+
+        // The stamp is stored in the lock-file and is replaced when touching it again.
+        let content = std::fs::read_to_string(project.lock_file_path()).unwrap();
+        assert!(content.starts_with(consts::LOCK_FILE_STAMP));
+        assert!(content.ends_with("\nversion: 5\n"));
+        touch_lock_file(&project).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(project.lock_file_path()).unwrap(),
+            content
+        );
+
+        // The stamp is still valid with Windows line endings.
+        std::fs::write(project.lock_file_path(), content.replace('\n', "\r\n")).unwrap();
+        assert!(is_lock_file_touched(&project));
+
+        // Changing the lock-file invalidates the stamp.
+        let (stamp, _) = split_stamp(&content);
+        std::fs::write(
+            project.lock_file_path(),
+            format!(
+                "{}{}\nversion: 5\nenvironments: {{}}\n",
+                consts::LOCK_FILE_STAMP,
+                stamp.unwrap()
+            ),
+        )
+        .unwrap();
+        assert!(!is_lock_file_touched(&project));
+    }
+}
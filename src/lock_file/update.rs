@@ -10,9 +10,11 @@ use crate::{
         self, LockFileUsage, PerEnvironmentAndPlatform, PerGroup, PerGroupAndPlatform, PythonStatus,
     },
     load_lock_file,
-    lock_file::{self, update, OutdatedEnvironments, PypiRecordsByName, RepoDataRecordsByName},
+    lock_file::{
+        self, update, OutdatedEnvironments, PypiRecordsByName, RepoDataRecordsByName, SolveStrategy,
+    },
     prefix::Prefix,
-    progress::global_multi_progress,
+    progress::{emit_progress_event, global_multi_progress, ProgressEvent},
     project::{grouped_environment::GroupedEnvironment, Environment},
     repodata::fetch_sparse_repodata_targets,
     utils::BarrierCell,
@@ -68,8 +70,27 @@ pub struct UpdateLockFileOptions {
     pub existing_repo_data: IndexMap<(Channel, Platform), SparseRepoData>,
 
     /// The maximum number of concurrent solves that are allowed to run. If this value is None
-    /// a heuristic is used based on the number of cores available from the system.
+    /// the `concurrency.solves` configuration is used, which defaults to a heuristic based on the
+    /// number of cores available from the system.
     pub max_concurrent_solves: Option<usize>,
+
+    /// The lock-file to update instead of the lock-file on disk.
+    pub existing_lock_file: Option<LockFile>,
+
+    /// Don't write the updated lock-file to disk.
+    pub dry_run: bool,
+
+    /// Keep the previously locked packages of the platforms that cannot be solved, instead of
+    /// failing. The current platform always has to be solvable.
+    pub allow_partial_platforms: bool,
+
+    /// The versions the conda solver prefers. With a strategy other than the default all
+    /// environments are solved again.
+    pub solve_strategy: SolveStrategy,
+
+    /// Solve all environments again while preferring the locked packages from the highest
+    /// priority channel that provides them, so packages move to a newly added channel.
+    pub upgrade_channels: bool,
 }
 
 /// A struct that holds the lock-file and any potential derived data that was computed when calling
@@ -94,6 +115,10 @@ pub struct LockFileDerivedData<'p> {
 
     /// The cached uv context
     pub uv_context: Option<UvResolutionContext>,
+
+    /// Whether the lock files may be updated, this includes the build environments of the pypi
+    /// source distributions that are recorded when they are built.
+    pub allow_lock_file_updates: bool,
 }
 
 impl<'p> LockFileDerivedData<'p> {
@@ -102,14 +127,29 @@ impl<'p> LockFileDerivedData<'p> {
         if let Some(prefix) = self.updated_pypi_prefixes.get(environment) {
             return Ok(prefix.clone());
         }
+        self.prefix_excluding(environment, &HashSet::new()).await
+    }
 
+    /// Returns the prefix for the given environment with all locked packages installed except
+    /// for the conda and pypi packages with the given normalized names, these are removed from
+    /// the prefix if they are installed.
+    pub async fn prefix_excluding(
+        &mut self,
+        environment: &Environment<'p>,
+        excluded: &HashSet<String>,
+    ) -> miette::Result<Prefix> {
         // Get the prefix with the conda packages installed.
         let platform = environment.best_platform();
-        let (prefix, python_status) = self.conda_prefix(environment).await?;
+        let (prefix, python_status) = self.conda_prefix(environment, excluded).await?;
         let repodata_records = self
             .repodata_records(environment, platform)
             .unwrap_or_default();
-        let pypi_records = self.pypi_records(environment, platform).unwrap_or_default();
+        let pypi_records = self
+            .pypi_records(environment, platform)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(data, _)| !excluded.contains(data.name.as_ref()))
+            .collect_vec();
 
         // No `uv` support for WASM right now
         // TODO - figure out if we can create the `uv` context more lazily
@@ -126,6 +166,14 @@ impl<'p> LockFileDerivedData<'p> {
             Some(context) => context.clone(),
         };
 
+        // Refuse to install locked distributions in a format that the manifest disallows.
+        let pypi_dependencies = environment.pypi_dependencies(Some(platform));
+        let uv_context = uv_context.with_distribution_formats(
+            pypi_dependencies
+                .iter_specs()
+                .map(|(name, requirement)| (name.as_normalized(), requirement)),
+        );
+
         let env_variables = environment.project().get_env_variables(environment).await?;
         // Update the prefix with Pypi records
         environment::update_prefix_pypi(
@@ -141,12 +189,16 @@ impl<'p> LockFileDerivedData<'p> {
             env_variables,
             self.project.root(),
             environment.best_platform(),
+            self.allow_lock_file_updates,
         )
         .await?;
 
-        // Store that we updated the environment, so we won't have to do it again.
-        self.updated_pypi_prefixes
-            .insert(environment.clone(), prefix.clone());
+        // Store that we updated the environment, so we won't have to do it again. A prefix that
+        // is missing packages is not up-to-date.
+        if excluded.is_empty() {
+            self.updated_pypi_prefixes
+                .insert(environment.clone(), prefix.clone());
+        }
 
         Ok(prefix)
     }
@@ -178,10 +230,13 @@ impl<'p> LockFileDerivedData<'p> {
     async fn conda_prefix(
         &mut self,
         environment: &Environment<'p>,
+        excluded: &HashSet<String>,
     ) -> miette::Result<(Prefix, PythonStatus)> {
         // If we previously updated this environment, early out.
         if let Some((prefix, python_status)) = self.updated_conda_prefixes.get(environment) {
-            return Ok((prefix.clone(), python_status.clone()));
+            if excluded.is_empty() {
+                return Ok((prefix.clone(), python_status.clone()));
+            }
         }
 
         let prefix = Prefix::new(environment.dir());
@@ -201,7 +256,10 @@ impl<'p> LockFileDerivedData<'p> {
         // Get the locked environment from the lock-file.
         let records = self
             .repodata_records(environment, platform)
-            .unwrap_or_default();
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|record| !excluded.contains(record.package_record.name.as_normalized()))
+            .collect_vec();
 
         // Update the prefix with conda packages.
         let python_status = environment::update_prefix_conda(
@@ -212,12 +270,17 @@ impl<'p> LockFileDerivedData<'p> {
             installed_packages,
             &records,
             platform,
+            environment.project().config().no_cache(),
+            environment.project().config().offline(),
+            environment.project().config().link_method(),
         )
         .await?;
 
         // Store that we updated the environment, so we won't have to do it again.
-        self.updated_conda_prefixes
-            .insert(environment.clone(), (prefix.clone(), python_status.clone()));
+        if excluded.is_empty() {
+            self.updated_conda_prefixes
+                .insert(environment.clone(), (prefix.clone(), python_status.clone()));
+        }
 
         Ok((prefix, python_status))
     }
@@ -396,12 +459,6 @@ impl<'p> UpdateContext<'p> {
     }
 }
 
-/// Returns the default number of concurrent solves.
-fn default_max_concurrent_solves() -> usize {
-    let available_parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
-    (available_parallelism.saturating_sub(2)).min(4).max(1)
-}
-
 /// If the project has any source dependencies, like `git` or `path` dependencies.
 /// for pypi dependencies, we need to limit the solve to 1,
 /// because of uv internals
@@ -418,7 +475,7 @@ fn determine_pypi_solve_permits(project: &Project) -> usize {
         }
     }
 
-    default_max_concurrent_solves()
+    project.config().max_concurrent_solves()
 }
 
 /// Ensures that the lock-file is up-to-date with the project.
@@ -435,12 +492,15 @@ pub async fn ensure_up_to_date_lock_file(
     project: &Project,
     options: UpdateLockFileOptions,
 ) -> miette::Result<LockFileDerivedData<'_>> {
-    let lock_file = load_lock_file(project).await?;
+    let lock_file = match options.existing_lock_file {
+        Some(lock_file) => lock_file,
+        None => load_lock_file(project).await?,
+    };
     let current_platform = Platform::current();
     let package_cache = Arc::new(PackageCache::new(config::get_cache_dir()?.join("pkgs")));
     let max_concurrent_solves = options
         .max_concurrent_solves
-        .unwrap_or_else(default_max_concurrent_solves);
+        .unwrap_or_else(|| project.config().max_concurrent_solves());
 
     let solve_semaphore = Arc::new(Semaphore::new(max_concurrent_solves));
 
@@ -459,11 +519,18 @@ pub async fn ensure_up_to_date_lock_file(
             updated_conda_prefixes: Default::default(),
             updated_pypi_prefixes: Default::default(),
             uv_context: None,
+            allow_lock_file_updates: options.lock_file_usage.allows_lock_file_updates(),
         });
     }
 
-    // Check which environments are out of date.
-    let outdated = OutdatedEnvironments::from_project_and_lock_file(project, &lock_file);
+    // Check which environments are out of date, a solve strategy or upgrading the channels requires
+    // a fresh solve.
+    let outdated = if options.solve_strategy == SolveStrategy::Highest && !options.upgrade_channels
+    {
+        OutdatedEnvironments::from_project_and_lock_file(project, &lock_file)
+    } else {
+        OutdatedEnvironments::all(project)
+    };
     if outdated.is_empty() {
         tracing::info!("the lock-file is up-to-date");
 
@@ -476,6 +543,7 @@ pub async fn ensure_up_to_date_lock_file(
             updated_conda_prefixes: Default::default(),
             updated_pypi_prefixes: Default::default(),
             uv_context: None,
+            allow_lock_file_updates: options.lock_file_usage.allows_lock_file_updates(),
         });
     }
 
@@ -491,11 +559,11 @@ pub async fn ensure_up_to_date_lock_file(
     // environment.
     let mut fetch_targets = IndexSet::new();
     for (environment, platforms) in outdated.conda.iter() {
-        for channel in environment.channels() {
-            for platform in platforms {
+        for platform in platforms {
+            for channel in environment.platform_channels(Some(*platform)) {
                 fetch_targets.insert((channel.clone(), *platform));
+                fetch_targets.insert((channel.clone(), Platform::NoArch));
             }
-            fetch_targets.insert((channel.clone(), Platform::NoArch));
         }
     }
 
@@ -686,13 +754,32 @@ pub async fn ensure_up_to_date_lock_file(
             // Spawn a task to solve the group.
             let group_solve_task = spawn_solve_conda_environment_task(
                 source.clone(),
-                locked_group_records,
+                locked_group_records.clone(),
                 context.repo_data.clone(),
                 platform,
                 solve_semaphore.clone(),
                 project.client().clone(),
-            )
-            .boxed_local();
+                options.solve_strategy,
+                options.upgrade_channels,
+            );
+
+            // If partial platforms are allowed, a failing solve falls back to the locked records.
+            let group_solve_task =
+                if options.allow_partial_platforms && platform != current_platform {
+                    let group_name = source.name();
+                    group_solve_task
+                        .or_else(move |err| {
+                            ready(Ok(TaskResult::CondaGroupSolveFailed(
+                                group_name,
+                                platform,
+                                locked_group_records,
+                                err,
+                            )))
+                        })
+                        .boxed_local()
+                } else {
+                    group_solve_task.boxed_local()
+                };
 
             // Store the task so we can poll it later.
             pending_futures.push(group_solve_task);
@@ -835,10 +922,29 @@ pub async fn ensure_up_to_date_lock_file(
             env_variables,
             pypi_solve_semaphore.clone(),
             project.root().to_path_buf(),
-            locked_pypi_records,
+            locked_pypi_records.clone(),
         );
 
-        pending_futures.push(pypi_solve_future.boxed_local());
+        // If partial platforms are allowed, a failing solve falls back to the locked records.
+        let pypi_solve_future = if options.allow_partial_platforms && platform != current_platform {
+            let group_name = group.name();
+            pypi_solve_future
+                .or_else(move |err| {
+                    ready(Ok(TaskResult::PypiGroupSolveFailed(
+                        group_name,
+                        platform,
+                        Arc::new(PypiRecordsByName::from_iter(
+                            locked_pypi_records.iter().cloned(),
+                        )),
+                        err,
+                    )))
+                })
+                .boxed_local()
+        } else {
+            pypi_solve_future.boxed_local()
+        };
+
+        pending_futures.push(pypi_solve_future);
 
         let previous_cell = context
             .grouped_solved_pypi_records
@@ -918,9 +1024,40 @@ pub async fn ensure_up_to_date_lock_file(
     //    race-conditions where data has already been broadcasted before a task subscribes to it.
     // 2. The futures stored in `pending_futures` do not necessarily have to be `'static`. Which
     //    makes them easier to work with.
+    let mut failed_platforms = Vec::new();
     while let Some(result) = pending_futures.next().await {
         top_level_progress.inc(1);
         match result? {
+            TaskResult::CondaGroupSolveFailed(group_name, platform, locked_records, err) => {
+                let group = GroupedEnvironment::from_name(project, &group_name)
+                    .expect("group should exist");
+
+                context
+                    .grouped_solved_repodata_records
+                    .get_mut(&group)
+                    .expect("the entry for this environment should exist")
+                    .get_mut(&platform)
+                    .expect("the entry for this platform should exist")
+                    .set(locked_records)
+                    .expect("records should not be solved twice");
+
+                failed_platforms.push((group_name, platform, err));
+            }
+            TaskResult::PypiGroupSolveFailed(group_name, platform, locked_records, err) => {
+                let group = GroupedEnvironment::from_name(project, &group_name)
+                    .expect("group should exist");
+
+                context
+                    .grouped_solved_pypi_records
+                    .get_mut(&group)
+                    .expect("the entry for this environment should exist")
+                    .get_mut(&platform)
+                    .expect("the entry for this platform should exist")
+                    .set(locked_records)
+                    .expect("records should not be solved twice");
+
+                failed_platforms.push((group_name, platform, err));
+            }
             TaskResult::CondaGroupSolved(group_name, platform, records, duration) => {
                 let group = GroupedEnvironment::from_name(project, &group_name)
                     .expect("group should exist");
@@ -1062,12 +1199,16 @@ pub async fn ensure_up_to_date_lock_file(
         let mut has_pypi_records = false;
         for platform in environment.platforms() {
             if let Some(records) = context.take_latest_repodata_records(&environment, platform) {
-                for record in records.into_inner() {
+                let mut records = records.into_inner();
+                lock_file::sort_conda_records(&mut records);
+                for record in records {
                     builder.add_conda_package(&environment_name, platform, record.into());
                 }
             }
             if let Some(records) = context.take_latest_pypi_records(&environment, platform) {
-                for (pkg_data, pkg_env_data) in records.into_inner() {
+                let mut records = records.into_inner();
+                lock_file::sort_pypi_records(&mut records);
+                for (pkg_data, pkg_env_data) in records {
                     builder.add_pypi_package(&environment_name, platform, pkg_data, pkg_env_data);
                     has_pypi_records = true;
                 }
@@ -1082,13 +1223,23 @@ pub async fn ensure_up_to_date_lock_file(
 
     // Store the lock file
     let lock_file = builder.finish();
-    lock_file
-        .to_path(&project.lock_file_path())
-        .into_diagnostic()
-        .context("failed to write lock-file to disk")?;
+    if !options.dry_run {
+        lock_file::write_lock_file(project, &lock_file)?;
+    }
 
     top_level_progress.finish_and_clear();
 
+    // Report the platforms that could not be solved.
+    for (group_name, platform, err) in failed_platforms {
+        eprintln!(
+            "{}Failed to solve '{}' for {}, the previously locked packages of this platform are kept: {:?}",
+            console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+            group_name.fancy_display(),
+            consts::PLATFORM_STYLE.apply_to(platform),
+            err
+        );
+    }
+
     Ok(LockFileDerivedData {
         project,
         lock_file,
@@ -1098,6 +1249,7 @@ pub async fn ensure_up_to_date_lock_file(
         repo_data: Arc::into_inner(context.repo_data)
             .expect("repo data should not be shared anymore"),
         uv_context,
+        allow_lock_file_updates: options.lock_file_usage.allows_lock_file_updates(),
     })
 }
 
@@ -1177,6 +1329,24 @@ enum TaskResult {
         Duration,
     ),
 
+    /// Solving the conda dependencies of a grouped environment failed, the previously locked
+    /// records are used instead.
+    CondaGroupSolveFailed(
+        GroupedEnvironmentName,
+        Platform,
+        Arc<RepoDataRecordsByName>,
+        miette::Report,
+    ),
+
+    /// Solving the pypi dependencies of a grouped environment failed, the previously locked
+    /// records are used instead.
+    PypiGroupSolveFailed(
+        GroupedEnvironmentName,
+        Platform,
+        Arc<PypiRecordsByName>,
+        miette::Report,
+    ),
+
     /// The records for a specific environment have been extracted from a grouped solve.
     ExtractedRecordsSubset(
         EnvironmentName,
@@ -1186,6 +1356,24 @@ enum TaskResult {
     ),
 }
 
+/// Returns the restrictions and preferences of the project for the conda packages that are selected
+/// for the solve group.
+pub fn conda_solve_options(
+    group: &GroupedEnvironment<'_>,
+    strategy: SolveStrategy,
+) -> miette::Result<lock_file::CondaSolveOptions> {
+    Ok(lock_file::CondaSolveOptions {
+        preferred_features: group.project().preferred_features().to_vec(),
+        avoided_features: group.project().avoided_features().to_vec(),
+        constraints: group.project().constraints()?,
+        excludes: group.excludes(),
+        disallowed_licenses: group.project().disallowed_licenses().to_vec(),
+        python_implementation: group.project().python_implementation(),
+        noarch_python_min: group.project().noarch_python_min().cloned(),
+        strategy,
+    })
+}
+
 /// A task that solves the conda dependencies for a given environment.
 async fn spawn_solve_conda_environment_task(
     group: GroupedEnvironment<'_>,
@@ -1194,6 +1382,8 @@ async fn spawn_solve_conda_environment_task(
     platform: Platform,
     concurrency_semaphore: Arc<Semaphore>,
     client: reqwest::Client,
+    solve_strategy: SolveStrategy,
+    upgrade_channels: bool,
 ) -> miette::Result<TaskResult> {
     // Get the dependencies for this platform
     let dependencies = group.dependencies(None, Some(platform));
@@ -1205,7 +1395,11 @@ async fn spawn_solve_conda_environment_task(
     let group_name = group.name();
 
     // The list of channels and platforms we need for this task
-    let channels = group.channels().into_iter().cloned().collect_vec();
+    let channels = group
+        .platform_channels(Some(platform))
+        .into_iter()
+        .cloned()
+        .collect_vec();
 
     // Capture local variables
     let sparse_repo_data = sparse_repo_data.clone();
@@ -1215,6 +1409,10 @@ async fn spawn_solve_conda_environment_task(
 
     // Whether we should use custom mapping location
     let pypi_name_mapping_location = group.project().pypi_name_mapping_source();
+    let offline = group.project().config().offline();
+
+    // The restrictions and preferences of the project for the selected packages
+    let solve_options = conda_solve_options(&group, solve_strategy)?;
 
     tokio::spawn(
         async move {
@@ -1229,6 +1427,11 @@ async fn spawn_solve_conda_environment_task(
                 group_name.clone(),
             ));
             pb.start();
+            emit_progress_event(ProgressEvent::SolveStarted {
+                environment: group_name.as_str(),
+                platform,
+                kind: "conda",
+            });
 
             let start = Instant::now();
 
@@ -1253,13 +1456,25 @@ async fn spawn_solve_conda_environment_task(
             )
             .await?;
 
+            // The solver prefers the locked packages, when upgrading the channels it prefers their
+            // equivalents from the highest priority channels instead.
+            let locked_records = if upgrade_channels {
+                lock_file::channel_equivalent_records(
+                    existing_repodata_records.records.clone(),
+                    &available_packages,
+                )
+            } else {
+                existing_repodata_records.records.clone()
+            };
+
             // Solve conda packages
             pb.set_message("resolving conda");
             let mut records = lock_file::resolve_conda(
                 match_specs,
                 virtual_packages,
-                existing_repodata_records.records.clone(),
+                locked_records,
                 available_packages,
+                solve_options,
             )
             .await
             .with_context(|| {
@@ -1278,6 +1493,7 @@ async fn spawn_solve_conda_environment_task(
                     pypi_name_mapping_location,
                     &mut records,
                     Some(pb.purl_amend_reporter()),
+                    offline,
                 )
                 .await?;
             }
@@ -1289,6 +1505,11 @@ async fn spawn_solve_conda_environment_task(
 
             // Finish the progress bar
             pb.finish();
+            emit_progress_event(ProgressEvent::SolveFinished {
+                environment: group_name.as_str(),
+                platform,
+                kind: "conda",
+            });
 
             Ok(TaskResult::CondaGroupSolved(
                 group_name,
@@ -1508,6 +1729,7 @@ async fn spawn_solve_pypi_task(
         pypi_name_mapping_location,
         &mut conda_records,
         None,
+        environment.project().config().offline(),
     )
     .await?;
 
@@ -1520,6 +1742,11 @@ async fn spawn_solve_pypi_task(
             environment_name.clone(),
         );
         pb.start();
+        emit_progress_event(ProgressEvent::SolveStarted {
+            environment: environment_name.as_str(),
+            platform,
+            kind: "pypi",
+        });
 
         let python_path = python_status
             .location()
@@ -1556,6 +1783,11 @@ async fn spawn_solve_pypi_task(
         let end = Instant::now();
 
         pb.finish();
+        emit_progress_event(ProgressEvent::SolveFinished {
+            environment: environment_name.as_str(),
+            platform,
+            kind: "pypi",
+        });
 
         Ok::<(_, _), miette::Report>((PypiRecordsByName::from_iter(records), end - start))
     }
@@ -1585,6 +1817,9 @@ async fn spawn_create_prefix_task(
     let group_name = group.name().clone();
     let prefix = group.prefix();
     let client = group.project().authenticated_client().clone();
+    let no_cache = group.project().config().no_cache();
+    let offline = group.project().config().offline();
+    let link_method = group.project().config().link_method();
 
     // Spawn a task to determine the currently installed packages.
     let installed_packages_future = tokio::spawn({
@@ -1615,6 +1850,9 @@ async fn spawn_create_prefix_task(
                 installed_packages,
                 &conda_records.records,
                 Platform::current(),
+                no_cache,
+                offline,
+                link_method,
             )
             .await?;
             let end = Instant::now();
@@ -0,0 +1,80 @@
+//! The `CONDA_OVERRIDE_*` environment variables change the virtual packages an environment is
+//! solved for. The variables that were set during a solve are recorded in a comment at the top
+//! of the lock-file, a lock-file that was solved with other overrides is solved again.
+
+use super::existing_lock_files;
+use crate::project::virtual_packages::virtual_package_overrides;
+use crate::{consts, Project};
+use miette::{IntoDiagnostic, WrapErr};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Returns the overrides that are recorded in the content of a lock-file, the comments are at the
+/// top of the lock-file.
+fn recorded(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .find_map(|line| line.strip_prefix(consts::LOCK_FILE_VIRTUAL_PACKAGE_OVERRIDES))
+        .and_then(|overrides| serde_json::from_str(overrides.trim_end()).ok())
+        .unwrap_or_default()
+}
+
+/// Records the overrides in the lock-file that was just written, nothing is written without
+/// overrides.
+pub(super) fn record(path: &Path, overrides: &BTreeMap<String, String>) -> miette::Result<()> {
+    if overrides.is_empty() {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read '{}'", path.display()))?;
+    let recorded = format!(
+        "{}{}\n{content}",
+        consts::LOCK_FILE_VIRTUAL_PACKAGE_OVERRIDES,
+        serde_json::to_string(overrides).into_diagnostic()?
+    );
+    std::fs::write(path, recorded)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to write '{}'", path.display()))
+}
+
+/// Returns true if a lock-file of the project was solved with other `CONDA_OVERRIDE_*`
+/// variables than the ones that are set now.
+pub fn virtual_package_overrides_changed(project: &Project) -> bool {
+    let overrides = virtual_package_overrides();
+    existing_lock_files(project).iter().any(|path| {
+        std::fs::read_to_string(path).is_ok_and(|content| recorded(&content) != overrides)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(consts::PROJECT_LOCK_FILE);
+        std::fs::write(&path, "version: 5\n").unwrap();
+
+        // Without overrides the lock-file is left as is.
+        record(&path, &BTreeMap::new()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "version: 5\n");
+        assert!(recorded(&content).is_empty());
+
+        let overrides = BTreeMap::from([
+            (String::from("CONDA_OVERRIDE_CUDA"), String::from("12.1")),
+            (String::from("CONDA_OVERRIDE_GLIBC"), String::new()),
+        ]);
+        record(&path, &overrides).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.ends_with("\nversion: 5\n"));
+        assert_eq!(recorded(&content), overrides);
+
+        // The overrides are still found below the stamp of a touched lock-file.
+        let touched = format!("{}{{}}\n{content}", consts::LOCK_FILE_STAMP);
+        assert_eq!(recorded(&touched), overrides);
+    }
+}
@@ -1,5 +1,7 @@
 use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState};
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use rattler_conda_types::Platform;
+use serde::Serialize;
 use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::fmt::Write;
@@ -21,6 +23,85 @@ pub fn global_multi_progress() -> MultiProgress {
     GLOBAL_MP.clone()
 }
 
+/// The format in which progress is reported to the user.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Show progress bars
+    #[default]
+    Human,
+    /// Write newline-delimited JSON events to stderr
+    Json,
+}
+
+static PROGRESS_FORMAT: OnceCell<ProgressFormat> = OnceCell::new();
+
+/// Sets the format that is used to report progress for the remainder of the process. When
+/// progress is reported as JSON the progress bars are hidden.
+pub fn set_progress_format(format: ProgressFormat) {
+    if PROGRESS_FORMAT.set(format).is_ok() && format == ProgressFormat::Json {
+        global_multi_progress().set_draw_target(ProgressDrawTarget::hidden());
+    }
+}
+
+/// Returns the format that is used to report progress.
+pub fn progress_format() -> ProgressFormat {
+    PROGRESS_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// A machine-readable progress event, see [`ProgressFormat::Json`].
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    SolveStarted {
+        environment: &'a str,
+        platform: Platform,
+        kind: &'a str,
+    },
+    SolveFinished {
+        environment: &'a str,
+        platform: Platform,
+        kind: &'a str,
+    },
+    DownloadStarted {
+        package: &'a str,
+        bytes: Option<u64>,
+    },
+    DownloadFinished {
+        package: &'a str,
+        bytes: Option<u64>,
+    },
+    LinkStarted {
+        package: &'a str,
+        bytes: Option<u64>,
+    },
+    LinkFinished {
+        package: &'a str,
+        bytes: Option<u64>,
+    },
+}
+
+#[derive(Serialize)]
+struct TimestampedProgressEvent<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    event: ProgressEvent<'a>,
+}
+
+/// Writes the event to stderr as a single line of JSON if progress is reported as JSON,
+/// otherwise this does nothing.
+pub fn emit_progress_event(event: ProgressEvent<'_>) {
+    if progress_format() != ProgressFormat::Json {
+        return;
+    }
+    let event = TimestampedProgressEvent {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        event,
+    };
+    if let Ok(line) = serde_json::to_string(&event) {
+        eprintln!("{line}");
+    }
+}
+
 /// Returns the style to use for a progressbar that is currently in progress.
 pub fn default_bytes_style() -> indicatif::ProgressStyle {
     indicatif::ProgressStyle::default_bar()
@@ -237,3 +318,23 @@ impl ProgressBarMessageFormatter {
         self.pb
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_event_json() {
+        let event = TimestampedProgressEvent {
+            timestamp: "2024-05-01T12:00:00+00:00".to_string(),
+            event: ProgressEvent::DownloadStarted {
+                package: "numpy",
+                bytes: Some(1024),
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"timestamp":"2024-05-01T12:00:00+00:00","event":"download_started","package":"numpy","bytes":1024}"#
+        );
+    }
+}
@@ -8,8 +8,9 @@ use crate::project::has_features::HasFeatures;
 use crate::consts;
 use crate::task::TaskName;
 use crate::{task::Task, Project};
+use indexmap::IndexMap;
 use itertools::Either;
-use rattler_conda_types::{Arch, Platform};
+use rattler_conda_types::{Arch, PackageName, Platform};
 use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
@@ -95,8 +96,16 @@ impl<'p> Environment<'p> {
         self.environment
     }
 
+    /// Returns the conda packages that must never be part of this environment.
+    pub fn excludes(&self) -> &'p [PackageName] {
+        &self.environment.excludes
+    }
+
     /// Returns the directory where this environment is stored.
     pub fn dir(&self) -> std::path::PathBuf {
+        if let Some(prefix) = self.project.prefix_override(&self.environment.name) {
+            return prefix.to_path_buf();
+        }
         self.project
             .environments_dir()
             .join(self.environment.name.as_str())
@@ -227,12 +236,24 @@ impl<'p> Environment<'p> {
     /// environment.
     pub fn activation_scripts(&self, platform: Option<Platform>) -> Vec<String> {
         self.features()
+            .filter(|f| f.is_available_on(platform))
             .filter_map(|f| f.activation_scripts(platform))
             .flatten()
             .cloned()
             .collect()
     }
 
+    /// Returns the environment variables that are set when the environment is activated on the
+    /// given platform. Variables of a feature override those of the default feature, features that
+    /// are listed first take precedence.
+    pub fn activation_env(&self, platform: Option<Platform>) -> IndexMap<String, String> {
+        self.features()
+            .rev()
+            .filter(|f| f.is_available_on(platform))
+            .flat_map(|f| f.activation_env(platform))
+            .collect()
+    }
+
     /// Validates that the given platform is supported by this environment.
     fn validate_platform_support(
         &self,
@@ -704,4 +725,92 @@ mod tests {
             vec!["https://1.com/", "https://2.com/"]
         )
     }
+
+    #[test]
+    fn test_omit_feature_on_other_platforms() {
+        let manifest = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foobar"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-arm64"]
+
+        [dependencies]
+        python = "*"
+
+        [feature.gpu]
+        platforms = ["linux-64"]
+        omit-on-other-platforms = true
+        channels = ["nvidia", "conda-forge"]
+        dependencies = { cuda = "*" }
+        pypi-dependencies = { jax = "*" }
+
+        [feature.gpu.activation]
+        scripts = ["cuda.sh"]
+        env = { CUDA_HOME = "$CONDA_PREFIX" }
+
+        [feature.restricted]
+        platforms = ["linux-64"]
+        dependencies = { cuda = "*" }
+
+        [environments]
+        gpu = ["gpu"]
+        restricted = ["restricted"]
+        "#,
+        )
+        .unwrap();
+
+        let gpu = manifest.environment("gpu").unwrap();
+        assert_eq!(
+            gpu.platforms(),
+            HashSet::from([Platform::Linux64, Platform::OsxArm64])
+        );
+        let linux_deps = gpu.dependencies(None, Some(Platform::Linux64));
+        assert_eq!(
+            linux_deps
+                .names()
+                .map(|n| n.as_source())
+                .sorted()
+                .collect_vec(),
+            vec!["cuda", "python"]
+        );
+        let osx_deps = gpu.dependencies(None, Some(Platform::OsxArm64));
+        assert_eq!(
+            osx_deps.names().map(|n| n.as_source()).collect_vec(),
+            vec!["python"]
+        );
+        assert_eq!(
+            gpu.pypi_dependencies(Some(Platform::Linux64)).names().len(),
+            1
+        );
+        assert!(gpu.pypi_dependencies(Some(Platform::OsxArm64)).is_empty());
+
+        // The channels and the activation of the feature are omitted as well.
+        let channel_names = |platform| {
+            gpu.platform_channels(Some(platform))
+                .into_iter()
+                .map(|c| c.name().to_string())
+                .collect_vec()
+        };
+        assert_eq!(
+            channel_names(Platform::Linux64),
+            vec!["nvidia", "conda-forge"]
+        );
+        assert_eq!(channel_names(Platform::OsxArm64), vec!["conda-forge"]);
+        assert_eq!(gpu.channels().len(), 2);
+        assert_eq!(
+            gpu.activation_scripts(Some(Platform::Linux64)),
+            vec!["cuda.sh"]
+        );
+        assert!(gpu.activation_scripts(Some(Platform::OsxArm64)).is_empty());
+        assert!(gpu
+            .activation_env(Some(Platform::Linux64))
+            .contains_key("CUDA_HOME"));
+        assert!(gpu.activation_env(Some(Platform::OsxArm64)).is_empty());
+
+        // Without opting in the feature restricts the platforms of the environment.
+        let restricted = manifest.environment("restricted").unwrap();
+        assert_eq!(restricted.platforms(), HashSet::from([Platform::Linux64]));
+    }
 }
@@ -3,13 +3,13 @@ use crate::{
     consts,
     prefix::Prefix,
     project::{
-        manifest::SystemRequirements, virtual_packages::get_minimal_virtual_packages, Environment,
+        manifest::SystemRequirements, virtual_packages::get_virtual_packages, Environment,
         SolveGroup,
     },
     EnvironmentName, Project,
 };
-use itertools::Either;
-use rattler_conda_types::{GenericVirtualPackage, Platform};
+use itertools::{Either, Itertools};
+use rattler_conda_types::{GenericVirtualPackage, PackageName, Platform};
 use std::path::PathBuf;
 
 use super::has_features::HasFeatures;
@@ -103,9 +103,17 @@ impl<'p> GroupedEnvironment<'p> {
         }
     }
 
+    /// Returns the conda packages that are excluded from any of the environments in the group.
+    pub fn excludes(&self) -> Vec<PackageName> {
+        self.environments()
+            .flat_map(|env| env.excludes().iter().cloned())
+            .unique()
+            .collect()
+    }
+
     /// Returns the virtual packages from the group based on the system requirements.
     pub fn virtual_packages(&self, platform: Platform) -> Vec<GenericVirtualPackage> {
-        get_minimal_virtual_packages(platform, &self.system_requirements())
+        get_virtual_packages(platform, &self.system_requirements())
             .into_iter()
             .map(GenericVirtualPackage::from)
             .collect()
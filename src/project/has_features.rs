@@ -25,10 +25,18 @@ pub trait HasFeatures<'p> {
     /// If a feature does not specify any channel the default channels from the project metadata are
     /// used instead.
     fn channels(&self) -> IndexSet<&'p Channel> {
+        self.platform_channels(None)
+    }
+
+    /// Returns the channels that are used on the given `platform`, which are the
+    /// [`Self::channels`] without the channels of the features that omit themselves on the
+    /// platform.
+    fn platform_channels(&self, platform: Option<Platform>) -> IndexSet<&'p Channel> {
         // Collect all the channels from the features in one set,
         // deduplicate them and sort them on feature index, default feature comes last.
         let channels: IndexSet<_> = self
             .features()
+            .filter(|feature| feature.is_available_on(platform))
             .flat_map(|feature| match &feature.channels {
                 Some(channels) => channels,
                 None => &self.project().manifest.parsed.project.channels,
@@ -54,13 +62,15 @@ pub trait HasFeatures<'p> {
     /// intersection of the platforms supported by its features.
     ///
     /// Features can specify which platforms they support through the `platforms` key. If a feature
-    /// does not specify any platforms the features defined by the project are used.
+    /// does not specify any platforms the features defined by the project are used. The same
+    /// holds for features that set `omit-on-other-platforms`, their dependencies are omitted on the
+    /// platforms they do not support instead.
     fn platforms(&self) -> HashSet<Platform> {
         self.features()
             .map(|feature| {
                 match &feature.platforms {
-                    Some(platforms) => &platforms.value,
-                    None => &self.project().manifest.parsed.project.platforms.value,
+                    Some(platforms) if !feature.omit_on_other_platforms => &platforms.value,
+                    _ => &self.project().manifest.parsed.project.platforms.value,
                 }
                 .iter()
                 .copied()
@@ -98,6 +108,7 @@ pub trait HasFeatures<'p> {
     /// requirements per package are sorted in the same order as the features they came from.
     fn pypi_dependencies(&self, platform: Option<Platform>) -> PyPiDependencies {
         self.features()
+            .filter(|f| f.is_available_on(platform))
             .filter_map(|f| f.pypi_dependencies(platform))
             .into()
     }
@@ -113,6 +124,7 @@ pub trait HasFeatures<'p> {
         platform: Option<Platform>,
     ) -> CondaDependencies {
         self.features()
+            .filter(|f| f.is_available_on(platform))
             .filter_map(|f| f.dependencies(kind, platform))
             .into()
     }
@@ -1,7 +1,11 @@
+use indexmap::IndexMap;
 use serde::Deserialize;
 
 #[derive(Default, Clone, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Activation {
     pub scripts: Option<Vec<String>>,
+    /// Environment variables that are set when the environment is activated. The values can
+    /// reference `$CONDA_PREFIX` and `$PIXI_PROJECT_ROOT`.
+    pub env: Option<IndexMap<String, String>>,
 }
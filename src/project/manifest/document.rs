@@ -1,10 +1,10 @@
 use rattler_conda_types::{NamelessMatchSpec, PackageName, Platform};
 use std::{fmt, str::FromStr};
-use toml_edit::{value, Array, InlineTable, Item, Table, Value};
+use toml_edit::{value, Array, InlineTable, Item, Table, TableLike, Value};
 
 use crate::{consts, util::default_channel_config, FeatureName, SpecType, Task};
 
-use super::{error::TomlError, python::PyPiPackageName, PyPiRequirement};
+use super::{error::TomlError, python::PyPiPackageName, DistributionFormat, PyPiRequirement};
 
 const PYPROJECT_PIXI_PREFIX: &str = "tool.pixi";
 
@@ -198,6 +198,29 @@ impl ManifestSource {
         Ok(())
     }
 
+    /// Sorts the keys of a dependency table alphabetically. Comments are attached to the keys
+    /// they precede or follow, so they move along with them.
+    ///
+    /// It will be a no-op if the table does not exist.
+    pub fn sort_dependencies(
+        &mut self,
+        table_name: &str,
+        platform: Option<Platform>,
+        feature_name: &FeatureName,
+    ) -> Result<(), TomlError> {
+        let table_name = self.get_nested_toml_table_name(feature_name, platform, Some(table_name));
+        let mut current = Some(self.as_table_mut() as &mut dyn TableLike);
+        for part in table_name.split('.') {
+            current = current
+                .and_then(|table| table.get_mut(part))
+                .and_then(|item| item.as_table_like_mut());
+        }
+        if let Some(table) = current {
+            table.sort_values();
+        }
+        Ok(())
+    }
+
     /// Adds a conda dependency to the TOML manifest
     ///
     /// If a dependency with the same name already exists, it will be replaced.
@@ -211,9 +234,10 @@ impl ManifestSource {
     ) -> Result<(), TomlError> {
         let dependency_table =
             self.get_or_insert_toml_table(platform, feature_name, spec_type.name())?;
-        dependency_table.insert(
+        update_dependency_in_place(
+            dependency_table,
             name.as_normalized(),
-            Item::Value(nameless_match_spec_to_toml(spec)),
+            nameless_match_spec_to_toml(spec),
         );
         Ok(())
     }
@@ -226,8 +250,29 @@ impl ManifestSource {
         requirement: &pep508_rs::Requirement,
         platform: Option<Platform>,
         feature_name: &FeatureName,
+        allow_prerelease: bool,
+        format: Option<DistributionFormat>,
     ) -> Result<(), TomlError> {
+        let pypi_requirement = PyPiRequirement::from(requirement.clone())
+            .with_prerelease(allow_prerelease)
+            .with_format(format);
         match self {
+            // Pre-releases and distribution formats can't be expressed in a pep508 requirement,
+            // so these are stored in the pixi specific pypi dependencies table instead.
+            ManifestSource::PyProjectToml(_)
+                if pypi_requirement.allows_prerelease() || pypi_requirement.format().is_some() =>
+            {
+                self.remove_pypi_dependency(
+                    &PyPiPackageName::from_normalized(requirement.name.clone()),
+                    platform,
+                    feature_name,
+                )?;
+                self.get_or_insert_toml_table(platform, feature_name, consts::PYPI_DEPENDENCIES)?
+                    .insert(
+                        requirement.name.as_ref(),
+                        Item::Value(pypi_requirement.into()),
+                    );
+            }
             ManifestSource::PyProjectToml(_) => {
                 // Pypi dependencies can be stored in different places
                 // so we remove any potential dependency of the same name before adding it back
@@ -248,7 +293,7 @@ impl ManifestSource {
                 self.get_or_insert_toml_table(platform, feature_name, consts::PYPI_DEPENDENCIES)?
                     .insert(
                         requirement.name.as_ref(),
-                        Item::Value(PyPiRequirement::from(requirement.clone()).into()),
+                        Item::Value(pypi_requirement.into()),
                     );
             }
         };
@@ -285,6 +330,26 @@ impl ManifestSource {
         Ok(())
     }
 
+    /// Moves a task from the tasks table of one feature to another. The TOML item is moved as is,
+    /// so the formatting of the task definition is preserved.
+    pub fn move_task(
+        &mut self,
+        name: &str,
+        platform: Option<Platform>,
+        from: &FeatureName,
+        to: &FeatureName,
+    ) -> Result<(), TomlError> {
+        let item = self
+            .get_or_insert_toml_table(platform, from, "tasks")?
+            .remove(name);
+        if let Some(item) = item {
+            self.get_or_insert_toml_table(platform, to, "tasks")?
+                .insert(name, item);
+        }
+
+        Ok(())
+    }
+
     /// Sets the description of the project
     pub fn set_description(&mut self, description: &str) {
         self.as_table_mut()["project"]["description"] = value(description);
@@ -294,6 +359,77 @@ impl ManifestSource {
     pub fn set_version(&mut self, version: &str) {
         self.as_table_mut()["project"]["version"] = value(version);
     }
+
+    /// Adds an environment that is made up of the given features to the TOML manifest
+    pub fn add_environment(&mut self, name: &str, features: &[String]) -> Result<(), TomlError> {
+        let table_name =
+            self.get_nested_toml_table_name(&FeatureName::Default, None, Some("environments"));
+        self.get_or_insert_nested_table(&table_name)?
+            .insert(name, value(Array::from_iter(features)));
+        Ok(())
+    }
+
+    /// Adds a feature to an environment in the `[environments]` table, the environment can be
+    /// defined as an array of features or as a table with a `features` array.
+    pub fn add_environment_feature(&mut self, name: &str, feature: &str) -> Result<(), TomlError> {
+        let table_name =
+            self.get_nested_toml_table_name(&FeatureName::Default, None, Some("environments"));
+        let item = self
+            .get_or_insert_nested_table(&table_name)?
+            .get_mut(name)
+            .ok_or_else(|| TomlError::table_error(name, &table_name))?;
+        let features = if item.is_array() {
+            item.as_array_mut()
+        } else {
+            item.as_table_like_mut().and_then(|environment| {
+                environment
+                    .entry("features")
+                    .or_insert(Item::Value(Value::Array(Array::new())))
+                    .as_array_mut()
+            })
+        };
+        features
+            .ok_or_else(|| TomlError::array_error("features", &format!("{table_name}.{name}")))?
+            .push(feature);
+        Ok(())
+    }
+}
+
+/// Sets the value of a dependency in a dependency table. An existing entry is updated in place so
+/// its position, the comments around it and the formatting of the fields that did not change are
+/// preserved.
+fn update_dependency_in_place(table: &mut Table, name: &str, value: Value) {
+    let key = table
+        .iter()
+        .map(|(key, _)| key)
+        .find(|key| key.to_lowercase() == name)
+        .map(ToString::to_string);
+    let Some(Item::Value(existing)) = key.and_then(|key| table.get_mut(&key)) else {
+        table.insert(name, Item::Value(value));
+        return;
+    };
+
+    match (existing, value) {
+        (Value::InlineTable(existing), Value::InlineTable(new)) => {
+            existing.retain(|key, _| new.contains_key(key));
+            for (key, value) in new.iter() {
+                match existing.get_mut(key) {
+                    Some(existing_value) => {
+                        let decor = existing_value.decor().clone();
+                        *existing_value = value.clone();
+                        *existing_value.decor_mut() = decor;
+                    }
+                    None => {
+                        existing.insert(key, value.clone());
+                    }
+                }
+            }
+        }
+        (existing, mut value) => {
+            *value.decor_mut() = existing.decor().clone();
+            *existing = value;
+        }
+    }
 }
 
 /// Given a nameless matchspec convert it into a TOML value. If the spec only contains a version a
@@ -2,6 +2,7 @@ use crate::consts;
 use crate::utils::spanned::PixiSpanned;
 use lazy_static::lazy_static;
 use miette::Diagnostic;
+use rattler_conda_types::PackageName;
 use regex::Regex;
 use serde::{self, Deserialize, Deserializer};
 use serde_with::SerializeDisplay;
@@ -145,6 +146,10 @@ pub struct Environment {
 
     /// Whether to include the default feature in that environment
     pub no_default_feature: bool,
+
+    /// The conda packages that must never be part of this environment, even if a dependency
+    /// requires them.
+    pub excludes: Vec<PackageName>,
 }
 
 impl Default for Environment {
@@ -155,6 +160,7 @@ impl Default for Environment {
             features_source_loc: None,
             solve_group: None,
             no_default_feature: false,
+            excludes: Vec::new(),
         }
     }
 }
@@ -169,6 +175,8 @@ pub(super) struct TomlEnvironment {
     pub solve_group: Option<String>,
     #[serde(default)]
     pub no_default_feature: bool,
+    #[serde(default)]
+    pub exclude: Vec<PackageName>,
 }
 
 pub(super) enum TomlEnvironmentMapOrSeq {
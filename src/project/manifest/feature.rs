@@ -119,6 +119,11 @@ pub struct Feature {
     /// platforms from the project should be used.
     pub platforms: Option<PixiSpanned<IndexSet<Platform>>>,
 
+    /// Whether the dependencies of this feature should be omitted on platforms that are not part
+    /// of [`Self::platforms`] instead of restricting the platforms of the environments that
+    /// include this feature.
+    pub omit_on_other_platforms: bool,
+
     /// Channels specific to this feature.
     ///
     /// This value is `None` if this feature does not specify any channels and the default
@@ -141,6 +146,7 @@ impl Feature {
         Feature {
             name,
             platforms: None,
+            omit_on_other_platforms: false,
             channels: None,
             system_requirements: SystemRequirements::default(),
             pypi_options: None,
@@ -160,6 +166,19 @@ impl Feature {
             .get_mut()
     }
 
+    /// Returns true if this feature contributes to an environment on the given `platform`.
+    ///
+    /// A feature is only unavailable if it opted into omitting itself on platforms it does not
+    /// list in its `platforms`.
+    pub fn is_available_on(&self, platform: Option<Platform>) -> bool {
+        match (&self.platforms, platform) {
+            (Some(platforms), Some(platform)) if self.omit_on_other_platforms => {
+                platforms.value.contains(&platform)
+            }
+            _ => true,
+        }
+    }
+
     /// Returns a mutable reference to the channels of the feature. Create them if needed
     pub fn channels_mut(&mut self) -> &mut IndexSet<PrioritizedChannel> {
         self.channels.get_or_insert_with(Default::default)
@@ -242,6 +261,20 @@ impl Feature {
             .next()
     }
 
+    /// Returns the activation environment variables of all the targets that match the given
+    /// `platform`. Variables of a more specific target override those of a less specific target.
+    pub fn activation_env(&self, platform: Option<Platform>) -> IndexMap<String, String> {
+        let targets: Vec<_> = self.targets.resolve(platform).collect();
+        targets
+            .into_iter()
+            .rev()
+            .filter_map(|t| t.activation.as_ref())
+            .filter_map(|a| a.env.as_ref())
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
     /// Returns true if the feature contains any reference to a pypi dependencies.
     pub fn has_pypi_dependencies(&self) -> bool {
         self.targets
@@ -267,6 +300,8 @@ impl<'de> Deserialize<'de> for Feature {
             #[serde(default)]
             platforms: Option<PixiSpanned<IndexSet<Platform>>>,
             #[serde(default)]
+            omit_on_other_platforms: bool,
+            #[serde(default)]
             channels: Option<Vec<TomlPrioritizedChannelStrOrMap>>,
             #[serde(default)]
             system_requirements: SystemRequirements,
@@ -317,6 +352,7 @@ impl<'de> Deserialize<'de> for Feature {
         Ok(Feature {
             name: FeatureName::Default,
             platforms: inner.platforms,
+            omit_on_other_platforms: inner.omit_on_other_platforms,
             channels: inner.channels.map(|channels| {
                 channels
                     .into_iter()
@@ -438,6 +474,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_activation_env() {
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = ["linux-64", "osx-64", "win-64"]
+        channels = []
+
+        [activation]
+        env = { DATA_DIR = "$PIXI_PROJECT_ROOT/data", LEVEL = "info" }
+
+        [target.linux-64.activation]
+        env = { LEVEL = "debug" }
+        "#,
+        )
+        .unwrap();
+
+        let env = manifest.default_feature().activation_env(None);
+        assert_eq!(env.get("DATA_DIR").unwrap(), "$PIXI_PROJECT_ROOT/data");
+        assert_eq!(env.get("LEVEL").unwrap(), "info");
+
+        let env = manifest
+            .default_feature()
+            .activation_env(Some(Platform::Linux64));
+        assert_eq!(env.get("DATA_DIR").unwrap(), "$PIXI_PROJECT_ROOT/data");
+        assert_eq!(
+            env.get("LEVEL").unwrap(),
+            "debug",
+            "the [linux-64] section should override the [activation] section"
+        );
+    }
+
     #[test]
     pub fn test_pypi_options_manifest() {
         let manifest = Manifest::from_str(
@@ -1,9 +1,9 @@
 use crate::utils::spanned::PixiSpanned;
 use indexmap::IndexSet;
-use rattler_conda_types::{Platform, Version};
+use rattler_conda_types::{PackageRecord, Platform, Version};
 use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, fmt, path::PathBuf};
 use url::Url;
 
 /// Describes the contents of the `[package]` section of the project manifest.
@@ -54,4 +54,94 @@ pub struct ProjectMetadata {
 
     /// URL or Path of the conda to pypi name mapping
     pub conda_pypi_map: Option<HashMap<String, String>>,
+
+    /// Conda features (e.g. `mkl` or `nomkl`) that are preferred by the solver. Package variants
+    /// that provide one of these features are selected over variants that do not.
+    #[serde(default)]
+    pub preferred_features: Vec<String>,
+
+    /// Conda features (e.g. `mkl`) that are avoided by the solver. Package variants that provide
+    /// one of these features are only selected if no other variant fits.
+    #[serde(default)]
+    pub avoided_features: Vec<String>,
+
+    /// Files (relative to the project root) that contain a matchspec per line. The matchspecs
+    /// bound the versions of packages without adding them as dependencies.
+    #[serde(default)]
+    pub constraints: Vec<PathBuf>,
+
+    /// Licenses (e.g. `GPL-3.0` or the `GPL` license family) of conda packages that must not be
+    /// selected by the solver.
+    #[serde(default)]
+    pub disallowed_licenses: Vec<String>,
+
+    /// The implementation of python (e.g. `pypy`) that the solver selects for the environments.
+    pub python_implementation: Option<PythonImplementation>,
+
+    /// The minimum python version that the selected `noarch: python` packages have to support.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub noarch_python_min: Option<Version>,
+
+    /// The proxy to use for all http(s) requests of the project. This takes precedence over the
+    /// `HTTPS_PROXY` and `HTTP_PROXY` environment variables, hosts in `NO_PROXY` are still
+    /// requested directly.
+    pub proxy: Option<Url>,
+
+    /// Write a `pixi.<environment>.lock` file per environment instead of a single `pixi.lock`.
+    #[serde(default)]
+    pub lockfile_per_environment: bool,
+
+    /// A task that is run before the tasks of every `pixi run`.
+    pub pre_run_task: Option<String>,
+
+    /// A task that is run after the tasks of every `pixi run`, also when one of them failed.
+    pub post_run_task: Option<String>,
+
+    /// A command that is run in the environment after `pixi install` to verify the installation.
+    pub post_install_check: Option<String>,
+
+    /// Sort the dependency tables alphabetically when adding dependencies with `pixi add`.
+    #[serde(default)]
+    pub sort_dependencies: bool,
+
+    /// Set `PYTHONHASHSEED`, `SOURCE_DATE_EPOCH` and `TZ` to fixed values in the environment of
+    /// the tasks that are run with `pixi run`.
+    #[serde(default)]
+    pub reproducible_env: bool,
+}
+
+/// The implementation of python that is selected by the solver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PythonImplementation {
+    CPython,
+    PyPy,
+}
+
+impl PythonImplementation {
+    /// The packages of which the builds differ per python implementation.
+    const PACKAGES: [&'static str; 2] = ["python", "python_abi"];
+
+    /// Returns true if the record can be used with this implementation. Only the builds of
+    /// `python` and `python_abi` are specific to an implementation, the builds for PyPy are
+    /// recognized by `pypy` in their build string.
+    pub fn matches(&self, record: &PackageRecord) -> bool {
+        if !Self::PACKAGES.contains(&record.name.as_normalized()) {
+            return true;
+        }
+        let is_pypy = record.build.contains("pypy");
+        match self {
+            PythonImplementation::CPython => !is_pypy,
+            PythonImplementation::PyPy => is_pypy,
+        }
+    }
+}
+
+impl fmt::Display for PythonImplementation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PythonImplementation::CPython => write!(f, "cpython"),
+            PythonImplementation::PyPy => write!(f, "pypy"),
+        }
+    }
 }
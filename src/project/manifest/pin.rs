@@ -0,0 +1,49 @@
+use rattler_conda_types::{ParseStrictness, VersionSpec};
+use serde::{de::Error, Deserialize, Deserializer};
+
+/// A version of a conda package that is pinned for the whole project in the `[pins]` table.
+///
+/// A pin is either a version spec, e.g. `numpy = "1.26.4"`, or a table with an optional reason
+/// for the pin, e.g. `numpy = { version = "1.26.4", reason = "ABI compat with X" }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    /// The versions of the package that are allowed.
+    pub version: VersionSpec,
+
+    /// Why the package is pinned.
+    pub reason: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Pin {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct TomlPin {
+            version: String,
+            reason: Option<String>,
+        }
+
+        let parse_version = |version: &str| -> Result<VersionSpec, serde_untagged::de::Error> {
+            VersionSpec::from_str(version, ParseStrictness::Strict).map_err(Error::custom)
+        };
+        serde_untagged::UntaggedEnumVisitor::new()
+            .string(|version| {
+                Ok(Pin {
+                    version: parse_version(version)?,
+                    reason: None,
+                })
+            })
+            .map(|map| {
+                let pin: TomlPin = map.deserialize()?;
+                Ok(Pin {
+                    version: parse_version(&pin.version)?,
+                    reason: pin.reason,
+                })
+            })
+            .expecting("a version or a table with a `version` and a `reason`")
+            .deserialize(deserializer)
+    }
+}
@@ -10,6 +10,9 @@ use url::Url;
 
 use uv_normalize::{ExtraName, InvalidNameError, PackageName};
 
+/// A version specifier that matches every version, including pre-releases.
+const ANY_PRERELEASE_SPECIFIER: &str = ">=0.0.0.dev0";
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 /// A package name for PyPI that also stores the source version of the name.
 pub struct PyPiPackageName {
@@ -141,10 +144,35 @@ pub enum PyPiRequirement {
         version: VersionOrStar,
         #[serde(default)]
         extras: Vec<ExtraName>,
+        /// Whether the resolver should consider pre-release versions of this package.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        prerelease: bool,
+        /// Restricts the distributions of this package that may be used to wheels or sdists.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<DistributionFormat>,
     },
     RawVersion(VersionOrStar),
 }
 
+/// The kind of distribution a pypi package may be installed from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum DistributionFormat {
+    /// Only use built distributions, never build the package from source.
+    Wheel,
+    /// Only use source distributions, always build the package from source.
+    Sdist,
+}
+
+impl fmt::Display for DistributionFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributionFormat::Wheel => write!(f, "wheel"),
+            DistributionFormat::Sdist => write!(f, "sdist"),
+        }
+    }
+}
+
 impl PyPiRequirement {
     /// Returns true if the requirement is a direct dependency.
     /// I.e. a url, path or git requirement.
@@ -156,6 +184,75 @@ impl PyPiRequirement {
                 | PyPiRequirement::Url { .. }
         )
     }
+
+    /// Returns true if pre-release versions of this package may be selected by the resolver.
+    pub fn allows_prerelease(&self) -> bool {
+        matches!(
+            self,
+            PyPiRequirement::Version {
+                prerelease: true,
+                ..
+            }
+        )
+    }
+
+    /// Returns the requirement with pre-releases allowed or disallowed. Only version
+    /// requirements can allow pre-releases, other requirements are returned unchanged.
+    pub fn with_prerelease(self, allow: bool) -> Self {
+        match self {
+            PyPiRequirement::Version {
+                version,
+                extras,
+                format,
+                ..
+            } => PyPiRequirement::Version {
+                version,
+                extras,
+                prerelease: allow,
+                format,
+            },
+            PyPiRequirement::RawVersion(version) if allow => PyPiRequirement::Version {
+                version,
+                extras: Vec::new(),
+                prerelease: true,
+                format: None,
+            },
+            requirement => requirement,
+        }
+    }
+
+    /// Returns the distribution format this package is restricted to, if any.
+    pub fn format(&self) -> Option<DistributionFormat> {
+        match self {
+            PyPiRequirement::Version { format, .. } => *format,
+            _ => None,
+        }
+    }
+
+    /// Returns the requirement restricted to the given distribution format. Only version
+    /// requirements can be restricted, other requirements are returned unchanged.
+    pub fn with_format(self, format: Option<DistributionFormat>) -> Self {
+        match self {
+            PyPiRequirement::Version {
+                version,
+                extras,
+                prerelease,
+                ..
+            } => PyPiRequirement::Version {
+                version,
+                extras,
+                prerelease,
+                format,
+            },
+            PyPiRequirement::RawVersion(version) if format.is_some() => PyPiRequirement::Version {
+                version,
+                extras: Vec::new(),
+                prerelease: false,
+                format,
+            },
+            requirement => requirement,
+        }
+    }
 }
 
 impl Default for PyPiRequirement {
@@ -199,34 +296,70 @@ impl From<PyPiRequirement> for toml_edit::Value {
         }
 
         match &val {
-            PyPiRequirement::Version { version, extras } => {
+            PyPiRequirement::Version {
+                version,
+                extras,
+                prerelease,
+                format,
+            } => {
                 let mut table = toml_edit::Table::new().into_inline_table();
                 table.insert(
                     "version",
                     toml_edit::Value::String(toml_edit::Formatted::new(version.to_string())),
                 );
                 insert_extras(&mut table, extras);
+                if *prerelease {
+                    table.insert("prerelease", toml_edit::Value::from(true));
+                }
+                if let Some(format) = format {
+                    table.insert("format", toml_edit::Value::from(format.to_string()));
+                }
                 toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::Git {
-                git: _,
-                branch: _,
-                tag: _,
-                rev: _,
-                subdirectory: _,
-                extras: _,
+                git,
+                branch,
+                tag,
+                rev,
+                subdirectory,
+                extras,
             } => {
-                todo!("git")
+                let mut table = toml_edit::Table::new().into_inline_table();
+                table.insert("git", toml_edit::Value::from(git.to_string()));
+                for (key, value) in [
+                    ("branch", branch),
+                    ("tag", tag),
+                    ("rev", rev),
+                    ("subdirectory", subdirectory),
+                ] {
+                    if let Some(value) = value {
+                        table.insert(key, toml_edit::Value::from(value.clone()));
+                    }
+                }
+                insert_extras(&mut table, extras);
+                toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::Path {
-                path: _,
-                editable: _,
-                extras: _,
+                path,
+                editable,
+                extras,
             } => {
-                todo!("path")
+                let mut table = toml_edit::Table::new().into_inline_table();
+                table.insert(
+                    "path",
+                    toml_edit::Value::from(path.to_string_lossy().into_owned()),
+                );
+                if let Some(editable) = editable {
+                    table.insert("editable", toml_edit::Value::from(*editable));
+                }
+                insert_extras(&mut table, extras);
+                toml_edit::Value::InlineTable(table.to_owned())
             }
-            PyPiRequirement::Url { url: _, extras: _ } => {
-                unimplemented!("url")
+            PyPiRequirement::Url { url, extras } => {
+                let mut table = toml_edit::Table::new().into_inline_table();
+                table.insert("url", toml_edit::Value::from(url.to_string()));
+                insert_extras(&mut table, extras);
+                toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::RawVersion(version) => {
                 toml_edit::Value::String(toml_edit::Formatted::new(version.to_string()))
@@ -243,12 +376,16 @@ impl From<pep508_rs::Requirement> for PyPiRequirement {
                 pep508_rs::VersionOrUrl::VersionSpecifier(v) => PyPiRequirement::Version {
                     version: VersionOrStar::Version(v),
                     extras: req.extras,
+                    prerelease: false,
+                    format: None,
                 },
                 pep508_rs::VersionOrUrl::Url(u) => {
                     let url = u.to_url();
                     // Have a different code path when the url is a file.
                     // i.e. package @ file:///path/to/package
-                    if url.scheme() == "file" {
+                    if let Some(requirement) = git_requirement(&url, &req.extras) {
+                        requirement
+                    } else if url.scheme() == "file" {
                         // Convert the file url to a path.
                         let file = url
                             .to_file_path()
@@ -270,6 +407,8 @@ impl From<pep508_rs::Requirement> for PyPiRequirement {
             PyPiRequirement::Version {
                 version: VersionOrStar::Star,
                 extras: req.extras,
+                prerelease: false,
+                format: None,
             }
         } else {
             PyPiRequirement::RawVersion(VersionOrStar::Star)
@@ -277,6 +416,37 @@ impl From<pep508_rs::Requirement> for PyPiRequirement {
     }
 }
 
+/// Returns the git requirement of a `git+` url, e.g.
+/// `git+https://github.com/org/repo.git@v1.0#subdirectory=python`, or `None` if the url is not a
+/// git url.
+fn git_requirement(url: &Url, extras: &[ExtraName]) -> Option<PyPiRequirement> {
+    let subdirectory = url
+        .fragment()
+        .and_then(|fragment| {
+            fragment
+                .split('&')
+                .find_map(|part| part.strip_prefix("subdirectory="))
+        })
+        .map(String::from);
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let git = url.as_str().strip_prefix("git+")?;
+
+    // The revision follows the last `@` of the path, an `@` before it belongs to the user info.
+    let (git, rev) = match git.rsplit_once('@') {
+        Some((repository, rev)) if !rev.contains('/') => (repository, Some(rev.to_string())),
+        _ => (git, None),
+    };
+    Some(PyPiRequirement::Git {
+        git: git.parse().ok()?,
+        branch: None,
+        tag: None,
+        rev,
+        subdirectory,
+        extras: extras.to_vec(),
+    })
+}
+
 /// Create a url that uv can use to install a version
 fn create_uv_url(
     url: &Url,
@@ -401,7 +571,33 @@ impl PyPiRequirement {
         project_root: &Path,
     ) -> Result<RequirementOrEditable, AsPep508Error> {
         let version_or_url = match self {
-            PyPiRequirement::Version { version, extras: _ } => version.clone().into(),
+            PyPiRequirement::Version {
+                version,
+                prerelease,
+                ..
+            } => {
+                let version_or_url: Option<pep508_rs::VersionOrUrl> = version.clone().into();
+                if *prerelease {
+                    // The resolver considers pre-releases for a package when one of its direct
+                    // requirements explicitly mentions a pre-release. Add a specifier that
+                    // matches every version, including pre-releases, to opt this package in.
+                    let specifiers = match version_or_url {
+                        Some(pep508_rs::VersionOrUrl::VersionSpecifier(specifiers))
+                            if !specifiers.is_empty() =>
+                        {
+                            format!("{specifiers},{ANY_PRERELEASE_SPECIFIER}")
+                        }
+                        _ => ANY_PRERELEASE_SPECIFIER.to_string(),
+                    };
+                    Some(pep508_rs::VersionOrUrl::VersionSpecifier(
+                        VersionSpecifiers::from_str(&specifiers).expect(
+                            "appending the pre-release specifier keeps the specifiers valid",
+                        ),
+                    ))
+                } else {
+                    version_or_url
+                }
+            }
             PyPiRequirement::Path {
                 path,
                 editable,
@@ -473,6 +669,87 @@ impl PyPiRequirement {
     }
 }
 
+/// A line of a `requirements.txt` file that is not added as a pypi dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRequirementLine {
+    /// The line number, starting at 1.
+    pub line: usize,
+    pub content: String,
+    pub reason: &'static str,
+}
+
+/// The lines of a `requirements.txt` file, split into PEP 508 requirements and unsupported lines.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RequirementsTxtLines {
+    /// The requirements together with their line number, starting at 1.
+    pub requirements: Vec<(usize, String)>,
+    pub skipped: Vec<SkippedRequirementLine>,
+}
+
+/// Splits the contents of a `requirements.txt` file into PEP 508 requirements. Comments, blank
+/// lines and line continuations are handled. Pip options like `-e`, `-r` and `--index-url` are
+/// skipped, options of a single requirement like `--hash` are dropped from the requirement.
+pub fn parse_requirements_txt(contents: &str) -> RequirementsTxtLines {
+    let mut result = RequirementsTxtLines::default();
+    let mut lines = contents.lines().enumerate();
+    while let Some((idx, line)) = lines.next() {
+        let line_number = idx + 1;
+
+        // Join the lines that end with a backslash.
+        let mut line = line.to_string();
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => line.push_str(next),
+                None => break,
+            }
+        }
+
+        // Strip comments, a `#` only starts a comment at the start or after whitespace.
+        let content = match line
+            .char_indices()
+            .find(|(i, c)| *c == '#' && (*i == 0 || line[..*i].ends_with(char::is_whitespace)))
+        {
+            Some((i, _)) => &line[..i],
+            None => line.as_str(),
+        }
+        .trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let skip = |reason| SkippedRequirementLine {
+            line: line_number,
+            content: content.to_string(),
+            reason,
+        };
+        if content.starts_with('-') {
+            let option = content.split([' ', '=']).next().unwrap_or_default();
+            result.skipped.push(skip(match option {
+                "-e" | "--editable" => "editable requirements are not supported",
+                "-r" | "--requirement" => "included requirement files are not supported",
+                "-c" | "--constraint" => "constraint files are not supported",
+                _ => "pip options are not supported",
+            }));
+            continue;
+        }
+
+        let requirement = match content.split_once(" --") {
+            Some((requirement, _)) => {
+                result.skipped.push(skip(
+                    "options like `--hash` are not supported, the requirement is added without them",
+                ));
+                requirement.trim_end()
+            }
+            None => content,
+        };
+        result
+            .requirements
+            .push((line_number, requirement.to_string()));
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -481,6 +758,47 @@ mod tests {
     use pep508_rs::Requirement;
     use std::str::FromStr;
 
+    #[test]
+    fn test_parse_requirements_txt() {
+        let contents = r#"
+# The dependencies of the project
+requests>=2.31 # http
+numpy==1.26.4 \
+    ; python_version >= "3.9"
+-e ./local
+-r dev-requirements.txt
+--index-url https://pypi.org/simple
+flask==3.0.0 --hash=sha256:abc
+pkg@https://example.com/pkg#egg=pkg
+"#;
+        let lines = parse_requirements_txt(contents);
+        assert_eq!(
+            lines.requirements,
+            vec![
+                (3, String::from("requests>=2.31")),
+                (
+                    4,
+                    String::from("numpy==1.26.4     ; python_version >= \"3.9\"")
+                ),
+                (9, String::from("flask==3.0.0")),
+                (10, String::from("pkg@https://example.com/pkg#egg=pkg")),
+            ]
+        );
+        assert_eq!(
+            lines
+                .skipped
+                .iter()
+                .map(|skipped| (skipped.line, skipped.content.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (6, "-e ./local"),
+                (7, "-r dev-requirements.txt"),
+                (8, "--index-url https://pypi.org/simple"),
+                (9, "flask==3.0.0 --hash=sha256:abc"),
+            ]
+        );
+    }
+
     #[test]
     fn test_pypi_to_string() {
         let req = pep508_rs::Requirement::from_str("numpy[testing]==1.0.0; os_name == \"posix\"")
@@ -496,6 +814,31 @@ mod tests {
         assert_eq!(pypi.to_string(), "\"*\"");
     }
 
+    #[test]
+    fn test_direct_reference_to_string() {
+        let to_string = |requirement: &str| {
+            PyPiRequirement::from(pep508_rs::Requirement::from_str(requirement).unwrap())
+                .to_string()
+        };
+        assert_eq!(
+            to_string("pkg[cli] @ https://example.com/pkg-1.0-py3-none-any.whl"),
+            "{ url = \"https://example.com/pkg-1.0-py3-none-any.whl\", extras = [\"cli\"] }"
+        );
+        assert_eq!(
+            to_string("pkg @ git+https://github.com/org/pkg.git@v1.0#subdirectory=python"),
+            "{ git = \"https://github.com/org/pkg.git\", rev = \"v1.0\", subdirectory = \"python\" }"
+        );
+        assert_eq!(
+            to_string("pkg @ git+ssh://git@github.com/org/pkg.git"),
+            "{ git = \"ssh://git@github.com/org/pkg.git\" }"
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            to_string("pkg @ file:///tmp/pkg"),
+            "{ path = \"/tmp/pkg\" }"
+        );
+    }
+
     #[test]
     fn test_only_version() {
         let requirement: IndexMap<uv_normalize::PackageName, PyPiRequirement> =
@@ -547,6 +890,8 @@ mod tests {
             &PyPiRequirement::Version {
                 version: ">=3.12".parse().unwrap(),
                 extras: vec![ExtraName::from_str("bar").unwrap()],
+                prerelease: false,
+                format: None,
             }
         );
 
@@ -567,8 +912,71 @@ mod tests {
                     ExtraName::from_str("bar").unwrap(),
                     ExtraName::from_str("foo").unwrap(),
                 ],
+                prerelease: false,
+                format: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_prerelease() {
+        let requirement: IndexMap<uv_normalize::PackageName, PyPiRequirement> =
+            toml_edit::de::from_str(r#"foo = { version = ">=1.0", prerelease = true }"#).unwrap();
+        let (name, requirement) = requirement.first().unwrap();
+        assert_eq!(
+            requirement,
+            &PyPiRequirement::Version {
+                version: ">=1.0".parse().unwrap(),
+                extras: vec![],
+                prerelease: true,
+                format: None,
             }
         );
+        assert_eq!(
+            requirement.to_string(),
+            r#"{ version = ">=1.0", prerelease = true }"#
+        );
+
+        let pep508 = requirement
+            .as_pep508(name, Path::new(""))
+            .unwrap()
+            .into_requirement()
+            .unwrap();
+        assert_eq!(pep508.to_string(), "foo>=1.0, >=0.0.0.dev0");
+
+        // Allowing pre-releases on a plain version turns it into a table.
+        let requirement = PyPiRequirement::default().with_prerelease(true);
+        assert!(requirement.allows_prerelease());
+        assert_eq!(
+            requirement.to_string(),
+            r#"{ version = "*", prerelease = true }"#
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let requirement: IndexMap<uv_normalize::PackageName, PyPiRequirement> =
+            toml_edit::de::from_str(r#"foo = { version = ">=1.0", format = "wheel" }"#).unwrap();
+        let requirement = requirement.first().unwrap().1;
+        assert_eq!(requirement.format(), Some(DistributionFormat::Wheel));
+        assert_eq!(
+            requirement.to_string(),
+            r#"{ version = ">=1.0", format = "wheel" }"#
+        );
+
+        // Restricting a plain version turns it into a table.
+        let requirement = PyPiRequirement::default().with_format(Some(DistributionFormat::Sdist));
+        assert_eq!(
+            requirement.to_string(),
+            r#"{ version = "*", format = "sdist" }"#
+        );
+
+        assert!(
+            toml_edit::de::from_str::<IndexMap<uv_normalize::PackageName, PyPiRequirement>>(
+                r#"foo = { version = ">=1.0", format = "egg" }"#
+            )
+            .is_err()
+        );
     }
 
     #[test]
@@ -590,6 +998,8 @@ mod tests {
                     ExtraName::from_str("feature1").unwrap(),
                     ExtraName::from_str("feature2").unwrap()
                 ],
+                prerelease: false,
+                format: None,
             }
         );
     }
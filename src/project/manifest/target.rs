@@ -3,7 +3,10 @@ use crate::project::manifest::python::PyPiPackageName;
 use crate::task::TaskName;
 use crate::utils::spanned::PixiSpanned;
 use crate::{
-    project::{manifest::PyPiRequirement, SpecType},
+    project::{
+        manifest::{DistributionFormat, PyPiRequirement},
+        SpecType,
+    },
     task::Task,
 };
 use indexmap::map::Entry;
@@ -157,23 +160,6 @@ impl Target {
             .insert(dep_name.clone(), spec.clone());
     }
 
-    /// Adds a dependency to a target
-    ///
-    /// This will return an error if the exact same dependency already exist
-    /// This will overwrite any existing dependency of the same name
-    pub fn try_add_dependency(
-        &mut self,
-        dep_name: &PackageName,
-        spec: &NamelessMatchSpec,
-        spec_type: SpecType,
-    ) -> Result<(), DependencyError> {
-        if self.has_dependency(dep_name, Some(spec_type), Some(spec)) {
-            return Err(DependencyError::Duplicate(dep_name.as_normalized().into()));
-        }
-        self.add_dependency(dep_name, spec, spec_type);
-        Ok(())
-    }
-
     /// Checks if this target contains a specific pypi dependency
     pub fn has_pypi_dependency(&self, requirement: &pep508_rs::Requirement, exact: bool) -> bool {
         let current_requirement = self
@@ -222,11 +208,23 @@ impl Target {
     pub fn try_add_pypi_dependency(
         &mut self,
         requirement: &pep508_rs::Requirement,
+        allow_prerelease: bool,
+        format: Option<DistributionFormat>,
     ) -> Result<(), DependencyError> {
-        if self.has_pypi_dependency(requirement, true) {
+        let name = PyPiPackageName::from_normalized(requirement.name.clone());
+        let pypi_requirement = PyPiRequirement::from(requirement.clone())
+            .with_prerelease(allow_prerelease)
+            .with_format(format);
+        let current_requirement = self
+            .pypi_dependencies
+            .as_ref()
+            .and_then(|deps| deps.get(&name));
+        if current_requirement == Some(&pypi_requirement) {
             return Err(DependencyError::Duplicate(requirement.name.to_string()));
         }
-        self.add_pypi_dependency(requirement);
+        self.pypi_dependencies
+            .get_or_insert_with(Default::default)
+            .insert(name, pypi_requirement);
         Ok(())
     }
 }
@@ -240,6 +238,8 @@ pub enum TargetSelector {
     Linux,
     Win,
     MacOs,
+    /// Applies to every platform, `all` or `*` in the manifest.
+    All,
     // TODO: Add minijinja coolness here.
 }
 
@@ -252,6 +252,30 @@ impl TargetSelector {
             TargetSelector::Unix => platform.is_unix(),
             TargetSelector::Win => platform.is_windows(),
             TargetSelector::MacOs => platform.is_osx(),
+            TargetSelector::All => true,
+        }
+    }
+
+    /// Returns the platforms from the given platforms that are matched by this selector.
+    pub fn expand(&self, platforms: impl IntoIterator<Item = Platform>) -> Vec<Platform> {
+        platforms
+            .into_iter()
+            .filter(|platform| self.matches(*platform))
+            .collect()
+    }
+}
+
+impl FromStr for TargetSelector {
+    type Err = rattler_conda_types::ParsePlatformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linux" => Ok(TargetSelector::Linux),
+            "unix" => Ok(TargetSelector::Unix),
+            "win" => Ok(TargetSelector::Win),
+            "osx" => Ok(TargetSelector::MacOs),
+            "all" | "*" => Ok(TargetSelector::All),
+            _ => Platform::from_str(s).map(TargetSelector::Platform),
         }
     }
 }
@@ -264,6 +288,7 @@ impl ToString for TargetSelector {
             TargetSelector::Unix => "unix".to_string(),
             TargetSelector::Win => "win".to_string(),
             TargetSelector::MacOs => "osx".to_string(),
+            TargetSelector::All => "all".to_string(),
         }
     }
 }
@@ -280,15 +305,7 @@ impl<'de> Deserialize<'de> for TargetSelector {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        match s.as_str() {
-            "linux" => Ok(TargetSelector::Linux),
-            "unix" => Ok(TargetSelector::Unix),
-            "win" => Ok(TargetSelector::Win),
-            "osx" => Ok(TargetSelector::MacOs),
-            _ => Platform::from_str(&s)
-                .map(TargetSelector::Platform)
-                .map_err(serde::de::Error::custom),
-        }
+        TargetSelector::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
@@ -396,7 +413,7 @@ impl Targets {
     /// Multiple selectors might match for a given platform. This function returns all of them in
     /// order, with the most specific selector first and the default target last.
     ///
-    /// This also always includes the default target.
+    /// This also always includes the default target and the `all` target.
     pub fn resolve(
         &self,
         platform: Option<Platform>,
@@ -404,7 +421,11 @@ impl Targets {
         if let Some(platform) = platform {
             Either::Left(self.resolve_for_platform(platform))
         } else {
-            Either::Right(std::iter::once(&self.default_target))
+            Either::Right(
+                std::iter::once(&self.default_target)
+                    .chain(self.targets.get(&TargetSelector::All))
+                    .rev(),
+            )
         }
     }
 
@@ -421,8 +442,11 @@ impl Targets {
         platform: Platform,
     ) -> impl DoubleEndedIterator<Item = &'_ Target> + '_ {
         std::iter::once(&self.default_target)
+            // The `all` target is less specific than any other selector, regardless of the order
+            // in which the targets are defined.
+            .chain(self.targets.get(&TargetSelector::All))
             .chain(self.targets.iter().filter_map(move |(selector, target)| {
-                if selector.matches(platform) {
+                if selector != &TargetSelector::All && selector.matches(platform) {
                     Some(target)
                 } else {
                     None
@@ -513,10 +537,50 @@ impl Targets {
 
 #[cfg(test)]
 mod tests {
+    use super::TargetSelector;
+    use crate::project::has_features::HasFeatures;
     use crate::Project;
     use insta::assert_snapshot;
     use itertools::Itertools;
+    use rattler_conda_types::Platform;
     use std::path::Path;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_expand_target_selector() {
+        let platforms = [
+            Platform::Linux64,
+            Platform::LinuxAarch64,
+            Platform::Osx64,
+            Platform::OsxArm64,
+            Platform::Win64,
+        ];
+        let expand = |selector: &str| {
+            TargetSelector::from_str(selector)
+                .unwrap()
+                .expand(platforms)
+        };
+
+        assert_eq!(
+            expand("unix"),
+            vec![
+                Platform::Linux64,
+                Platform::LinuxAarch64,
+                Platform::Osx64,
+                Platform::OsxArm64
+            ]
+        );
+        assert_eq!(
+            expand("linux"),
+            vec![Platform::Linux64, Platform::LinuxAarch64]
+        );
+        assert_eq!(expand("osx"), vec![Platform::Osx64, Platform::OsxArm64]);
+        assert_eq!(expand("win"), vec![Platform::Win64]);
+        assert_eq!(expand("osx-arm64"), vec![Platform::OsxArm64]);
+        assert_eq!(expand("all"), platforms.to_vec());
+        assert_eq!(expand("*"), platforms.to_vec());
+        assert!(TargetSelector::from_str("beos").is_err());
+    }
 
     #[test]
     fn test_targets_overwrite_order() {
@@ -558,4 +622,47 @@ mod tests {
         build = ==1.0
         "###);
     }
+
+    #[test]
+    fn test_all_target() {
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "test"
+        channels = []
+        platforms = ["linux-64", "win-64"]
+
+        [target.linux-64.dependencies]
+        bar = "2.0"
+        baz = "1.0"
+
+        [target.all.dependencies]
+        foo = "1.0"
+        bar = "1.0"
+        "#,
+        )
+        .unwrap();
+
+        let dependencies = |platform| {
+            project
+                .default_environment()
+                .dependencies(None, Some(platform))
+                .iter_specs()
+                .map(|(name, spec)| format!("{} = {}", name.as_source(), spec))
+                .sorted()
+                .join("\n")
+        };
+
+        // Concrete targets add to the `all` target and take precedence over it.
+        assert_snapshot!(dependencies(Platform::Linux64), @r###"
+        bar = ==2.0
+        baz = ==1.0
+        foo = ==1.0
+        "###);
+        assert_snapshot!(dependencies(Platform::Win64), @r###"
+        bar = ==1.0
+        foo = ==1.0
+        "###);
+    }
 }
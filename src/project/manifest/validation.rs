@@ -10,6 +10,7 @@ use std::{
 };
 
 use super::pypi_options::PypiOptions;
+use crate::utils::reqwest::strip_credentials;
 
 impl ProjectManifest {
     /// Validate the project manifest.
@@ -66,6 +67,8 @@ impl ProjectManifest {
                             ));
                         }
                     }
+                    // The `all` selector applies to any platform.
+                    TargetSelector::All => {}
                     TargetSelector::Unix => {
                         if !platforms.as_ref().iter().any(|p| p.is_unix()) {
                             return Err(create_unsupported_platform_report(
@@ -121,6 +124,17 @@ impl ProjectManifest {
             Ok(())
         };
 
+        // Only http(s) proxies are supported.
+        if let Some(proxy) = &self.project.proxy {
+            if !matches!(proxy.scheme(), "http" | "https") {
+                miette::bail!(
+                    help = "use an `http://` or `https://` proxy url",
+                    "unsupported proxy scheme '{}' in `project.proxy`",
+                    proxy.scheme()
+                );
+            }
+        }
+
         check_file_existence(&self.project.license_file)?;
         check_file_existence(&self.project.readme)?;
 
@@ -191,6 +205,23 @@ impl ProjectManifest {
             ));
         }
 
+        // Credentials belong in the authentication storage, not in the manifest.
+        if let Some(url) = features
+            .iter()
+            .chain(std::iter::once(&default_feature))
+            .filter_map(|feature| feature.pypi_options())
+            .find_map(|opts| opts.urls_with_credentials().next())
+        {
+            return Err(miette::miette!(
+                help = format!(
+                    "remove the credentials from the url and store them with `pixi auth login {}`",
+                    url.host_str().unwrap_or_default()
+                ),
+                "the pypi index url '{}' contains credentials",
+                strip_credentials(url)
+            ));
+        }
+
         // Check if there are no conflicts in pypi options between features
         features
             .iter()
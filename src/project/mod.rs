@@ -8,10 +8,10 @@ mod solve_group;
 pub mod virtual_packages;
 
 use async_once_cell::OnceCell as AsyncCell;
-use indexmap::{Equivalent, IndexSet};
-use miette::{IntoDiagnostic, NamedSource};
+use indexmap::{Equivalent, IndexMap, IndexSet};
+use miette::{Context, IntoDiagnostic, NamedSource};
 
-use rattler_conda_types::{Channel, Platform, Version};
+use rattler_conda_types::{Channel, MatchSpec, PackageName, ParseStrictness, Platform, Version};
 use reqwest_middleware::ClientWithMiddleware;
 use std::hash::Hash;
 
@@ -34,7 +34,7 @@ use crate::{
     consts::{self, PROJECT_MANIFEST, PYPROJECT_MANIFEST},
     task::Task,
 };
-use manifest::{EnvironmentName, Manifest, SystemRequirements};
+use manifest::{EnvironmentName, Manifest, Pin, PythonImplementation, SystemRequirements};
 
 use self::{
     has_features::HasFeatures,
@@ -105,6 +105,8 @@ pub struct Project {
     env_vars: HashMap<EnvironmentName, Arc<AsyncCell<HashMap<String, String>>>>,
     /// The global configuration as loaded from the config file(s)
     config: Config,
+    /// Custom locations of the prefixes of environments, overriding the default managed location
+    prefix_overrides: HashMap<EnvironmentName, PathBuf>,
 }
 
 impl Debug for Project {
@@ -123,8 +125,9 @@ impl Project {
 
         let root = manifest.path.parent().unwrap_or(Path::new("")).to_owned();
 
-        let config =
-            Config::load(&root.join(consts::PIXI_DIR)).unwrap_or_else(|_| Config::load_global());
+        let config = Config::load(&root.join(consts::PIXI_DIR))
+            .unwrap_or_else(|_| Config::load_global())
+            .with_proxy(manifest.parsed.project.proxy.clone());
 
         let (client, authenticated_client) = build_reqwest_clients(Some(&config));
         Self {
@@ -134,6 +137,7 @@ impl Project {
             manifest,
             env_vars,
             config,
+            prefix_overrides: HashMap::new(),
         }
     }
 
@@ -193,8 +197,24 @@ impl Project {
         NamedSource::new(self.manifest.file_name(), self.manifest.contents.clone())
     }
 
-    /// Loads a project from manifest file.
+    /// Loads a project from manifest file. If the path points to a directory, the manifest in that
+    /// directory is loaded.
     pub fn load(manifest_path: &Path) -> miette::Result<Self> {
+        let manifest_path_buf;
+        let manifest_path = if manifest_path.is_dir() {
+            manifest_path_buf = find_manifest_in_dir(manifest_path).ok_or_else(|| {
+                miette::miette!(
+                    "could not find {} or {} in '{}'",
+                    PROJECT_MANIFEST,
+                    PYPROJECT_MANIFEST,
+                    manifest_path.display()
+                )
+            })?;
+            manifest_path_buf.as_path()
+        } else {
+            manifest_path
+        };
+
         // Determine the parent directory of the manifest file
         let full_path = dunce::canonicalize(manifest_path).into_diagnostic()?;
 
@@ -207,7 +227,8 @@ impl Project {
 
         let env_vars = Project::init_env_vars(&manifest.parsed.environments);
 
-        let config = Config::load(&root.join(consts::PIXI_DIR))?;
+        let config = Config::load(&root.join(consts::PIXI_DIR))?
+            .with_proxy(manifest.parsed.project.proxy.clone());
 
         let (client, authenticated_client) = build_reqwest_clients(Some(&config));
 
@@ -218,6 +239,7 @@ impl Project {
             manifest,
             env_vars,
             config,
+            prefix_overrides: HashMap::new(),
         })
     }
 
@@ -252,10 +274,51 @@ impl Project {
     where
         C: Into<Config>,
     {
+        let offline = self.config.offline();
         self.config = self.config.merge_config(config.into());
+
+        // The clients refuse all requests once network access is disabled.
+        if self.config.offline() && !offline {
+            (self.client, self.authenticated_client) = build_reqwest_clients(Some(&self.config));
+        }
         self
     }
 
+    /// Installs the prefix of the given environment at a custom location instead of the default
+    /// managed location. The location may not collide with a location managed by pixi.
+    pub fn with_prefix_override(
+        mut self,
+        environment: EnvironmentName,
+        prefix: &Path,
+    ) -> miette::Result<Self> {
+        let prefix = if prefix.is_absolute() {
+            prefix.to_path_buf()
+        } else {
+            env::current_dir().into_diagnostic()?.join(prefix)
+        };
+
+        let managed_dirs = [self.environments_dir(), self.solve_group_environments_dir()]
+            .into_iter()
+            .chain(crate::config::home_path().map(|home| home.join(consts::ENVIRONMENTS_DIR)));
+        for managed_dir in managed_dirs {
+            if prefix.starts_with(&managed_dir) || managed_dir.starts_with(&prefix) {
+                miette::bail!(
+                    "the prefix '{}' collides with the environments managed by pixi in '{}'",
+                    prefix.display(),
+                    managed_dir.display()
+                );
+            }
+        }
+
+        self.prefix_overrides.insert(environment, prefix);
+        Ok(self)
+    }
+
+    /// Returns the custom location of the prefix of the given environment, if any.
+    pub(crate) fn prefix_override(&self, environment: &EnvironmentName) -> Option<&Path> {
+        self.prefix_overrides.get(environment).map(PathBuf::as_path)
+    }
+
     /// Returns the name of the project
     pub fn name(&self) -> &str {
         self.manifest
@@ -306,6 +369,61 @@ impl Project {
         self.root.join(consts::PROJECT_LOCK_FILE)
     }
 
+    /// Returns true if the project writes a lock file per environment instead of a single lock
+    /// file.
+    pub fn lock_file_per_environment(&self) -> bool {
+        self.manifest.parsed.project.lockfile_per_environment
+    }
+
+    /// Returns the task that is run before the tasks of every `pixi run`, if any.
+    pub fn pre_run_task(&self) -> Option<&str> {
+        self.manifest.parsed.project.pre_run_task.as_deref()
+    }
+
+    /// Returns the task that is run after the tasks of every `pixi run`, if any.
+    pub fn post_run_task(&self) -> Option<&str> {
+        self.manifest.parsed.project.post_run_task.as_deref()
+    }
+
+    /// Returns the command that verifies an environment after `pixi install`, if any.
+    pub fn post_install_check(&self) -> Option<&str> {
+        self.manifest.parsed.project.post_install_check.as_deref()
+    }
+
+    /// Returns true if the dependency tables should be sorted alphabetically when adding
+    /// dependencies.
+    pub fn sort_dependencies(&self) -> bool {
+        self.manifest.parsed.project.sort_dependencies
+    }
+
+    /// Returns true if the tasks should run with the reproducibility environment variables.
+    pub fn reproducible_env(&self) -> bool {
+        self.manifest.parsed.project.reproducible_env
+    }
+
+    /// Returns the path to the lock file of a single environment, used when the project writes a
+    /// lock file per environment.
+    pub fn environment_lock_file_path(&self, environment: &str) -> PathBuf {
+        self.root.join(format!("pixi.{environment}.lock"))
+    }
+
+    /// Returns the paths of all the lock files of the project.
+    pub fn lock_file_paths(&self) -> Vec<PathBuf> {
+        if self.lock_file_per_environment() {
+            self.environments()
+                .iter()
+                .map(|env| self.environment_lock_file_path(env.name().as_str()))
+                .collect()
+        } else {
+            vec![self.lock_file_path()]
+        }
+    }
+
+    /// Returns true if at least one of the lock files of the project exists.
+    pub fn has_lock_file(&self) -> bool {
+        self.lock_file_paths().iter().any(|path| path.is_file())
+    }
+
     /// Save back changes
     pub fn save(&mut self) -> miette::Result<()> {
         self.manifest.save()
@@ -469,6 +587,75 @@ impl Project {
             .expect("mapping source should be ok")
     }
 
+    /// Returns the conda features that are preferred when solving the environments of the project.
+    pub fn preferred_features(&self) -> &[String] {
+        &self.manifest.parsed.project.preferred_features
+    }
+
+    /// Returns the conda features that are avoided when solving the environments of the project.
+    pub fn avoided_features(&self) -> &[String] {
+        &self.manifest.parsed.project.avoided_features
+    }
+
+    /// Returns the licenses of conda packages that may not be part of the environments of the
+    /// project.
+    pub fn disallowed_licenses(&self) -> &[String] {
+        &self.manifest.parsed.project.disallowed_licenses
+    }
+
+    /// Returns the implementation of python that is selected when solving the environments of the
+    /// project, if any.
+    pub fn python_implementation(&self) -> Option<PythonImplementation> {
+        self.manifest.parsed.project.python_implementation
+    }
+
+    /// Returns the minimum python version that the `noarch: python` packages in the environments
+    /// of the project have to support, if any.
+    pub fn noarch_python_min(&self) -> Option<&Version> {
+        self.manifest.parsed.project.noarch_python_min.as_ref()
+    }
+
+    /// Returns the versions of conda packages that are pinned for all environments of the project.
+    pub fn pins(&self) -> &IndexMap<PackageName, Pin> {
+        &self.manifest.parsed.pins
+    }
+
+    /// Returns the version constraints of the project, read from the constraints files that are
+    /// specified in the manifest followed by the pins. Empty lines and lines starting with `#`
+    /// are ignored.
+    pub fn constraints(&self) -> miette::Result<Vec<MatchSpec>> {
+        let mut constraints = Vec::new();
+        for path in &self.manifest.parsed.project.constraints {
+            let path = self.root().join(path);
+            let contents = std::fs::read_to_string(&path)
+                .into_diagnostic()
+                .with_context(|| format!("failed to read constraints file '{}'", path.display()))?;
+            for line in contents.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let spec = MatchSpec::from_str(line, ParseStrictness::Strict)
+                    .into_diagnostic()
+                    .with_context(|| {
+                        format!("invalid constraint '{line}' in '{}'", path.display())
+                    })?;
+                if spec.name.is_none() {
+                    miette::bail!(
+                        "the constraint '{line}' in '{}' does not specify a package name",
+                        path.display()
+                    );
+                }
+                constraints.push(spec);
+            }
+        }
+        constraints.extend(self.pins().iter().map(|(name, pin)| MatchSpec {
+            name: Some(name.clone()),
+            version: Some(pin.version.clone()),
+            ..MatchSpec::default()
+        }));
+        Ok(constraints)
+    }
+
     /// Returns the reqwest client used for http networking
     pub fn client(&self) -> &reqwest::Client {
         &self.client
@@ -514,30 +701,55 @@ impl Project {
     pub(crate) fn task_cache_folder(&self) -> PathBuf {
         self.pixi_dir().join(consts::TASK_CACHE_DIR)
     }
+
+    /// Returns the folder with the pid and log files of the tasks started with `--detach`.
+    pub(crate) fn detached_tasks_folder(&self) -> PathBuf {
+        self.pixi_dir().join(consts::DETACHED_TASKS_DIR)
+    }
+
+    /// Returns the folder with the cached activation environments of `pixi run`.
+    pub(crate) fn activation_cache_folder(&self) -> PathBuf {
+        self.pixi_dir().join(consts::ACTIVATION_CACHE_DIR)
+    }
 }
 
 /// Iterates over the current directory and all its parent directories and returns the manifest path in the first
 /// directory path that contains the [`consts::PROJECT_MANIFEST`] or [`consts::PYPROJECT_MANIFEST`].
 pub fn find_project_manifest() -> Option<PathBuf> {
     let current_dir = env::current_dir().ok()?;
-    std::iter::successors(Some(current_dir.as_path()), |prev| prev.parent()).find_map(|dir| {
-        [PROJECT_MANIFEST, PYPROJECT_MANIFEST]
-            .iter()
-            .find_map(|manifest| {
-                let path = dir.join(manifest);
-                if path.is_file() {
-                    match *manifest {
-                        PROJECT_MANIFEST => Some(path.to_path_buf()),
-                        PYPROJECT_MANIFEST if PyProjectToml::is_pixi(&path) => {
-                            Some(path.to_path_buf())
-                        }
-                        _ => None,
-                    }
-                } else {
-                    None
+    std::iter::successors(Some(current_dir.as_path()), |prev| prev.parent())
+        .find_map(find_manifest_in_dir)
+}
+
+/// Returns the path to the pixi manifest file in the given directory, without looking at the
+/// parent directories.
+pub fn find_manifest_in_dir(dir: &Path) -> Option<PathBuf> {
+    [PROJECT_MANIFEST, PYPROJECT_MANIFEST]
+        .iter()
+        .find_map(|manifest| {
+            let path = dir.join(manifest);
+            if path.is_file() {
+                match *manifest {
+                    PROJECT_MANIFEST => Some(path.to_path_buf()),
+                    PYPROJECT_MANIFEST if PyProjectToml::is_pixi(&path) => Some(path.to_path_buf()),
+                    _ => None,
                 }
-            })
-    })
+            } else {
+                None
+            }
+        })
+}
+
+/// Splits a task that targets a nested project, e.g. `packages/foo:test`, into the path of the
+/// manifest of that project and the name of the task. Returns `None` if the part before the last
+/// `:` is not a directory that contains a pixi manifest.
+pub fn split_nested_project_task(task: &str) -> Option<(PathBuf, &str)> {
+    let (dir, task_name) = task.rsplit_once(':')?;
+    if dir.is_empty() || task_name.is_empty() {
+        return None;
+    }
+    let manifest = find_manifest_in_dir(Path::new(dir))?;
+    Some((manifest, task_name))
 }
 
 #[cfg(test)]
@@ -725,4 +937,43 @@ mod tests {
             .tasks(Some(Platform::Linux64), &FeatureName::Default)
             .unwrap());
     }
+
+    #[test]
+    fn test_prefix_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let project =
+            Project::from_str(&dir.path().join("pixi.toml"), PROJECT_BOILERPLATE).unwrap();
+
+        // A prefix inside the managed environments is rejected.
+        let managed = project.environments_dir().join("custom");
+        assert!(project
+            .clone()
+            .with_prefix_override(EnvironmentName::Default, &managed)
+            .is_err());
+
+        // A custom location is used as the directory of the environment.
+        let custom = dir.path().join("custom-prefix");
+        let project = project
+            .with_prefix_override(EnvironmentName::Default, &custom)
+            .unwrap();
+        assert_eq!(project.default_environment().dir(), custom);
+    }
+
+    #[test]
+    fn test_split_nested_project_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("packages").join("foo");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join(PROJECT_MANIFEST), PROJECT_BOILERPLATE).unwrap();
+
+        let task = format!("{}:test", nested.display());
+        let (manifest, task_name) = split_nested_project_task(&task).unwrap();
+        assert_eq!(manifest, nested.join(PROJECT_MANIFEST));
+        assert_eq!(task_name, "test");
+
+        // Directories without a manifest are not nested projects.
+        let task = format!("{}:test", dir.path().display());
+        assert!(split_nested_project_task(&task).is_none());
+        assert!(split_nested_project_task("test").is_none());
+    }
 }
@@ -10,7 +10,7 @@ use rattler_conda_types::{GenericVirtualPackage, Platform, Version};
 use rattler_virtual_packages::{
     Archspec, Cuda, DetectVirtualPackageError, LibC, Linux, Osx, VirtualPackage,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use thiserror::Error;
 
 /// The default GLIBC version to use. This is used when no system requirements are specified.
@@ -94,11 +94,105 @@ pub fn get_minimal_virtual_packages(
     virtual_packages
 }
 
+/// The environment variables that override the version of a virtual package, the same variables
+/// are used by conda.
+pub const VIRTUAL_PACKAGE_OVERRIDES: [&str; 4] = [
+    "CONDA_OVERRIDE_CUDA",
+    "CONDA_OVERRIDE_GLIBC",
+    "CONDA_OVERRIDE_OSX",
+    "CONDA_OVERRIDE_LINUX",
+];
+
+/// Returns the [`VIRTUAL_PACKAGE_OVERRIDES`] that are set, an empty value is kept because it
+/// removes the virtual package.
+pub fn virtual_package_overrides() -> BTreeMap<String, String> {
+    VIRTUAL_PACKAGE_OVERRIDES
+        .into_iter()
+        .filter_map(|name| {
+            Some((
+                name.to_string(),
+                std::env::var(name).ok()?.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the virtual packages to solve for, which are the minimal virtual packages with the
+/// overrides from the `CONDA_OVERRIDE_*` environment variables applied.
+pub fn get_virtual_packages(
+    platform: Platform,
+    system_requirements: &SystemRequirements,
+) -> Vec<VirtualPackage> {
+    apply_virtual_package_overrides(
+        get_minimal_virtual_packages(platform, system_requirements),
+        platform,
+        |name| std::env::var(name).ok(),
+    )
+}
+
+/// Overrides the versions of the virtual packages with the values returned by `lookup` for the
+/// [`VIRTUAL_PACKAGE_OVERRIDES`]. An empty value removes the virtual package, like conda does.
+/// Overrides that don't apply to the platform and values that are not a valid version are
+/// ignored.
+pub fn apply_virtual_package_overrides(
+    mut virtual_packages: Vec<VirtualPackage>,
+    platform: Platform,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Vec<VirtualPackage> {
+    for name in VIRTUAL_PACKAGE_OVERRIDES {
+        let Some(value) = lookup(name) else {
+            continue;
+        };
+        let applies = match name {
+            "CONDA_OVERRIDE_CUDA" => platform.is_linux() || platform.is_windows(),
+            "CONDA_OVERRIDE_GLIBC" | "CONDA_OVERRIDE_LINUX" => platform.is_linux(),
+            _ => platform.is_osx(),
+        };
+        if !applies {
+            continue;
+        }
+
+        let is_overridden = |package: &VirtualPackage| {
+            matches!(
+                (name, package),
+                ("CONDA_OVERRIDE_CUDA", VirtualPackage::Cuda(_))
+                    | ("CONDA_OVERRIDE_GLIBC", VirtualPackage::LibC(_))
+                    | ("CONDA_OVERRIDE_OSX", VirtualPackage::Osx(_))
+                    | ("CONDA_OVERRIDE_LINUX", VirtualPackage::Linux(_))
+            )
+        };
+        virtual_packages.retain(|package| !is_overridden(package));
+        if value.trim().is_empty() {
+            continue;
+        }
+
+        let version = match value.trim().parse::<Version>() {
+            Ok(version) => version,
+            Err(err) => {
+                tracing::warn!("ignoring {name}, '{value}' is not a valid version: {err}");
+                continue;
+            }
+        };
+        tracing::info!("overriding the virtual package version with {name}={version}");
+        virtual_packages.push(match name {
+            "CONDA_OVERRIDE_CUDA" => VirtualPackage::Cuda(Cuda { version }),
+            "CONDA_OVERRIDE_GLIBC" => VirtualPackage::LibC(LibC {
+                family: String::from("glibc"),
+                version,
+            }),
+            "CONDA_OVERRIDE_LINUX" => VirtualPackage::Linux(Linux { version }),
+            _ => VirtualPackage::Osx(Osx { version }),
+        });
+    }
+    virtual_packages
+}
+
 impl Environment<'_> {
     /// Returns the set of virtual packages to use for the specified platform. This method
-    /// takes into account the system requirements specified in the project manifest.
+    /// takes into account the system requirements specified in the project manifest and the
+    /// `CONDA_OVERRIDE_*` environment variables.
     pub fn virtual_packages(&self, platform: Platform) -> Vec<VirtualPackage> {
-        get_minimal_virtual_packages(platform, &self.system_requirements())
+        get_virtual_packages(platform, &self.system_requirements())
     }
 }
 
@@ -151,12 +245,15 @@ pub fn verify_current_platform_has_required_virtual_packages(
         )));
     }
 
-    let system_virtual_packages = VirtualPackage::current()?
-        .iter()
-        .cloned()
-        .map(GenericVirtualPackage::from)
-        .map(|vpkg| (vpkg.name.clone(), vpkg))
-        .collect::<HashMap<_, _>>();
+    let system_virtual_packages = apply_virtual_package_overrides(
+        VirtualPackage::current()?.to_vec(),
+        current_platform,
+        |name| std::env::var(name).ok(),
+    )
+    .into_iter()
+    .map(GenericVirtualPackage::from)
+    .map(|vpkg| (vpkg.name.clone(), vpkg))
+    .collect::<HashMap<_, _>>();
     let required_pkgs = environment
         .virtual_packages(current_platform)
         .into_iter()
@@ -203,7 +300,10 @@ mod tests {
     use super::*;
     use crate::project::manifest::SystemRequirements;
     use insta::assert_debug_snapshot;
-    use rattler_conda_types::Platform;
+    use rattler_conda_types::{
+        MatchSpec, PackageName, PackageRecord, ParseStrictness, Platform, RepoDataRecord,
+    };
+    use std::str::FromStr;
 
     // Regression test on the virtual packages so there is not accidental changes
     #[test]
@@ -229,4 +329,74 @@ mod tests {
             assert_debug_snapshot!(snapshot_name, packages);
         }
     }
+
+    #[tokio::test]
+    async fn test_virtual_package_overrides() {
+        let overrides = |cuda: &'static str| {
+            move |name: &str| match name {
+                "CONDA_OVERRIDE_CUDA" => Some(cuda.to_string()),
+                "CONDA_OVERRIDE_GLIBC" => Some(String::from("2.28")),
+                _ => None,
+            }
+        };
+        let virtual_packages = |cuda| {
+            apply_virtual_package_overrides(
+                get_minimal_virtual_packages(Platform::Linux64, &SystemRequirements::default()),
+                Platform::Linux64,
+                overrides(cuda),
+            )
+            .into_iter()
+            .map(GenericVirtualPackage::from)
+            .collect_vec()
+        };
+
+        let overridden = virtual_packages("12.1");
+        let version_of = |name: &str| {
+            overridden
+                .iter()
+                .find(|package| package.name.as_normalized() == name)
+                .map(|package| package.version.to_string())
+        };
+        assert_eq!(version_of("__cuda"), Some(String::from("12.1")));
+        assert_eq!(version_of("__glibc"), Some(String::from("2.28")));
+
+        // An empty override removes the virtual package.
+        assert!(virtual_packages("")
+            .iter()
+            .all(|package| package.name.as_normalized() != "__cuda"));
+
+        // The solver selects the CUDA variant of a package only with the overridden `__cuda`.
+        let record = |build: &str, build_number: u64, depends: &[&str]| {
+            let mut package_record = PackageRecord::new(
+                PackageName::new_unchecked("pytorch"),
+                Version::from_str("2.0").unwrap(),
+                build.to_string(),
+            );
+            package_record.build_number = build_number;
+            package_record.depends = depends.iter().map(ToString::to_string).collect();
+            let file_name = format!("pytorch-2.0-{build}.conda");
+            RepoDataRecord {
+                package_record,
+                url: url::Url::parse(&format!("https://example.com/{file_name}")).unwrap(),
+                file_name,
+                channel: String::from("https://example.com"),
+            }
+        };
+        let available = vec![vec![
+            record("cuda120", 1, &["__cuda >=12"]),
+            record("cpu", 0, &[]),
+        ]];
+        for (cuda, build) in [("12.1", "cuda120"), ("", "cpu")] {
+            let records = crate::lock_file::resolve_conda(
+                vec![MatchSpec::from_str("pytorch", ParseStrictness::Strict).unwrap()],
+                virtual_packages(cuda),
+                vec![],
+                available.clone(),
+                crate::lock_file::CondaSolveOptions::default(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(records[0].package_record.build, build);
+        }
+    }
 }
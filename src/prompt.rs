@@ -1,17 +1,31 @@
+/// The indicator that is prepended to the prompt, it is also exported as `PIXI_PROMPT` so custom
+/// prompts can include it.
+fn prompt_indicator(env_name: &str) -> String {
+    format!("({}) ", env_name)
+}
+
 /// Set default pixi prompt for the bash shell
+///
+/// The indicator of an outer pixi shell is removed first so nested shells don't stack indicators.
 pub fn get_bash_hook(env_name: &str) -> String {
     format!(
-        "export PS1=\"({}) $PS1\"\n{}",
-        env_name,
+        "PS1=\"${{PS1#\"${{PIXI_PROMPT:-}}\"}}\"\n\
+         export PIXI_PROMPT=\"{}\"\n\
+         export PS1=\"${{PIXI_PROMPT}}$PS1\"\n{}",
+        prompt_indicator(env_name),
         include_str!("shell_snippets/pixi-bash.sh")
     )
 }
 
 /// Set default pixi prompt for the zsh shell
+///
+/// The indicator of an outer pixi shell is removed first so nested shells don't stack indicators.
 pub fn get_zsh_hook(env_name: &str) -> String {
     format!(
-        "export PS1=\"({}) $PS1\"\n{}",
-        env_name,
+        "PS1=\"${{PS1#\"${{PIXI_PROMPT:-}}\"}}\"\n\
+         export PIXI_PROMPT=\"{}\"\n\
+         export PS1=\"${{PIXI_PROMPT}}$PS1\"\n{}",
+        prompt_indicator(env_name),
         include_str!("shell_snippets/pixi-zsh.sh")
     )
 }
@@ -19,39 +33,46 @@ pub fn get_zsh_hook(env_name: &str) -> String {
 /// Set default pixi prompt for the fish shell
 pub fn get_fish_prompt(env_name: &str) -> String {
     format!(
-        "functions -c fish_prompt old_fish_prompt; \
+        "set -gx PIXI_PROMPT \"{}\"; \
+         functions -q old_fish_prompt; or functions -c fish_prompt old_fish_prompt; \
          function fish_prompt; \
-             echo \"({})\" (old_fish_prompt); \
+             echo -n \"$PIXI_PROMPT\"; old_fish_prompt; \
          end;",
-        env_name
+        prompt_indicator(env_name)
     )
 }
 
 /// Set default pixi prompt for the xonsh shell
-pub fn get_xonsh_prompt() -> String {
+pub fn get_xonsh_prompt(env_name: &str) -> String {
     // Xonsh' default prompt can find the environment for some reason.
-    "".to_string()
+    format!("$PIXI_PROMPT = \"{}\"", prompt_indicator(env_name))
 }
 
 /// Set default pixi prompt for the powershell
 pub fn get_powershell_prompt(env_name: &str) -> String {
     format!(
-        "$old_prompt = $function:prompt\n\
-         function prompt {{\"({}) $($old_prompt.Invoke())\"}}",
-        env_name
+        "$env:PIXI_PROMPT = \"{}\"\n\
+         if (-not (Test-Path variable:global:pixi_old_prompt)) {{ $global:pixi_old_prompt = $function:prompt }}\n\
+         function prompt {{\"$($env:PIXI_PROMPT)$($global:pixi_old_prompt.Invoke())\"}}",
+        prompt_indicator(env_name)
     )
 }
 
 /// Set default pixi prompt for the Nu shell
 pub fn get_nu_prompt(env_name: &str) -> String {
     format!(
-        "let old_prompt = $env.PROMPT_COMMAND; \
-         $env.PROMPT_COMMAND = {{|| echo $\"\\({}\\) (do $old_prompt)\"}}",
-        env_name
+        "$env.PIXI_PROMPT = \"{}\"; \
+         let old_prompt = $env.PROMPT_COMMAND; \
+         $env.PROMPT_COMMAND = {{|| echo $\"($env.PIXI_PROMPT)(do $old_prompt)\"}}",
+        prompt_indicator(env_name)
     )
 }
 
 /// Set default pixi prompt for the cmd.exe command prompt
 pub fn get_cmd_prompt(env_name: &str) -> String {
-    format!(r"@PROMPT ({}) $P$G", env_name)
+    format!(
+        "@SET \"PIXI_PROMPT={}\"\r\n@PROMPT {}$P$G",
+        prompt_indicator(env_name),
+        prompt_indicator(env_name)
+    )
 }
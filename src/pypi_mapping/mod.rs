@@ -37,12 +37,18 @@ pub async fn amend_pypi_purls(
     mapping_source: &MappingSource,
     conda_packages: &mut [RepoDataRecord],
     reporter: Option<Arc<dyn Reporter>>,
+    offline: bool,
 ) -> miette::Result<()> {
-    // Construct a client with a retry policy and local caching
+    // Construct a client with a retry policy and local caching, offline only the cached mappings
+    // are used.
     let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
     let retry_strategy = RetryTransientMiddleware::new_with_policy(retry_policy);
     let cache_strategy = Cache(HttpCache {
-        mode: CacheMode::Default,
+        mode: if offline {
+            CacheMode::OnlyIfCached
+        } else {
+            CacheMode::Default
+        },
         manager: CACacheManager {
             path: get_cache_dir()
                 .expect("missing cache directory")
@@ -1,6 +1,7 @@
 use crate::config::Config;
 use crate::project::has_features::HasFeatures;
 use crate::project::Environment;
+use crate::utils::reqwest::proxy_description;
 use crate::{config, progress, project::Project};
 use futures::{stream, StreamExt, TryStreamExt};
 use indexmap::IndexMap;
@@ -8,7 +9,7 @@ use indicatif::ProgressBar;
 use itertools::Itertools;
 use miette::{Context, IntoDiagnostic};
 use rattler_conda_types::{Channel, Platform};
-use rattler_repodata_gateway::fetch::FetchRepoDataOptions;
+use rattler_repodata_gateway::fetch::{CacheAction, FetchRepoDataOptions};
 use rattler_repodata_gateway::{fetch, sparse::SparseRepoData};
 use reqwest_middleware::ClientWithMiddleware;
 use std::{path::Path, time::Duration};
@@ -88,7 +89,7 @@ pub async fn fetch_sparse_repodata_targets(
     let multi_progress = progress::global_multi_progress();
     let mut progress_bars = Vec::new();
 
-    let fetch_repodata_options = config
+    let mut fetch_repodata_options = config
         .as_ref()
         .and_then(|config| config.repodata_config.as_ref())
         .map(|config| FetchRepoDataOptions {
@@ -99,6 +100,16 @@ pub async fn fetch_sparse_repodata_targets(
         })
         .unwrap_or_default();
 
+    // Always fetch fresh repodata if caching is disabled.
+    if config.is_some_and(Config::no_cache) {
+        fetch_repodata_options.cache_action = CacheAction::NoCache;
+    }
+
+    // Only read the cached repodata when network access is disabled.
+    if config.is_some_and(Config::offline) {
+        fetch_repodata_options.cache_action = CacheAction::ForceCacheOnly;
+    }
+
     let repo_data = stream::iter(fetch_targets)
         .map(|(channel, platform)| {
             // Construct a progress bar for the fetch
@@ -142,8 +153,15 @@ pub async fn fetch_sparse_repodata_targets(
         pb.finish_and_clear()
     }
 
-    // If there was an error, report it.
-    repo_data.wrap_err("failed to fetch repodata from channels")
+    // If there was an error, report it. Mention the proxy because a misconfigured proxy is a
+    // common cause of connection errors.
+    let repo_data = repo_data.wrap_err("failed to fetch repodata from channels");
+    match proxy_description(config).filter(|_| !config.is_some_and(Config::offline)) {
+        Some(proxy) => repo_data.wrap_err(format!(
+            "failed to fetch repodata through the proxy '{proxy}', check that the proxy is reachable"
+        )),
+        None => repo_data,
+    }
 }
 
 /// Given a channel and platform, download and cache the `repodata.json` for it. This function
@@ -158,6 +176,7 @@ async fn fetch_repo_data_records_with_progress(
     fetch_options: FetchRepoDataOptions,
 ) -> miette::Result<Option<SparseRepoData>> {
     // Download the repodata.json
+    let offline = matches!(fetch_options.cache_action, CacheAction::ForceCacheOnly);
     let download_progress_progress_bar = progress_bar.clone();
     let result = fetch::fetch_repo_data(
         channel.platform_url(platform),
@@ -180,6 +199,16 @@ async fn fetch_repo_data_records_with_progress(
                 return Ok(None);
             }
 
+            if matches!(&e, fetch::FetchRepoDataError::NoCacheAvailable) && offline {
+                progress_bar.set_style(progress::errored_progress_style());
+                progress_bar.finish_with_message("Not cached");
+                miette::bail!(
+                    help = "run the command once without `--offline` to populate the repodata cache",
+                    "the required repodata of '{}' is not in the cache and can't be fetched offline",
+                    channel.platform_url(platform)
+                );
+            }
+
             progress_bar.set_style(progress::errored_progress_style());
             progress_bar.finish_with_message("404 not found");
             return Err(e).into_diagnostic();
@@ -0,0 +1,128 @@
+//! Determines which files in a project changed relative to a git reference. This is used by
+//! `pixi run --changed-only` to skip tasks whose `inputs` did not change.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use super::file_hashes::build_filter;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ChangedFilesError {
+    #[error("failed to execute git, is it installed and on the PATH?")]
+    FailedToExecuteGit(#[source] std::io::Error),
+
+    #[error("failed to determine the files that changed since '{reference}'")]
+    #[diagnostic(help("{stderr}"))]
+    GitDiffFailed { reference: String, stderr: String },
+
+    #[error(transparent)]
+    InvalidGlob(#[from] ignore::Error),
+}
+
+/// The set of files (relative to the project root) that changed compared to a git reference.
+///
+/// This includes committed changes since the reference, uncommitted changes in the working tree
+/// and untracked files that are not ignored.
+#[derive(Debug, Default, Clone)]
+pub struct ChangedFiles {
+    root: PathBuf,
+    files: HashSet<PathBuf>,
+}
+
+impl ChangedFiles {
+    /// Queries git for all files below `root` that changed since `reference`.
+    pub fn since(root: &Path, reference: &str) -> Result<Self, ChangedFilesError> {
+        let diff = run_git(
+            root,
+            ["diff", "--name-only", "--relative", reference, "--"],
+            reference,
+        )?;
+        let untracked = run_git(
+            root,
+            ["ls-files", "--others", "--exclude-standard"],
+            reference,
+        )?;
+
+        Ok(Self::from_paths(
+            root,
+            diff.lines().chain(untracked.lines()).map(PathBuf::from),
+        ))
+    }
+
+    /// Constructs an instance from a list of paths relative to `root`.
+    pub fn from_paths(root: &Path, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            files: paths
+                .into_iter()
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect(),
+        }
+    }
+
+    /// Returns true if any of the changed files matches the given set of input globs. The globs
+    /// follow the same rules as the `inputs` of a task.
+    pub fn matches_any(
+        &self,
+        globs: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<bool, ChangedFilesError> {
+        let filter = build_filter(&self.root, globs)?;
+        Ok(self
+            .files
+            .iter()
+            .any(|file| filter.matched(self.root.join(file), false).is_whitelist()))
+    }
+}
+
+/// Runs a git command in `root` and returns its stdout.
+fn run_git<const N: usize>(
+    root: &Path,
+    args: [&str; N],
+    reference: &str,
+) -> Result<String, ChangedFilesError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .map_err(ChangedFilesError::FailedToExecuteGit)?;
+
+    if !output.status.success() {
+        return Err(ChangedFilesError::GitDiffFailed {
+            reference: reference.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{create_dir, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn matches_input_globs() {
+        let root = tempdir().unwrap();
+        create_dir(root.path().join("src")).unwrap();
+        write(root.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+        let changed = ChangedFiles::from_paths(
+            root.path(),
+            [PathBuf::from("src/main.rs"), PathBuf::from("README.md")],
+        );
+
+        assert!(changed.matches_any(["src/*.rs"]).unwrap());
+        assert!(changed.matches_any(["src"]).unwrap());
+        assert!(changed.matches_any(["*.md"]).unwrap());
+        assert!(!changed.matches_any(["docs/**"]).unwrap());
+        assert!(!changed.matches_any(["src/*.rs", "!src/main.rs"]).unwrap());
+    }
+}
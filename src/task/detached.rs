@@ -0,0 +1,275 @@
+//! Tasks can be started in the background with `pixi run --detach`. The detached task runs in
+//! its own `pixi run` process, whose pid is recorded in a pid file in the `.pixi` directory so
+//! it can later be stopped with `pixi run --stop`. The output of the task is written to a log
+//! file next to the pid file.
+
+use miette::{IntoDiagnostic, WrapErr};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The registry of the detached tasks of a project, stored in a single directory.
+#[derive(Debug, Clone)]
+pub struct DetachedTasks {
+    folder: PathBuf,
+}
+
+/// A detached task that was started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetachedTask {
+    pub pid: u32,
+    pub log_file: PathBuf,
+}
+
+/// The result of stopping a detached task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// The process tree of the task was terminated.
+    Stopped(u32),
+    /// The pid file pointed to a process that no longer exists, the pid file was removed.
+    Stale(u32),
+    /// There is no pid file for the task.
+    NotRunning,
+}
+
+impl DetachedTasks {
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Self {
+            folder: folder.into(),
+        }
+    }
+
+    /// The path of the pid file of the given task.
+    pub fn pid_file(&self, task: &str) -> PathBuf {
+        self.folder.join(format!("{}.pid", file_stem(task)))
+    }
+
+    /// The path of the file the output of the given task is written to.
+    pub fn log_file(&self, task: &str) -> PathBuf {
+        self.folder.join(format!("{}.log", file_stem(task)))
+    }
+
+    /// Returns the pid of the task if it is running. A pid file of a process that no longer
+    /// exists is removed.
+    pub fn running_pid(&self, task: &str) -> Option<u32> {
+        let pid_file = self.pid_file(task);
+        let pid = read_pid(&pid_file)?;
+        if is_process_alive(pid) {
+            Some(pid)
+        } else {
+            tracing::debug!("removing stale pid file '{}'", pid_file.display());
+            let _ = std::fs::remove_file(pid_file);
+            None
+        }
+    }
+
+    /// Spawns `program` with `args` in the background and records its pid for `task`. Fails if
+    /// the task is already running.
+    pub fn spawn(
+        &self,
+        task: &str,
+        program: &Path,
+        args: impl IntoIterator<Item = OsString>,
+    ) -> miette::Result<DetachedTask> {
+        if let Some(pid) = self.running_pid(task) {
+            miette::bail!(
+                help = format!("stop it first with `pixi run --stop {task}`"),
+                "the task '{task}' is already running in the background (pid {pid})"
+            );
+        }
+
+        std::fs::create_dir_all(&self.folder)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to create '{}'", self.folder.display()))?;
+        let log_file = self.log_file(task);
+        let log = std::fs::File::create(&log_file)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to create '{}'", log_file.display()))?;
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone().into_diagnostic()?)
+            .stderr(log);
+        detach(&mut command);
+
+        let child = command
+            .spawn()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to start the task '{task}' in the background"))?;
+        let pid = child.id();
+
+        let pid_file = self.pid_file(task);
+        std::fs::write(&pid_file, pid.to_string())
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write '{}'", pid_file.display()))?;
+
+        Ok(DetachedTask { pid, log_file })
+    }
+
+    /// Terminates the process tree of the task and removes its pid file.
+    pub fn stop(&self, task: &str) -> miette::Result<StopOutcome> {
+        let pid_file = self.pid_file(task);
+        let Some(pid) = read_pid(&pid_file) else {
+            return Ok(StopOutcome::NotRunning);
+        };
+
+        let outcome = if is_process_alive(pid) {
+            terminate_process_tree(pid)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to stop the task '{task}' (pid {pid})"))?;
+            StopOutcome::Stopped(pid)
+        } else {
+            StopOutcome::Stale(pid)
+        };
+
+        std::fs::remove_file(&pid_file)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to remove '{}'", pid_file.display()))?;
+        Ok(outcome)
+    }
+}
+
+/// Turns a task name into something that can safely be used as a file name.
+fn file_stem(task: &str) -> String {
+    task.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn read_pid(pid_file: &Path) -> Option<u32> {
+    let content = std::fs::read_to_string(pid_file).ok()?;
+    match content.trim().parse() {
+        Ok(pid) => Some(pid),
+        Err(_) => {
+            // A pid file we cannot read is as good as a stale one.
+            let _ = std::fs::remove_file(pid_file);
+            None
+        }
+    }
+}
+
+/// Starts the process in its own process group so it outlives the current process and its whole
+/// tree can be terminated at once.
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    // Sending no signal only checks whether the process exists.
+    match kill(Pid::from_raw(pid as i32), None) {
+        Ok(()) => true,
+        Err(err) => err == nix::errno::Errno::EPERM,
+    }
+}
+
+#[cfg(windows)]
+fn is_process_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .any(|word| word == pid.to_string())
+        })
+        .unwrap_or(false)
+}
+
+/// Terminates the process and all processes it started. The detached process is the leader of
+/// its own process group, so the whole group is signalled.
+#[cfg(unix)]
+pub(super) fn terminate_process_tree(pid: u32) -> std::io::Result<()> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+    match killpg(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+        Ok(()) | Err(nix::errno::Errno::ESRCH) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(windows)]
+pub(super) fn terminate_process_tree(pid: u32) -> std::io::Result<()> {
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "taskkill exited with {status}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("devserver"), "devserver");
+        assert_eq!(file_stem("docs/serve"), "docs_serve");
+        assert_eq!(file_stem("a b:c"), "a_b_c");
+    }
+
+    #[test]
+    fn test_stale_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks = DetachedTasks::new(dir.path());
+
+        assert_eq!(tasks.stop("devserver").unwrap(), StopOutcome::NotRunning);
+
+        // A pid that cannot belong to a running process.
+        std::fs::write(tasks.pid_file("devserver"), i32::MAX.to_string()).unwrap();
+        assert_eq!(tasks.running_pid("devserver"), None);
+        assert!(!tasks.pid_file("devserver").exists());
+
+        std::fs::write(tasks.pid_file("devserver"), i32::MAX.to_string()).unwrap();
+        assert_eq!(
+            tasks.stop("devserver").unwrap(),
+            StopOutcome::Stale(i32::MAX as u32)
+        );
+        assert!(!tasks.pid_file("devserver").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spawn_and_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks = DetachedTasks::new(dir.path());
+
+        let task = tasks
+            .spawn("sleep", Path::new("sleep"), [OsString::from("60")])
+            .unwrap();
+        assert_eq!(tasks.running_pid("sleep"), Some(task.pid));
+        assert!(tasks
+            .spawn("sleep", Path::new("sleep"), [OsString::from("60")])
+            .is_err());
+
+        assert_eq!(tasks.stop("sleep").unwrap(), StopOutcome::Stopped(task.pid));
+        assert!(!tasks.pid_file("sleep").exists());
+    }
+}
@@ -39,3 +39,57 @@ impl Diagnostic for AmbiguousTaskError {
         )))
     }
 }
+
+/// An error that occurs when the command line arguments of a task do not match the arguments
+/// that the task declares.
+#[derive(Debug, Error)]
+pub enum TaskArgumentError {
+    #[error("missing argument '{argument}' for task '{}'", task_name.fancy_display())]
+    Missing {
+        task_name: TaskName,
+        argument: String,
+        choices: Option<Vec<String>>,
+    },
+
+    #[error("invalid value '{value}' for argument '{argument}' of task '{}'", task_name.fancy_display())]
+    InvalidChoice {
+        task_name: TaskName,
+        argument: String,
+        value: String,
+        choices: Vec<String>,
+    },
+
+    #[error("unexpected argument '{value}' for task '{}'", task_name.fancy_display())]
+    Unexpected { task_name: TaskName, value: String },
+
+    #[error("undefined variable '{{{{ {variable} }}}}' in the command of task '{}'", task_name.fancy_display())]
+    UndefinedVariable {
+        task_name: TaskName,
+        variable: String,
+        available: Vec<String>,
+    },
+}
+
+impl Diagnostic for TaskArgumentError {
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        match self {
+            TaskArgumentError::Missing {
+                choices: Some(choices),
+                ..
+            }
+            | TaskArgumentError::InvalidChoice { choices, .. } => Some(Box::new(format!(
+                "the allowed values are: {}",
+                choices.iter().format(", ")
+            ))),
+            TaskArgumentError::Missing { choices: None, .. } => None,
+            TaskArgumentError::Unexpected { task_name, .. } => Some(Box::new(format!(
+                "the task '{}' does not accept any more arguments",
+                task_name.fancy_display()
+            ))),
+            TaskArgumentError::UndefinedVariable { available, .. } => Some(Box::new(format!(
+                "the available variables are: {}, use '\\{{{{' to write a literal '{{{{'",
+                available.iter().format(", ")
+            ))),
+        }
+    }
+}
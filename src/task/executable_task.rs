@@ -4,25 +4,110 @@ use crate::project::Environment;
 use crate::task::TaskName;
 use crate::{
     task::task_graph::{TaskGraph, TaskId},
-    task::{quote_arguments, Task},
+    task::{
+        quote, quote_arguments, render_task_arguments, render_task_arguments_with, CmdArgs, Custom,
+        Task,
+    },
     Project,
 };
 use deno_task_shell::{
     execute_with_pipes, parser::SequentialList, pipe, ShellPipeWriter, ShellState,
 };
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::Diagnostic;
 use std::{
     borrow::Cow,
     collections::HashMap,
+    ffi::OsStr,
     fmt::{Display, Formatter},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
 };
 use thiserror::Error;
 use tokio::task::JoinHandle;
 
 use super::task_hash::{InputHashesError, TaskCache, TaskHash};
 
+/// The kind of the `shell` of a task, which determines how a script is passed to the shell and
+/// how its arguments are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShellKind {
+    Posix,
+    PowerShell,
+    Cmd,
+}
+
+impl ShellKind {
+    /// Returns the kind of the shell at the path, a shell that is not known is treated as a POSIX
+    /// shell.
+    fn from_path(path: &Path) -> Self {
+        match path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("pwsh" | "powershell") => Self::PowerShell,
+            Some("cmd") => Self::Cmd,
+            _ => Self::Posix,
+        }
+    }
+
+    /// Returns the arguments that make the shell run the script that follows them.
+    fn script_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Posix => &["-c"],
+            Self::PowerShell => &["-NoLogo", "-NoProfile", "-Command"],
+            Self::Cmd => &["/C"],
+        }
+    }
+
+    /// Quotes an argument so the shell passes it to the command as a single argument.
+    fn quote(self, arg: &str) -> Cow<'_, str> {
+        let is_plain = |special: &[char]| {
+            !arg.is_empty()
+                && !arg
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || special.contains(&c))
+        };
+        match self {
+            Self::Posix if is_plain(&POSIX_SPECIAL_CHARS) => arg.into(),
+            Self::Posix => format!("'{}'", arg.replace('\'', r"'\''")).into(),
+            Self::PowerShell if is_plain(&POWERSHELL_SPECIAL_CHARS) => arg.into(),
+            Self::PowerShell => {
+                // Within single quotes nothing is expanded, a quote is escaped by doubling it.
+                let mut quoted = String::from("'");
+                for c in arg.chars() {
+                    if matches!(c, '\'' | '‘' | '’') {
+                        quoted.push(c);
+                    }
+                    quoted.push(c);
+                }
+                quoted.push('\'');
+                quoted.into()
+            }
+            Self::Cmd if is_plain(&CMD_SPECIAL_CHARS) => arg.into(),
+            // Note that `cmd.exe` expands `%VARIABLE%` even within quotes.
+            Self::Cmd => format!("\"{}\"", arg.replace('"', "\"\"")).into(),
+        }
+    }
+}
+
+/// The characters that have a special meaning in a POSIX shell.
+const POSIX_SPECIAL_CHARS: [char; 20] = [
+    '\'', '"', '\\', '$', '`', '!', '&', '|', ';', '<', '>', '(', ')', '*', '?', '[', ']', '#',
+    '~', '{',
+];
+
+/// The characters that have a special meaning in PowerShell.
+const POWERSHELL_SPECIAL_CHARS: [char; 18] = [
+    '\'', '"', '`', '$', '&', '|', ';', '<', '>', '(', ')', '{', '}', '@', '#', ',', '‘', '’',
+];
+
+/// The characters that have a special meaning in `cmd.exe`.
+const CMD_SPECIAL_CHARS: [char; 8] = ['"', '&', '|', '<', '>', '^', '(', ')'];
+
 /// Runs task in project.
 #[derive(Default, Debug)]
 pub struct RunOutput {
@@ -44,11 +129,31 @@ pub struct InvalidWorkingDirectory {
     pub path: String,
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("the shell '{shell}' of the task could not be found on the PATH")]
+pub struct ShellNotFound {
+    pub shell: String,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("failed to read the secret '{name}' from the keyring entry '{entry}': {reason}")]
+#[diagnostic(help(
+    "the entries of `env-from-keyring` have the form `service:account` and must be stored in the keyring of the operating system"
+))]
+pub struct KeyringSecretError {
+    pub name: String,
+    pub entry: String,
+    pub reason: String,
+}
+
 #[derive(Debug, Error, Diagnostic)]
 pub enum TaskExecutionError {
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
 
+    #[error(transparent)]
+    ShellNotFound(#[from] ShellNotFound),
+
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
 }
@@ -70,6 +175,15 @@ pub enum CanSkip {
     No(Option<TaskHash>),
 }
 
+/// The environment variables that are set for reproducible runs, with `pixi run --reproducible`
+/// or `reproducible-env = true` in the manifest. `SOURCE_DATE_EPOCH` is 1980-01-01, the earliest
+/// timestamp that a zip file, and thus a wheel, can store.
+pub const REPRODUCIBLE_ENV: [(&str, &str); 3] = [
+    ("PYTHONHASHSEED", "0"),
+    ("SOURCE_DATE_EPOCH", "315532800"),
+    ("TZ", "UTC"),
+];
+
 /// A task that contains enough information to be able to execute it. The lifetime [`'p`] refers to
 /// the lifetime of the project that contains the tasks.
 #[derive(Clone)]
@@ -79,6 +193,11 @@ pub struct ExecutableTask<'p> {
     pub task: Cow<'p, Task>,
     pub run_environment: Environment<'p>,
     pub additional_args: Vec<String>,
+    pub arguments: IndexMap<String, String>,
+    pub variables: IndexMap<String, String>,
+    pub working_directory_override: Option<PathBuf>,
+    /// Whether the environment of the task contains the [`REPRODUCIBLE_ENV`] variables.
+    pub reproducible_env: bool,
 }
 
 impl<'p> ExecutableTask<'p> {
@@ -91,9 +210,60 @@ impl<'p> ExecutableTask<'p> {
             task: node.task.clone(),
             run_environment: node.run_environment.clone(),
             additional_args: node.additional_args.clone(),
+            arguments: node.arguments.clone(),
+            variables: node.variables.clone(),
+            working_directory_override: None,
+            reproducible_env: false,
+        }
+    }
+
+    /// Constructs a new executable task that runs a command in the environment, like a command
+    /// that is not the name of a task in `pixi run`.
+    pub fn from_cmd(environment: Environment<'p>, cmd: &str) -> Self {
+        Self {
+            project: environment.project(),
+            name: None,
+            task: Cow::Owned(
+                Custom {
+                    cmd: CmdArgs::Single(cmd.to_string()),
+                    cwd: None,
+                }
+                .into(),
+            ),
+            run_environment: environment,
+            additional_args: Vec::new(),
+            arguments: IndexMap::new(),
+            variables: IndexMap::new(),
+            working_directory_override: None,
+            reproducible_env: false,
+        }
+    }
+
+    /// Runs the task in the given directory instead of the working directory of the task.
+    pub fn with_working_directory(self, working_directory: Option<PathBuf>) -> Self {
+        Self {
+            working_directory_override: working_directory,
+            ..self
         }
     }
 
+    /// Marks the environment of the task as containing the [`REPRODUCIBLE_ENV`] variables, the
+    /// `env` of the task overrides them.
+    pub fn with_reproducible_env(self, reproducible_env: bool) -> Self {
+        Self {
+            reproducible_env,
+            ..self
+        }
+    }
+
+    /// Returns true if the `env` of the task should set the variable. The `env` doesn't override the
+    /// variables that are set in the shell of the user, but it does override the reproducibility
+    /// defaults.
+    fn env_overrides(&self, key: &str) -> bool {
+        std::env::var(key).is_err()
+            || (self.reproducible_env && REPRODUCIBLE_ENV.iter().any(|(name, _)| *name == key))
+    }
+
     /// Returns the name of the task or `None` if this is an anonymous task.
     pub fn name(&self) -> Option<&str> {
         self.name.as_ref().map(|name| name.as_str())
@@ -121,14 +291,13 @@ impl<'p> ExecutableTask<'p> {
         let Some(task) = self.task.as_single_command() else {
             return Ok(None);
         };
+        let task = render_task_arguments(&task, &self.variables);
 
         // Append the environment variables if they don't exist
         let mut export = String::new();
         if let Some(env) = self.task.env() {
             for (key, value) in env {
-                if value.contains(format!("${}", key).as_str())
-                    || std::env::var(key.as_str()).is_err()
-                {
+                if value.contains(format!("${}", key).as_str()) || self.env_overrides(key) {
                     tracing::info!("Setting environment variable: {}=\"{}\"", key, value);
                     export.push_str(&format!("export \"{}={}\";\n", key, value));
                 } else {
@@ -137,6 +306,11 @@ impl<'p> ExecutableTask<'p> {
             }
         }
 
+        // Expose the arguments of the task as shell variables
+        for (name, value) in &self.arguments {
+            export.push_str(&format!("{name}={};\n", quote(value)));
+        }
+
         // Append the command line arguments
         let cli_args = quote_arguments(self.additional_args.iter().map(|arg| arg.as_str()));
 
@@ -156,8 +330,86 @@ impl<'p> ExecutableTask<'p> {
             .map(Some)
     }
 
-    /// Returns the working directory for this task.
+    /// Returns the command that runs the task in the `shell` of the task, or `None` if the task
+    /// runs in the deno task shell. The shell is searched for on the `PATH` of the command
+    /// environment, the environment variables and arguments of the task are passed as
+    /// environment variables.
+    ///
+    /// The resource limits of the task are set in the process of the shell.
+    pub fn as_shell_command(
+        &self,
+        command_env: &HashMap<String, String>,
+    ) -> Result<Option<Command>, ShellNotFound> {
+        let (Some(shell), Some(task)) = (self.task.shell(), self.task.as_single_command()) else {
+            return Ok(None);
+        };
+        let path = command_env
+            .get("PATH")
+            .map(String::from)
+            .or_else(|| std::env::var("PATH").ok());
+        let Some(shell_path) = find_executable(shell, path.as_deref()) else {
+            return Err(ShellNotFound {
+                shell: shell.to_string(),
+            });
+        };
+
+        // Append the command line arguments, quoted for the shell that runs them
+        let kind = ShellKind::from_path(&shell_path);
+        let task = render_task_arguments_with(&task, &self.variables, |arg| kind.quote(arg));
+        let cli_args = self
+            .additional_args
+            .iter()
+            .map(|arg| kind.quote(arg))
+            .join(" ");
+        let script = format!("{task} {cli_args}").trim().to_string();
+
+        let mut command = Command::new(&shell_path);
+        command
+            .args(kind.script_args())
+            .arg(script)
+            .envs(command_env);
+        if let Some(env) = self.task.env() {
+            for (key, value) in env {
+                if self.env_overrides(key) {
+                    command.env(key, value);
+                }
+            }
+        }
+        command.envs(&self.arguments);
+        self.task.resource_limits().apply(&mut command);
+
+        Ok(Some(command))
+    }
+
+    /// Reads the secrets of the `env-from-keyring` of the task from the keyring of the operating
+    /// system. A secret that can't be read is an error, so the task never runs without it.
+    pub fn keyring_env(&self) -> Result<HashMap<String, String>, KeyringSecretError> {
+        let Some(env) = self.task.env_from_keyring() else {
+            return Ok(HashMap::new());
+        };
+        env.iter()
+            .map(|(name, entry)| {
+                let error = |reason: String| KeyringSecretError {
+                    name: name.clone(),
+                    entry: entry.clone(),
+                    reason,
+                };
+                let (service, account) = parse_keyring_entry(entry)
+                    .ok_or_else(|| error(String::from("expected `service:account`")))?;
+                let secret = keyring::Entry::new(service, account)
+                    .and_then(|entry| entry.get_password())
+                    .map_err(|err| error(err.to_string()))?;
+                Ok((name.clone(), secret))
+            })
+            .collect()
+    }
+
+    /// Returns the working directory for this task. An override of the working directory takes
+    /// precedence over the `cwd` of the task.
     pub fn working_directory(&self) -> Result<PathBuf, InvalidWorkingDirectory> {
+        if let Some(cwd) = &self.working_directory_override {
+            return Ok(cwd.clone());
+        }
         Ok(match self.task.working_directory() {
             Some(cwd) if cwd.is_absolute() => cwd.to_path_buf(),
             Some(cwd) => {
@@ -179,7 +431,8 @@ impl<'p> ExecutableTask<'p> {
     /// This function returns `None` if the task does not define a command to execute. This is the
     /// case for alias only commands.
     pub fn full_command(&self) -> Option<String> {
-        let mut cmd = self.task.as_single_command()?.to_string();
+        let command = self.task.as_single_command()?;
+        let mut cmd = render_task_arguments(&command, &self.variables).into_owned();
 
         if !self.additional_args.is_empty() {
             cmd.push(' ');
@@ -295,7 +548,11 @@ struct ExecutableTaskConsoleDisplay<'p, 't> {
 
 impl<'p, 't> Display for ExecutableTaskConsoleDisplay<'p, 't> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let command = self.task.task.as_single_command();
+        let command = self
+            .task
+            .task
+            .as_single_command()
+            .map(|command| render_task_arguments(&command, &self.task.variables).into_owned());
         write!(
             f,
             "{}",
@@ -313,9 +570,230 @@ impl<'p, 't> Display for ExecutableTaskConsoleDisplay<'p, 't> {
         Ok(())
     }
 }
+/// Searches for an executable in the directories of `path`, a name with a directory is used as is.
+fn find_executable(name: &str, path: Option<&str>) -> Option<PathBuf> {
+    let candidate = Path::new(name);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    let extensions: &[&str] = if cfg!(windows) {
+        &["exe", "cmd", "bat"]
+    } else {
+        &[]
+    };
+    std::env::split_paths(path?).find_map(|dir| {
+        let path = dir.join(name);
+        if path.is_file() {
+            return Some(path);
+        }
+        extensions
+            .iter()
+            .map(|extension| path.with_extension(extension))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Splits a keyring entry of the form `service:account`, the service may contain colons itself,
+/// e.g. when it is a URL.
+fn parse_keyring_entry(entry: &str) -> Option<(&str, &str)> {
+    entry
+        .rsplit_once(':')
+        .filter(|(service, account)| !service.is_empty() && !account.is_empty())
+}
+
 /// Helper function to create a pipe that we can get the output from.
 fn get_output_writer_and_handle() -> (ShellPipeWriter, JoinHandle<String>) {
     let (reader, writer) = pipe();
     let handle = reader.pipe_to_string_handle();
     (writer, handle)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::task_environment::SearchEnvironments;
+    use std::path::Path;
+
+    const PROJECT: &str = r#"
+    [project]
+    name = "foo"
+    channels = []
+    platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+
+    [tasks]
+    and = "echo first && echo second"
+    or = "pixi-command-that-does-not-exist || echo fallback"
+    sequence = "echo one; echo two"
+    export = "export GREETING=hello; echo $GREETING"
+    env = "echo $GREETING"
+    greet = { cmd = "echo {{ greeting }} $name", args = [{ name = "greeting", choices = ["hello", "bye"] }, { name = "name" }] }
+    posix = { cmd = "echo $name from sh", shell = "sh", args = [{ name = "name" }] }
+    quoted = { cmd = "echo {{ text }}", shell = "sh", args = [{ name = "text" }] }
+    missing-shell = { cmd = "echo", shell = "pixi-shell-that-does-not-exist" }
+    deploy = { cmd = "echo $TOKEN", env-from-keyring = { TOKEN = "no-account" } }
+    limited = { cmd = "ulimit -n", shell = "sh", nice = 5, ulimit = { open-files = 64 } }
+    "#;
+
+    /// Constructs the executable task from the given command line.
+    fn executable_task<'p>(project: &'p Project, args: &str) -> ExecutableTask<'p> {
+        let search_envs = SearchEnvironments::from_opt_env(project, None, None);
+        let args = args.split_whitespace().map(String::from).collect();
+        let graph = TaskGraph::from_cmd_args(project, &search_envs, args).unwrap();
+        let task_id = *graph.topological_order().last().unwrap();
+        ExecutableTask::from_task_graph(&graph, task_id)
+            .with_working_directory(Some(std::env::current_dir().unwrap()))
+    }
+
+    /// Runs the task from the given command line through the task shell and returns its exit code
+    /// and stdout lines.
+    async fn run_task(args: &str, command_env: HashMap<String, String>) -> (i32, Vec<String>) {
+        let project = Project::from_str(Path::new("pixi.toml"), PROJECT).unwrap();
+        let executable_task = executable_task(&project, args);
+
+        let output = executable_task
+            .execute_with_pipes(&command_env, None)
+            .await
+            .unwrap();
+        let lines = output
+            .stdout
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+        (output.exit_code, lines)
+    }
+
+    #[tokio::test]
+    async fn test_portable_boolean_lists() {
+        assert_eq!(
+            run_task("and", HashMap::new()).await,
+            (0, vec!["first".to_string(), "second".to_string()])
+        );
+        assert_eq!(
+            run_task("or", HashMap::new()).await,
+            (0, vec!["fallback".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_portable_sequential_lists() {
+        assert_eq!(
+            run_task("sequence", HashMap::new()).await,
+            (0, vec!["one".to_string(), "two".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_portable_env_expansion() {
+        assert_eq!(
+            run_task("export", HashMap::new()).await,
+            (0, vec!["hello".to_string()])
+        );
+        let command_env = HashMap::from([("GREETING".to_string(), "world".to_string())]);
+        assert_eq!(
+            run_task("env", command_env).await,
+            (0, vec!["world".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_arguments() {
+        assert_eq!(
+            run_task("greet bye pixi", HashMap::new()).await,
+            (0, vec!["bye pixi".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_shell_not_found() {
+        let project = Project::from_str(Path::new("pixi.toml"), PROJECT).unwrap();
+        let err = executable_task(&project, "missing-shell")
+            .as_shell_command(&HashMap::new())
+            .unwrap_err();
+        assert_eq!(err.shell, "pixi-shell-that-does-not-exist");
+        assert!(executable_task(&project, "and")
+            .as_shell_command(&HashMap::new())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_keyring_env() {
+        assert_eq!(
+            parse_keyring_entry("pypi:__token__"),
+            Some(("pypi", "__token__"))
+        );
+        assert_eq!(
+            parse_keyring_entry("https://example.com:deploy"),
+            Some(("https://example.com", "deploy"))
+        );
+        assert_eq!(parse_keyring_entry("pypi:"), None);
+
+        let project = Project::from_str(Path::new("pixi.toml"), PROJECT).unwrap();
+        assert!(executable_task(&project, "and")
+            .keyring_env()
+            .unwrap()
+            .is_empty());
+        let err = executable_task(&project, "deploy")
+            .keyring_env()
+            .unwrap_err();
+        assert_eq!(err.name, "TOKEN");
+        assert_eq!(err.entry, "no-account");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_task_shell() {
+        let project = Project::from_str(Path::new("pixi.toml"), PROJECT).unwrap();
+        let output = executable_task(&project, "posix pixi")
+            .as_shell_command(&HashMap::new())
+            .unwrap()
+            .unwrap()
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "pixi from sh\n");
+
+        // The arguments are quoted for the shell
+        let output = executable_task(&project, "quoted it's$HOME")
+            .as_shell_command(&HashMap::new())
+            .unwrap()
+            .unwrap()
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "it's$HOME\n");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        let posix = ShellKind::from_path(Path::new("/bin/bash"));
+        assert_eq!(posix, ShellKind::Posix);
+        assert_eq!(posix.quote("foo-1.0"), "foo-1.0");
+        assert_eq!(posix.quote(""), "''");
+        assert_eq!(posix.quote("foo bar"), "'foo bar'");
+        assert_eq!(posix.quote("it's $HOME"), r"'it'\''s $HOME'");
+
+        let pwsh = ShellKind::from_path(Path::new("pwsh.exe"));
+        assert_eq!(pwsh, ShellKind::PowerShell);
+        assert_eq!(pwsh.quote("foo"), "foo");
+        assert_eq!(pwsh.quote("it's $HOME"), "'it''s $HOME'");
+
+        let cmd = ShellKind::from_path(Path::new("cmd.exe"));
+        assert_eq!(cmd, ShellKind::Cmd);
+        assert_eq!(cmd.quote("C:\\Users"), "C:\\Users");
+        assert_eq!(cmd.quote("a \"b\" & c"), "\"a \"\"b\"\" & c\"");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_task_resource_limits() {
+        let project = Project::from_str(Path::new("pixi.toml"), PROJECT).unwrap();
+        let output = executable_task(&project, "limited")
+            .as_shell_command(&HashMap::new())
+            .unwrap()
+            .expect("the task runs in its shell")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "64\n");
+    }
+}
@@ -7,7 +7,10 @@
 //! The main entry-point to compute the hashes of all files in a directory is the
 //! [`FileHashes::from_files`] method.
 
-use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use ignore::{
+    overrides::{Override, OverrideBuilder},
+    WalkBuilder,
+};
 use itertools::Itertools;
 use std::hash::Hash;
 use std::{
@@ -72,31 +75,7 @@ impl FileHashes {
         }
 
         // Construct the custom filter
-        let mut ignore_builder = OverrideBuilder::new(root);
-        for ignore_line in filters {
-            let path = root.join(ignore_line.as_ref());
-            let mut pat = if ignore_line.as_ref().ends_with('/') {
-                format!("{}**", ignore_line.as_ref())
-            } else if path.exists() && path.is_dir() {
-                format!("{}/**", ignore_line.as_ref())
-            } else {
-                ignore_line.as_ref().to_owned()
-            };
-
-            if pat.starts_with('!') && !pat.starts_with("!/") {
-                // make sure there is a `/` at the 2nd place so that the pattern reads
-                // `!/**/lib.rs` instead of `!**/lib.rs`
-                pat.insert(1, '/');
-            } else {
-                // Same for the others, make sure they start in the right folder
-                if !pat.starts_with('/') {
-                    pat.insert(0, '/');
-                }
-            }
-            ignore_builder.add(&pat)?;
-        }
-
-        let filter = ignore_builder.build()?;
+        let filter = build_filter(root, filters)?;
 
         // Spawn a thread that will collect the results from a channel.
         let (tx, rx) = crossbeam_channel::bounded(100);
@@ -149,6 +128,41 @@ impl FileHashes {
     }
 }
 
+/// Constructs a filter from a set of gitignore-style globs, relative to `root`.
+///
+/// Globs that refer to an existing directory (or end with a `/`) match everything inside that
+/// directory.
+pub(crate) fn build_filter(
+    root: &Path,
+    filters: impl IntoIterator<Item = impl AsRef<str>>,
+) -> Result<Override, ignore::Error> {
+    let mut ignore_builder = OverrideBuilder::new(root);
+    for ignore_line in filters {
+        let path = root.join(ignore_line.as_ref());
+        let mut pat = if ignore_line.as_ref().ends_with('/') {
+            format!("{}**", ignore_line.as_ref())
+        } else if path.exists() && path.is_dir() {
+            format!("{}/**", ignore_line.as_ref())
+        } else {
+            ignore_line.as_ref().to_owned()
+        };
+
+        if pat.starts_with('!') && !pat.starts_with("!/") {
+            // make sure there is a `/` at the 2nd place so that the pattern reads
+            // `!/**/lib.rs` instead of `!**/lib.rs`
+            pat.insert(1, '/');
+        } else {
+            // Same for the others, make sure they start in the right folder
+            if !pat.starts_with('/') {
+                pat.insert(0, '/');
+            }
+        }
+        ignore_builder.add(&pat)?;
+    }
+
+    ignore_builder.build()
+}
+
 /// Computes the xxh3 hash of a file.
 fn compute_file_hash(path: &Path) -> Result<String, FileHashesError> {
     let mut file =
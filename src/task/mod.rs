@@ -1,24 +1,39 @@
+use crate::EnvironmentName;
 use indexmap::IndexMap;
 use itertools::Itertools;
+use rattler_conda_types::Platform;
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::PreferMany, serde_as, OneOrMany};
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 
+mod changed_files;
+mod detached;
 mod error;
 mod executable_task;
 mod file_hashes;
+mod output_filter;
+mod parallel_output;
+mod resource_limits;
 mod task_environment;
 mod task_graph;
 mod task_hash;
+mod watch;
 
+pub use changed_files::{ChangedFiles, ChangedFilesError};
+pub use detached::{DetachedTask, DetachedTasks, StopOutcome};
+pub use error::TaskArgumentError;
 pub use file_hashes::{FileHashes, FileHashesError};
+pub use output_filter::OutputFilter;
+pub use parallel_output::{ParallelOutput, TaskOutput};
+pub use resource_limits::{ResourceLimits, ULimit};
 pub use task_hash::{ComputationHash, InputHashes, TaskHash};
+pub use watch::{run_on_changes, FileWatcher};
 
 pub use executable_task::{
     CanSkip, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory, RunOutput,
-    TaskExecutionError,
+    ShellNotFound, TaskExecutionError, REPRODUCIBLE_ENV,
 };
 pub use task_environment::{
     AmbiguousTask, FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment,
@@ -55,6 +70,259 @@ impl From<TaskName> for String {
     }
 }
 
+/// A dependency of a task on another task.
+///
+/// In the manifest a dependency is either the name of a task or a table that also specifies
+/// when the dependency applies, e.g. `{ task = "win-setup", platform = "win-64" }`, or whether
+/// the dependency is allowed to fail, e.g. `{ task = "warm-cache", allow-failure = true }`.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(from = "TomlTaskDependency")]
+pub struct TaskDependency {
+    /// The name of the task that is depended on
+    pub task: TaskName,
+
+    /// When specified, the dependency only applies when running on this platform
+    pub platform: Option<Platform>,
+
+    /// When true, a non-zero exit code of the dependency is reported as a warning instead of
+    /// aborting the execution of the dependent tasks.
+    pub allow_failure: bool,
+}
+
+impl TaskDependency {
+    /// Returns true if this dependency applies when running on the given platform.
+    pub fn matches_platform(&self, platform: Platform) -> bool {
+        self.platform.map_or(true, |p| p == platform)
+    }
+}
+
+impl From<TaskName> for TaskDependency {
+    fn from(task: TaskName) -> Self {
+        TaskDependency {
+            task,
+            platform: None,
+            allow_failure: false,
+        }
+    }
+}
+
+/// The representation of a [`TaskDependency`] in the manifest.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TomlTaskDependency {
+    Name(TaskName),
+    Table(TomlTaskDependencyTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct TomlTaskDependencyTable {
+    task: TaskName,
+    platform: Option<Platform>,
+    #[serde(default)]
+    allow_failure: bool,
+}
+
+impl From<TomlTaskDependency> for TaskDependency {
+    fn from(value: TomlTaskDependency) -> Self {
+        match value {
+            TomlTaskDependency::Name(task) => task.into(),
+            TomlTaskDependency::Table(table) => TaskDependency {
+                task: table.task,
+                platform: table.platform,
+                allow_failure: table.allow_failure,
+            },
+        }
+    }
+}
+
+/// An argument of a task that is passed on the command line.
+///
+/// In the manifest an argument is a table with the name of the argument and optionally the values
+/// that are allowed and a default, e.g. `{ name = "env", choices = ["staging", "prod"] }`. The
+/// value is available in the command as `$env` and `{{ env }}`.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskArg {
+    /// The name of the argument, it is exported as a shell variable so it has to be a valid
+    /// identifier.
+    #[serde(deserialize_with = "deserialize_arg_name")]
+    pub name: String,
+
+    /// The values that are allowed for this argument, any value is allowed if this is `None`.
+    pub choices: Option<Vec<String>>,
+
+    /// The value that is used when the argument is not specified on the command line.
+    pub default: Option<String>,
+}
+
+/// The environment variables that the activation of an environment relies on, an argument with one
+/// of these names would override the activated value.
+const RESERVED_ARG_NAMES: [&str; 8] = [
+    "PATH",
+    "HOME",
+    "PYTHONPATH",
+    "PYTHONHOME",
+    "LD_LIBRARY_PATH",
+    "DYLD_LIBRARY_PATH",
+    "PATHEXT",
+    "SHELL",
+];
+
+/// The prefixes of the environment variables that are set by the activation of an environment.
+const RESERVED_ARG_PREFIXES: [&str; 2] = ["CONDA_", "PIXI_"];
+
+/// Returns true if an argument with the given name would override an environment variable that is
+/// set by the activation. Environment variables are case-insensitive on Windows.
+fn is_reserved_arg_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    RESERVED_ARG_NAMES.contains(&name.as_str())
+        || RESERVED_ARG_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Deserializes the name of a [`TaskArg`], the name has to match `[A-Za-z_][A-Za-z0-9_]*` and
+/// may not be the name of an environment variable that is set by the activation.
+fn deserialize_arg_name<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    let mut chars = name.chars();
+    let is_valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_valid {
+        return Err(serde::de::Error::custom(format!(
+            "'{name}' is not a valid argument name, a name may only contain letters, digits and \
+            underscores and may not start with a digit"
+        )));
+    }
+    if is_reserved_arg_name(&name) {
+        return Err(serde::de::Error::custom(format!(
+            "'{name}' is not a valid argument name, the argument would override the environment \
+            variable '{name}' of the activated environment"
+        )));
+    }
+    Ok(name)
+}
+
+/// Binds the command line `values` to the arguments declared by the task with the given name.
+///
+/// Values are bound to the arguments in the order in which the arguments are declared. Arguments
+/// without a value use their default.
+pub fn bind_task_arguments(
+    task_name: &TaskName,
+    args: &[TaskArg],
+    values: Vec<String>,
+) -> Result<IndexMap<String, String>, TaskArgumentError> {
+    if values.len() > args.len() {
+        return Err(TaskArgumentError::Unexpected {
+            task_name: task_name.clone(),
+            value: values[args.len()].clone(),
+        });
+    }
+
+    let mut values = values.into_iter();
+    let mut arguments = IndexMap::with_capacity(args.len());
+    for arg in args {
+        let Some(value) = values.next().or_else(|| arg.default.clone()) else {
+            return Err(TaskArgumentError::Missing {
+                task_name: task_name.clone(),
+                argument: arg.name.clone(),
+                choices: arg.choices.clone(),
+            });
+        };
+        if let Some(choices) = &arg.choices {
+            if !choices.contains(&value) {
+                return Err(TaskArgumentError::InvalidChoice {
+                    task_name: task_name.clone(),
+                    argument: arg.name.clone(),
+                    value,
+                    choices: choices.clone(),
+                });
+            }
+        }
+        arguments.insert(arg.name.clone(), value);
+    }
+    Ok(arguments)
+}
+
+/// Returns the variables that are available in the command of every task of the project, the
+/// arguments of a task take precedence over them.
+pub fn task_variables(
+    project_root: &Path,
+    environment: &EnvironmentName,
+    platform: Platform,
+) -> IndexMap<String, String> {
+    IndexMap::from([
+        (
+            String::from("project_root"),
+            project_root.display().to_string(),
+        ),
+        (String::from("env_name"), environment.as_str().to_string()),
+        (String::from("platform"), platform.to_string()),
+    ])
+}
+
+lazy_static::lazy_static! {
+    /// Matches a `{{ name }}` placeholder, a placeholder that is preceded by a backslash is escaped.
+    static ref PLACEHOLDER: regex::Regex =
+        regex::Regex::new(r"(\\)?\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}")
+            .expect("the placeholder regex is valid");
+}
+
+/// Returns the names of the placeholders in the command that do not refer to one of the
+/// variables, escaped placeholders are skipped.
+pub fn undefined_task_variables<'a>(
+    command: &'a str,
+    variables: &IndexMap<String, String>,
+) -> Vec<&'a str> {
+    PLACEHOLDER
+        .captures_iter(command)
+        .filter(|captures| captures.get(1).is_none())
+        .filter_map(|captures| captures.get(2))
+        .map(|name| name.as_str())
+        .filter(|name| !variables.contains_key(*name))
+        .unique()
+        .collect()
+}
+
+/// Replaces all occurrences of `{{ name }}` in the command with the quoted value of the variable
+/// with that name, `\{{ name }}` is replaced by the literal `{{ name }}`. Placeholders that do
+/// not refer to a variable are left untouched, they are rejected when the task graph is built.
+///
+/// Without any variables the command is returned verbatim, which is the case for commands that
+/// are not tasks of the manifest.
+pub fn render_task_arguments<'a>(
+    command: &'a str,
+    variables: &IndexMap<String, String>,
+) -> Cow<'a, str> {
+    render_task_arguments_with(command, variables, quote)
+}
+
+/// Like [`render_task_arguments`], but quotes the values of the variables with `quote`, e.g. for
+/// a task that runs in a shell of its own.
+pub fn render_task_arguments_with<'a>(
+    command: &'a str,
+    variables: &IndexMap<String, String>,
+    quote: impl Fn(&str) -> Cow<str>,
+) -> Cow<'a, str> {
+    if variables.is_empty() {
+        return Cow::Borrowed(command);
+    }
+    PLACEHOLDER.replace_all(command, |captures: &regex::Captures<'_>| {
+        if captures.get(1).is_some() {
+            return captures[0][1..].to_string();
+        }
+        match variables.get(&captures[2]) {
+            Some(value) => quote(value).into_owned(),
+            None => captures[0].to_string(),
+        }
+    })
+}
+
 /// Represents different types of scripts
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
@@ -69,8 +337,8 @@ pub enum Task {
 }
 
 impl Task {
-    /// Returns the names of the task that this task depends on
-    pub fn depends_on(&self) -> &[TaskName] {
+    /// Returns the dependencies of this task on other tasks
+    pub fn depends_on(&self) -> &[TaskDependency] {
         match self {
             Task::Plain(_) | Task::Custom(_) => &[],
             Task::Execute(cmd) => &cmd.depends_on,
@@ -78,6 +346,14 @@ impl Task {
         }
     }
 
+    /// Returns the arguments that this task accepts from the command line.
+    pub fn args(&self) -> &[TaskArg] {
+        match self {
+            Task::Execute(cmd) => &cmd.args,
+            _ => &[],
+        }
+    }
+
     /// If this task is a plain task, returns the task string
     pub fn as_plain(&self) -> Option<&String> {
         match self {
@@ -140,6 +416,33 @@ impl Task {
         }
     }
 
+    /// Returns the environment variables that are read from the keyring.
+    pub fn env_from_keyring(&self) -> Option<&IndexMap<String, String>> {
+        match self {
+            Task::Execute(exe) => exe.env_from_keyring.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the shell the task runs in instead of the deno task shell.
+    pub fn shell(&self) -> Option<&str> {
+        match self {
+            Task::Execute(exe) => exe.shell.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the priority and the limits of the process of the task.
+    pub fn resource_limits(&self) -> ResourceLimits {
+        match self {
+            Task::Execute(exe) => ResourceLimits {
+                nice: exe.nice,
+                ulimit: exe.ulimit.unwrap_or_default(),
+            },
+            _ => ResourceLimits::default(),
+        }
+    }
+
     /// Returns the working directory for the task to run in.
     pub fn working_directory(&self) -> Option<&Path> {
         match self {
@@ -175,13 +478,33 @@ pub struct Execute {
     // BREAK: Make the alias a renamed field to force kebab-case
     #[serde(default, alias = "depends-on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<TaskDependency>,
 
     /// The working directory for the command relative to the root of the project.
     pub cwd: Option<PathBuf>,
 
     /// A list of environment variables to set before running the command
     pub env: Option<IndexMap<String, String>>,
+
+    /// Environment variables whose values are read from the keyring of the operating system when
+    /// the command is run, the values have the form `service:account`.
+    #[serde(rename = "env-from-keyring")]
+    pub env_from_keyring: Option<IndexMap<String, String>>,
+
+    /// The arguments that can be passed to the command on the command line
+    #[serde(default)]
+    pub args: Vec<TaskArg>,
+
+    /// The shell (e.g. `pwsh` or `bash`) to run the command in instead of the cross-platform
+    /// deno task shell. The shell is searched for on the `PATH` of the environment.
+    pub shell: Option<String>,
+
+    /// The niceness of the process of the command, from -20 (highest priority) to 19 (lowest
+    /// priority). On Windows this is mapped to a priority class.
+    pub nice: Option<i32>,
+
+    /// The limits of the resources the process of the command can use, only supported on Unix.
+    pub ulimit: Option<ULimit>,
 }
 
 impl From<Execute> for Task {
@@ -250,7 +573,7 @@ pub struct Alias {
     /// A list of commands that should be run before this one
     #[serde(alias = "depends-on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<TaskDependency>,
 }
 
 impl Display for Task {
@@ -272,13 +595,13 @@ impl Display for Task {
                 write!(
                     f,
                     ", depends-on = '{}'",
-                    depends_on.iter().map(|t| t.fancy_display()).join(",")
+                    depends_on.iter().map(|t| t.task.fancy_display()).join(",")
                 )?;
             } else {
                 write!(
                     f,
                     ", depends-on = [{}]",
-                    depends_on.iter().map(|t| t.fancy_display()).join(",")
+                    depends_on.iter().map(|t| t.task.fancy_display()).join(",")
                 )?;
             }
         }
@@ -326,7 +649,7 @@ pub fn quote_arguments<'a>(args: impl IntoIterator<Item = &'a str>) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::quote;
+    use super::{quote, TaskArg};
 
     #[test]
     fn test_quote() {
@@ -342,4 +665,30 @@ mod tests {
         );
         assert_eq!(quote("name=[64,64]"), "\"name=[64,64]\"");
     }
+
+    #[test]
+    fn test_task_arg_name() {
+        let arg: TaskArg = toml_edit::de::from_str(r#"name = "out_dir""#).unwrap();
+        assert_eq!(arg.name, "out_dir");
+        for name in ["out-dir", "1st", "", "out dir"] {
+            let err = toml_edit::de::from_str::<TaskArg>(&format!("name = {name:?}")).unwrap_err();
+            assert!(
+                err.to_string().contains("is not a valid argument name"),
+                "{name}: {err}"
+            );
+        }
+        for name in [
+            "PATH",
+            "path",
+            "PYTHONPATH",
+            "CONDA_PREFIX",
+            "pixi_project_root",
+        ] {
+            let err = toml_edit::de::from_str::<TaskArg>(&format!("name = {name:?}")).unwrap_err();
+            assert!(
+                err.to_string().contains("would override the environment"),
+                "{name}: {err}"
+            );
+        }
+    }
 }
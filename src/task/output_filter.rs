@@ -0,0 +1,155 @@
+//! Filtering of the output of tasks with `pixi run --grep`. Only the lines that match the regex
+//! are written, the exit code of the task is not affected.
+
+use deno_task_shell::{pipe, ShellPipeWriter};
+use regex::bytes::Regex;
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use tokio::task::JoinHandle;
+
+/// Which output of a task is filtered and the regex the lines have to match.
+#[derive(Debug, Clone)]
+pub struct OutputFilter {
+    pub regex: Regex,
+    /// Whether stderr is filtered as well, otherwise it is passed through untouched.
+    pub stderr: bool,
+}
+
+impl OutputFilter {
+    /// Creates a pipe for the deno task shell. The lines written to it are filtered and written
+    /// to `writer` on a background thread, the returned handle finishes when the pipe is closed.
+    pub fn shell_pipe(
+        &self,
+        writer: impl Write + Send + 'static,
+    ) -> (ShellPipeWriter, JoinHandle<()>) {
+        let (reader, pipe_writer) = pipe();
+        let mut filter = LineFilter::new(self.regex.clone(), writer);
+        let handle = tokio::task::spawn_blocking(move || {
+            if let Err(err) = reader.pipe_to(&mut filter).and_then(|_| filter.finish()) {
+                tracing::debug!("failed to write the filtered output of the task: {err}");
+            }
+        });
+        (pipe_writer, handle)
+    }
+
+    /// Runs the command to completion while filtering its output. With `merge_stderr` the stderr
+    /// of the command is filtered and written to stdout as well.
+    pub fn status(&self, command: &mut Command, merge_stderr: bool) -> io::Result<ExitStatus> {
+        command.stdout(Stdio::piped());
+        if self.stderr || merge_stderr {
+            command.stderr(Stdio::piped());
+        }
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        std::thread::scope(|scope| {
+            if let Some(mut stderr) = stderr {
+                scope.spawn(move || {
+                    if merge_stderr {
+                        self.copy(&mut stderr, io::stdout())
+                    } else {
+                        self.copy(&mut stderr, io::stderr())
+                    }
+                });
+            }
+            if let Some(mut stdout) = stdout {
+                self.copy(&mut stdout, io::stdout());
+            }
+            child.wait()
+        })
+    }
+
+    /// Copies the lines of `reader` that match the regex to `writer`.
+    fn copy(&self, reader: &mut impl Read, writer: impl Write) {
+        let mut filter = LineFilter::new(self.regex.clone(), writer);
+        if let Err(err) = io::copy(reader, &mut filter).and_then(|_| filter.finish()) {
+            tracing::debug!("failed to write the filtered output of the task: {err}");
+        }
+    }
+}
+
+/// A writer that only passes on the lines that match a regex. Lines are buffered until they are
+/// complete, [`LineFilter::finish`] handles a last line without a newline.
+pub struct LineFilter<W: Write> {
+    regex: Regex,
+    writer: W,
+    line: Vec<u8>,
+}
+
+impl<W: Write> LineFilter<W> {
+    pub fn new(regex: Regex, writer: W) -> Self {
+        Self {
+            regex,
+            writer,
+            line: Vec::new(),
+        }
+    }
+
+    /// Writes the last line if it matches and flushes the writer.
+    pub fn finish(mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.write_line(&line)?;
+        }
+        self.writer.flush()
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        if self.regex.is_match(content) {
+            self.writer.write_all(line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for LineFilter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            self.write_line(&line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_filter() {
+        let mut output = Vec::new();
+        let mut filter = LineFilter::new(Regex::new("^(error|warning):").unwrap(), &mut output);
+        filter.write_all(b"compiling\nerror: foo\r\nwarn").unwrap();
+        filter.write_all(b"ing: bar\nwarning: last").unwrap();
+        filter.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "error: foo\r\nwarning: bar\nwarning: last"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_status_keeps_exit_code() {
+        let filter = OutputFilter {
+            regex: Regex::new("^match").unwrap(),
+            stderr: true,
+        };
+        let status = filter
+            .status(
+                Command::new("sh").args(["-c", "echo match; echo other >&2; exit 3"]),
+                false,
+            )
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+}
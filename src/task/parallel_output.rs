@@ -0,0 +1,300 @@
+//! The output of tasks that run in parallel with `pixi run --interleave` or `pixi run
+//! --serialize`. Independent tasks would otherwise write to the terminal at the same time.
+
+use super::output_filter::{LineFilter, OutputFilter};
+use deno_task_shell::{pipe, ShellPipeWriter};
+use regex::bytes::Regex;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// How the output of tasks that run in parallel is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParallelOutput {
+    /// Stream the output live, every line is prefixed with the name of the task.
+    Interleave,
+    /// Buffer the output of every task and print it once all tasks of a layer finished, in the
+    /// order of the tasks.
+    Serialize,
+}
+
+impl ParallelOutput {
+    /// Returns where the output of the task with the given name is written to.
+    pub fn task_output(self, name: &str) -> TaskOutput {
+        match self {
+            ParallelOutput::Interleave => TaskOutput::Prefixed(
+                format!("{} ", console::style(format!("[{name}]")).cyan()).into_bytes(),
+            ),
+            ParallelOutput::Serialize => TaskOutput::Buffered {
+                stdout: SharedBuffer::default(),
+                stderr: SharedBuffer::default(),
+            },
+        }
+    }
+}
+
+/// The destination of the output of a single task that runs in parallel with other tasks.
+#[derive(Debug, Clone)]
+pub enum TaskOutput {
+    /// Every line is written immediately, prefixed with these bytes.
+    Prefixed(Vec<u8>),
+    /// The output is kept in memory until [`TaskOutput::print_buffered`] is called.
+    Buffered {
+        stdout: SharedBuffer,
+        stderr: SharedBuffer,
+    },
+}
+
+impl TaskOutput {
+    /// Creates the stdout and stderr pipes for the deno task shell. The returned handles finish
+    /// when all the output is written.
+    pub fn shell_pipes(
+        &self,
+        filter: Option<&OutputFilter>,
+        merge_stderr: bool,
+    ) -> (ShellPipeWriter, ShellPipeWriter, Vec<JoinHandle<()>>) {
+        let (stdout_writer, stderr_writer) = self.writers(merge_stderr);
+        let (stdout, stdout_handle) = shell_pipe(stdout_writer, stdout_regex(filter));
+        let (stderr, stderr_handle) = shell_pipe(stderr_writer, stderr_regex(filter, merge_stderr));
+        (stdout, stderr, vec![stdout_handle, stderr_handle])
+    }
+
+    /// Runs the command to completion while writing its output to this destination.
+    pub fn status(
+        &self,
+        command: &mut Command,
+        filter: Option<&OutputFilter>,
+        merge_stderr: bool,
+    ) -> io::Result<ExitStatus> {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let (stdout_writer, stderr_writer) = self.writers(merge_stderr);
+
+        std::thread::scope(|scope| {
+            if let Some(mut stderr) = stderr {
+                scope.spawn(move || {
+                    forward(
+                        stderr_writer,
+                        stderr_regex(filter, merge_stderr),
+                        |writer| io::copy(&mut stderr, writer).map(|_| ()),
+                    )
+                });
+            }
+            if let Some(mut stdout) = stdout {
+                forward(stdout_writer, stdout_regex(filter), |writer| {
+                    io::copy(&mut stdout, writer).map(|_| ())
+                });
+            }
+            child.wait()
+        })
+    }
+
+    /// Writes the buffered output of the task, prefixed output has already been written.
+    pub fn print_buffered(&self) -> io::Result<()> {
+        if let TaskOutput::Buffered { stdout, stderr } = self {
+            io::stdout().write_all(&stdout.take())?;
+            io::stderr().write_all(&stderr.take())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the writers for stdout and stderr, with `merge_stderr` stderr is written to the
+    /// destination of stdout.
+    fn writers(&self, merge_stderr: bool) -> (Box<dyn Write + Send>, Box<dyn Write + Send>) {
+        match self {
+            TaskOutput::Prefixed(prefix) => {
+                let stderr: Box<dyn Write + Send> = if merge_stderr {
+                    Box::new(LinePrefix::new(prefix.clone(), io::stdout()))
+                } else {
+                    Box::new(LinePrefix::new(prefix.clone(), io::stderr()))
+                };
+                (
+                    Box::new(LinePrefix::new(prefix.clone(), io::stdout())),
+                    stderr,
+                )
+            }
+            TaskOutput::Buffered { stdout, stderr } => {
+                let stderr = if merge_stderr { stdout } else { stderr };
+                (Box::new(stdout.clone()), Box::new(stderr.clone()))
+            }
+        }
+    }
+}
+
+fn stdout_regex(filter: Option<&OutputFilter>) -> Option<Regex> {
+    filter.map(|filter| filter.regex.clone())
+}
+
+/// Merged stderr is part of stdout, so it is filtered like stdout.
+fn stderr_regex(filter: Option<&OutputFilter>, merge_stderr: bool) -> Option<Regex> {
+    filter
+        .filter(|filter| filter.stderr || merge_stderr)
+        .map(|filter| filter.regex.clone())
+}
+
+/// Creates a pipe for the deno task shell that forwards to `writer` on a background thread.
+fn shell_pipe(
+    writer: Box<dyn Write + Send>,
+    regex: Option<Regex>,
+) -> (ShellPipeWriter, JoinHandle<()>) {
+    let (reader, pipe_writer) = pipe();
+    let handle = tokio::task::spawn_blocking(move || {
+        forward(writer, regex, |writer| reader.pipe_to(writer));
+    });
+    (pipe_writer, handle)
+}
+
+/// Forwards the output produced by `copy` to `writer`, only the lines that match the regex are
+/// written if one is given.
+fn forward(
+    writer: Box<dyn Write + Send>,
+    regex: Option<Regex>,
+    copy: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) {
+    let result = match regex {
+        Some(regex) => {
+            let mut filter = LineFilter::new(regex, writer);
+            copy(&mut filter).and_then(|_| filter.finish())
+        }
+        None => {
+            let mut writer = writer;
+            copy(&mut writer).and_then(|_| writer.flush())
+        }
+    };
+    if let Err(err) = result {
+        tracing::debug!("failed to write the output of the task: {err}");
+    }
+}
+
+/// A buffer that is shared between the threads that write the output of a task.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer that prefixes every line. Complete lines are written at once so lines of tasks that
+/// run at the same time don't mix, a last line without a newline is written when it is dropped.
+struct LinePrefix<W: Write> {
+    prefix: Vec<u8>,
+    writer: W,
+    line: Vec<u8>,
+}
+
+impl<W: Write> LinePrefix<W> {
+    fn new(prefix: Vec<u8>, writer: W) -> Self {
+        Self {
+            prefix,
+            writer,
+            line: Vec::new(),
+        }
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let mut prefixed = self.prefix.clone();
+        prefixed.extend_from_slice(line);
+        self.writer.write_all(&prefixed)
+    }
+}
+
+impl<W: Write> Write for LinePrefix<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            self.write_line(&line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for LinePrefix<W> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            let mut line = std::mem::take(&mut self.line);
+            line.push(b'\n');
+            let _ = self.write_line(&line).and_then(|_| self.writer.flush());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_prefix() {
+        let mut output = Vec::new();
+        {
+            let mut writer = LinePrefix::new(b"[build] ".to_vec(), &mut output);
+            writer.write_all(b"first\nsec").unwrap();
+            writer.write_all(b"ond\nlast").unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[build] first\n[build] second\n[build] last\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_buffered_status() {
+        let output = ParallelOutput::Serialize.task_output("test");
+        let status = output
+            .status(
+                Command::new("sh").args(["-c", "echo out; echo err >&2; exit 2"]),
+                None,
+                false,
+            )
+            .unwrap();
+        assert_eq!(status.code(), Some(2));
+        let TaskOutput::Buffered { stdout, stderr } = &output else {
+            panic!("expected buffered output");
+        };
+        assert_eq!(stdout.take(), b"out\n");
+        assert_eq!(stderr.take(), b"err\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_buffered_status_merge_stderr() {
+        let output = ParallelOutput::Serialize.task_output("test");
+        let status = output
+            .status(
+                Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+                None,
+                true,
+            )
+            .unwrap();
+        assert!(status.success());
+        let TaskOutput::Buffered { stdout, stderr } = &output else {
+            panic!("expected buffered output");
+        };
+        let stdout = String::from_utf8(stdout.take()).unwrap();
+        let mut lines = stdout.lines().collect::<Vec<_>>();
+        lines.sort();
+        assert_eq!(lines, vec!["err", "out"]);
+        assert!(stderr.take().is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! The resource limits of tasks, `nice` and `ulimit`. The limits are set in the process of the
+//! task before the command is executed, so they also apply to the processes the task starts.
+
+use serde::Deserialize;
+use std::process::Command;
+
+/// The limits of the `ulimit` of a task.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ULimit {
+    /// The maximum size of the virtual memory of the process in MiB.
+    pub memory: Option<u64>,
+    /// The maximum number of file descriptors the process can open.
+    pub open_files: Option<u64>,
+}
+
+/// The priority and the limits of the process of a task.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// The niceness of the process, from -20 (highest priority) to 19 (lowest priority).
+    pub nice: Option<i32>,
+    pub ulimit: ULimit,
+}
+
+impl ResourceLimits {
+    /// Returns true if the task runs without any limits.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets the limits in the process of the command before it is executed.
+    #[cfg(unix)]
+    pub fn apply(&self, command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+        if self.is_empty() {
+            return;
+        }
+        let limits = *self;
+        // SAFETY: the closure only calls async-signal-safe functions and doesn't allocate.
+        unsafe {
+            command.pre_exec(move || limits.set_for_current_process());
+        }
+    }
+
+    /// Maps the niceness to a priority class of the process of the command, the `ulimit` is not
+    /// supported on Windows and ignored with a warning.
+    #[cfg(windows)]
+    pub fn apply(&self, command: &mut Command) {
+        use std::os::windows::process::CommandExt;
+        if let Some(nice) = self.nice {
+            command.creation_flags(priority_class(nice));
+        }
+        if self.ulimit != ULimit::default() {
+            eprintln!(
+                "{}the `ulimit` of a task is not supported on Windows and is ignored",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+            );
+        }
+    }
+
+    /// Sets the priority class of the niceness for the pixi process until the returned guard is
+    /// dropped. A task without a `shell` runs in the deno task shell inside of pixi, the
+    /// processes it starts inherit the priority class of pixi.
+    #[cfg(windows)]
+    pub fn enter_priority_class(&self) -> Option<PriorityClassGuard> {
+        if self.ulimit != ULimit::default() {
+            eprintln!(
+                "{}the `ulimit` of a task is not supported on Windows and is ignored",
+                console::style(console::Emoji("⚠️ ", "")).yellow().bold(),
+            );
+        }
+        let nice = self.nice?;
+        // SAFETY: the pseudo handle of the current process is always valid.
+        unsafe {
+            let process = GetCurrentProcess();
+            let previous = GetPriorityClass(process);
+            if previous == 0 || SetPriorityClass(process, priority_class(nice)) == 0 {
+                return None;
+            }
+            Some(PriorityClassGuard { previous })
+        }
+    }
+
+    #[cfg(unix)]
+    fn set_for_current_process(&self) -> std::io::Result<()> {
+        if let Some(nice) = self.nice {
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        if let Some(memory) = self.ulimit.memory {
+            set_soft_limit(libc::RLIMIT_AS, memory.saturating_mul(1024 * 1024))?;
+        }
+        if let Some(open_files) = self.ulimit.open_files {
+            set_soft_limit(libc::RLIMIT_NOFILE, open_files)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+/// Sets the soft limit of the resource, it can't be raised above the hard limit.
+#[cfg(unix)]
+fn set_soft_limit(resource: Resource, value: u64) -> std::io::Result<()> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(resource, &mut limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    limit.rlim_cur = (value as libc::rlim_t).min(limit.rlim_max);
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Restores the priority class of the pixi process when it is dropped.
+#[cfg(windows)]
+pub struct PriorityClassGuard {
+    previous: u32,
+}
+
+#[cfg(windows)]
+impl Drop for PriorityClassGuard {
+    fn drop(&mut self) {
+        // SAFETY: the pseudo handle of the current process is always valid.
+        unsafe {
+            SetPriorityClass(GetCurrentProcess(), self.previous);
+        }
+    }
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetCurrentProcess() -> *mut std::ffi::c_void;
+    fn GetPriorityClass(process: *mut std::ffi::c_void) -> u32;
+    fn SetPriorityClass(process: *mut std::ffi::c_void, priority_class: u32) -> i32;
+}
+
+/// Returns the Windows priority class that matches the niceness best.
+#[cfg(windows)]
+fn priority_class(nice: i32) -> u32 {
+    const IDLE_PRIORITY_CLASS: u32 = 0x0000_0040;
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+    const NORMAL_PRIORITY_CLASS: u32 = 0x0000_0020;
+    const ABOVE_NORMAL_PRIORITY_CLASS: u32 = 0x0000_8000;
+    const HIGH_PRIORITY_CLASS: u32 = 0x0000_0080;
+    match nice {
+        15.. => IDLE_PRIORITY_CLASS,
+        1..=14 => BELOW_NORMAL_PRIORITY_CLASS,
+        0 => NORMAL_PRIORITY_CLASS,
+        -14..=-1 => ABOVE_NORMAL_PRIORITY_CLASS,
+        _ => HIGH_PRIORITY_CLASS,
+    }
+}
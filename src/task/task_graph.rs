@@ -2,15 +2,20 @@ use crate::project::virtual_packages::{
     verify_current_platform_has_required_virtual_packages, VerifyCurrentPlatformError,
 };
 use crate::project::Environment;
-use crate::task::error::AmbiguousTaskError;
+use crate::task::error::{AmbiguousTaskError, TaskArgumentError};
 use crate::task::task_environment::{FindTaskError, FindTaskSource, SearchEnvironments};
-use crate::task::{TaskDisambiguation, TaskName};
+use crate::task::{
+    bind_task_arguments, render_task_arguments, task_variables, undefined_task_variables,
+    TaskDisambiguation, TaskName,
+};
 use crate::{
     task::{error::MissingTaskError, CmdArgs, Custom, Task},
     Project,
 };
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::Diagnostic;
+use rattler_conda_types::Platform;
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
@@ -40,8 +45,18 @@ pub struct TaskNode<'p> {
     /// Additional arguments to pass to the command
     pub additional_args: Vec<String>,
 
+    /// The values of the arguments that the task declares
+    pub arguments: IndexMap<String, String>,
+
+    /// The values of the variables that are available in the command, the arguments together with
+    /// variables like `project_root`. This is empty for custom commands.
+    pub variables: IndexMap<String, String>,
+
     /// The id's of the task that this task depends on.
     pub dependencies: Vec<TaskId>,
+
+    /// True if all the tasks that depend on this task allow it to fail.
+    pub allow_failure: bool,
 }
 impl fmt::Display for TaskNode<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -68,7 +83,8 @@ impl<'p> TaskNode<'p> {
     /// This function returns `None` if the task does not define a command to execute. This is the
     /// case for alias only commands.
     pub fn full_command(&self) -> Option<String> {
-        let mut cmd = self.task.as_single_command()?.to_string();
+        let command = self.task.as_single_command()?;
+        let mut cmd = render_task_arguments(&command, &self.variables).into_owned();
 
         if !self.additional_args.is_empty() {
             cmd.push(' ');
@@ -128,6 +144,16 @@ impl<'p> TaskGraph<'p> {
                     return Err(TaskGraphError::AmbiguousTask(err))
                 }
                 Ok((task_env, task)) => {
+                    let name: TaskName = args.remove(0).into();
+
+                    // If the task declares arguments, the command line arguments are bound to
+                    // them instead of being appended to the command.
+                    let (arguments, additional_args) = if task.args().is_empty() {
+                        (IndexMap::new(), args)
+                    } else {
+                        (bind_task_arguments(&name, task.args(), args)?, Vec::new())
+                    };
+
                     // If an explicit environment was specified and the task is from the default
                     // environment use the specified environment instead.
                     let run_env = match search_envs.explicit_environment.clone() {
@@ -138,11 +164,14 @@ impl<'p> TaskGraph<'p> {
                         project,
                         search_envs,
                         TaskNode {
-                            name: Some(args.remove(0).into()),
+                            name: Some(name),
                             task: Cow::Borrowed(task),
                             run_environment: run_env,
-                            additional_args: args,
+                            additional_args,
+                            arguments,
+                            variables: IndexMap::new(),
                             dependencies: vec![],
+                            allow_failure: false,
                         },
                     );
                 }
@@ -169,7 +198,10 @@ impl<'p> TaskGraph<'p> {
                 ),
                 run_environment,
                 additional_args: vec![],
+                arguments: IndexMap::new(),
+                variables: IndexMap::new(),
                 dependencies: vec![],
+                allow_failure: false,
             },
         )
     }
@@ -187,14 +219,26 @@ impl<'p> TaskGraph<'p> {
         // Iterate over all the nodes in the graph and add them to the graph.
         let mut next_node_to_visit = 0;
         while next_node_to_visit < nodes.len() {
-            let dependency_names =
-                Vec::from_iter(nodes[next_node_to_visit].task.depends_on().iter().cloned());
+            // Dependencies that do not apply to the platform we are running on are skipped.
+            let platform = search_environments
+                .platform
+                .unwrap_or_else(|| nodes[next_node_to_visit].run_environment.best_platform());
+            Self::bind_variables(project, &mut nodes[next_node_to_visit], platform)?;
+            let dependency_names = nodes[next_node_to_visit]
+                .task
+                .depends_on()
+                .iter()
+                .filter(|dependency| dependency.matches_platform(platform))
+                .map(|dependency| (dependency.task.clone(), dependency.allow_failure))
+                .collect_vec();
 
             // Iterate over all the dependencies of the node and add them to the graph.
             let mut node_dependencies = Vec::with_capacity(dependency_names.len());
-            for dependency in dependency_names {
+            for (dependency, allow_failure) in dependency_names {
                 // Check if we visited this node before already.
                 if let Some(&task_id) = task_name_to_node.get(&dependency) {
+                    // A task may only fail if none of the tasks that depend on it require it.
+                    nodes[task_id.0].allow_failure &= allow_failure;
                     node_dependencies.push(task_id);
                     continue;
                 }
@@ -224,6 +268,9 @@ impl<'p> TaskGraph<'p> {
                     Ok(result) => result,
                 };
 
+                // Dependencies are run with the default values of their arguments.
+                let arguments = bind_task_arguments(&dependency, task_dependency.args(), vec![])?;
+
                 // Add the node to the graph
                 let task_id = TaskId(nodes.len());
                 nodes.push(TaskNode {
@@ -231,7 +278,10 @@ impl<'p> TaskGraph<'p> {
                     task: Cow::Borrowed(task_dependency),
                     run_environment: task_env,
                     additional_args: Vec::new(),
+                    arguments,
+                    variables: IndexMap::new(),
                     dependencies: Vec::new(),
+                    allow_failure,
                 });
 
                 // Store the task id in the map to be able to look up the name later
@@ -248,6 +298,31 @@ impl<'p> TaskGraph<'p> {
         Ok(Self { project, nodes })
     }
 
+    /// Sets the variables that are available in the command of a task of the manifest, a
+    /// placeholder that does not refer to one of them is an error.
+    fn bind_variables(
+        project: &Project,
+        node: &mut TaskNode<'p>,
+        platform: Platform,
+    ) -> Result<(), TaskArgumentError> {
+        let Some(name) = &node.name else {
+            return Ok(());
+        };
+        let mut variables = task_variables(project.root(), node.run_environment.name(), platform);
+        variables.extend(node.arguments.clone());
+        if let Some(command) = node.task.as_single_command() {
+            if let Some(variable) = undefined_task_variables(&command, &variables).first() {
+                return Err(TaskArgumentError::UndefinedVariable {
+                    task_name: name.clone(),
+                    variable: variable.to_string(),
+                    available: variables.keys().cloned().collect(),
+                });
+            }
+        }
+        node.variables = variables;
+        Ok(())
+    }
+
     /// Returns the topological order of the tasks in the graph.
     ///
     /// The topological order is the order in which the tasks should be executed to ensure that
@@ -279,6 +354,30 @@ impl<'p> TaskGraph<'p> {
             order.push(id);
         }
     }
+
+    /// Returns the tasks grouped in layers that can be executed in parallel.
+    ///
+    /// A task is placed in the layer after the last layer of its `depends-on` tasks, so all the
+    /// tasks in a layer only depend on tasks of earlier layers. Within a layer the tasks are in
+    /// topological order.
+    pub fn parallel_layers(&self) -> Vec<Vec<TaskId>> {
+        let mut depths: HashMap<TaskId, usize> = HashMap::new();
+        let mut layers: Vec<Vec<TaskId>> = Vec::new();
+        for id in self.topological_order() {
+            let depth = self.nodes[id.0]
+                .dependencies
+                .iter()
+                .map(|dependency| depths[dependency] + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(id, depth);
+            if layers.len() <= depth {
+                layers.resize_with(depth + 1, Vec::new);
+            }
+            layers[depth].push(id);
+        }
+        layers
+    }
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -293,6 +392,10 @@ pub enum TaskGraphError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     UnsupportedPlatform(#[from] VerifyCurrentPlatformError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    InvalidTaskArgument(#[from] TaskArgumentError),
 }
 
 #[cfg(test)]
@@ -352,6 +455,90 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parallel_layers() {
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        root = "echo root"
+        task1 = {cmd="echo task1", depends-on=["root"]}
+        task2 = {cmd="echo task2", depends-on=["root"]}
+        task3 = "echo task3"
+        top = {cmd="echo top", depends-on=["task1","task2","task3"]}
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["top".to_string()]).unwrap();
+
+        let layers = graph
+            .parallel_layers()
+            .into_iter()
+            .map(|layer| {
+                layer
+                    .into_iter()
+                    .filter_map(|task| graph[task].full_command())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            layers,
+            vec![
+                vec!["echo root", "echo task3"],
+                vec!["echo task1", "echo task2"],
+                vec!["echo top"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_names_are_passed_as_arguments() {
+        // Without `pixi run --each` the arguments are passed to the first task, even if they are
+        // the names of other tasks.
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        setup = "echo setup"
+        a = {cmd="echo a", depends-on=["setup"]}
+        b = {cmd="echo b", depends-on=["setup"]}
+    "#,
+                &["a", "b"],
+                None,
+                None
+            ),
+            vec!["echo setup", "echo a b"]
+        );
+
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        setup = "echo setup"
+        a = {cmd="echo a", depends-on=["setup"]}
+    "#,
+                &["a", "setup", "--verbose"],
+                None,
+                None
+            ),
+            vec!["echo setup", "echo a setup --verbose"]
+        );
+    }
+
     #[test]
     fn test_cycle_ordered_commands() {
         assert_eq!(
@@ -400,6 +587,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_conditional_depends_on() {
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        win-setup = "echo win-setup"
+        root = "echo root"
+        top = {cmd="echo top", depends-on=["root", { task = "win-setup", platform = "win-64" }]}
+    "#;
+        assert_eq!(
+            commands_in_order(project, &["top"], Some(Platform::Win64), None),
+            vec!["echo root", "echo win-setup", "echo top"]
+        );
+        assert_eq!(
+            commands_in_order(project, &["top"], Some(Platform::Linux64), None),
+            vec!["echo root", "echo top"]
+        );
+    }
+
+    #[test]
+    fn test_allow_failure_depends_on() {
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64"]
+        [tasks]
+        setup = "echo setup"
+        warmup = "exit 1"
+        build = {cmd="echo build", depends-on=["setup", { task = "warmup", allow-failure = true }]}
+        test = {cmd="echo test", depends-on=["build", "setup"]}
+    "#,
+        )
+        .unwrap();
+
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, Some(Platform::Linux64));
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["test".to_string()]).unwrap();
+
+        let allowed_to_fail = graph
+            .topological_order()
+            .into_iter()
+            .filter(|&id| graph[id].allow_failure)
+            .filter_map(|id| graph[id].name.clone().map(String::from))
+            .collect::<Vec<_>>();
+        assert_eq!(allowed_to_fail, vec!["warmup"]);
+    }
+
     #[test]
     fn test_custom_command() {
         assert_eq!(
@@ -556,4 +796,57 @@ mod test {
             None,
         );
     }
+
+    const TASK_ARGS_PROJECT: &str = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        build = { cmd = "echo building {{ target }}", args = [{ name = "target", default = "all" }] }
+        deploy = { cmd = "echo deploying to {{env}} $env", depends-on = ["build"], args = [{ name = "env", choices = ["staging", "prod"] }] }
+        where = "echo {{ env_name }} on {{platform}} \\{{ env_name }}"
+        typo = "echo {{ project_rot }}"
+    "#;
+
+    #[test]
+    fn test_task_arguments() {
+        assert_eq!(
+            commands_in_order(TASK_ARGS_PROJECT, &["deploy", "prod"], None, None),
+            vec!["echo building all", "echo deploying to prod $env"]
+        );
+        assert_eq!(
+            commands_in_order(TASK_ARGS_PROJECT, &["build", "docs"], None, None),
+            vec!["echo building docs"]
+        );
+    }
+
+    #[test]
+    fn test_task_variables() {
+        assert_eq!(
+            commands_in_order(TASK_ARGS_PROJECT, &["where"], Some(Platform::Win64), None),
+            vec!["echo default on win-64 {{ env_name }}"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_task_arguments() {
+        let project = Project::from_str(Path::new("pixi.toml"), TASK_ARGS_PROJECT).unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let graph_error = |args: &[&str]| {
+            TaskGraph::from_cmd_args(
+                &project,
+                &search_envs,
+                args.iter().map(|arg| arg.to_string()).collect(),
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert!(graph_error(&["deploy"]).starts_with("missing argument 'env'"));
+        assert!(graph_error(&["deploy", "dev"]).starts_with("invalid value 'dev'"));
+        assert!(graph_error(&["deploy", "prod", "now"]).starts_with("unexpected argument 'now'"));
+        assert!(graph_error(&["typo"]).starts_with("undefined variable '{{ project_rot }}'"));
+    }
 }
@@ -0,0 +1,220 @@
+//! Implements `pixi run --watch`, which runs a task again whenever the files that match a set of
+//! globs change.
+//!
+//! The task runs in a `pixi run` process of its own, in its own process group, so a run that is
+//! still in progress when the files change can be cancelled with all the processes it started.
+//! The files are polled by comparing their paths, sizes and modification times, the contents of
+//! the files are never read.
+
+use super::detached::terminate_process_tree;
+use super::file_hashes::build_filter;
+use super::FileHashesError;
+use ignore::WalkBuilder;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsString;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::Duration;
+
+/// The time between two checks of the watched files.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The time the watched files have to stay unchanged before the task is run again, so a burst of
+/// changes, e.g. saving many files at once, only results in a single run.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Watches the files in a directory that match a set of gitignore-style globs.
+#[derive(Debug)]
+pub struct FileWatcher {
+    root: PathBuf,
+    globs: Vec<String>,
+    state: u64,
+}
+
+impl FileWatcher {
+    /// Starts watching the files in `root` that match `globs`.
+    pub async fn new(root: &Path, globs: Vec<String>) -> Result<Self, FileHashesError> {
+        let state = files_state(root, &globs).await?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            globs,
+            state,
+        })
+    }
+
+    /// Returns true if the watched files changed since the last call. When they changed, this
+    /// waits until they stopped changing.
+    pub async fn changed(&mut self) -> Result<bool, FileHashesError> {
+        let mut state = files_state(&self.root, &self.globs).await?;
+        if state == self.state {
+            return Ok(false);
+        }
+        loop {
+            tokio::time::sleep(DEBOUNCE_INTERVAL).await;
+            let next = files_state(&self.root, &self.globs).await?;
+            if next == state {
+                break;
+            }
+            state = next;
+        }
+        self.state = state;
+        Ok(true)
+    }
+}
+
+/// Returns a hash of the paths, sizes and modification times of the files that match the globs.
+async fn files_state(root: &Path, globs: &[String]) -> Result<u64, FileHashesError> {
+    if !root.is_dir() {
+        return Ok(0);
+    }
+    let filter = build_filter(root, globs)?;
+    let root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        for entry in WalkBuilder::new(&root)
+            .overrides(filter)
+            .hidden(false)
+            .git_ignore(false)
+            .git_global(false)
+            .git_exclude(false)
+            .build()
+        {
+            let entry = entry?;
+            if entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir())
+            {
+                continue;
+            }
+            // A file that is removed while walking is simply not part of the state.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            files.push((entry.into_path(), metadata.len(), metadata.modified().ok()));
+        }
+        let mut hasher = DefaultHasher::new();
+        files
+            .into_iter()
+            .sorted()
+            .for_each(|file| file.hash(&mut hasher));
+        Ok(hasher.finish())
+    })
+    .await
+    .expect("the task was cancelled")
+}
+
+/// Runs `program` with `args` whenever the watched files change, until Ctrl-C is pressed. A run
+/// that is still in progress when the files change is terminated first.
+pub async fn run_on_changes(
+    mut watcher: FileWatcher,
+    program: &Path,
+    args: Vec<OsString>,
+) -> miette::Result<()> {
+    loop {
+        let mut child = spawn_in_process_group(program, &args)?;
+        let mut running = true;
+
+        // Wait until the files change, reporting the run once it finished.
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    if running {
+                        stop(&mut child)?;
+                    }
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+            if running {
+                if let Some(status) = child.try_wait().into_diagnostic()? {
+                    running = false;
+                    eprintln!(
+                        "{}The task finished ({status}), waiting for changes in {}",
+                        console::Emoji("👀 ", ""),
+                        watcher.globs.join(", ")
+                    );
+                }
+            }
+            if watcher.changed().await.into_diagnostic()? {
+                break;
+            }
+        }
+
+        if running {
+            eprintln!(
+                "{}Files changed, restarting the task",
+                console::Emoji("🔄 ", "")
+            );
+            stop(&mut child)?;
+        } else {
+            eprintln!(
+                "{}Files changed, running the task again",
+                console::Emoji("🔄 ", "")
+            );
+        }
+    }
+}
+
+/// Spawns the process in its own process group so it can be terminated with all the processes it
+/// started. The process group doesn't receive the Ctrl-C of the terminal, that is handled by the
+/// watcher.
+fn spawn_in_process_group(program: &Path, args: &[OsString]) -> miette::Result<Child> {
+    let mut command = Command::new(program);
+    command.args(args);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+    command
+        .spawn()
+        .into_diagnostic()
+        .wrap_err("failed to start the task")
+}
+
+/// Terminates the process tree of a run and waits for it to exit.
+fn stop(child: &mut Child) -> miette::Result<()> {
+    terminate_process_tree(child.id())
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to stop the task (pid {})", child.id()))?;
+    child.wait().into_diagnostic()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# pixi").unwrap();
+
+        let mut watcher = FileWatcher::new(dir.path(), vec![String::from("src/")])
+            .await
+            .unwrap();
+        assert!(!watcher.changed().await.unwrap());
+
+        // Files that don't match the globs are not watched.
+        std::fs::write(dir.path().join("README.md"), "# pixi!").unwrap();
+        assert!(!watcher.changed().await.unwrap());
+
+        std::fs::write(dir.path().join("src/lib.rs"), "fn main() { }").unwrap();
+        assert!(watcher.changed().await.unwrap());
+        assert!(!watcher.changed().await.unwrap());
+
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        assert!(watcher.changed().await.unwrap());
+    }
+}
@@ -7,8 +7,8 @@ use rattler_networking::{
     AuthenticationMiddleware, AuthenticationStorage, MirrorMiddleware, OciMiddleware,
 };
 
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest::{Client, NoProxy, Proxy, Request, Response};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
 use std::collections::HashMap;
 
 use crate::config::Config;
@@ -19,7 +19,10 @@ pub fn default_retry_policy() -> ExponentialBackoff {
     ExponentialBackoff::builder().build_with_max_retries(3)
 }
 
-fn auth_middleware(config: &Config) -> Result<AuthenticationMiddleware, FileStorageError> {
+/// Returns the storage from which the credentials for channels and pypi indexes are read.
+pub(crate) fn authentication_storage(
+    config: &Config,
+) -> Result<AuthenticationStorage, FileStorageError> {
     if let Some(auth_file) = config.authentication_override_file() {
         tracing::info!("Loading authentication from file: {:?}", auth_file);
 
@@ -32,10 +35,16 @@ fn auth_middleware(config: &Config) -> Result<AuthenticationMiddleware, FileStor
             authentication_storage::backends::file::FileStorage::new(PathBuf::from(&auth_file))?,
         ));
 
-        return Ok(AuthenticationMiddleware::new(store));
+        return Ok(store);
     }
 
-    Ok(AuthenticationMiddleware::default())
+    Ok(AuthenticationStorage::default())
+}
+
+fn auth_middleware(config: &Config) -> Result<AuthenticationMiddleware, FileStorageError> {
+    Ok(AuthenticationMiddleware::new(authentication_storage(
+        config,
+    )?))
 }
 
 fn mirror_middleware(config: &Config) -> MirrorMiddleware {
@@ -74,6 +83,66 @@ fn oci_middleware() -> OciMiddleware {
     OciMiddleware
 }
 
+/// Refuses every request, used when network access is disabled with `--offline` so that anything
+/// that is not in a cache results in an error instead of a request.
+struct OfflineMiddleware;
+
+#[derive(Debug, thiserror::Error)]
+#[error("'{0}' is required but not in the cache, network access is disabled by `--offline`")]
+struct OfflineError(url::Url);
+
+#[async_trait::async_trait]
+impl Middleware for OfflineMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut http::Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        Err(reqwest_middleware::Error::middleware(OfflineError(
+            strip_credentials(req.url()),
+        )))
+    }
+}
+
+/// The environment variables from which the proxy is read if no proxy is configured explicitly.
+const PROXY_ENV_VARS: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+/// Returns the proxy through which requests are made, if any, with its credentials removed.
+pub(crate) fn proxy_description(config: Option<&Config>) -> Option<String> {
+    if let Some(proxy) = config.and_then(Config::proxy) {
+        return Some(redact_credentials(proxy));
+    }
+    PROXY_ENV_VARS
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+        .map(|proxy| match url::Url::parse(&proxy) {
+            Ok(url) => redact_credentials(&url),
+            Err(_) => proxy,
+        })
+}
+
+/// Removes the username and password from a url so it can be shown to the user.
+fn redact_credentials(url: &url::Url) -> String {
+    strip_credentials(url).to_string()
+}
+
+/// Returns the url without its username and password.
+pub(crate) fn strip_credentials(url: &url::Url) -> url::Url {
+    let mut url = url.clone();
+    // These only fail for urls that cannot have credentials in the first place.
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url
+}
+
 pub(crate) fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMiddleware) {
     static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
@@ -89,16 +158,33 @@ pub(crate) fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientW
     }
 
     let timeout = 5 * 60;
-    let client = Client::builder()
+    let mut client_builder = Client::builder()
         .pool_max_idle_per_host(20)
         .user_agent(APP_USER_AGENT)
         .danger_accept_invalid_certs(config.tls_no_verify())
-        .read_timeout(Duration::from_secs(timeout))
+        .read_timeout(Duration::from_secs(timeout));
+
+    // Without an explicit proxy, reqwest uses the `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY`
+    // environment variables.
+    if let Some(proxy) = config.proxy() {
+        tracing::info!("Using proxy: {}", redact_credentials(proxy));
+        client_builder = client_builder.proxy(
+            Proxy::all(proxy.clone())
+                .expect("the proxy url was validated")
+                .no_proxy(NoProxy::from_env()),
+        );
+    }
+
+    let client = client_builder
         .build()
         .expect("failed to create reqwest Client");
 
     let mut client_builder = ClientBuilder::new(client.clone());
 
+    if config.offline() {
+        client_builder = client_builder.with(OfflineMiddleware);
+    }
+
     if !config.mirror_map().is_empty() {
         client_builder = client_builder
             .with(mirror_middleware(&config))
@@ -4,11 +4,12 @@ use crate::common::package_database::{Package, PackageDatabase};
 use crate::common::LockFileExt;
 use crate::common::PixiControl;
 use pixi::consts::DEFAULT_ENVIRONMENT_NAME;
-use pixi::{DependencyType, SpecType};
+use pixi::{DependencyType, FeatureName, SpecType};
 use rattler_conda_types::{PackageName, Platform};
 use serial_test::serial;
 use std::str::FromStr;
 use tempfile::TempDir;
+use url::Url;
 
 /// Test add functionality for different types of packages.
 /// Run, dev, build
@@ -253,3 +254,186 @@ async fn add_sdist_functionality() {
         .await
         .unwrap();
 }
+
+/// Test that adding a package that doesn't exist in the channels fails before solving.
+#[tokio::test]
+async fn add_unknown_package() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("rattler", "1").finish());
+    package_database.add_package(Package::build("rattler", "2").finish());
+
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+
+    let err = pixi.add("ratler").await.unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("no package named 'ratler' found in channels"));
+    assert_eq!(
+        err.help().map(|help| help.to_string()),
+        Some(String::from("did you mean 'rattler'?"))
+    );
+
+    let err = pixi.add("rattler>=3").await.unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("no version of 'rattler' matching"));
+
+    // Nothing is added to the manifest.
+    assert!(!pixi
+        .project()
+        .unwrap()
+        .manifest
+        .contents
+        .contains("rattler"));
+}
+
+/// Test that `--auto-platform` scopes every spec to the platforms it has builds for.
+#[tokio::test]
+async fn add_auto_platform() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(
+        Package::build("linux-only", "1")
+            .with_subdir(Platform::Linux64)
+            .finish(),
+    );
+    for platform in [Platform::Linux64, Platform::OsxArm64] {
+        package_database.add_package(
+            Package::build("everywhere", "1")
+                .with_subdir(platform)
+                .finish(),
+        );
+    }
+
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+        [project]
+        name = "auto-platform"
+        channels = ["{}"]
+        platforms = ["linux-64", "osx-arm64"]
+        "#,
+        Url::from_directory_path(channel_dir.path()).unwrap()
+    ))
+    .unwrap();
+
+    let mut add = pixi.add("linux-only").with_spec("everywhere");
+    add.args.auto_platform = true;
+    add.args.yes = true;
+    add.await.unwrap();
+
+    // A spec with builds for all platforms is not scoped by another spec that lacks builds.
+    let project = pixi.project().unwrap();
+    let has_dependency = |name: &str, platform| {
+        project.manifest.has_dependency(
+            &PackageName::from_str(name).unwrap(),
+            SpecType::Run,
+            platform,
+            &FeatureName::Default,
+        )
+    };
+    assert!(has_dependency("linux-only", Some(Platform::Linux64)));
+    assert!(!has_dependency("linux-only", None));
+    assert!(!has_dependency("linux-only", Some(Platform::OsxArm64)));
+    assert!(has_dependency("everywhere", None));
+    assert!(!has_dependency("everywhere", Some(Platform::Linux64)));
+
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(DEFAULT_ENVIRONMENT_NAME, Platform::Linux64, "linux-only==1"));
+    assert!(!lock.contains_match_spec(
+        DEFAULT_ENVIRONMENT_NAME,
+        Platform::OsxArm64,
+        "linux-only==1"
+    ));
+    assert!(lock.contains_match_spec(
+        DEFAULT_ENVIRONMENT_NAME,
+        Platform::OsxArm64,
+        "everywhere==1"
+    ));
+}
+
+/// Test that adding multiple specs that conflict points out the conflicting specs and adds none of
+/// them.
+#[tokio::test]
+async fn add_conflicting_specs() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("c", "1").finish());
+    package_database.add_package(Package::build("c", "2").finish());
+    package_database.add_package(Package::build("a", "1").with_dependency("c ==1").finish());
+    package_database.add_package(Package::build("b", "1").with_dependency("c ==2").finish());
+    package_database.add_package(Package::build("d", "1").finish());
+
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+
+    let err = pixi
+        .add("d")
+        .with_spec("b")
+        .with_spec("a")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "could not solve a, b, d together on {}, the conflict is likely caused by 'a', 'b'. None of the packages were added.",
+            Platform::current()
+        )
+    );
+
+    // Nothing is added to the manifest.
+    let project = pixi.project().unwrap();
+    for name in ["a", "b", "d"] {
+        assert!(!project.manifest.has_dependency(
+            &PackageName::from_str(name).unwrap(),
+            SpecType::Run,
+            None,
+            &FeatureName::Default,
+        ));
+    }
+}
+
+/// Test that the direct references of a requirements file are added and that requirements with
+/// environment markers are skipped.
+#[tokio::test]
+async fn add_requirements_file_with_url() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    let requirements = pixi.project_path().join("requirements.txt");
+    std::fs::write(
+        &requirements,
+        "pkg @ https://example.com/pkg-1.0-py3-none-any.whl\nother; python_version >= \"3.9\"\n",
+    )
+    .unwrap();
+
+    let mut add = pixi.add_multiple(Vec::new()).without_lockfile_update();
+    add.args.requirement = Some(requirements);
+    add.await.unwrap();
+
+    let manifest = std::fs::read_to_string(pixi.manifest_path()).unwrap();
+    assert!(manifest.contains(r#"pkg = { url = "https://example.com/pkg-1.0-py3-none-any.whl" }"#));
+    assert!(!manifest.contains("other"));
+}
@@ -304,6 +304,14 @@ impl InstallBuilder {
         self.args.lock_file_usage.frozen = true;
         self
     }
+    pub fn with_check(mut self) -> Self {
+        self.args.check = true;
+        self
+    }
+    pub fn with_offline(mut self) -> Self {
+        self.args.offline = true;
+        self
+    }
 }
 
 impl IntoFuture for InstallBuilder {
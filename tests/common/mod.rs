@@ -236,9 +236,22 @@ impl PixiControl {
                 no_install: true,
                 no_lockfile_update: false,
                 platform: Default::default(),
+                target: Default::default(),
+                auto_platform: false,
+                yes: false,
                 pypi: false,
                 feature: None,
                 config: Default::default(),
+                pin: None,
+                allow_prerelease: false,
+                wheel_only: false,
+                sdist_only: false,
+                sort: false,
+                sha256: None,
+                requirement: None,
+                optional: None,
+                solve_group: None,
+                dry_run: false,
             },
         }
     }
@@ -255,9 +268,22 @@ impl PixiControl {
                 no_install: true,
                 no_lockfile_update: false,
                 platform: Default::default(),
+                target: Default::default(),
+                auto_platform: false,
+                yes: false,
                 pypi: false,
                 feature: None,
                 config: Default::default(),
+                pin: None,
+                allow_prerelease: false,
+                wheel_only: false,
+                sdist_only: false,
+                sort: false,
+                sha256: None,
+                requirement: None,
+                optional: None,
+                solve_group: None,
+                dry_run: false,
             },
         }
     }
@@ -331,15 +357,19 @@ impl PixiControl {
             .map_err(RunError::TaskGraphError)?;
 
         // Iterate over all tasks in the graph and execute them.
+        let reproducible = args.reproducible || project.reproducible_env();
         let mut task_env = None;
         let mut result = RunOutput::default();
         for task_id in task_graph.topological_order() {
-            let task = ExecutableTask::from_task_graph(&task_graph, task_id);
+            let task = ExecutableTask::from_task_graph(&task_graph, task_id)
+                .with_reproducible_env(reproducible);
 
             // Construct the task environment if not already created.
             let task_env = match task_env.as_ref() {
                 None => {
-                    let env = get_task_env(&mut lock_file, &task.run_environment).await?;
+                    let env =
+                        get_task_env(&mut lock_file, &task.run_environment, false, reproducible)
+                            .await?;
                     task_env.insert(env) as &_
                 }
                 Some(task_env) => task_env,
@@ -362,12 +392,25 @@ impl PixiControl {
         InstallBuilder {
             args: Args {
                 environment: None,
+                with: Vec::new(),
                 manifest_path: Some(self.manifest_path()),
                 lock_file_usage: LockFileUsageArgs {
                     frozen: false,
                     locked: false,
                 },
                 config: Default::default(),
+                check: false,
+                prefix: None,
+                progress_format: Default::default(),
+                no_cache: false,
+                offline: false,
+                summary: false,
+                json: false,
+                allow_partial_platforms: false,
+                only_deps: Vec::new(),
+                skip_checks: false,
+                solve_strategy: Default::default(),
+                link_method: None,
             },
         }
     }
@@ -116,6 +116,96 @@ async fn test_incremental_lock_file() {
     );
 }
 
+/// Test that `pixi install --check` reports discrepancies without modifying the project.
+#[tokio::test]
+async fn install_check_does_not_modify() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+    pixi.add("foo").without_lockfile_update().await.unwrap();
+
+    let project = pixi.project().unwrap();
+    let lock_file_path = project.lock_file_path();
+    let env_dir = project.default_environment().dir();
+
+    // Neither the lock-file nor the environment exist.
+    assert!(pixi.install().with_check().await.is_err());
+    assert!(
+        !lock_file_path.exists(),
+        "the lock-file should not be created"
+    );
+    assert!(!env_dir.exists(), "the environment should not be created");
+
+    // The lock-file is up-to-date but the environment is not installed.
+    pixi.up_to_date_lock_file().await.unwrap();
+    assert!(pixi.install().with_check().await.is_err());
+    assert!(!env_dir.exists(), "the environment should not be created");
+}
+
+/// Test that `pixi install --offline` installs from the package cache and fails for packages that
+/// are not in it.
+#[tokio::test]
+async fn install_offline() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("offline-cached", "1").finish());
+    package_database.add_package(Package::build("offline-never-cached", "1").finish());
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    // Populate the package cache.
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+    pixi.add("offline-cached").with_install(true).await.unwrap();
+
+    // Without the environment and the channel, everything has to come from the cache.
+    let env_dir = pixi.project().unwrap().default_environment().dir();
+    std::fs::remove_dir_all(&env_dir).unwrap();
+    let missing = PixiControl::new().unwrap();
+    missing
+        .init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+    missing
+        .add("offline-never-cached")
+        .without_lockfile_update()
+        .await
+        .unwrap();
+    missing.up_to_date_lock_file().await.unwrap();
+    drop(channel_dir);
+
+    pixi.install().with_frozen().with_offline().await.unwrap();
+    assert!(env_dir.join("conda-meta").is_dir());
+
+    let err = missing
+        .install()
+        .with_frozen()
+        .with_offline()
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("'offline-never-cached-1-0' is not in the package cache"),
+        "unexpected error: {err}"
+    );
+}
+
 /// Test the `pixi install --locked` functionality.
 #[tokio::test]
 #[serial]
@@ -462,3 +552,108 @@ async fn test_installer_name() {
     let installer = std::fs::read_to_string(installer).unwrap();
     assert_eq!(installer, PIXI_UV_INSTALLER);
 }
+
+/// Test that `--allow-partial-platforms` locks the platforms that can be solved and that the
+/// install fails without it.
+#[tokio::test]
+async fn install_allow_partial_platforms() {
+    let other_platform = if Platform::current() == Platform::Linux64 {
+        Platform::Win64
+    } else {
+        Platform::Linux64
+    };
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(
+        Package::build("foo", "1")
+            .with_subdir(Platform::current())
+            .finish(),
+    );
+    package_database.add_package(
+        Package::build("bar", "1")
+            .with_subdir(other_platform)
+            .finish(),
+    );
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+        [project]
+        name = "partial-platforms"
+        channels = ["{}"]
+        platforms = ["{}", "{}"]
+
+        [dependencies]
+        foo = "*"
+        "#,
+        url::Url::from_directory_path(channel_dir.path()).unwrap(),
+        Platform::current(),
+        other_platform
+    ))
+    .unwrap();
+
+    // `foo` has no build for the other platform.
+    assert!(pixi.install().await.is_err());
+    assert!(!pixi.project().unwrap().has_lock_file());
+
+    let mut install = pixi.install();
+    install.args.allow_partial_platforms = true;
+    install.await.unwrap();
+
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(DEFAULT_ENVIRONMENT_NAME, Platform::current(), "foo ==1"));
+    assert!(!lock.contains_conda_package(DEFAULT_ENVIRONMENT_NAME, other_platform, "foo"));
+}
+
+/// Test that `--only-deps` installs the dependencies of a package without the package itself.
+#[tokio::test]
+async fn install_only_deps() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("bar", "1").finish());
+    package_database.add_package(
+        Package::build("foo", "1")
+            .with_dependency("bar >=1")
+            .finish(),
+    );
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+    pixi.add("foo").await.unwrap();
+
+    // Only packages that are in the manifest can be passed.
+    let mut install = pixi.install();
+    install.args.only_deps = vec![String::from("bar")];
+    assert!(install.await.is_err());
+
+    let mut install = pixi.install();
+    install.args.only_deps = vec![String::from("foo")];
+    install.await.unwrap();
+
+    let installed = std::fs::read_dir(
+        pixi.project()
+            .unwrap()
+            .default_environment()
+            .dir()
+            .join("conda-meta"),
+    )
+    .unwrap()
+    .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+    .collect::<Vec<_>>();
+    assert!(installed.iter().any(|name| name.starts_with("bar-1-")));
+    assert!(!installed.iter().any(|name| name.starts_with("foo-1-")));
+
+    // The package itself is still locked.
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(DEFAULT_ENVIRONMENT_NAME, Platform::current(), "foo ==1"));
+}
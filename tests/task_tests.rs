@@ -82,7 +82,9 @@ pub async fn add_command_types() {
     let project = pixi.project().unwrap();
     let tasks = project.default_environment().tasks(None).unwrap();
     let task = tasks.get(&<TaskName>::from("testing")).unwrap();
-    assert!(matches!(task, Task::Alias(a) if a.depends_on.first().unwrap().as_str() == "test"));
+    assert!(
+        matches!(task, Task::Alias(a) if a.depends_on.first().unwrap().task.as_str() == "test")
+    );
 }
 
 #[tokio::test]
@@ -239,3 +241,29 @@ async fn test_task_with_env() {
     assert_eq!(result.exit_code, 0);
     assert_eq!(result.stdout, "From a world with spaces\n");
 }
+
+#[tokio::test]
+async fn test_task_with_reproducible_env() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("reproducible".into(), None, FeatureName::Default)
+        .with_commands(["echo $PYTHONHASHSEED $SOURCE_DATE_EPOCH $TZ"])
+        .with_env(vec![(String::from("TZ"), String::from("Europe/Amsterdam"))])
+        .execute()
+        .unwrap();
+
+    let result = pixi
+        .run(Args {
+            task: vec!["reproducible".to_string()],
+            reproducible: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // The env of the task overrides the reproducible defaults.
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, "0 315532800 Europe/Amsterdam\n");
+}